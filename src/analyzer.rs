@@ -1,4 +1,6 @@
+use crate::contract::{ContractReport, ContractValidator};
 use crate::models::{BehaviorPattern, Dependency, DependencyType};
+use crate::openapi::OpenApi;
 use crate::storage::Storage;
 use anyhow::Result;
 use std::collections::HashMap;
@@ -59,7 +61,7 @@ impl Analyzer {
             });
         }
 
-        patterns.sort_by(|a, b| b.request_count.cmp(&a.request_count));
+        patterns.sort_by_key(|p| std::cmp::Reverse(p.request_count));
         Ok(patterns)
     }
 
@@ -95,6 +97,12 @@ impl Analyzer {
         aggregated.into_values().collect()
     }
 
+    /// Check captured traffic against a published OpenAPI contract
+    pub fn validate_contract(&self, spec: &OpenApi) -> Result<ContractReport> {
+        let requests = self.storage.get_all_requests()?;
+        Ok(ContractValidator::new(spec).validate(&requests))
+    }
+
     pub fn analyze(&self) -> Result<AnalysisReport> {
         let requests = self.storage.get_all_requests()?;
 
@@ -172,7 +180,7 @@ impl Analyzer {
             .sum();
 
         let mut endpoints: Vec<EndpointStats> = endpoint_stats.into_values().collect();
-        endpoints.sort_by(|a, b| b.count.cmp(&a.count));
+        endpoints.sort_by_key(|e| std::cmp::Reverse(e.count));
 
         let behavior_patterns = self.analyze_behavior_patterns().unwrap_or_default();
 
@@ -0,0 +1,269 @@
+//! Resilience expectations: what a backend must do for a request to pass
+//! under chaos
+//!
+//! Without expectations a replayed request passes when its status matches the
+//! captured one. Expectations replace that check for the endpoints they are
+//! scoped to, and all expectations for an endpoint must hold.
+
+use super::{in_scope, parse_duration};
+use anyhow::Result;
+use std::fmt;
+use std::time::Duration;
+
+/// Retries allowed by a bare `retry`
+const DEFAULT_RETRIES: usize = 3;
+
+/// Most retries a `retry:N` may ask for
+const MAX_RETRIES: usize = 10;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expectation {
+    /// `retry:N` - a request that failed, for any reason, must succeed when
+    /// re-sent up to N more times
+    Retry(usize),
+    /// `fallback` - any 2xx is accepted in place of the captured status, e.g.
+    /// a cached or degraded response
+    Fallback,
+    /// `unavailable:500ms` - the backend must answer within the budget, either
+    /// as captured or with a 503 carrying Retry-After
+    Unavailable(Duration),
+    /// `no-500` - any status except 500 is accepted
+    NoServerError,
+}
+
+impl Expectation {
+    pub fn parse(rule: &str) -> Result<Self> {
+        let (name, arg) = match rule.split_once(':') {
+            Some((name, arg)) => (name, Some(arg)),
+            None => (rule, None),
+        };
+
+        match (name, arg) {
+            ("retry", None) => Ok(Self::Retry(DEFAULT_RETRIES)),
+            ("retry", Some(n)) => match n.parse() {
+                Ok(n) if (1..=MAX_RETRIES).contains(&n) => Ok(Self::Retry(n)),
+                _ => anyhow::bail!("retry takes 1 to {} retries, got: {}", MAX_RETRIES, n),
+            },
+            ("fallback", None) => Ok(Self::Fallback),
+            ("unavailable", Some(budget)) => parse_duration(budget)
+                .map(Self::Unavailable)
+                .map_err(anyhow::Error::msg),
+            ("no-500", None) => Ok(Self::NoServerError),
+            _ => anyhow::bail!(
+                "Unknown expectation: {} (expected retry[:N], fallback, unavailable:<duration> or no-500)",
+                rule
+            ),
+        }
+    }
+
+    /// Why `reply` breaks this expectation, if it does
+    fn check(&self, captured_status: Option<u16>, reply: &Reply) -> Option<String> {
+        let as_captured = captured_status.is_none_or(|s| s == reply.status);
+        match self {
+            Self::Retry(_) => None,
+            Self::Fallback if as_captured || (200..300).contains(&reply.status) => None,
+            Self::Fallback => Some(format!("no fallback response, got {}", reply.status)),
+            Self::Unavailable(budget) if reply.latency > *budget => Some(format!(
+                "answered in {}ms, over the {}ms budget",
+                reply.latency.as_millis(),
+                budget.as_millis()
+            )),
+            Self::Unavailable(_) if as_captured => None,
+            Self::Unavailable(_) if reply.status == 503 && reply.retry_after => None,
+            Self::Unavailable(_) if reply.status == 503 => {
+                Some("503 without Retry-After".to_string())
+            }
+            Self::Unavailable(_) => Some(format!(
+                "expected {} or 503 with Retry-After, got {}",
+                captured_status.unwrap_or_default(),
+                reply.status
+            )),
+            Self::NoServerError if reply.status == 500 => {
+                Some("returned 500 Internal Server Error".to_string())
+            }
+            Self::NoServerError => None,
+        }
+    }
+}
+
+impl fmt::Display for Expectation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Retry(n) => write!(f, "retry:{}", n),
+            Self::Fallback => f.write_str("fallback"),
+            Self::Unavailable(budget) => write!(f, "unavailable:{}ms", budget.as_millis()),
+            Self::NoServerError => f.write_str("no-500"),
+        }
+    }
+}
+
+/// A response the backend gave to a replayed request
+#[derive(Debug, Clone)]
+pub struct Reply {
+    pub status: u16,
+    /// Whether it carried a Retry-After header
+    pub retry_after: bool,
+    pub latency: Duration,
+    pub content_type: Option<String>,
+    pub body: Vec<u8>,
+}
+
+/// Expectations by endpoint scope: `*`, a path pattern for every method, or
+/// `METHOD pattern`
+#[derive(Debug, Clone, Default)]
+pub struct Expectations {
+    rules: Vec<(String, Expectation)>,
+}
+
+impl Expectations {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// `ENDPOINT=RULE`, e.g. `GET /users/{id}=retry:3` or `*=no-500`
+    pub fn with_rule(mut self, rule: &str) -> Result<Self> {
+        let Some((scope, expectation)) = rule.rsplit_once('=') else {
+            anyhow::bail!("Expectations look like ENDPOINT=RULE, got: {}", rule);
+        };
+        let scope = scope.trim();
+        if scope.is_empty() {
+            anyhow::bail!("Expectations look like ENDPOINT=RULE, got: {}", rule);
+        }
+        self.rules
+            .push((scope.to_string(), Expectation::parse(expectation.trim())?));
+        Ok(self)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Expectations that apply to a `METHOD pattern` endpoint
+    pub fn for_endpoint(&self, endpoint: &str) -> Vec<&Expectation> {
+        self.rules
+            .iter()
+            .filter(|(scope, _)| in_scope(scope, endpoint))
+            .map(|(_, expectation)| expectation)
+            .collect()
+    }
+}
+
+/// How many times a failed request may be re-sent
+pub fn retries(expectations: &[&Expectation]) -> usize {
+    expectations
+        .iter()
+        .filter_map(|e| match e {
+            Expectation::Retry(n) => Some(*n),
+            _ => None,
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+/// Why `reply` is not acceptable: the captured status is required unless
+/// expectations say otherwise
+pub fn judge(
+    expectations: &[&Expectation],
+    captured_status: Option<u16>,
+    reply: &Reply,
+) -> Result<()> {
+    let judging: Vec<_> = expectations
+        .iter()
+        .filter(|e| !matches!(e, Expectation::Retry(_)))
+        .collect();
+
+    if judging.is_empty() {
+        if let Some(expected) = captured_status
+            && reply.status != expected
+        {
+            anyhow::bail!(
+                "Status mismatch: expected {}, got {}",
+                expected,
+                reply.status
+            );
+        }
+        return Ok(());
+    }
+
+    let broken: Vec<String> = judging
+        .iter()
+        .filter_map(|e| {
+            e.check(captured_status, reply)
+                .map(|why| format!("{}: {}", e, why))
+        })
+        .collect();
+    match broken.is_empty() {
+        true => Ok(()),
+        false => anyhow::bail!("{}", broken.join("; ")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reply(status: u16, retry_after: bool, latency_ms: u64) -> Reply {
+        Reply {
+            status,
+            retry_after,
+            latency: Duration::from_millis(latency_ms),
+            content_type: None,
+            body: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_parse_rules() {
+        let expectations = Expectations::new()
+            .with_rule("GET /users/{id}=retry")
+            .unwrap()
+            .with_rule("/users/{id} = unavailable:250ms")
+            .unwrap()
+            .with_rule("*=no-500")
+            .unwrap();
+
+        let found: Vec<String> = expectations
+            .for_endpoint("GET /users/{id}")
+            .iter()
+            .map(|e| e.to_string())
+            .collect();
+        assert_eq!(found, ["retry:3", "unavailable:250ms", "no-500"]);
+        assert_eq!(expectations.for_endpoint("POST /orders").len(), 1);
+        assert_eq!(expectations.for_endpoint("DELETE /users/{id}").len(), 2);
+
+        assert!(Expectations::new().with_rule("retry:3").is_err());
+        assert!(Expectations::new().with_rule("*=retry:0").is_err());
+        assert!(Expectations::new().with_rule("*=retry:64").is_err());
+        assert!(Expectations::new().with_rule("*=unavailable").is_err());
+        assert!(Expectations::new().with_rule("*=teapot").is_err());
+    }
+
+    #[test]
+    fn test_judge() {
+        let fallback = Expectation::Fallback;
+        let unavailable = Expectation::Unavailable(Duration::from_millis(200));
+        let no_500 = Expectation::NoServerError;
+        let retry = Expectation::Retry(2);
+
+        // Without judging expectations the captured status is required
+        assert!(judge(&[], Some(200), &reply(200, false, 5)).is_ok());
+        assert!(judge(&[&retry], Some(200), &reply(404, false, 5)).is_err());
+        assert_eq!(retries(&[&retry, &no_500]), 2);
+
+        assert!(judge(&[&fallback], Some(200), &reply(203, false, 5)).is_ok());
+        assert!(judge(&[&fallback], Some(200), &reply(502, false, 5)).is_err());
+
+        assert!(judge(&[&unavailable], Some(200), &reply(200, false, 50)).is_ok());
+        assert!(judge(&[&unavailable], Some(200), &reply(503, true, 50)).is_ok());
+        let err = judge(&[&unavailable], Some(200), &reply(503, false, 50)).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "unavailable:200ms: 503 without Retry-After"
+        );
+        assert!(judge(&[&unavailable], Some(200), &reply(503, true, 900)).is_err());
+
+        assert!(judge(&[&no_500], Some(200), &reply(404, false, 5)).is_ok());
+        assert!(judge(&[&no_500], Some(200), &reply(500, false, 5)).is_err());
+        assert!(judge(&[&no_500, &fallback], Some(200), &reply(404, false, 5)).is_err());
+    }
+}
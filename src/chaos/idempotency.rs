@@ -0,0 +1,532 @@
+//! Retry storms: writes re-sent the way clients retry them, checked for the
+//! duplicates they must not create
+//!
+//! The first captured request to each non-idempotent endpoint is sent as
+//! several concurrent copies, then again as an attempt the client gives up on
+//! after a short timeout and a retry overlapping it. Every copy carries the
+//! same idempotency key. Duplicates are counted with a follow-up GET of the
+//! collection when the capture has one, and by comparing the responses; either
+//! one showing duplicates is enough.
+
+use super::{Injector, http_client};
+use crate::generators::flow;
+use crate::models::CapturedRequest;
+use crate::parsers::http::{HttpParser, MethodType};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinSet;
+use tracing::{info, warn};
+
+/// Headers clients send to make a write safe to retry, the first one being
+/// sent when the captured request has none
+const KEY_HEADERS: [&str; 2] = ["Idempotency-Key", "X-Idempotency-Key"];
+
+/// How a backend rejects a copy it recognised as a duplicate in flight
+const CONFLICT: u16 = 409;
+
+/// Top-level fields naming the resource a write created
+const ID_FIELDS: [&str; 3] = ["id", "uuid", "_id"];
+
+/// How long the client waits for the first copy before retrying
+const GIVE_UP_AFTER: Duration = Duration::from_millis(10);
+
+/// How long a write the client gave up on is given to land before the
+/// follow-up GET counts it
+const SETTLE: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Scenario {
+    /// Every copy sent at once
+    Concurrent,
+    /// The client times out on the first copy and retries while it may still
+    /// be in flight
+    RetryAfterTimeout,
+}
+
+impl fmt::Display for Scenario {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Concurrent => "concurrent",
+            Self::RetryAfterTimeout => "retry after timeout",
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "verdict", content = "detail", rename_all = "snake_case")]
+pub enum Finding {
+    Deduplicated(String),
+    Duplicated(String),
+    /// Nothing to tell duplicates apart by, or no copy succeeded
+    Inconclusive(String),
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Deduplicated(why) | Self::Duplicated(why) | Self::Inconclusive(why) => {
+                f.write_str(why)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Storm {
+    pub scenario: Scenario,
+    pub finding: Finding,
+    /// Status of each copy; 0 when it got no response
+    pub statuses: Vec<u16>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EndpointIdempotency {
+    /// `METHOD uri` of the stormed request
+    pub request: String,
+    /// Idempotency key header the captured client sent, if any
+    pub key_header: Option<String>,
+    /// Whether duplicates were counted with a follow-up GET
+    pub follow_up: bool,
+    pub storms: Vec<Storm>,
+}
+
+impl EndpointIdempotency {
+    pub fn duplicated(&self) -> bool {
+        self.storms
+            .iter()
+            .any(|s| matches!(s.finding, Finding::Duplicated(_)))
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct IdempotencyReport {
+    /// Copies sent at once in the concurrent storm
+    pub copies: usize,
+    pub endpoints: BTreeMap<String, EndpointIdempotency>,
+}
+
+impl IdempotencyReport {
+    /// Endpoints that created duplicates when retried
+    pub fn lacking(&self) -> Vec<&String> {
+        self.endpoints
+            .iter()
+            .filter(|(_, endpoint)| endpoint.duplicated())
+            .map(|(name, _)| name)
+            .collect()
+    }
+
+    pub fn print(&self) {
+        println!("\nIdempotency (retry storms of {} copies):", self.copies);
+        if self.endpoints.is_empty() {
+            println!("  No POST or PATCH requests in the capture");
+            return;
+        }
+        let width = self.endpoints.keys().map(|e| e.len()).max().unwrap_or(0);
+        for (name, endpoint) in &self.endpoints {
+            let verdict = if endpoint.duplicated() {
+                "DUPLICATES"
+            } else if endpoint
+                .storms
+                .iter()
+                .all(|s| matches!(s.finding, Finding::Deduplicated(_)))
+            {
+                "ok"
+            } else {
+                "inconclusive"
+            };
+            println!("  {:<width$}  {}", name, verdict, width = width);
+            for storm in &endpoint.storms {
+                println!(
+                    "  {:<width$}    {}: {}",
+                    "",
+                    storm.scenario,
+                    storm.finding,
+                    width = width
+                );
+            }
+            if endpoint.duplicated() && endpoint.key_header.is_none() {
+                println!(
+                    "  {:<width$}    captured clients send no idempotency key",
+                    "",
+                    width = width
+                );
+            }
+        }
+    }
+}
+
+/// A response to one copy
+#[derive(Debug, Clone, PartialEq)]
+struct Answer {
+    status: u16,
+    /// What the response names the written resource by, and its value
+    identity: Option<(&'static str, String)>,
+}
+
+/// Storm the first captured request to every non-idempotent endpoint
+pub(super) async fn storm(
+    injector: &Arc<Injector>,
+    requests: &[CapturedRequest],
+    copies: usize,
+) -> IdempotencyReport {
+    let mut report = IdempotencyReport {
+        copies,
+        ..IdempotencyReport::default()
+    };
+
+    for request in requests {
+        let endpoint = flow::endpoint(request);
+        if HttpParser::classify_method(&request.request.method) != MethodType::NonIdempotent
+            || report.endpoints.contains_key(&endpoint)
+        {
+            continue;
+        }
+        info!("Retry storm: {}", endpoint);
+        let follow_up = follow_up(requests, &endpoint);
+        let key_header = request
+            .request
+            .headers
+            .keys()
+            .find(|name| KEY_HEADERS.iter().any(|k| k.eq_ignore_ascii_case(name)))
+            .cloned();
+
+        let mut storms = Vec::new();
+        for (scenario, sends) in [
+            (Scenario::Concurrent, copies),
+            (Scenario::RetryAfterTimeout, 2),
+        ] {
+            let keyed = with_key(request, key_header.as_deref());
+            let before = match follow_up {
+                Some(get) => count(injector, get).await,
+                None => None,
+            };
+
+            let answers = match scenario {
+                Scenario::Concurrent => {
+                    let mut copies = JoinSet::new();
+                    for _ in 0..sends {
+                        let (injector, keyed) = (Arc::clone(injector), keyed.clone());
+                        copies
+                            .spawn(async move { send(&injector, &injector.client, &keyed).await });
+                    }
+                    copies.join_all().await
+                }
+                Scenario::RetryAfterTimeout => {
+                    let impatient = match http_client(GIVE_UP_AFTER, injector.route.as_ref()) {
+                        Ok(client) => client,
+                        Err(e) => {
+                            warn!("{} ({}): {}", endpoint, scenario, e);
+                            continue;
+                        }
+                    };
+                    let lost = send(injector, &impatient, &keyed).await;
+                    let retried = send(injector, &injector.client, &keyed).await;
+                    if follow_up.is_some() {
+                        tokio::time::sleep(SETTLE).await;
+                    }
+                    vec![lost, retried]
+                }
+            };
+
+            let created = match (before, follow_up) {
+                (Some(before), Some(get)) => count(injector, get)
+                    .await
+                    .map(|after| after.saturating_sub(before)),
+                _ => None,
+            };
+            let finding = judge(&answers, created);
+            if let Finding::Duplicated(why) = &finding {
+                warn!("{} ({}): {}", endpoint, scenario, why);
+            }
+            storms.push(Storm {
+                scenario,
+                finding,
+                statuses: answers
+                    .iter()
+                    .map(|a| a.as_ref().map_or(0, |a| a.status))
+                    .collect(),
+            });
+        }
+
+        report.endpoints.insert(
+            endpoint,
+            EndpointIdempotency {
+                request: format!("{} {}", request.request.method, request.request.uri),
+                key_header,
+                follow_up: follow_up.is_some(),
+                storms,
+            },
+        );
+    }
+    report
+}
+
+/// A captured GET of the whole collection a write adds to, e.g. `GET /orders`
+/// for `POST /orders`
+///
+/// Single resources (`PATCH /orders/{id}`) have no collection to count, and
+/// GETs with a query string may only see one page or a filtered part of it.
+fn follow_up<'a>(requests: &'a [CapturedRequest], endpoint: &str) -> Option<&'a CapturedRequest> {
+    let (_, pattern) = endpoint.split_once(' ')?;
+    if pattern.ends_with('}') {
+        return None;
+    }
+    let wanted = format!("GET {}", pattern);
+    requests.iter().find(|r| {
+        flow::endpoint(r) == wanted
+            && !r.request.uri.contains('?')
+            && r.response
+                .as_ref()
+                .and_then(|response| response.body.as_deref())
+                .and_then(|body| serde_json::from_slice::<Value>(body).ok())
+                .is_some_and(|body| items(&body).is_some())
+    })
+}
+
+/// The request under a fresh idempotency key, so earlier sends of it do not
+/// count
+fn with_key(request: &CapturedRequest, header: Option<&str>) -> CapturedRequest {
+    let mut keyed = request.clone();
+    keyed.request.headers.insert(
+        header.unwrap_or(KEY_HEADERS[0]).to_string(),
+        uuid::Uuid::new_v4().to_string(),
+    );
+    keyed
+}
+
+async fn send(
+    injector: &Injector,
+    client: &reqwest::Client,
+    request: &CapturedRequest,
+) -> Option<Answer> {
+    let response = injector.request(client, request).send().await.ok()?;
+    let status = response.status().as_u16();
+    let location = response
+        .headers()
+        .get(reqwest::header::LOCATION)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string);
+    let body = response.bytes().await.ok()?;
+    Some(Answer {
+        status,
+        identity: identity(location, &body),
+    })
+}
+
+/// The Location header, else an id field, else the whole body
+fn identity(location: Option<String>, body: &[u8]) -> Option<(&'static str, String)> {
+    if let Some(location) = location {
+        return Some(("Location", location));
+    }
+    if let Ok(Value::Object(fields)) = serde_json::from_slice::<Value>(body)
+        && let Some((name, id)) = ID_FIELDS
+            .iter()
+            .find_map(|&name| fields.get(name).map(|id| (name, id)))
+    {
+        return Some((name, id.to_string()));
+    }
+    (!body.is_empty()).then(|| ("body", String::from_utf8_lossy(body).into_owned()))
+}
+
+/// Items in the collection
+async fn count(injector: &Injector, request: &CapturedRequest) -> Option<usize> {
+    let response = injector
+        .request(&injector.client, request)
+        .send()
+        .await
+        .ok()?;
+    if !response.status().is_success() {
+        return None;
+    }
+    items(&response.json::<Value>().await.ok()?)
+}
+
+/// Length of a JSON array, or of the first array field of an object
+fn items(body: &Value) -> Option<usize> {
+    match body {
+        Value::Array(items) => Some(items.len()),
+        Value::Object(fields) => fields.values().find_map(|v| v.as_array().map(Vec::len)),
+        _ => None,
+    }
+}
+
+/// Whether one logical write left more than one resource behind, by the
+/// follow-up GET count and by the responses
+fn judge(answers: &[Option<Answer>], created: Option<usize>) -> Finding {
+    let succeeded: Vec<&Answer> = answers
+        .iter()
+        .flatten()
+        .filter(|a| (200..300).contains(&a.status))
+        .collect();
+
+    if succeeded.is_empty() {
+        return Finding::Inconclusive(format!("none of {} copies succeeded", answers.len()));
+    }
+    let responses = compare(answers, &succeeded);
+    match created {
+        Some(n) if n > 1 => Finding::Duplicated(format!(
+            "{} resources created by {} copies of one write, per the follow-up GET",
+            n,
+            answers.len()
+        )),
+        _ if matches!(responses, Finding::Duplicated(_)) => responses,
+        Some(0) => Finding::Inconclusive(format!(
+            "{} of {} copies succeeded but the follow-up GET counted none created",
+            succeeded.len(),
+            answers.len()
+        )),
+        Some(_) => Finding::Deduplicated(format!(
+            "1 created by {} copies, per the follow-up GET",
+            answers.len()
+        )),
+        None => responses,
+    }
+}
+
+/// What the responses to the copies tell about duplicates
+fn compare(answers: &[Option<Answer>], succeeded: &[&Answer]) -> Finding {
+    let conflicts = answers
+        .iter()
+        .flatten()
+        .filter(|a| a.status == CONFLICT)
+        .count();
+    let lost = answers.iter().filter(|a| a.is_none()).count();
+
+    if succeeded.len() == 1 {
+        if conflicts == 0 && lost > 0 {
+            return Finding::Inconclusive(format!(
+                "1 copy succeeded and {} got no response, which may still have landed",
+                lost
+            ));
+        }
+        return Finding::Deduplicated(match conflicts {
+            0 => format!("1 of {} copies succeeded", answers.len()),
+            n => format!("1 copy succeeded, {} rejected with 409", n),
+        });
+    }
+
+    let identities: Option<Vec<&(&str, String)>> =
+        succeeded.iter().map(|a| a.identity.as_ref()).collect();
+    let Some(identities) = identities else {
+        return Finding::Inconclusive(
+            "responses carry no Location, id or body to compare".to_string(),
+        );
+    };
+    let label = identities[0].0;
+    let distinct: BTreeSet<&String> = identities.iter().map(|(_, value)| value).collect();
+    match distinct.len() {
+        1 => Finding::Deduplicated(format!(
+            "{} copies succeeded with the same {}",
+            succeeded.len(),
+            label
+        )),
+        n => Finding::Duplicated(format!(
+            "{} copies succeeded with {} different {} values",
+            succeeded.len(),
+            n,
+            label
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::captured;
+
+    fn answer(status: u16, id: Option<&str>) -> Option<Answer> {
+        Some(Answer {
+            status,
+            identity: id.map(|id| ("id", id.to_string())),
+        })
+    }
+
+    #[test]
+    fn test_identity() {
+        assert_eq!(
+            identity(Some("/orders/7".to_string()), b"{}"),
+            Some(("Location", "/orders/7".to_string()))
+        );
+        assert_eq!(
+            identity(None, br#"{"name":"a","uuid":"u-1"}"#),
+            Some(("uuid", "\"u-1\"".to_string()))
+        );
+        assert_eq!(identity(None, b"ok"), Some(("body", "ok".to_string())));
+        assert_eq!(identity(None, b""), None);
+    }
+
+    #[test]
+    fn test_judge() {
+        let duplicated = judge(
+            &[answer(201, Some("1")), answer(201, Some("2")), None],
+            None,
+        );
+        assert_eq!(
+            duplicated,
+            Finding::Duplicated("2 copies succeeded with 2 different id values".to_string())
+        );
+
+        let same = judge(&[answer(201, Some("1")), answer(200, Some("1"))], None);
+        assert!(matches!(same, Finding::Deduplicated(_)));
+
+        let rejected = judge(
+            &[answer(201, Some("1")), answer(409, None), answer(409, None)],
+            None,
+        );
+        assert_eq!(
+            rejected,
+            Finding::Deduplicated("1 copy succeeded, 2 rejected with 409".to_string())
+        );
+
+        // Either signal showing duplicates is enough
+        let counted = judge(&[answer(201, Some("1")), answer(201, Some("1"))], Some(2));
+        assert!(matches!(counted, Finding::Duplicated(_)));
+        let responded = judge(&[answer(201, Some("1")), answer(201, Some("2"))], Some(1));
+        assert!(matches!(responded, Finding::Duplicated(_)));
+        let uncounted = judge(&[answer(201, Some("1")), answer(201, Some("1"))], Some(0));
+        assert!(matches!(uncounted, Finding::Inconclusive(_)));
+        let counted_once = judge(&[answer(201, Some("1")), answer(201, Some("1"))], Some(1));
+        assert!(matches!(counted_once, Finding::Deduplicated(_)));
+
+        // A copy the client gave up on may have landed unseen
+        assert!(matches!(
+            judge(&[None, answer(201, Some("1"))], None),
+            Finding::Inconclusive(_)
+        ));
+        assert!(matches!(
+            judge(&[None, answer(201, Some("1"))], Some(1)),
+            Finding::Deduplicated(_)
+        ));
+
+        assert!(matches!(
+            judge(&[answer(500, None), None], None),
+            Finding::Inconclusive(_)
+        ));
+        assert!(matches!(
+            judge(&[answer(204, None), answer(204, None)], None),
+            Finding::Inconclusive(_)
+        ));
+    }
+
+    #[test]
+    fn test_follow_up() {
+        let requests = [
+            captured("POST", "/orders", "{}", 201, "{}"),
+            captured("GET", "/orders/1", "", 200, r#"{"tags":[]}"#),
+            captured("GET", "/orders?page=2", "", 200, "[]"),
+            captured("GET", "/orders", "", 200, r#"{"orders":[]}"#),
+            captured("GET", "/payments", "", 200, "{}"),
+        ];
+        let get = follow_up(&requests, "POST /orders").unwrap();
+        assert_eq!(get.request.uri, "/orders");
+        assert!(follow_up(&requests, "PATCH /orders/{id}").is_none());
+        // Not a collection
+        assert!(follow_up(&requests, "POST /payments").is_none());
+        assert!(follow_up(&requests, "POST /refunds").is_none());
+    }
+}
@@ -0,0 +1,1627 @@
+pub mod expect;
+pub mod idempotency;
+pub mod mutate;
+pub mod network;
+pub mod schedule;
+pub mod steady;
+
+use crate::compare;
+use crate::generators::{self, flow};
+use crate::models::CapturedRequest;
+use crate::redact;
+use crate::storage::Storage;
+use anyhow::Result;
+use serde::Serialize;
+use serde_json::{Value, json};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::task::JoinSet;
+use tracing::{info, warn};
+
+pub use expect::Expectations;
+use expect::Reply;
+use idempotency::IdempotencyReport;
+use mutate::Mutation;
+pub use mutate::Mutations;
+pub use network::NetworkFaults;
+use network::{FaultProxy, NetworkStats};
+pub use schedule::Schedule;
+use steady::{BlastRadius, Monitor, Sample, SteadyState, SteadyStateReport};
+
+/// Wait before the first retry; it doubles for each one after
+const RETRY_BACKOFF: Duration = Duration::from_millis(50);
+
+/// Longest wait between retries
+const MAX_RETRY_BACKOFF: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy)]
+pub enum ChaosLevel {
+    Mild,
+    Moderate,
+    Extreme,
+}
+
+impl ChaosLevel {
+    pub fn from_str(s: &str) -> Self {
+        match s.to_lowercase().as_str() {
+            "mild" => Self::Mild,
+            "extreme" => Self::Extreme,
+            _ => Self::Moderate,
+        }
+    }
+
+    pub fn failure_rate(&self) -> f64 {
+        match self {
+            Self::Mild => 0.05,
+            Self::Moderate => 0.15,
+            Self::Extreme => 0.30,
+        }
+    }
+
+    pub fn max_delay_ms(&self) -> u64 {
+        match self {
+            Self::Mild => 100,
+            Self::Moderate => 500,
+            Self::Extreme => 2000,
+        }
+    }
+}
+
+/// How many requests run at once and when each one starts
+#[derive(Debug, Clone, PartialEq)]
+pub struct Load {
+    /// Requests in flight at once
+    pub workers: usize,
+    /// Requests started per second across all workers; unlimited when `None`
+    pub rps: Option<f64>,
+    /// Workers join one by one over this period instead of all at once
+    pub ramp_up: Duration,
+    /// Start each request at its captured offset from the first one,
+    /// reproducing bursts and lulls
+    pub preserve_timing: bool,
+}
+
+impl Default for Load {
+    fn default() -> Self {
+        Self {
+            workers: 1,
+            rps: None,
+            ramp_up: Duration::ZERO,
+            preserve_timing: false,
+        }
+    }
+}
+
+impl Load {
+    /// When each request starts, relative to the start of the run
+    fn schedule(&self, requests: &[CapturedRequest]) -> Vec<Duration> {
+        if self.preserve_timing {
+            let Some(first) = requests.iter().map(|r| r.timestamp).min() else {
+                return Vec::new();
+            };
+            return requests
+                .iter()
+                .map(|r| (r.timestamp - first).to_std().unwrap_or_default())
+                .collect();
+        }
+
+        match self.rps.filter(|rps| *rps > 0.0) {
+            Some(rps) => (0..requests.len())
+                .map(|i| Duration::from_secs_f64(i as f64 / rps))
+                .collect(),
+            None => vec![Duration::ZERO; requests.len()],
+        }
+    }
+
+    /// When worker `k` starts taking requests
+    fn worker_start(&self, k: usize) -> Duration {
+        self.ramp_up.mul_f64(k as f64 / self.workers.max(1) as f64)
+    }
+}
+
+/// Whether `scope` - `*`, a path pattern for every method, or
+/// `METHOD pattern` - covers a `METHOD pattern` endpoint
+pub fn in_scope(scope: &str, endpoint: &str) -> bool {
+    scope == "*"
+        || scope == endpoint
+        || endpoint
+            .split_once(' ')
+            .is_some_and(|(_, pattern)| pattern == scope)
+}
+
+/// A `kind:key=value,...` spec, as schedules and network faults are given
+struct Spec<'a> {
+    text: &'a str,
+    kind: &'a str,
+    args: BTreeMap<&'a str, &'a str>,
+}
+
+impl<'a> Spec<'a> {
+    fn parse(text: &'a str) -> Result<Self> {
+        let (kind, args) = text.split_once(':').unwrap_or((text, ""));
+        let args = args
+            .split(',')
+            .filter(|arg| !arg.trim().is_empty())
+            .map(|arg| match arg.split_once('=') {
+                Some((key, value)) => Ok((key.trim(), value.trim())),
+                None => anyhow::bail!("Arguments look like key=value, got: {}", arg),
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self {
+            text,
+            kind: kind.trim(),
+            args,
+        })
+    }
+
+    fn take(&mut self, key: &str) -> Result<&'a str> {
+        self.args
+            .remove(key)
+            .ok_or_else(|| anyhow::anyhow!("{} needs {}=, got: {}", self.kind, key, self.text))
+    }
+
+    fn take_duration(&mut self, key: &str) -> Result<Duration> {
+        parse_duration(self.take(key)?).map_err(anyhow::Error::msg)
+    }
+
+    fn take_rate(&mut self, key: &str) -> Result<f64> {
+        parse_rate(self.take(key)?)
+    }
+
+    /// Fails on arguments nothing took
+    fn finish(self) -> Result<()> {
+        match self.args.keys().next() {
+            Some(key) => anyhow::bail!("{} does not take {}=, got: {}", self.kind, key, self.text),
+            None => Ok(()),
+        }
+    }
+}
+
+/// `30%` or `0.3`
+fn parse_rate(text: &str) -> Result<f64> {
+    let rate = match text.strip_suffix('%') {
+        Some(percent) => percent.parse::<f64>().map(|p| p / 100.0),
+        None => text.parse::<f64>(),
+    };
+    match rate {
+        Ok(rate) if (0.0..=1.0).contains(&rate) => Ok(rate),
+        _ => anyhow::bail!("Rates are 0-100% or 0.0-1.0, got: {}", text),
+    }
+}
+
+/// `512`, `64kb` or `2mb`
+fn parse_bytes(text: &str) -> Result<u64> {
+    let lower = text.to_ascii_lowercase();
+    let (number, scale) = if let Some(n) = lower.strip_suffix("kb") {
+        (n, 1024)
+    } else if let Some(n) = lower.strip_suffix("mb") {
+        (n, 1024 * 1024)
+    } else {
+        (lower.strip_suffix('b').unwrap_or(&lower), 1)
+    };
+    match number.trim().parse::<f64>() {
+        Ok(n) if n >= 0.0 => Ok((n * scale as f64) as u64),
+        _ => anyhow::bail!("Invalid size: {} (use bytes, kb or mb)", text),
+    }
+}
+
+/// `500ms`, `10s` or `2m`; a bare number is seconds
+pub fn parse_duration(text: &str) -> Result<Duration, String> {
+    let text = text.trim();
+    let (number, unit) = match text.find(|c: char| !c.is_ascii_digit() && c != '.') {
+        Some(i) => text.split_at(i),
+        None => (text, "s"),
+    };
+    let number: f64 = number
+        .parse()
+        .map_err(|_| format!("Invalid duration: {}", text))?;
+
+    match unit {
+        "ms" => Ok(Duration::from_secs_f64(number / 1000.0)),
+        "s" => Ok(Duration::from_secs_f64(number)),
+        "m" => Ok(Duration::from_secs_f64(number * 60.0)),
+        _ => Err(format!(
+            "Invalid duration unit in {} (use ms, s or m)",
+            text
+        )),
+    }
+}
+
+pub struct ChaosEngine {
+    storage: Storage,
+    level: ChaosLevel,
+    target_url: String,
+    load: Load,
+    expectations: Expectations,
+    hypothesis: Option<(steady::Source, BlastRadius)>,
+    schedule: Schedule,
+    network: NetworkFaults,
+    mutations: Mutations,
+    /// Copies sent in retry storms of write endpoints
+    retry_storm: Option<usize>,
+}
+
+impl ChaosEngine {
+    pub fn new(storage: Storage, level: ChaosLevel, target_url: String) -> Self {
+        Self {
+            storage,
+            level,
+            target_url,
+            load: Load::default(),
+            expectations: Expectations::default(),
+            hypothesis: None,
+            schedule: Schedule::default(),
+            network: NetworkFaults::default(),
+            mutations: Mutations::default(),
+            retry_storm: None,
+        }
+    }
+
+    pub fn with_load(mut self, load: Load) -> Self {
+        self.load = load;
+        self
+    }
+
+    /// What counts as passing under chaos, per endpoint
+    pub fn with_expectations(mut self, expectations: Expectations) -> Self {
+        self.expectations = expectations;
+        self
+    }
+
+    /// Time-based fault profiles in place of the level's constant failure
+    /// rate
+    pub fn with_schedule(mut self, schedule: Schedule) -> Self {
+        self.schedule = schedule;
+        self
+    }
+
+    /// Route requests through a TCP proxy that applies `faults` on the wire
+    pub fn with_network_faults(mut self, faults: NetworkFaults) -> Self {
+        self.network = faults;
+        self
+    }
+
+    /// Corrupt successful responses, per endpoint, before they are judged
+    pub fn with_mutations(mut self, mutations: Mutations) -> Self {
+        self.mutations = mutations;
+        self
+    }
+
+    /// After the run, re-send the first write to each POST and PATCH
+    /// endpoint as `copies` concurrent copies and as a retry after a lost
+    /// response, and check whether duplicates were created
+    pub fn with_retry_storm(mut self, copies: usize) -> Self {
+        self.retry_storm = Some(copies);
+        self
+    }
+
+    /// Measure a steady state before injecting faults and abort the run,
+    /// rolling back faults, once it drifts outside `radius`
+    pub fn with_steady_state(mut self, source: steady::Source, radius: BlastRadius) -> Self {
+        self.hypothesis = Some((source, radius));
+        self
+    }
+
+    pub async fn run_chaos_tests(&self) -> Result<ChaosReport> {
+        let requests = self.storage.get_all_requests()?;
+
+        if requests.is_empty() {
+            anyhow::bail!("No requests found in capture file");
+        }
+
+        if self.schedule.is_empty() {
+            info!("Running chaos tests with {:?} level", self.level);
+        }
+        for profile in self.schedule.profiles() {
+            info!("Fault schedule: {}", profile);
+        }
+        info!(
+            "Replaying {} requests with {} workers",
+            requests.len(),
+            self.load.workers
+        );
+
+        let route = match self.network.is_empty() {
+            true => None,
+            false => Some(Route::through_proxy(&self.target_url, &self.network).await?),
+        };
+        let injector = Arc::new(Injector {
+            level: self.level,
+            target_url: route
+                .as_ref()
+                .map_or_else(|| self.target_url.clone(), |r| r.target_url.clone()),
+            client: http_client(Duration::from_secs(10), route.as_ref())?,
+            expectations: self.expectations.clone(),
+            schedule: self.schedule.clone(),
+            route,
+            schemas: match self.mutations.is_empty() {
+                true => BTreeMap::new(),
+                false => mutate::schemas(&requests),
+            },
+            mutations: self.mutations.clone(),
+        });
+
+        let steady = match &self.hypothesis {
+            Some((steady::Source::Capture, _)) => Some(SteadyState::from_capture(&requests)),
+            Some((steady::Source::Baseline, _)) => Some(baseline(&injector, &requests).await),
+            None => None,
+        };
+        if let Some(steady) = &steady {
+            info!("Steady state: {}", steady);
+        }
+        let monitor = steady
+            .clone()
+            .zip(self.hypothesis.as_ref())
+            .map(|(steady, (_, radius))| {
+                Arc::new(Mutex::new(Monitor::new(steady, radius.clone())))
+            });
+        let aborted = Arc::new(AtomicBool::new(false));
+
+        let mut jobs: Vec<(usize, Duration)> = self
+            .load
+            .schedule(&requests)
+            .into_iter()
+            .enumerate()
+            .collect();
+        jobs.sort_by_key(|(_, offset)| *offset);
+        let jobs = Arc::new(jobs);
+        let requests = Arc::new(requests);
+        let next = Arc::new(AtomicUsize::new(0));
+        let start = Instant::now();
+
+        let mut workers = JoinSet::new();
+        for k in 0..self.load.workers.max(1) {
+            let worker_start = self.load.worker_start(k);
+            let (jobs, requests, next) = (jobs.clone(), requests.clone(), next.clone());
+            let (injector, monitor, aborted) = (injector.clone(), monitor.clone(), aborted.clone());
+
+            workers.spawn(async move {
+                tokio::time::sleep(worker_start).await;
+
+                let mut outcomes = Vec::new();
+                while !aborted.load(Ordering::Relaxed)
+                    && let Some(&(i, offset)) = jobs.get(next.fetch_add(1, Ordering::Relaxed))
+                {
+                    tokio::time::sleep_until((start + offset).into()).await;
+                    if aborted.load(Ordering::Relaxed) {
+                        break;
+                    }
+
+                    let request = &requests[i];
+                    info!(
+                        "Test {}/{}: {} {}",
+                        i + 1,
+                        requests.len(),
+                        request.request.method,
+                        request.request.uri
+                    );
+                    let outcome = injector.run(request, Some(start)).await;
+
+                    if let Some(monitor) = &monitor {
+                        let deviation = monitor.lock().unwrap().observe(outcome.sample());
+                        if let Some(deviation) = deviation {
+                            warn!(
+                                "Steady state lost: {}; rolling back faults and aborting",
+                                deviation
+                            );
+                            aborted.store(true, Ordering::Relaxed);
+                        }
+                    }
+                    outcomes.push(outcome);
+                }
+                outcomes
+            });
+        }
+
+        let mut report = ChaosReport::default();
+        let mut samples = Vec::new();
+        while let Some(outcomes) = workers.join_next().await {
+            for outcome in outcomes? {
+                samples.push(outcome.sample());
+                report.record(outcome);
+            }
+        }
+        report.total_tests = samples.len();
+        report.network = injector.route.as_ref().map(|r| r.stats.snapshot());
+        report.elapsed = start.elapsed();
+        for (name, endpoint) in report.endpoints.iter_mut() {
+            endpoint.expectations = self
+                .expectations
+                .for_endpoint(name)
+                .iter()
+                .map(|e| e.to_string())
+                .collect();
+        }
+        if let (Some((source, _)), Some(steady), Some(monitor)) =
+            (&self.hypothesis, steady, monitor)
+        {
+            report.steady_state = Some(SteadyStateReport {
+                source: *source,
+                steady,
+                observed: SteadyState::measure(&samples),
+                aborted: monitor.lock().unwrap().aborted().cloned(),
+                skipped: requests.len() - samples.len(),
+            });
+        }
+
+        if let Some(copies) = self.retry_storm
+            && !aborted.load(Ordering::Relaxed)
+        {
+            report.idempotency = Some(idempotency::storm(&injector, &requests, copies).await);
+        }
+
+        Ok(report)
+    }
+}
+
+/// Failure injected into a replayed request
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Fault {
+    Delay,
+    Timeout,
+    ConnectionError,
+    /// Every request to the endpoint fails during a scheduled window
+    Outage,
+    /// The connection failed under TCP-level faults
+    Network,
+    /// A successful response was corrupted before the client saw it
+    Mutation,
+}
+
+impl fmt::Display for Fault {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Delay => "delay",
+            Self::Timeout => "timeout",
+            Self::ConnectionError => "connection error",
+            Self::Outage => "outage",
+            Self::Network => "network",
+            Self::Mutation => "mutation",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Verdict {
+    #[default]
+    Passed,
+    /// The injected fault made the client give up, which is what it is for
+    ExpectedFailure,
+    /// The backend answered with the wrong status or not at all
+    Failed,
+}
+
+/// What happened to one replayed request
+#[derive(Debug, Default)]
+struct Outcome {
+    /// `METHOD pattern`
+    endpoint: String,
+    fault: Option<Fault>,
+    verdict: Verdict,
+    /// Time the request itself took, when one was sent
+    latency_ms: Option<u64>,
+    /// Status of the last answer, if the backend answered
+    status: Option<u16>,
+    /// `METHOD uri: reason` for requests that did not pass
+    error: Option<String>,
+    /// Re-sends it took to pass
+    retries: usize,
+    /// The mutation applied to the response, what it changed and what a
+    /// consumer validating responses would notice
+    mutation: Option<(Mutation, String, Option<String>)>,
+}
+
+impl Outcome {
+    fn sample(&self) -> Sample {
+        Sample {
+            failed: match self.status {
+                Some(status) => steady::server_error(status),
+                // Unanswered, unless the injected fault is to blame
+                None => self.verdict == Verdict::Failed,
+            },
+            // How long a request the fault cut short took says nothing
+            // about the backend
+            latency_ms: self
+                .latency_ms
+                .filter(|_| self.verdict != Verdict::ExpectedFailure),
+        }
+    }
+}
+
+/// The steady state of a fault-free replay of the first requests
+async fn baseline(injector: &Injector, requests: &[CapturedRequest]) -> SteadyState {
+    let count = requests.len().min(steady::BASELINE_REQUESTS);
+    info!("Measuring steady state over {} fault-free requests", count);
+
+    let mut samples = Vec::with_capacity(count);
+    for request in &requests[..count] {
+        samples.push(injector.run(request, None).await.sample());
+    }
+    SteadyState::measure(&samples)
+}
+
+/// Replays single requests, injecting failures at the chaos level's rate
+struct Injector {
+    level: ChaosLevel,
+    target_url: String,
+    client: reqwest::Client,
+    expectations: Expectations,
+    schedule: Schedule,
+    /// Set when requests go through the network fault proxy
+    route: Option<Route>,
+    mutations: Mutations,
+    /// Captured response schemas by endpoint, that mutated responses are
+    /// validated against
+    schemas: BTreeMap<String, Value>,
+}
+
+/// How requests reach the target through the network fault proxy
+struct Route {
+    /// The target URL on the proxy's port
+    target_url: String,
+    /// A target domain, resolved to the proxy so TLS still verifies it
+    resolve: Option<(String, SocketAddr)>,
+    /// The target's `host[:port]`, sent as the Host header
+    host: String,
+    stats: Arc<NetworkStats>,
+}
+
+impl Route {
+    async fn through_proxy(target_url: &str, faults: &NetworkFaults) -> Result<Self> {
+        let mut url = reqwest::Url::parse(target_url)?;
+        let (Some(host), Some(port)) = (url.host_str(), url.port_or_known_default()) else {
+            anyhow::bail!("Network faults need a host and port in {}", target_url);
+        };
+        let target = format!("{}:{}", host, port);
+        let host = match url.port() {
+            Some(_) => target.clone(),
+            None => host.to_string(),
+        };
+
+        let proxy = FaultProxy::new(target, faults.clone());
+        let addr = proxy.spawn().await?;
+        info!("Injecting network faults through a proxy on {}", addr);
+
+        // Resolve overrides keep the URL's port, so it is pointed at the proxy
+        // either way
+        let cannot_proxy = |_| anyhow::anyhow!("Cannot proxy {}", target_url);
+        url.set_port(Some(addr.port())).map_err(cannot_proxy)?;
+        let resolve = match url.domain() {
+            Some(domain) => Some((domain.to_string(), addr)),
+            None => {
+                url.set_ip_host(addr.ip()).map_err(cannot_proxy)?;
+                None
+            }
+        };
+
+        Ok(Self {
+            target_url: url.as_str().trim_end_matches('/').to_string(),
+            resolve,
+            host,
+            stats: proxy.stats(),
+        })
+    }
+}
+
+/// Wait before retry number `attempt`, counting from 1
+fn backoff(attempt: usize) -> Duration {
+    u32::try_from(attempt.saturating_sub(1))
+        .ok()
+        .and_then(|shift| 1u32.checked_shl(shift))
+        .and_then(|factor| RETRY_BACKOFF.checked_mul(factor))
+        .map_or(MAX_RETRY_BACKOFF, |wait| wait.min(MAX_RETRY_BACKOFF))
+}
+
+fn http_client(timeout: Duration, route: Option<&Route>) -> reqwest::Result<reqwest::Client> {
+    let mut builder = reqwest::Client::builder().timeout(timeout);
+    if let Some((domain, addr)) = route.and_then(|r| r.resolve.as_ref()) {
+        builder = builder.resolve(domain, *addr);
+    }
+    builder.build()
+}
+
+impl Injector {
+    /// Replay `request`, with faults when `run_start` says how far into
+    /// the experiment it is
+    async fn run(&self, request: &CapturedRequest, run_start: Option<Instant>) -> Outcome {
+        let mut outcome = Outcome {
+            endpoint: flow::endpoint(request),
+            ..Outcome::default()
+        };
+        let expectations = self.expectations.for_endpoint(&outcome.endpoint);
+        let captured_status = request.response.as_ref().map(|r| r.status_code);
+
+        let result = match run_start.map(|start| start.elapsed()) {
+            Some(at) if self.schedule.in_outage(&outcome.endpoint, at) => self.outage(&mut outcome),
+            Some(at) => {
+                let degradation = self.schedule.degradation(at);
+                if !degradation.is_zero() {
+                    outcome.fault = Some(Fault::Delay);
+                    tokio::time::sleep(degradation).await;
+                }
+                if self.should_inject_chaos(at) {
+                    self.inject_chaos(request, &mut outcome).await
+                } else {
+                    self.timed_replay(&self.client, request, &mut outcome).await
+                }
+            }
+            None => self.timed_replay(&self.client, request, &mut outcome).await,
+        };
+        if let (Some(start), Ok(reply)) = (run_start, &result)
+            && outcome.fault.is_none()
+        {
+            self.mutate(request, reply, start.elapsed(), &mut outcome);
+        }
+        let mut result =
+            result.and_then(|reply| expect::judge(&expectations, captured_status, &reply));
+
+        let retries = expect::retries(&expectations);
+        let mut attempt = 0;
+        while result.is_err() && attempt < retries {
+            attempt += 1;
+            tokio::time::sleep(backoff(attempt)).await;
+            info!(
+                "Retrying {} {} ({}/{})",
+                request.request.method, request.request.uri, attempt, retries
+            );
+            result = match run_start {
+                Some(start) if self.schedule.in_outage(&outcome.endpoint, start.elapsed()) => {
+                    self.outage(&mut outcome)
+                }
+                _ => self.timed_replay(&self.client, request, &mut outcome).await,
+            }
+            .and_then(|reply| expect::judge(&expectations, captured_status, &reply));
+        }
+        if result.is_ok() && attempt > 0 {
+            outcome.retries = attempt;
+        }
+
+        if let Err(e) = &result
+            && matches!(outcome.fault, None | Some(Fault::Delay))
+            && self.route.is_some()
+            && e.downcast_ref::<reqwest::Error>().is_some()
+        {
+            // Transport errors on a faulty network are the network's doing; a
+            // delay before sending cannot cause one
+            outcome.fault = Some(Fault::Network);
+        }
+
+        if let Err(e) = result {
+            let caused_by_fault = match outcome.fault {
+                // A retry expectation means the fault had to be recovered from
+                _ if retries > 0 => false,
+                Some(Fault::ConnectionError | Fault::Outage | Fault::Network) => true,
+                Some(Fault::Timeout) => e
+                    .downcast_ref::<reqwest::Error>()
+                    .is_some_and(|e| e.is_timeout()),
+                Some(Fault::Delay | Fault::Mutation) | None => false,
+            };
+            outcome.verdict = if caused_by_fault {
+                Verdict::ExpectedFailure
+            } else {
+                Verdict::Failed
+            };
+            let reason = match retries {
+                0 => e.to_string(),
+                n => format!("{} (still failing after {} retries)", e, n),
+            };
+            outcome.error = Some(format!(
+                "{} {}: {}",
+                request.request.method, request.request.uri, reason
+            ));
+        }
+        outcome
+    }
+
+    /// Chaos level's failure rate, or the schedule's `at` into the run
+    fn should_inject_chaos(&self, at: Duration) -> bool {
+        use rand::Rng as _;
+        let failure_rate = match self.schedule.is_empty() {
+            true => self.level.failure_rate(),
+            false => self.schedule.failure_rate(at),
+        };
+        let mut rng = rand::rng();
+        let random_val: f64 = rng.random();
+        random_val < failure_rate
+    }
+
+    fn outage(&self, outcome: &mut Outcome) -> Result<Reply> {
+        warn!("Scheduled outage of {}", outcome.endpoint);
+        outcome.fault = Some(Fault::Outage);
+        Err(anyhow::anyhow!("Chaos: scheduled outage"))
+    }
+
+    /// Corrupt a successful response as a consumer would receive it; the
+    /// backend is still judged on the live one
+    fn mutate(
+        &self,
+        request: &CapturedRequest,
+        reply: &Reply,
+        at: Duration,
+        outcome: &mut Outcome,
+    ) {
+        if !(200..300).contains(&reply.status) {
+            return;
+        }
+        let failure_rate = match self.schedule.is_empty() {
+            true => self.level.failure_rate(),
+            false => self.schedule.failure_rate(at),
+        };
+        let Some(mutation) = self.mutations.pick(&outcome.endpoint, failure_rate) else {
+            return;
+        };
+        let Some(mutated) = mutation.apply(
+            &reply.body,
+            reply.content_type.as_deref(),
+            request.response.as_ref(),
+        ) else {
+            return;
+        };
+
+        warn!("Mutating response: {}", mutated.change);
+        let noticed = mutated.noticed(self.schemas.get(&outcome.endpoint));
+        outcome.fault = Some(Fault::Mutation);
+        outcome.mutation = Some((mutation, mutated.change, noticed));
+    }
+
+    async fn inject_chaos(
+        &self,
+        request: &CapturedRequest,
+        outcome: &mut Outcome,
+    ) -> Result<Reply> {
+        use rand::Rng as _;
+        let (chaos_type, delay) = {
+            let mut rng = rand::rng();
+            (
+                rng.random_range(0..3),
+                rng.random_range(0..self.level.max_delay_ms()),
+            )
+        };
+
+        match chaos_type {
+            0 => {
+                warn!("Injecting delay: {}ms", delay);
+                outcome.fault = Some(Fault::Delay);
+                tokio::time::sleep(Duration::from_millis(delay)).await;
+                self.timed_replay(&self.client, request, outcome).await
+            }
+            1 => {
+                warn!("Injecting timeout");
+                outcome.fault = Some(Fault::Timeout);
+                let short_timeout = Duration::from_millis(1);
+                let short_client = http_client(short_timeout, self.route.as_ref())?;
+                self.timed_replay(&short_client, request, outcome).await
+            }
+            _ => {
+                warn!("Simulating connection error");
+                outcome.fault = Some(Fault::ConnectionError);
+                Err(anyhow::anyhow!("Chaos: simulated connection failure"))
+            }
+        }
+    }
+
+    /// A captured request aimed at the target, through the network fault
+    /// proxy when there is one
+    fn request(
+        &self,
+        client: &reqwest::Client,
+        request: &CapturedRequest,
+    ) -> reqwest::RequestBuilder {
+        let builder = build_request(client, &self.target_url, request);
+        match &self.route {
+            Some(route) => builder.header(reqwest::header::HOST, &route.host),
+            None => builder,
+        }
+    }
+
+    async fn timed_replay(
+        &self,
+        client: &reqwest::Client,
+        request: &CapturedRequest,
+        outcome: &mut Outcome,
+    ) -> Result<Reply> {
+        let started = Instant::now();
+        let response = async {
+            let response = self.request(client, request).send().await?;
+            let status = response.status().as_u16();
+            let retry_after = response
+                .headers()
+                .contains_key(reqwest::header::RETRY_AFTER);
+            let content_type = response
+                .headers()
+                .get(reqwest::header::CONTENT_TYPE)
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_string);
+            // A body cut short or throttled on the wire fails or slows here
+            let body = response.bytes().await?;
+            Ok::<_, reqwest::Error>((status, retry_after, content_type, body))
+        }
+        .await;
+        let latency = started.elapsed();
+        outcome.latency_ms = Some(latency.as_millis() as u64);
+        outcome.status = response.as_ref().ok().map(|(status, ..)| *status);
+
+        let (status, retry_after, content_type, body) = response?;
+        Ok(Reply {
+            status,
+            retry_after,
+            latency,
+            content_type,
+            body: body.to_vec(),
+        })
+    }
+}
+
+/// A captured request aimed at `target_url`, with redacted values filled in
+/// from the environment
+pub fn build_request(
+    client: &reqwest::Client,
+    target_url: &str,
+    request: &CapturedRequest,
+) -> reqwest::RequestBuilder {
+    let url = format!("{}{}", target_url, redact::resolve(&request.request.uri));
+
+    let mut req_builder = match request.request.method.as_str() {
+        "GET" => client.get(&url),
+        "POST" => client.post(&url),
+        "PUT" => client.put(&url),
+        "DELETE" => client.delete(&url),
+        "PATCH" => client.patch(&url),
+        _ => client.get(&url),
+    };
+
+    for (key, value) in generators::request_headers(request) {
+        req_builder = req_builder.header(key, redact::resolve(value));
+    }
+
+    if let Some(body) = request.request.body.as_deref().filter(|b| !b.is_empty()) {
+        req_builder = match std::str::from_utf8(body) {
+            Ok(text) => req_builder.body(redact::resolve(text)),
+            Err(_) => req_builder.body(body.to_vec()),
+        };
+    }
+
+    req_builder
+}
+
+/// Upper bounds of the latency histogram buckets in milliseconds; slower
+/// requests land in a final open bucket
+const LATENCY_BUCKETS_MS: [u64; 11] = [5, 10, 25, 50, 100, 250, 500, 1000, 2500, 5000, 10000];
+
+/// Width of the longest histogram bar
+const HISTOGRAM_WIDTH: usize = 40;
+
+/// Latencies of the requests a run sent
+#[derive(Debug, Clone, Default)]
+pub struct LatencyHistogram {
+    /// Sorted
+    samples: Vec<u64>,
+}
+
+impl LatencyHistogram {
+    pub fn record(&mut self, latency_ms: u64) {
+        let at = self.samples.partition_point(|&s| s <= latency_ms);
+        self.samples.insert(at, latency_ms);
+    }
+
+    pub fn percentile(&self, p: u8) -> Option<u64> {
+        compare::percentile(&self.samples, p)
+    }
+
+    pub fn total_ms(&self) -> u64 {
+        self.samples.iter().sum()
+    }
+
+    /// Request counts per bucket, as (upper bound, count); the last bucket
+    /// has no upper bound
+    pub fn buckets(&self) -> Vec<(Option<u64>, usize)> {
+        let mut counts = vec![0; LATENCY_BUCKETS_MS.len() + 1];
+        for &sample in &self.samples {
+            let bucket = LATENCY_BUCKETS_MS.partition_point(|&bound| bound < sample);
+            counts[bucket] += 1;
+        }
+
+        LATENCY_BUCKETS_MS
+            .iter()
+            .map(|&bound| Some(bound))
+            .chain([None])
+            .zip(counts)
+            .collect()
+    }
+
+    pub fn to_json(&self) -> Value {
+        let buckets: Vec<Value> = self
+            .buckets()
+            .into_iter()
+            .map(|(bound, count)| json!({ "le_ms": bound, "count": count }))
+            .collect();
+        json!({
+            "count": self.samples.len(),
+            "p50_ms": self.percentile(50),
+            "p95_ms": self.percentile(95),
+            "p99_ms": self.percentile(99),
+            "max_ms": self.samples.last(),
+            "buckets": buckets,
+        })
+    }
+
+    pub fn print(&self) {
+        if self.samples.is_empty() {
+            return;
+        }
+
+        println!(
+            "\nLatency (ms): p50 {}, p95 {}, p99 {}, max {}",
+            self.percentile(50).unwrap_or_default(),
+            self.percentile(95).unwrap_or_default(),
+            self.percentile(99).unwrap_or_default(),
+            self.samples.last().copied().unwrap_or_default()
+        );
+
+        let buckets = self.buckets();
+        let first = buckets.iter().position(|(_, n)| *n > 0).unwrap_or(0);
+        let last = buckets.iter().rposition(|(_, n)| *n > 0).unwrap_or(0);
+        let most = buckets.iter().map(|(_, n)| *n).max().unwrap_or(1);
+
+        for (bound, count) in &buckets[first..=last] {
+            let label = match bound {
+                Some(bound) => format!("<= {:>5}", bound),
+                None => format!(" > {:>5}", LATENCY_BUCKETS_MS[LATENCY_BUCKETS_MS.len() - 1]),
+            };
+            let bar = "#".repeat((count * HISTOGRAM_WIDTH).div_ceil(most));
+            println!(
+                "  {} | {:<width$} {}",
+                label,
+                bar,
+                count,
+                width = HISTOGRAM_WIDTH
+            );
+        }
+    }
+}
+
+/// Results for the requests of one `METHOD pattern`
+#[derive(Debug, Default)]
+pub struct EndpointReport {
+    pub requests: usize,
+    pub passed: usize,
+    pub expected_failures: usize,
+    pub failed: usize,
+    /// Failed requests that had a fault injected, as opposed to ones the
+    /// backend got wrong unprovoked
+    pub failed_under_fault: usize,
+    /// Requests that passed only after being re-sent
+    pub recovered: usize,
+    pub faults: BTreeMap<Fault, usize>,
+    pub latency: LatencyHistogram,
+    pub errors: Vec<String>,
+    /// Resilience expectations the requests were held to
+    pub expectations: Vec<String>,
+    pub mutations: BTreeMap<Mutation, MutationTally>,
+}
+
+/// Responses corrupted by one mutation and how many of them a consumer
+/// validating against the captured schema would notice
+#[derive(Debug, Default, Serialize)]
+pub struct MutationTally {
+    pub injected: usize,
+    pub noticed: usize,
+    /// Distinct changes nothing would notice, which consumers must cope
+    /// with on their own
+    pub silent: BTreeSet<String>,
+}
+
+impl EndpointReport {
+    /// `truncate 4 (4 noticed), reorder 2 (0 noticed)`
+    fn mutation_summary(&self) -> String {
+        self.mutations
+            .iter()
+            .map(|(mutation, tally)| {
+                format!(
+                    "{} {} ({} noticed)",
+                    mutation, tally.injected, tally.noticed
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    fn to_json(&self) -> Value {
+        json!({
+            "requests": self.requests,
+            "passed": self.passed,
+            "expected_failures": self.expected_failures,
+            "failed": self.failed,
+            "failed_under_fault": self.failed_under_fault,
+            "recovered": self.recovered,
+            "expectations": self.expectations,
+            "faults": self.faults,
+            "mutations": self.mutations,
+            "latency": self.latency.to_json(),
+            "errors": self.errors,
+        })
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ChaosReport {
+    pub total_tests: usize,
+    pub passed: usize,
+    pub expected_failures: usize,
+    pub failed: usize,
+    pub chaos_injected: usize,
+    pub timeouts: usize,
+    pub errors: Vec<String>,
+    pub latency: LatencyHistogram,
+    /// Wall-clock time of the whole run
+    pub elapsed: Duration,
+    pub endpoints: BTreeMap<String, EndpointReport>,
+    pub steady_state: Option<SteadyStateReport>,
+    pub network: Option<NetworkStats>,
+    pub idempotency: Option<IdempotencyReport>,
+}
+
+impl ChaosReport {
+    fn record(&mut self, outcome: Outcome) {
+        let endpoint = self.endpoints.entry(outcome.endpoint).or_default();
+        endpoint.requests += 1;
+
+        if let Some(fault) = outcome.fault {
+            self.chaos_injected += 1;
+            *endpoint.faults.entry(fault).or_default() += 1;
+        }
+        if outcome.fault == Some(Fault::Timeout) {
+            self.timeouts += 1;
+        }
+        if let Some((mutation, change, noticed)) = outcome.mutation {
+            let tally = endpoint.mutations.entry(mutation).or_default();
+            tally.injected += 1;
+            match noticed {
+                Some(_) => tally.noticed += 1,
+                None => {
+                    tally.silent.insert(change);
+                }
+            }
+        }
+        if let Some(latency_ms) = outcome.latency_ms {
+            self.latency.record(latency_ms);
+            endpoint.latency.record(latency_ms);
+        }
+
+        match outcome.verdict {
+            Verdict::Passed => {
+                self.passed += 1;
+                endpoint.passed += 1;
+                if outcome.retries > 0 {
+                    endpoint.recovered += 1;
+                }
+            }
+            Verdict::ExpectedFailure => {
+                self.expected_failures += 1;
+                endpoint.expected_failures += 1;
+            }
+            Verdict::Failed => {
+                self.failed += 1;
+                endpoint.failed += 1;
+                if outcome.fault.is_some() {
+                    endpoint.failed_under_fault += 1;
+                }
+                if let Some(error) = outcome.error {
+                    endpoint.errors.push(error.clone());
+                    self.errors.push(error);
+                }
+            }
+        }
+    }
+
+    pub fn print(&self) {
+        println!("\n=== Chaos Testing Report ===\n");
+        println!("Total Tests: {}", self.total_tests);
+        println!(
+            "Passed: {} ({:.1}%)",
+            self.passed,
+            (self.passed as f64 / self.total_tests as f64) * 100.0
+        );
+        println!(
+            "Expected Failures: {} (requests an injected fault cut short)",
+            self.expected_failures
+        );
+        println!(
+            "Failed: {} ({:.1}%)",
+            self.failed,
+            (self.failed as f64 / self.total_tests as f64) * 100.0
+        );
+        println!("Chaos Injected: {}", self.chaos_injected);
+        println!("Timeouts: {}", self.timeouts);
+        if !self.elapsed.is_zero() {
+            println!(
+                "Duration: {:.1}s ({:.1} req/s)",
+                self.elapsed.as_secs_f64(),
+                self.total_tests as f64 / self.elapsed.as_secs_f64()
+            );
+        }
+        self.latency.print();
+        if let Some(steady_state) = &self.steady_state {
+            steady_state.print();
+        }
+        if let Some(network) = &self.network {
+            network.print();
+        }
+
+        if !self.endpoints.is_empty() {
+            let width = self.endpoints.keys().map(|e| e.len()).max().unwrap_or(0);
+            println!("\nEndpoints:");
+            for (name, endpoint) in &self.endpoints {
+                let faults: Vec<String> = endpoint
+                    .faults
+                    .iter()
+                    .map(|(fault, n)| format!("{} {}", fault, n))
+                    .collect();
+                println!(
+                    "  {:<width$}  {:>4} req  {:>4} ok  {:>4} expected  {:>4} failed  p50/p95/p99 {}/{}/{}ms{}",
+                    name,
+                    endpoint.requests,
+                    endpoint.passed,
+                    endpoint.expected_failures,
+                    endpoint.failed,
+                    endpoint.latency.percentile(50).unwrap_or_default(),
+                    endpoint.latency.percentile(95).unwrap_or_default(),
+                    endpoint.latency.percentile(99).unwrap_or_default(),
+                    if faults.is_empty() {
+                        String::new()
+                    } else {
+                        format!("  faults: {}", faults.join(", "))
+                    },
+                    width = width
+                );
+                if !endpoint.expectations.is_empty() {
+                    println!(
+                        "  {:<width$}  expects {}{}",
+                        "",
+                        endpoint.expectations.join(", "),
+                        match endpoint.recovered {
+                            0 => String::new(),
+                            n => format!("; {} recovered by retrying", n),
+                        },
+                        width = width
+                    );
+                }
+            }
+        }
+
+        if let Some(idempotency) = &self.idempotency {
+            idempotency.print();
+        }
+
+        if self.endpoints.values().any(|e| !e.mutations.is_empty()) {
+            println!("\nResponse Mutations (noticed by validating against the captured schema):");
+            for (name, endpoint) in &self.endpoints {
+                if endpoint.mutations.is_empty() {
+                    continue;
+                }
+                println!("  {}: {}", name, endpoint.mutation_summary());
+                let silent: BTreeSet<&String> = endpoint
+                    .mutations
+                    .values()
+                    .flat_map(|tally| &tally.silent)
+                    .collect();
+                for change in silent.iter().take(5) {
+                    println!("    silent: {}", change);
+                }
+                if silent.len() > 5 {
+                    println!("    ... and {} more", silent.len() - 5);
+                }
+            }
+        }
+
+        for (name, endpoint) in &self.endpoints {
+            if endpoint.errors.is_empty() {
+                continue;
+            }
+            println!(
+                "\n{}: {} failed, {} under injected faults",
+                name, endpoint.failed, endpoint.failed_under_fault
+            );
+            for (i, error) in endpoint.errors.iter().take(10).enumerate() {
+                println!("  {}. {}", i + 1, error);
+            }
+            if endpoint.errors.len() > 10 {
+                println!(
+                    "  ... and {} more (see --report-format json)",
+                    endpoint.errors.len() - 10
+                );
+            }
+        }
+
+        println!("\n");
+    }
+
+    pub fn to_json(&self) -> Value {
+        let endpoints: Vec<Value> = self
+            .endpoints
+            .iter()
+            .map(|(name, endpoint)| {
+                let mut value = endpoint.to_json();
+                value["endpoint"] = json!(name);
+                value
+            })
+            .collect();
+
+        json!({
+            "summary": {
+                "requests": self.total_tests,
+                "passed": self.passed,
+                "expected_failures": self.expected_failures,
+                "failed": self.failed,
+                "chaos_injected": self.chaos_injected,
+                "timeouts": self.timeouts,
+                "duration_ms": self.elapsed.as_millis() as u64,
+                "latency": self.latency.to_json(),
+            },
+            "endpoints": endpoints,
+            "steady_state": self.steady_state,
+            "network": self.network,
+            "idempotency": self.idempotency,
+        })
+    }
+
+    /// One test case per endpoint, failing when any of its requests failed
+    /// for a reason other than the fault injected into it
+    pub fn to_junit(&self) -> String {
+        let mut tests = self.endpoints.len();
+        let mut failing = self.endpoints.values().filter(|e| e.failed > 0).count();
+        if let Some(steady_state) = &self.steady_state {
+            tests += 1;
+            if steady_state.aborted.is_some() {
+                failing += 1;
+            }
+        }
+        if let Some(idempotency) = &self.idempotency {
+            tests += idempotency.endpoints.len();
+            failing += idempotency.lacking().len();
+        }
+
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        xml.push_str(&format!(
+            "<testsuites name=\"chaos\" tests=\"{}\" failures=\"{}\" time=\"{:.3}\">\n",
+            tests,
+            failing,
+            self.elapsed.as_secs_f64()
+        ));
+        xml.push_str(&format!(
+            "  <testsuite name=\"chaos\" tests=\"{}\" failures=\"{}\" errors=\"0\" time=\"{:.3}\">\n",
+            tests,
+            failing,
+            self.elapsed.as_secs_f64()
+        ));
+
+        if let Some(steady_state) = &self.steady_state {
+            xml.push_str("    <testcase classname=\"chaos\" name=\"steady-state hypothesis\">\n");
+            if let Some(abort) = &steady_state.aborted {
+                xml.push_str(&format!(
+                    "      <failure message=\"aborted after {} requests\">{}</failure>\n",
+                    abort.after,
+                    xml_escape(&abort.deviation.to_string())
+                ));
+            }
+            xml.push_str(&format!(
+                "      <system-out>{}</system-out>\n",
+                xml_escape(&format!(
+                    "hypothesis: {}; observed: {}",
+                    steady_state.steady, steady_state.observed
+                ))
+            ));
+            xml.push_str("    </testcase>\n");
+        }
+
+        for (name, endpoint) in &self.endpoints {
+            xml.push_str(&format!(
+                "    <testcase classname=\"chaos\" name=\"{}\" time=\"{:.3}\">\n",
+                xml_escape(name),
+                endpoint.latency.total_ms() as f64 / 1000.0
+            ));
+            if endpoint.failed > 0 {
+                xml.push_str(&format!(
+                    "      <failure message=\"{} of {} requests failed ({} under injected faults)\">{}</failure>\n",
+                    endpoint.failed,
+                    endpoint.requests,
+                    endpoint.failed_under_fault,
+                    xml_escape(&endpoint.errors.join("\n"))
+                ));
+            }
+            let faults: Vec<String> = endpoint
+                .faults
+                .iter()
+                .map(|(fault, n)| format!("{} {}", fault, n))
+                .collect();
+            let mut summary = format!(
+                "requests {}, passed {}, expected failures {}, failed {}; faults: {}; latency p50 {}ms, p95 {}ms, p99 {}ms",
+                endpoint.requests,
+                endpoint.passed,
+                endpoint.expected_failures,
+                endpoint.failed,
+                if faults.is_empty() {
+                    "none".to_string()
+                } else {
+                    faults.join(", ")
+                },
+                endpoint.latency.percentile(50).unwrap_or_default(),
+                endpoint.latency.percentile(95).unwrap_or_default(),
+                endpoint.latency.percentile(99).unwrap_or_default()
+            );
+            if !endpoint.expectations.is_empty() {
+                summary.push_str(&format!(
+                    "; expects {}; recovered {}",
+                    endpoint.expectations.join(", "),
+                    endpoint.recovered
+                ));
+            }
+            if !endpoint.mutations.is_empty() {
+                summary.push_str(&format!("; mutations: {}", endpoint.mutation_summary()));
+            }
+            xml.push_str(&format!(
+                "      <system-out>{}</system-out>\n",
+                xml_escape(&summary)
+            ));
+            xml.push_str("    </testcase>\n");
+        }
+
+        for (name, endpoint) in self.idempotency.iter().flat_map(|i| &i.endpoints) {
+            xml.push_str(&format!(
+                "    <testcase classname=\"chaos.idempotency\" name=\"{}\">\n",
+                xml_escape(name)
+            ));
+            let storms: Vec<String> = endpoint
+                .storms
+                .iter()
+                .map(|storm| format!("{}: {}", storm.scenario, storm.finding))
+                .collect();
+            if endpoint.duplicated() {
+                xml.push_str(&format!(
+                    "      <failure message=\"duplicates created when retried\">{}</failure>\n",
+                    xml_escape(&storms.join("\n"))
+                ));
+            }
+            xml.push_str(&format!(
+                "      <system-out>{}</system-out>\n",
+                xml_escape(&storms.join("; "))
+            ));
+            xml.push_str("    </testcase>\n");
+        }
+
+        xml.push_str("  </testsuite>\n</testsuites>\n");
+        xml
+    }
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chaos_level_from_str() {
+        assert!(matches!(ChaosLevel::from_str("mild"), ChaosLevel::Mild));
+        assert!(matches!(
+            ChaosLevel::from_str("moderate"),
+            ChaosLevel::Moderate
+        ));
+        assert!(matches!(
+            ChaosLevel::from_str("extreme"),
+            ChaosLevel::Extreme
+        ));
+        assert!(matches!(
+            ChaosLevel::from_str("unknown"),
+            ChaosLevel::Moderate
+        ));
+    }
+
+    #[test]
+    fn test_chaos_level_failure_rate() {
+        assert_eq!(ChaosLevel::Mild.failure_rate(), 0.05);
+        assert_eq!(ChaosLevel::Moderate.failure_rate(), 0.15);
+        assert_eq!(ChaosLevel::Extreme.failure_rate(), 0.30);
+    }
+
+    #[test]
+    fn test_chaos_level_max_delay() {
+        assert_eq!(ChaosLevel::Mild.max_delay_ms(), 100);
+        assert_eq!(ChaosLevel::Moderate.max_delay_ms(), 500);
+        assert_eq!(ChaosLevel::Extreme.max_delay_ms(), 2000);
+    }
+
+    #[test]
+    fn test_chaos_report_default() {
+        let report = ChaosReport::default();
+        assert_eq!(report.total_tests, 0);
+        assert_eq!(report.passed, 0);
+        assert_eq!(report.failed, 0);
+        assert_eq!(report.chaos_injected, 0);
+        assert_eq!(report.timeouts, 0);
+        assert!(report.errors.is_empty());
+    }
+
+    #[test]
+    fn test_load_schedule() {
+        use crate::fixtures::captured;
+        use chrono::TimeDelta;
+
+        let mut requests: Vec<CapturedRequest> = (0..3)
+            .map(|i| captured("GET", &format!("/{}", i), "", 200, ""))
+            .collect();
+        let first = requests[0].timestamp;
+        requests[1].timestamp = first + TimeDelta::milliseconds(250);
+        requests[2].timestamp = first + TimeDelta::seconds(2);
+
+        assert_eq!(Load::default().schedule(&requests), vec![Duration::ZERO; 3]);
+
+        let paced = Load {
+            rps: Some(4.0),
+            ..Load::default()
+        };
+        assert_eq!(
+            paced.schedule(&requests),
+            [0, 250, 500].map(Duration::from_millis)
+        );
+
+        let timed = Load {
+            preserve_timing: true,
+            ..Load::default()
+        };
+        assert_eq!(
+            timed.schedule(&requests),
+            [0, 250, 2000].map(Duration::from_millis)
+        );
+
+        let ramped = Load {
+            workers: 4,
+            ramp_up: Duration::from_secs(2),
+            ..Load::default()
+        };
+        assert_eq!(ramped.worker_start(0), Duration::ZERO);
+        assert_eq!(ramped.worker_start(3), Duration::from_millis(1500));
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("500ms"), Ok(Duration::from_millis(500)));
+        assert_eq!(parse_duration("1.5s"), Ok(Duration::from_millis(1500)));
+        assert_eq!(parse_duration("2m"), Ok(Duration::from_secs(120)));
+        assert_eq!(parse_duration("3"), Ok(Duration::from_secs(3)));
+        assert!(parse_duration("3h").is_err());
+        assert!(parse_duration("fast").is_err());
+    }
+
+    #[test]
+    fn test_outcome_sample() {
+        let failed = |status, verdict| {
+            Outcome {
+                status,
+                verdict,
+                ..Outcome::default()
+            }
+            .sample()
+            .failed
+        };
+        assert!(failed(Some(503), Verdict::Failed));
+        assert!(failed(Some(500), Verdict::Passed));
+        // As in the capture, only server errors count
+        assert!(!failed(Some(404), Verdict::Failed));
+        assert!(failed(None, Verdict::Failed));
+        assert!(!failed(None, Verdict::ExpectedFailure));
+    }
+
+    #[test]
+    fn test_backoff() {
+        assert_eq!(backoff(1), Duration::from_millis(50));
+        assert_eq!(backoff(3), Duration::from_millis(200));
+        assert_eq!(backoff(8), MAX_RETRY_BACKOFF);
+        assert_eq!(backoff(64), MAX_RETRY_BACKOFF);
+    }
+
+    #[test]
+    fn test_latency_histogram() {
+        let mut histogram = LatencyHistogram::default();
+        for ms in [30, 3, 7, 12000, 10, 40] {
+            histogram.record(ms);
+        }
+
+        assert_eq!(histogram.percentile(50), Some(10));
+        assert_eq!(histogram.percentile(99), Some(12000));
+        let buckets = histogram.buckets();
+        assert_eq!(buckets[0], (Some(5), 1));
+        assert_eq!(buckets[1], (Some(10), 2));
+        assert_eq!(buckets[3], (Some(50), 2));
+        assert_eq!(buckets[buckets.len() - 1], (None, 1));
+        assert_eq!(buckets.iter().map(|(_, n)| n).sum::<usize>(), 6);
+    }
+
+    fn outcomes() -> ChaosReport {
+        let mut report = ChaosReport::default();
+        report.record(Outcome {
+            endpoint: "GET /users/{id}".to_string(),
+            latency_ms: Some(12),
+            retries: 1,
+            ..Outcome::default()
+        });
+        report.record(Outcome {
+            endpoint: "GET /users/{id}".to_string(),
+            fault: Some(Fault::Timeout),
+            verdict: Verdict::ExpectedFailure,
+            latency_ms: Some(1),
+            error: Some("GET /users/1: timeout".to_string()),
+            ..Outcome::default()
+        });
+        report.record(Outcome {
+            endpoint: "POST /orders".to_string(),
+            fault: Some(Fault::Delay),
+            verdict: Verdict::Failed,
+            latency_ms: Some(40),
+            error: Some("POST /orders: expected 201, got 500 & <retry>".to_string()),
+            ..Outcome::default()
+        });
+        report
+    }
+
+    #[test]
+    fn test_report_records_outcomes() {
+        let report = outcomes();
+
+        assert_eq!(
+            (report.passed, report.expected_failures, report.failed),
+            (1, 1, 1)
+        );
+        assert_eq!((report.chaos_injected, report.timeouts), (2, 1));
+        assert_eq!(report.errors.len(), 1);
+        assert_eq!(report.latency.percentile(100), Some(40));
+
+        let users = &report.endpoints["GET /users/{id}"];
+        assert_eq!((users.requests, users.passed, users.failed), (2, 1, 0));
+        assert_eq!(users.faults[&Fault::Timeout], 1);
+        assert_eq!(users.recovered, 1);
+        assert!(users.errors.is_empty());
+
+        let orders = &report.endpoints["POST /orders"];
+        assert_eq!((orders.failed, orders.failed_under_fault), (1, 1));
+        assert_eq!(orders.errors.len(), 1);
+    }
+
+    #[test]
+    fn test_report_formats() {
+        let report = outcomes();
+
+        let json = report.to_json();
+        assert_eq!(json["summary"]["expected_failures"], 1);
+        assert_eq!(json["endpoints"][0]["endpoint"], "GET /users/{id}");
+        assert_eq!(json["endpoints"][0]["faults"]["timeout"], 1);
+        assert_eq!(json["endpoints"][1]["latency"]["p50_ms"], 40);
+
+        let junit = report.to_junit();
+        assert!(junit.contains(r#"<testsuite name="chaos" tests="2" failures="1""#));
+        assert!(junit.contains(r#"<testcase classname="chaos" name="GET /users/{id}""#));
+        assert!(junit.contains(
+            r#"<failure message="1 of 1 requests failed (1 under injected faults)">POST /orders: expected 201, got 500 &amp; &lt;retry&gt;</failure>"#
+        ));
+        assert_eq!(junit.matches("<failure").count(), 1);
+    }
+
+    #[test]
+    fn test_chaos_type_selection() {
+        let level = ChaosLevel::Moderate;
+        let rate = level.failure_rate();
+        assert!(rate > 0.0 && rate < 1.0);
+    }
+}
@@ -0,0 +1,711 @@
+//! Response mutations: successful responses corrupted before the client sees
+//! them, to show what consumers of the API must cope with
+//!
+//! The backend is still judged on the live response. A mutation is reported
+//! as caught when a consumer validating responses against the schema of the
+//! captured ones would notice it, and as silent when nothing would.
+//!
+//! The `observe` and `serve` proxies apply mutations to what real clients
+//! receive through a [`Mutator`], which reports what each client did next.
+
+use super::in_scope;
+use crate::generators::flow;
+use crate::models::{CapturedRequest, ResponseData};
+use crate::parsers::http::HttpParser;
+use crate::schema;
+use anyhow::Result;
+use hyper::Uri;
+use rand::Rng as _;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::net::IpAddr;
+use std::sync::Mutex;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Mutation {
+    /// `truncate` - the body is cut off part way
+    Truncate,
+    /// `drop-field` - one field is removed from a JSON object
+    DropField,
+    /// `change-type` - one JSON value changes type, e.g. a number to a string
+    ChangeType,
+    /// `content-type` - the body is labelled as HTML or plain text
+    ContentType,
+    /// `duplicate` - one element of a JSON array appears twice
+    Duplicate,
+    /// `reorder` - the elements of a JSON array are shuffled
+    Reorder,
+    /// `stale` - the captured response is returned in place of the live one
+    Stale,
+}
+
+impl Mutation {
+    pub fn parse(name: &str) -> Result<Self> {
+        match name {
+            "truncate" => Ok(Self::Truncate),
+            "drop-field" => Ok(Self::DropField),
+            "change-type" => Ok(Self::ChangeType),
+            "content-type" => Ok(Self::ContentType),
+            "duplicate" => Ok(Self::Duplicate),
+            "reorder" => Ok(Self::Reorder),
+            "stale" => Ok(Self::Stale),
+            other => anyhow::bail!(
+                "Unknown mutation: {} (expected truncate, drop-field, change-type, content-type, duplicate, reorder or stale)",
+                other
+            ),
+        }
+    }
+
+    /// The mutated response, or None when the live one has nothing this
+    /// mutation can change
+    pub fn apply(
+        self,
+        body: &[u8],
+        content_type: Option<&str>,
+        captured: Option<&ResponseData>,
+    ) -> Option<Mutated> {
+        let unchanged = |body: Vec<u8>, change: String| Mutated {
+            body,
+            content_type: content_type.map(str::to_string),
+            change,
+        };
+
+        match self {
+            Self::Truncate if body.len() > 1 => {
+                let keep = rand::rng().random_range(1..body.len());
+                Some(unchanged(
+                    body[..keep].to_vec(),
+                    format!("truncated to {} of {} bytes", keep, body.len()),
+                ))
+            }
+            Self::Truncate => None,
+            Self::ContentType => {
+                let replacement = match content_type {
+                    Some(current) if current.contains("html") => "text/plain",
+                    _ => "text/html; charset=utf-8",
+                };
+                Some(Mutated {
+                    body: body.to_vec(),
+                    content_type: Some(replacement.to_string()),
+                    change: format!(
+                        "Content-Type {} replaced with {}",
+                        content_type.unwrap_or("(none)"),
+                        replacement
+                    ),
+                })
+            }
+            Self::Stale => {
+                let captured = captured?;
+                let stale = captured.body.clone()?;
+                Some(Mutated {
+                    change: match stale == body {
+                        true => "captured response served, identical to the live one",
+                        false => "captured response served in place of the live one",
+                    }
+                    .to_string(),
+                    body: stale,
+                    content_type: content_type_of(captured)
+                        .or(content_type)
+                        .map(str::to_string),
+                })
+            }
+            Self::DropField | Self::ChangeType | Self::Duplicate | Self::Reorder => {
+                let mut value: Value = serde_json::from_slice(body).ok()?;
+                let change = self.mutate_json(&mut value)?;
+                Some(unchanged(serde_json::to_vec(&value).ok()?, change))
+            }
+        }
+    }
+
+    fn mutate_json(self, value: &mut Value) -> Option<String> {
+        let nodes = nodes(value);
+        match self {
+            Self::DropField => {
+                let fields: Vec<&Node> = nodes.iter().filter(|n| n.field.is_some()).collect();
+                let node = pick(&fields)?;
+                let (parent, key) = node.field.as_ref()?;
+                value.pointer_mut(parent)?.as_object_mut()?.remove(key);
+                Some(format!("dropped {}", node.path))
+            }
+            Self::ChangeType => {
+                let leaves: Vec<&Node> = nodes.iter().filter(|n| n.kind == Kind::Leaf).collect();
+                let node = pick(&leaves)?;
+                let target = value.pointer_mut(&node.pointer)?;
+                let retyped = retype(target);
+                let change = format!(
+                    "{} changed from {} to {}",
+                    node.path,
+                    type_name(target),
+                    type_name(&retyped)
+                );
+                *target = retyped;
+                Some(change)
+            }
+            Self::Duplicate => {
+                let arrays: Vec<&Node> = nodes
+                    .iter()
+                    .filter(|n| matches!(n.kind, Kind::Array { len, .. } if len > 0))
+                    .collect();
+                let node = pick(&arrays)?;
+                let items = value.pointer_mut(&node.pointer)?.as_array_mut()?;
+                let i = rand::rng().random_range(0..items.len());
+                items.insert(i + 1, items[i].clone());
+                Some(format!("duplicated {}[{}]", node.path, i))
+            }
+            Self::Reorder => {
+                let arrays: Vec<&Node> = nodes
+                    .iter()
+                    .filter(|n| matches!(n.kind, Kind::Array { varied: true, .. }))
+                    .collect();
+                let node = pick(&arrays)?;
+                let items = value.pointer_mut(&node.pointer)?.as_array_mut()?;
+                let original = items.clone();
+                // Elements differ, so some order other than the original exists
+                while *items == original {
+                    use rand::seq::SliceRandom as _;
+                    items.shuffle(&mut rand::rng());
+                }
+                Some(format!("reordered {}", node.path))
+            }
+            Self::Truncate | Self::ContentType | Self::Stale => None,
+        }
+    }
+}
+
+impl fmt::Display for Mutation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Truncate => "truncate",
+            Self::DropField => "drop-field",
+            Self::ChangeType => "change-type",
+            Self::ContentType => "content-type",
+            Self::Duplicate => "duplicate",
+            Self::Reorder => "reorder",
+            Self::Stale => "stale",
+        })
+    }
+}
+
+/// A response as the client receives it after mutation
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mutated {
+    pub body: Vec<u8>,
+    pub content_type: Option<String>,
+    /// What was changed, e.g. `dropped $.user.email`
+    pub change: String,
+}
+
+impl Mutated {
+    /// What a consumer validating responses against `schema` would notice,
+    /// if anything; without a schema the endpoint never answered with JSON
+    /// and there is nothing to validate against
+    pub fn noticed(&self, schema: Option<&Value>) -> Option<String> {
+        let schema = schema?;
+        if !self.content_type.as_deref().is_some_and(is_json) {
+            return Some(format!(
+                "Content-Type {} is not JSON",
+                self.content_type.as_deref().unwrap_or("(none)")
+            ));
+        }
+        let value: Value = match serde_json::from_slice(&self.body) {
+            Ok(value) => value,
+            Err(e) => return Some(format!("invalid JSON: {}", e)),
+        };
+        schema::validate(&value, schema, schema).into_iter().next()
+    }
+}
+
+/// Mutations by endpoint scope, each with the chance of applying to a
+/// response; without one the run's failure rate applies
+#[derive(Debug, Clone, Default)]
+pub struct Mutations {
+    rules: Vec<(String, Mutation, Option<f64>)>,
+}
+
+impl Mutations {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// `ENDPOINT=MUTATION[:CHANCE]`, e.g. `GET /users/{id}=drop-field` or
+    /// `*=truncate:10%`
+    pub fn with_rule(mut self, rule: &str) -> Result<Self> {
+        let Some((scope, mutation)) = rule.rsplit_once('=') else {
+            anyhow::bail!("Mutations look like ENDPOINT=MUTATION, got: {}", rule);
+        };
+        let scope = scope.trim();
+        if scope.is_empty() {
+            anyhow::bail!("Mutations look like ENDPOINT=MUTATION, got: {}", rule);
+        }
+        let (name, chance) = match mutation.trim().split_once(':') {
+            Some((name, chance)) => (name, Some(super::parse_rate(chance)?)),
+            None => (mutation.trim(), None),
+        };
+        self.rules
+            .push((scope.to_string(), Mutation::parse(name)?, chance));
+        Ok(self)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Rolls each mutation scoped to `endpoint` and picks one of those that
+    /// came up
+    pub fn pick(&self, endpoint: &str, failure_rate: f64) -> Option<Mutation> {
+        let mut rng = rand::rng();
+        let rolled: Vec<Mutation> = self
+            .rules
+            .iter()
+            .filter(|(scope, _, _)| in_scope(scope, endpoint))
+            .filter(|(_, _, chance)| rng.random::<f64>() < chance.unwrap_or(failure_rate))
+            .map(|(_, mutation, _)| *mutation)
+            .collect();
+        pick(&rolled).copied()
+    }
+}
+
+/// Schemas of the successful JSON responses captured for each endpoint
+pub fn schemas(requests: &[CapturedRequest]) -> BTreeMap<String, Value> {
+    let mut bodies: BTreeMap<String, Vec<Value>> = BTreeMap::new();
+    for request in requests {
+        let Some(response) = &request.response else {
+            continue;
+        };
+        if !(200..300).contains(&response.status_code) {
+            continue;
+        }
+        if let Some(value) = response
+            .body
+            .as_deref()
+            .and_then(|body| serde_json::from_slice::<Value>(body).ok())
+        {
+            bodies
+                .entry(flow::endpoint(request))
+                .or_default()
+                .push(value);
+        }
+    }
+
+    bodies
+        .into_iter()
+        .filter_map(|(endpoint, values)| Some((endpoint, schema::infer_all(&values)?)))
+        .collect()
+}
+
+/// What a client did after receiving a mutated response, judged by the next
+/// request from the same address
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Reaction {
+    /// It sent the same request again
+    Retried,
+    /// It went on to a different request
+    MovedOn,
+}
+
+/// Mutated responses of one kind served to proxy clients, and their reactions
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ConsumerTally {
+    pub injected: usize,
+    pub retried: usize,
+    pub moved_on: usize,
+    /// The client sent nothing more before the proxy stopped
+    pub went_quiet: usize,
+    /// One of the changes made, e.g. `dropped $.user.email`
+    pub example: String,
+}
+
+#[derive(Default)]
+struct Served {
+    /// Latest successful live response per endpoint, served by `stale`
+    last: HashMap<String, ResponseData>,
+    /// The mutated request each client has not followed up on yet
+    awaiting: HashMap<IpAddr, (String, Mutation, String)>,
+    tallies: BTreeMap<String, BTreeMap<Mutation, ConsumerTally>>,
+}
+
+/// Mutations applied by a proxy to the responses its clients receive
+///
+/// Without a chance in the rule, every successful response in scope is
+/// mutated.
+pub struct Mutator {
+    mutations: Mutations,
+    served: Mutex<Served>,
+}
+
+impl Mutator {
+    pub fn new(mutations: Mutations) -> Self {
+        Self {
+            mutations,
+            served: Mutex::new(Served::default()),
+        }
+    }
+
+    /// Note a request arriving from `client`
+    pub fn observe(&self, client: IpAddr, method: &str, uri: &Uri) {
+        let request = format!("{} {}", method, uri);
+        let mut served = self.served.lock().unwrap();
+        let Some((endpoint, mutation, mutated)) = served.awaiting.remove(&client) else {
+            return;
+        };
+        let reaction = match mutated == request {
+            true => Reaction::Retried,
+            false => Reaction::MovedOn,
+        };
+        let tally = served
+            .tallies
+            .entry(endpoint)
+            .or_default()
+            .entry(mutation)
+            .or_default();
+        match reaction {
+            Reaction::Retried => tally.retried += 1,
+            Reaction::MovedOn => tally.moved_on += 1,
+        }
+    }
+
+    /// The response `client` should receive instead of `response`, if a
+    /// mutation comes up for it
+    pub fn mutate(
+        &self,
+        client: IpAddr,
+        method: &str,
+        uri: &Uri,
+        response: &ResponseData,
+    ) -> Option<ResponseData> {
+        if !(200..300).contains(&response.status_code) {
+            return None;
+        }
+        let endpoint = format!("{} {}", method, HttpParser::extract_endpoint_pattern(uri));
+        let mut served = self.served.lock().unwrap();
+        let previous = served.last.insert(endpoint.clone(), response.clone());
+        let mutation = self.mutations.pick(&endpoint, 1.0)?;
+        let mutated = mutation.apply(
+            response.body.as_deref().unwrap_or_default(),
+            content_type_of(response),
+            previous.as_ref(),
+        )?;
+
+        let mut headers: HashMap<String, String> = response
+            .headers
+            .iter()
+            .filter(|(name, _)| !name.eq_ignore_ascii_case("content-type"))
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect();
+        if let Some(content_type) = &mutated.content_type {
+            headers.insert("content-type".to_string(), content_type.clone());
+        }
+
+        let tally = served
+            .tallies
+            .entry(endpoint.clone())
+            .or_default()
+            .entry(mutation)
+            .or_default();
+        tally.injected += 1;
+        tally.example = mutated.change;
+        let request = format!("{} {}", method, uri);
+        served
+            .awaiting
+            .insert(client, (endpoint, mutation, request));
+
+        Some(ResponseData {
+            status_code: response.status_code,
+            headers,
+            body: Some(mutated.body),
+        })
+    }
+
+    /// Tallies by endpoint, clients still awaited counting as gone quiet
+    pub fn report(&self) -> ConsumerReport {
+        let served = self.served.lock().unwrap();
+        let mut endpoints = served.tallies.clone();
+        for (endpoint, mutation, _) in served.awaiting.values() {
+            if let Some(tally) = endpoints
+                .get_mut(endpoint)
+                .and_then(|tallies| tallies.get_mut(mutation))
+            {
+                tally.went_quiet += 1;
+            }
+        }
+        ConsumerReport { endpoints }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ConsumerReport {
+    pub endpoints: BTreeMap<String, BTreeMap<Mutation, ConsumerTally>>,
+}
+
+impl ConsumerReport {
+    pub fn print(&self) {
+        println!("\nResponse mutations served:");
+        if self.endpoints.is_empty() {
+            println!("  None");
+            return;
+        }
+        for (endpoint, tallies) in &self.endpoints {
+            println!("  {}", endpoint);
+            for (mutation, tally) in tallies {
+                println!(
+                    "    {} x{}: {} retried, {} moved on, {} went quiet (e.g. {})",
+                    mutation,
+                    tally.injected,
+                    tally.retried,
+                    tally.moved_on,
+                    tally.went_quiet,
+                    tally.example
+                );
+            }
+        }
+    }
+}
+
+fn content_type_of(response: &ResponseData) -> Option<&str> {
+    response
+        .headers
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case("content-type"))
+        .map(|(_, value)| value.as_str())
+}
+
+fn is_json(content_type: &str) -> bool {
+    content_type.to_ascii_lowercase().contains("json")
+}
+
+fn pick<T>(items: &[T]) -> Option<&T> {
+    match items.len() {
+        0 => None,
+        n => items.get(rand::rng().random_range(0..n)),
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Kind {
+    Leaf,
+    Object,
+    Array { len: usize, varied: bool },
+}
+
+/// A value inside a JSON document
+struct Node {
+    /// `$.items[0].id`
+    path: String,
+    /// `/items/0/id`
+    pointer: String,
+    /// Pointer to the parent object and the key, for object fields
+    field: Option<(String, String)>,
+    kind: Kind,
+}
+
+fn nodes(value: &Value) -> Vec<Node> {
+    fn walk(
+        value: &Value,
+        path: String,
+        pointer: String,
+        field: Option<(String, String)>,
+        out: &mut Vec<Node>,
+    ) {
+        let kind = match value {
+            Value::Object(fields) => {
+                for (key, child) in fields {
+                    let escaped = key.replace('~', "~0").replace('/', "~1");
+                    walk(
+                        child,
+                        format!("{}.{}", path, key),
+                        format!("{}/{}", pointer, escaped),
+                        Some((pointer.clone(), key.clone())),
+                        out,
+                    );
+                }
+                Kind::Object
+            }
+            Value::Array(items) => {
+                for (i, child) in items.iter().enumerate() {
+                    walk(
+                        child,
+                        format!("{}[{}]", path, i),
+                        format!("{}/{}", pointer, i),
+                        None,
+                        out,
+                    );
+                }
+                Kind::Array {
+                    len: items.len(),
+                    varied: items.iter().any(|item| *item != items[0]),
+                }
+            }
+            _ => Kind::Leaf,
+        };
+        out.push(Node {
+            path,
+            pointer,
+            field,
+            kind,
+        });
+    }
+
+    let mut out = Vec::new();
+    walk(value, "$".to_string(), String::new(), None, &mut out);
+    out
+}
+
+/// The same value as a different JSON type
+fn retype(value: &Value) -> Value {
+    match value {
+        Value::String(s) => Value::from(s.len()),
+        Value::Number(n) => Value::String(n.to_string()),
+        Value::Bool(b) => Value::String(b.to_string()),
+        Value::Null => Value::Object(Default::default()),
+        Value::Array(_) | Value::Object(_) => Value::Null,
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const BODY: &str = r#"{"id":7,"name":"Ada","tags":["a","b","c"],"active":true}"#;
+
+    fn mutate(mutation: Mutation) -> (Value, Mutated) {
+        let mutated = mutation
+            .apply(BODY.as_bytes(), Some("application/json"), None)
+            .unwrap();
+        (serde_json::from_slice(&mutated.body).unwrap(), mutated)
+    }
+
+    #[test]
+    fn test_mutator_reactions() {
+        use crate::fixtures::captured;
+
+        let mutator = Mutator::new(
+            Mutations::new()
+                .with_rule("GET /users/{id}=drop-field")
+                .unwrap(),
+        );
+        let (ada, bob): (IpAddr, IpAddr) =
+            ("10.0.0.1".parse().unwrap(), "10.0.0.2".parse().unwrap());
+        let (user, orders): (Uri, Uri) = ("/users/7".parse().unwrap(), "/orders".parse().unwrap());
+        let response = captured("GET", "/users/7", "", 200, BODY).response.unwrap();
+
+        mutator.observe(ada, "GET", &user);
+        let mutated = mutator.mutate(ada, "GET", &user, &response).unwrap();
+        let body: Value = serde_json::from_slice(mutated.body.as_deref().unwrap()).unwrap();
+        assert_eq!(body.as_object().unwrap().len(), 3);
+        // Ada retries, Bob never comes back
+        mutator.observe(ada, "GET", &user);
+        assert!(mutator.mutate(ada, "GET", &user, &response).is_some());
+        mutator.observe(ada, "GET", &orders);
+        assert!(mutator.mutate(ada, "GET", &orders, &response).is_none());
+        assert!(mutator.mutate(bob, "GET", &user, &response).is_some());
+
+        let error = captured("GET", "/users/8", "", 404, BODY).response.unwrap();
+        assert!(mutator.mutate(bob, "GET", &user, &error).is_none());
+
+        let report = mutator.report();
+        let tally = &report.endpoints["GET /users/{id}"][&Mutation::DropField];
+        assert_eq!(
+            (
+                tally.injected,
+                tally.retried,
+                tally.moved_on,
+                tally.went_quiet
+            ),
+            (3, 1, 1, 1)
+        );
+    }
+
+    #[test]
+    fn test_parse_rules() {
+        let mutations = Mutations::new()
+            .with_rule("GET /users/{id}=drop-field")
+            .unwrap()
+            .with_rule("* = truncate:100%")
+            .unwrap();
+
+        assert_eq!(
+            mutations.pick("POST /orders", 0.0),
+            Some(Mutation::Truncate)
+        );
+        assert!(mutations.pick("GET /users/{id}", 1.0).is_some());
+        assert!(Mutations::new().pick("GET /users/{id}", 1.0).is_none());
+
+        assert!(Mutations::new().with_rule("truncate").is_err());
+        assert!(Mutations::new().with_rule("*=scramble").is_err());
+        assert!(Mutations::new().with_rule("*=reorder:150%").is_err());
+    }
+
+    #[test]
+    fn test_mutations() {
+        let original: Value = serde_json::from_str(BODY).unwrap();
+        let schema = schema::infer(&original);
+
+        let (value, mutated) = mutate(Mutation::DropField);
+        assert_eq!(value.as_object().unwrap().len(), 3);
+        assert!(mutated.change.starts_with("dropped $."));
+        assert!(mutated.noticed(Some(&schema)).is_some());
+
+        let (_, mutated) = mutate(Mutation::ChangeType);
+        assert!(mutated.change.contains(" changed from "));
+        assert!(mutated.noticed(Some(&schema)).is_some());
+
+        let (value, mutated) = mutate(Mutation::Reorder);
+        assert_eq!(mutated.change, "reordered $.tags");
+        assert_ne!(value["tags"], original["tags"]);
+        assert_eq!(value["tags"].as_array().unwrap().len(), 3);
+        // Reordering keeps the response valid, so only consumers relying on
+        // the order break
+        assert!(mutated.noticed(Some(&schema)).is_none());
+
+        let (value, _) = mutate(Mutation::Duplicate);
+        assert_eq!(value["tags"].as_array().unwrap().len(), 4);
+
+        let truncated = Mutation::Truncate
+            .apply(BODY.as_bytes(), Some("application/json"), None)
+            .unwrap();
+        assert!(truncated.body.len() < BODY.len());
+        assert!(
+            truncated
+                .noticed(Some(&schema))
+                .unwrap()
+                .starts_with("invalid JSON")
+        );
+
+        let relabelled = Mutation::ContentType
+            .apply(BODY.as_bytes(), Some("application/json"), None)
+            .unwrap();
+        assert_eq!(relabelled.body, BODY.as_bytes());
+        assert!(relabelled.noticed(Some(&schema)).is_some());
+        assert!(relabelled.noticed(None).is_none());
+
+        let captured = ResponseData {
+            status_code: 200,
+            headers: Default::default(),
+            body: Some(json!({"id": 7}).to_string().into_bytes()),
+        };
+        let stale = Mutation::Stale
+            .apply(BODY.as_bytes(), Some("application/json"), Some(&captured))
+            .unwrap();
+        assert_eq!(stale.body, captured.body.unwrap());
+        assert_eq!(stale.content_type.as_deref(), Some("application/json"));
+
+        // Nothing to change in a scalar body
+        assert!(Mutation::DropField.apply(b"42", None, None).is_none());
+        assert!(Mutation::Reorder.apply(b"[1,1]", None, None).is_none());
+    }
+}
@@ -0,0 +1,450 @@
+//! TCP-level faults: a proxy between the replayer and the target that
+//! throttles, stalls, resets and abandons connections
+//!
+//! HTTP-level faults never reach the wire. These do, so they reproduce what
+//! a flaky network does to clients: slow bodies, reads that hang mid-response,
+//! connections reset part way and peers that vanish without a FIN.
+
+use super::Spec;
+use anyhow::Result;
+use serde::Serialize;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tracing::{debug, warn};
+
+/// Largest read relayed at once
+const CHUNK: usize = 16 * 1024;
+
+/// Throttled relays send at most this many chunks per second, so a rate is
+/// spread over the second rather than sent in one burst
+const THROTTLE_TICKS: u64 = 10;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum NetworkFault {
+    /// `bandwidth:rate=64kb` - bytes per second, per connection and direction
+    Bandwidth(u64),
+    /// `stall:chance=5%,for=2s` - a relayed chunk waits this long first
+    Stall { chance: f64, lasting: Duration },
+    /// `reset:after=4kb[,chance=50%]` - the connection is reset once this
+    /// many bytes have been relayed
+    Reset { after: u64, chance: f64 },
+    /// `half-open:chance=5%` - the connection is accepted and read from but
+    /// never answered or closed
+    HalfOpen(f64),
+    /// `delay-accept:for=200ms` - wait before serving an accepted connection
+    DelayAccept(Duration),
+    /// `delay-connect:for=200ms` - wait before connecting to the target
+    DelayConnect(Duration),
+}
+
+impl NetworkFault {
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut spec = Spec::parse(text)?;
+        let fault = match spec.kind {
+            "bandwidth" => match super::parse_bytes(spec.take("rate")?)? {
+                0 => anyhow::bail!("bandwidth needs rate= above zero, got: {}", text),
+                rate => Self::Bandwidth(rate),
+            },
+            "stall" => Self::Stall {
+                chance: spec.take_rate("chance")?,
+                lasting: spec.take_duration("for")?,
+            },
+            "reset" => Self::Reset {
+                after: super::parse_bytes(spec.take("after")?)?,
+                chance: match spec.take("chance") {
+                    Ok(rate) => super::parse_rate(rate)?,
+                    Err(_) => 1.0,
+                },
+            },
+            "half-open" => Self::HalfOpen(spec.take_rate("chance")?),
+            "delay-accept" => Self::DelayAccept(spec.take_duration("for")?),
+            "delay-connect" => Self::DelayConnect(spec.take_duration("for")?),
+            _ => anyhow::bail!(
+                "Unknown network fault: {} (expected bandwidth, stall, reset, half-open, delay-accept or delay-connect)",
+                text
+            ),
+        };
+        spec.finish()?;
+        Ok(fault)
+    }
+}
+
+/// Every network fault applied to proxied connections
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NetworkFaults {
+    pub bandwidth: Option<u64>,
+    pub stall: Option<(f64, Duration)>,
+    pub reset: Option<(u64, f64)>,
+    pub half_open: f64,
+    pub accept_delay: Duration,
+    pub connect_delay: Duration,
+}
+
+impl NetworkFaults {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Later faults of the same kind replace earlier ones
+    pub fn with_fault(mut self, spec: &str) -> Result<Self> {
+        match NetworkFault::parse(spec)? {
+            NetworkFault::Bandwidth(rate) => self.bandwidth = Some(rate),
+            NetworkFault::Stall { chance, lasting } => self.stall = Some((chance, lasting)),
+            NetworkFault::Reset { after, chance } => self.reset = Some((after, chance)),
+            NetworkFault::HalfOpen(chance) => self.half_open = chance,
+            NetworkFault::DelayAccept(delay) => self.accept_delay = delay,
+            NetworkFault::DelayConnect(delay) => self.connect_delay = delay,
+        }
+        Ok(self)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// What the proxy did to the connections it carried
+#[derive(Debug, Default, Serialize)]
+pub struct NetworkStats {
+    pub connections: AtomicUsize,
+    pub half_open: AtomicUsize,
+    pub resets: AtomicUsize,
+    pub stalls: AtomicUsize,
+    pub bytes: AtomicU64,
+}
+
+impl NetworkStats {
+    /// The counts so far
+    pub fn snapshot(&self) -> Self {
+        let load = |n: &AtomicUsize| AtomicUsize::new(n.load(Ordering::Relaxed));
+        Self {
+            connections: load(&self.connections),
+            half_open: load(&self.half_open),
+            resets: load(&self.resets),
+            stalls: load(&self.stalls),
+            bytes: AtomicU64::new(self.bytes.load(Ordering::Relaxed)),
+        }
+    }
+
+    pub fn print(&self) {
+        println!("\nNetwork Faults:");
+        println!(
+            "  Connections: {} ({} bytes relayed)",
+            self.connections.load(Ordering::Relaxed),
+            self.bytes.load(Ordering::Relaxed)
+        );
+        println!("  Half-open: {}", self.half_open.load(Ordering::Relaxed));
+        println!("  Resets: {}", self.resets.load(Ordering::Relaxed));
+        println!("  Stalls: {}", self.stalls.load(Ordering::Relaxed));
+    }
+}
+
+/// Proxies TCP connections to `target`, applying `faults` to each
+pub struct FaultProxy {
+    faults: Arc<NetworkFaults>,
+    target: String,
+    stats: Arc<NetworkStats>,
+}
+
+impl FaultProxy {
+    /// `target` is `host:port`
+    pub fn new(target: String, faults: NetworkFaults) -> Self {
+        Self {
+            faults: Arc::new(faults),
+            target,
+            stats: Arc::new(NetworkStats::default()),
+        }
+    }
+
+    pub fn stats(&self) -> Arc<NetworkStats> {
+        Arc::clone(&self.stats)
+    }
+
+    /// Listen on an ephemeral localhost port and proxy in the background
+    /// until the runtime shuts down
+    pub async fn spawn(&self) -> Result<SocketAddr> {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).await?;
+        let addr = listener.local_addr()?;
+        let (faults, target, stats) = (
+            Arc::clone(&self.faults),
+            self.target.clone(),
+            Arc::clone(&self.stats),
+        );
+
+        tokio::spawn(async move {
+            loop {
+                let client = match listener.accept().await {
+                    Ok((client, _)) => client,
+                    Err(e) => {
+                        warn!("Fault proxy stopped accepting: {}", e);
+                        return;
+                    }
+                };
+                let (faults, target, stats) =
+                    (Arc::clone(&faults), target.clone(), Arc::clone(&stats));
+                tokio::spawn(async move {
+                    if let Err(e) = serve(client, &target, &faults, &stats).await {
+                        debug!("Proxied connection ended: {}", e);
+                    }
+                });
+            }
+        });
+
+        Ok(addr)
+    }
+}
+
+fn chance(probability: f64) -> bool {
+    use rand::Rng as _;
+    probability > 0.0 && rand::rng().random::<f64>() < probability
+}
+
+async fn serve(
+    client: TcpStream,
+    target: &str,
+    faults: &NetworkFaults,
+    stats: &NetworkStats,
+) -> io::Result<()> {
+    stats.connections.fetch_add(1, Ordering::Relaxed);
+    tokio::time::sleep(faults.accept_delay).await;
+
+    if chance(faults.half_open) {
+        stats.half_open.fetch_add(1, Ordering::Relaxed);
+        return hold(client).await;
+    }
+
+    tokio::time::sleep(faults.connect_delay).await;
+    let server = TcpStream::connect(target).await?;
+
+    let reset_after = faults
+        .reset
+        .filter(|(_, probability)| chance(*probability))
+        .map(|(after, _)| after);
+    if reset_after.is_some() {
+        // Dropping the socket then sends RST instead of FIN
+        client.set_linger(Some(Duration::ZERO))?;
+    }
+
+    let relayed = AtomicU64::new(0);
+    let (mut client, mut server) = (client, server);
+    // Borrowed halves: owned ones shut down on drop, and the FIN would reach
+    // the client before the RST
+    let (client_read, client_write) = client.split();
+    let (server_read, server_write) = server.split();
+    let result = tokio::try_join!(
+        relay(
+            client_read,
+            server_write,
+            faults,
+            stats,
+            &relayed,
+            reset_after
+        ),
+        relay(
+            server_read,
+            client_write,
+            faults,
+            stats,
+            &relayed,
+            reset_after
+        ),
+    );
+
+    if let Err(e) = &result
+        && e.kind() == io::ErrorKind::ConnectionReset
+        && reset_after.is_some()
+    {
+        stats.resets.fetch_add(1, Ordering::Relaxed);
+    }
+    result.map(|_| ())
+}
+
+/// Read and discard until the client gives up, never answering
+async fn hold(mut client: TcpStream) -> io::Result<()> {
+    let mut buf = [0; 1024];
+    while client.read(&mut buf).await? > 0 {}
+    // Even after the client's FIN nothing is sent back
+    std::future::pending().await
+}
+
+async fn relay(
+    mut from: impl AsyncRead + Unpin,
+    mut to: impl AsyncWrite + Unpin,
+    faults: &NetworkFaults,
+    stats: &NetworkStats,
+    relayed: &AtomicU64,
+    reset_after: Option<u64>,
+) -> io::Result<()> {
+    let chunk = faults
+        .bandwidth
+        .map(|rate| (rate / THROTTLE_TICKS).clamp(1, CHUNK as u64) as usize)
+        .unwrap_or(CHUNK);
+    let mut buf = vec![0; chunk];
+
+    loop {
+        let mut n = from.read(&mut buf).await?;
+        if n == 0 {
+            return to.shutdown().await;
+        }
+
+        if let Some((probability, lasting)) = faults.stall
+            && chance(probability)
+        {
+            stats.stalls.fetch_add(1, Ordering::Relaxed);
+            tokio::time::sleep(lasting).await;
+        }
+
+        let before = relayed.fetch_add(n as u64, Ordering::Relaxed);
+        let reset = reset_after.is_some_and(|after| before + n as u64 >= after);
+        if let Some(after) = reset_after.filter(|_| reset) {
+            n = after.saturating_sub(before) as usize;
+        }
+
+        to.write_all(&buf[..n]).await?;
+        stats.bytes.fetch_add(n as u64, Ordering::Relaxed);
+        if reset {
+            warn!("Resetting connection after {} bytes", before + n as u64);
+            return Err(io::Error::new(
+                io::ErrorKind::ConnectionReset,
+                "Chaos: injected connection reset",
+            ));
+        }
+
+        if let Some(rate) = faults.bandwidth {
+            tokio::time::sleep(Duration::from_secs_f64(n as f64 / rate as f64)).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    /// A target sending `size` bytes to every connection, then closing it
+    async fn target(size: usize) -> String {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let _ = stream.write_all(&vec![b'x'; size]).await;
+                });
+            }
+        });
+        addr.to_string()
+    }
+
+    /// Connect through a proxy applying `faults` and read until the end
+    async fn read_through(
+        faults: &str,
+        size: usize,
+    ) -> (Arc<NetworkStats>, usize, io::Result<()>, Duration) {
+        let proxy = FaultProxy::new(
+            target(size).await,
+            NetworkFaults::new().with_fault(faults).unwrap(),
+        );
+        let addr = proxy.spawn().await.unwrap();
+        let mut client = TcpStream::connect(addr).await.unwrap();
+
+        let started = Instant::now();
+        let mut received = 0;
+        let mut buf = [0; 1024];
+        let result = tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                match client.read(&mut buf).await? {
+                    0 => return Ok(()),
+                    n => received += n,
+                }
+            }
+        })
+        .await
+        .expect("proxy never finished the connection");
+        (proxy.stats(), received, result, started.elapsed())
+    }
+
+    #[tokio::test]
+    async fn test_reset_after_bytes() {
+        let (stats, received, result, _) = read_through("reset:after=100", 1000).await;
+        // The RST may discard what was relayed before it
+        assert!(received <= 100, "{}", received);
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::ConnectionReset);
+        let stats = stats.snapshot();
+        assert_eq!(stats.resets.into_inner(), 1);
+        assert_eq!(stats.bytes.into_inner(), 100);
+    }
+
+    #[tokio::test]
+    async fn test_bandwidth_throttles() {
+        let (stats, received, result, elapsed) = read_through("bandwidth:rate=1kb", 512).await;
+        result.unwrap();
+        assert_eq!(received, 512);
+        // Half a second at 1kb/s, less the last tick's sleep racing the close
+        assert!(elapsed >= Duration::from_millis(400), "{:?}", elapsed);
+        let stats = stats.snapshot();
+        assert_eq!(stats.connections.into_inner(), 1);
+        assert_eq!(stats.bytes.into_inner(), 512);
+        assert_eq!(stats.resets.into_inner(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_half_open_never_answers() {
+        let proxy = FaultProxy::new(
+            target(10).await,
+            NetworkFaults::new()
+                .with_fault("half-open:chance=100%")
+                .unwrap(),
+        );
+        let mut client = TcpStream::connect(proxy.spawn().await.unwrap())
+            .await
+            .unwrap();
+        client.write_all(b"GET / HTTP/1.1\r\n\r\n").await.unwrap();
+
+        let mut buf = [0; 16];
+        let read = tokio::time::timeout(Duration::from_millis(300), client.read(&mut buf)).await;
+        assert!(read.is_err(), "half-open connection answered: {:?}", read);
+        let stats = proxy.stats().snapshot();
+        assert_eq!(stats.half_open.into_inner(), 1);
+        assert_eq!(stats.bytes.into_inner(), 0);
+    }
+
+    #[test]
+    fn test_parse_network_faults() {
+        let faults = NetworkFaults::new()
+            .with_fault("bandwidth:rate=64kb")
+            .unwrap()
+            .with_fault("stall:chance=5%,for=2s")
+            .unwrap()
+            .with_fault("reset:after=4kb")
+            .unwrap()
+            .with_fault("half-open:chance=0.1")
+            .unwrap()
+            .with_fault("delay-connect:for=150ms")
+            .unwrap();
+
+        assert_eq!(faults.bandwidth, Some(64 * 1024));
+        assert_eq!(faults.stall, Some((0.05, Duration::from_secs(2))));
+        assert_eq!(faults.reset, Some((4096, 1.0)));
+        assert_eq!(faults.half_open, 0.1);
+        assert_eq!(faults.connect_delay, Duration::from_millis(150));
+        assert_eq!(faults.accept_delay, Duration::ZERO);
+        assert!(NetworkFaults::new().is_empty());
+
+        assert_eq!(
+            NetworkFault::parse("reset:after=100,chance=25%").unwrap(),
+            NetworkFault::Reset {
+                after: 100,
+                chance: 0.25
+            }
+        );
+        assert!(NetworkFault::parse("bandwidth:rate=0").is_err());
+        assert!(NetworkFault::parse("bandwidth:rate=fast").is_err());
+        assert!(NetworkFault::parse("stall:chance=5%").is_err());
+        assert!(NetworkFault::parse("jitter:for=1s").is_err());
+    }
+}
@@ -0,0 +1,254 @@
+//! Time-based fault profiles, consulted by elapsed time into the run
+//!
+//! A schedule replaces the chaos level's constant failure rate. The rate at a
+//! moment is the highest of the ramps and bursts active then, outages fail
+//! every request in their scope, and degradations add latency before sending.
+
+use super::{Spec, in_scope};
+use anyhow::Result;
+use std::fmt;
+use std::time::Duration;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Profile {
+    /// `ramp:to=30%,over=5m` - failure rate grows from 0 to `to` over `over`,
+    /// then holds
+    Ramp { to: f64, over: Duration },
+    /// `burst:rate=50%,every=1m,for=10s` - failure rate jumps to `rate` for
+    /// the first `for` of every `every`
+    Burst {
+        rate: f64,
+        every: Duration,
+        lasting: Duration,
+    },
+    /// `outage:from=2m,until=3m[,endpoint=GET /users/{id}]` - every request
+    /// in scope fails inside the window
+    Outage {
+        from: Duration,
+        until: Duration,
+        scope: String,
+    },
+    /// `degrade:to=500ms,over=5m` - delay before each request grows from 0 to
+    /// `to` over `over`, then holds
+    Degrade { to: Duration, over: Duration },
+}
+
+impl Profile {
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut spec = Spec::parse(text)?;
+        let profile = match spec.kind {
+            "ramp" => Self::Ramp {
+                to: spec.take_rate("to")?,
+                over: spec.take_duration("over")?,
+            },
+            "burst" => Self::Burst {
+                rate: spec.take_rate("rate")?,
+                every: spec.take_duration("every")?,
+                lasting: spec.take_duration("for")?,
+            },
+            "outage" => Self::Outage {
+                from: spec.take_duration("from")?,
+                until: spec.take_duration("until")?,
+                scope: spec.take("endpoint").unwrap_or("*").to_string(),
+            },
+            "degrade" => Self::Degrade {
+                to: spec.take_duration("to")?,
+                over: spec.take_duration("over")?,
+            },
+            _ => anyhow::bail!(
+                "Unknown schedule: {} (expected ramp, burst, outage or degrade)",
+                text
+            ),
+        };
+        spec.finish()?;
+
+        match &profile {
+            Self::Burst { every, .. } if every.is_zero() => {
+                anyhow::bail!("burst needs every= above zero, got: {}", text)
+            }
+            Self::Outage { from, until, .. } if until <= from => {
+                anyhow::bail!("outage must end after it starts, got: {}", text)
+            }
+            _ => {}
+        }
+        Ok(profile)
+    }
+}
+
+impl fmt::Display for Profile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Ramp { to, over } => {
+                write!(f, "ramp to {:.0}% over {}s", to * 100.0, over.as_secs_f64())
+            }
+            Self::Burst {
+                rate,
+                every,
+                lasting,
+            } => write!(
+                f,
+                "{:.0}% bursts for {}s every {}s",
+                rate * 100.0,
+                lasting.as_secs_f64(),
+                every.as_secs_f64()
+            ),
+            Self::Outage { from, until, scope } => write!(
+                f,
+                "outage of {} from {}s to {}s",
+                scope,
+                from.as_secs_f64(),
+                until.as_secs_f64()
+            ),
+            Self::Degrade { to, over } => write!(
+                f,
+                "latency degrading to {}ms over {}s",
+                to.as_millis(),
+                over.as_secs_f64()
+            ),
+        }
+    }
+}
+
+/// How far through `over` the run is, from 0.0 to 1.0
+fn progress(at: Duration, over: Duration) -> f64 {
+    if over.is_zero() {
+        1.0
+    } else {
+        (at.as_secs_f64() / over.as_secs_f64()).min(1.0)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Schedule {
+    profiles: Vec<Profile>,
+}
+
+impl Schedule {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_profile(mut self, spec: &str) -> Result<Self> {
+        self.profiles.push(Profile::parse(spec)?);
+        Ok(self)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.profiles.is_empty()
+    }
+
+    pub fn profiles(&self) -> &[Profile] {
+        &self.profiles
+    }
+
+    /// Chance of injecting a fault `at` into the run
+    pub fn failure_rate(&self, at: Duration) -> f64 {
+        self.profiles
+            .iter()
+            .map(|profile| match profile {
+                Profile::Ramp { to, over } => to * progress(at, *over),
+                Profile::Burst {
+                    rate,
+                    every,
+                    lasting,
+                } => {
+                    let into_period = at.as_nanos() % every.as_nanos();
+                    if into_period < lasting.as_nanos() {
+                        *rate
+                    } else {
+                        0.0
+                    }
+                }
+                Profile::Outage { .. } | Profile::Degrade { .. } => 0.0,
+            })
+            .fold(0.0, f64::max)
+    }
+
+    /// Whether `endpoint` is down `at` into the run
+    pub fn in_outage(&self, endpoint: &str, at: Duration) -> bool {
+        self.profiles.iter().any(|profile| match profile {
+            Profile::Outage { from, until, scope } => {
+                (*from..*until).contains(&at) && in_scope(scope, endpoint)
+            }
+            _ => false,
+        })
+    }
+
+    /// Delay added before a request `at` into the run
+    pub fn degradation(&self, at: Duration) -> Duration {
+        self.profiles
+            .iter()
+            .filter_map(|profile| match profile {
+                Profile::Degrade { to, over } => Some(to.mul_f64(progress(at, *over))),
+                _ => None,
+            })
+            .max()
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn secs(s: u64) -> Duration {
+        Duration::from_secs(s)
+    }
+
+    #[test]
+    fn test_parse_profiles() {
+        assert_eq!(
+            Profile::parse("ramp:to=30%,over=5m").unwrap(),
+            Profile::Ramp {
+                to: 0.3,
+                over: secs(300)
+            }
+        );
+        assert_eq!(
+            Profile::parse("outage:from=2m, until=3m, endpoint=GET /users/{id}").unwrap(),
+            Profile::Outage {
+                from: secs(120),
+                until: secs(180),
+                scope: "GET /users/{id}".to_string()
+            }
+        );
+        assert_eq!(
+            Profile::parse("burst:rate=0.5,every=1m,for=10s")
+                .unwrap()
+                .to_string(),
+            "50% bursts for 10s every 60s"
+        );
+
+        assert!(Profile::parse("ramp:to=30%").is_err());
+        assert!(Profile::parse("ramp:to=130%,over=1m").is_err());
+        assert!(Profile::parse("degrade:to=1s,over=1m,rate=5%").is_err());
+        assert!(Profile::parse("outage:from=3m,until=2m").is_err());
+        assert!(Profile::parse("burst:rate=5%,every=0s,for=1s").is_err());
+        assert!(Profile::parse("flood:rate=5%").is_err());
+    }
+
+    #[test]
+    fn test_schedule_by_elapsed_time() {
+        let schedule = Schedule::new()
+            .with_profile("ramp:to=40%,over=100s")
+            .unwrap()
+            .with_profile("burst:rate=90%,every=60s,for=5s")
+            .unwrap()
+            .with_profile("outage:from=30s,until=40s,endpoint=/users/{id}")
+            .unwrap()
+            .with_profile("degrade:to=1s,over=10s")
+            .unwrap();
+
+        assert_eq!(schedule.failure_rate(secs(0)), 0.9);
+        assert_eq!(schedule.failure_rate(secs(50)), 0.2);
+        assert_eq!(schedule.failure_rate(secs(62)), 0.9);
+        assert_eq!(schedule.failure_rate(secs(500)), 0.4);
+
+        assert!(schedule.in_outage("GET /users/{id}", secs(30)));
+        assert!(!schedule.in_outage("GET /users/{id}", secs(40)));
+        assert!(!schedule.in_outage("GET /orders", secs(35)));
+
+        assert_eq!(schedule.degradation(secs(5)), Duration::from_millis(500));
+        assert_eq!(schedule.degradation(secs(60)), secs(1));
+    }
+}
@@ -0,0 +1,363 @@
+//! Steady-state hypothesis: the error rate and latency the system shows
+//! without faults, held against what it shows while faults are injected
+//!
+//! Only backend failures count as errors. Requests an injected fault cut
+//! short are the experiment working, not the system degrading.
+
+use crate::compare;
+use crate::models::CapturedRequest;
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::fmt;
+
+/// Recent requests the running error rate and latency are taken over
+const WINDOW: usize = 20;
+
+/// Requests seen before the hypothesis is first checked
+const MIN_SAMPLES: usize = 10;
+
+/// Latency growth below this many milliseconds is never a deviation, so
+/// single-digit baselines do not abort on noise
+const LATENCY_SLACK_MS: u64 = 50;
+
+/// Most requests replayed without faults to measure a baseline
+pub const BASELINE_REQUESTS: usize = 50;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Source {
+    /// Status codes and durations recorded in the capture
+    Capture,
+    /// A fault-free replay before the experiment
+    Baseline,
+}
+
+impl Source {
+    pub fn parse(name: &str) -> Result<Self> {
+        match name {
+            "capture" => Ok(Self::Capture),
+            "baseline" => Ok(Self::Baseline),
+            other => anyhow::bail!(
+                "Unknown steady-state source: {} (expected capture or baseline)",
+                other
+            ),
+        }
+    }
+}
+
+/// Whether a status counts against the error rate, in the capture and in the
+/// run alike; the proxy records unreachable backends as 502
+pub fn server_error(status: u16) -> bool {
+    status >= 500
+}
+
+/// One request as the hypothesis sees it
+#[derive(Debug, Clone, Copy)]
+pub struct Sample {
+    /// Answered with a server error, or not at all
+    pub failed: bool,
+    pub latency_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct SteadyState {
+    pub samples: usize,
+    /// Share of requests that failed, 0.0 to 1.0
+    pub error_rate: f64,
+    pub p50_ms: u64,
+    pub p95_ms: u64,
+    pub p99_ms: u64,
+}
+
+impl SteadyState {
+    pub fn measure<'a>(samples: impl IntoIterator<Item = &'a Sample>) -> Self {
+        let mut count = 0;
+        let mut failed = 0;
+        let mut latencies = Vec::new();
+        for sample in samples {
+            count += 1;
+            if sample.failed {
+                failed += 1;
+            }
+            latencies.extend(sample.latency_ms);
+        }
+        latencies.sort_unstable();
+
+        Self {
+            samples: count,
+            error_rate: if count == 0 {
+                0.0
+            } else {
+                failed as f64 / count as f64
+            },
+            p50_ms: compare::percentile(&latencies, 50).unwrap_or_default(),
+            p95_ms: compare::percentile(&latencies, 95).unwrap_or_default(),
+            p99_ms: compare::percentile(&latencies, 99).unwrap_or_default(),
+        }
+    }
+
+    /// Server errors and durations as recorded
+    pub fn from_capture(requests: &[CapturedRequest]) -> Self {
+        let samples: Vec<Sample> = requests
+            .iter()
+            .filter_map(|r| {
+                r.response.as_ref().map(|response| Sample {
+                    failed: server_error(response.status_code),
+                    latency_ms: r.duration_ms,
+                })
+            })
+            .collect();
+        Self::measure(&samples)
+    }
+}
+
+impl fmt::Display for SteadyState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "error rate {:.1}%, p50 {}ms, p95 {}ms, p99 {}ms ({} requests)",
+            self.error_rate * 100.0,
+            self.p50_ms,
+            self.p95_ms,
+            self.p99_ms,
+            self.samples
+        )
+    }
+}
+
+/// How far the system may drift from its steady state before the
+/// experiment is aborted
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlastRadius {
+    /// Allowed rise in error rate, in absolute terms (0.2 = 20 points)
+    pub max_error_rate_increase: f64,
+    /// Allowed growth of p95 latency as a multiple of the steady state
+    pub max_latency_factor: f64,
+}
+
+impl Default for BlastRadius {
+    fn default() -> Self {
+        Self {
+            max_error_rate_increase: 0.2,
+            max_latency_factor: 3.0,
+        }
+    }
+}
+
+impl BlastRadius {
+    pub fn check(&self, steady: &SteadyState, observed: &SteadyState) -> Option<Deviation> {
+        let error_limit = steady.error_rate + self.max_error_rate_increase;
+        if observed.error_rate > error_limit {
+            return Some(Deviation {
+                metric: "error rate".to_string(),
+                steady: steady.error_rate * 100.0,
+                observed: observed.error_rate * 100.0,
+                limit: error_limit * 100.0,
+                unit: "%",
+            });
+        }
+
+        let latency_limit = (steady.p95_ms as f64 * self.max_latency_factor)
+            .max((steady.p95_ms + LATENCY_SLACK_MS) as f64);
+        if observed.p95_ms as f64 > latency_limit {
+            return Some(Deviation {
+                metric: "p95 latency".to_string(),
+                steady: steady.p95_ms as f64,
+                observed: observed.p95_ms as f64,
+                limit: latency_limit,
+                unit: "ms",
+            });
+        }
+
+        None
+    }
+}
+
+/// A metric outside the blast radius
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Deviation {
+    pub metric: String,
+    pub steady: f64,
+    pub observed: f64,
+    pub limit: f64,
+    pub unit: &'static str,
+}
+
+impl fmt::Display for Deviation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {:.1}{unit} against a steady state of {:.1}{unit} (limit {:.1}{unit})",
+            self.metric,
+            self.observed,
+            self.steady,
+            self.limit,
+            unit = self.unit
+        )
+    }
+}
+
+/// Checks the hypothesis over a sliding window while the experiment runs
+#[derive(Debug)]
+pub struct Monitor {
+    steady: SteadyState,
+    radius: BlastRadius,
+    window: VecDeque<Sample>,
+    seen: usize,
+    aborted: Option<Abort>,
+}
+
+impl Monitor {
+    pub fn new(steady: SteadyState, radius: BlastRadius) -> Self {
+        Self {
+            steady,
+            radius,
+            window: VecDeque::with_capacity(WINDOW),
+            seen: 0,
+            aborted: None,
+        }
+    }
+
+    /// The deviation that ends the experiment, the first time one is seen
+    pub fn observe(&mut self, sample: Sample) -> Option<Deviation> {
+        self.seen += 1;
+        if self.window.len() == WINDOW {
+            self.window.pop_front();
+        }
+        self.window.push_back(sample);
+
+        if self.aborted.is_some() || self.window.len() < MIN_SAMPLES {
+            return None;
+        }
+        let observed = SteadyState::measure(&self.window);
+        let deviation = self.radius.check(&self.steady, &observed)?;
+        self.aborted = Some(Abort {
+            after: self.seen,
+            window: observed,
+            deviation: deviation.clone(),
+        });
+        Some(deviation)
+    }
+
+    pub fn aborted(&self) -> Option<&Abort> {
+        self.aborted.as_ref()
+    }
+}
+
+/// Why and when an experiment stopped early
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Abort {
+    /// Requests completed when the deviation was seen
+    pub after: usize,
+    /// The window that deviated
+    pub window: SteadyState,
+    pub deviation: Deviation,
+}
+
+/// The hypothesis and how the experiment held up against it
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SteadyStateReport {
+    pub source: Source,
+    pub steady: SteadyState,
+    /// Over the whole experiment
+    pub observed: SteadyState,
+    pub aborted: Option<Abort>,
+    /// Requests never sent because the experiment was aborted
+    pub skipped: usize,
+}
+
+impl SteadyStateReport {
+    pub fn print(&self) {
+        println!("\nSteady State ({}):", self.source_name());
+        println!("  Hypothesis: {}", self.steady);
+        println!("  Observed:   {}", self.observed);
+        match &self.aborted {
+            Some(abort) => {
+                println!(
+                    "  ABORTED after {} requests: {}",
+                    abort.after, abort.deviation
+                );
+                println!("  Faults rolled back, {} requests not sent", self.skipped);
+            }
+            None => println!("  Held for the whole experiment"),
+        }
+    }
+
+    fn source_name(&self) -> &'static str {
+        match self.source {
+            Source::Capture => "from capture",
+            Source::Baseline => "from baseline replay",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::captured;
+
+    fn sample(failed: bool, latency_ms: u64) -> Sample {
+        Sample {
+            failed,
+            latency_ms: Some(latency_ms),
+        }
+    }
+
+    #[test]
+    fn test_steady_state_from_capture() {
+        let mut requests: Vec<CapturedRequest> = [200, 200, 500, 201]
+            .iter()
+            .map(|&status| captured("GET", "/users/1", "", status, ""))
+            .collect();
+        for (i, request) in requests.iter_mut().enumerate() {
+            request.duration_ms = Some(10 * (i as u64 + 1));
+        }
+
+        let steady = SteadyState::from_capture(&requests);
+        assert_eq!(steady.samples, 4);
+        assert_eq!(steady.error_rate, 0.25);
+        assert_eq!((steady.p50_ms, steady.p99_ms), (20, 40));
+    }
+
+    #[test]
+    fn test_blast_radius() {
+        let radius = BlastRadius::default();
+        let steady = SteadyState::measure(&[sample(false, 100), sample(false, 100)]);
+
+        let failing = SteadyState::measure(&[sample(false, 250), sample(true, 100)]);
+        assert_eq!(failing.error_rate, 0.5);
+        let deviation = radius.check(&steady, &failing).unwrap();
+        assert_eq!(deviation.metric, "error rate");
+        assert_eq!(deviation.limit, 20.0);
+
+        let slow = SteadyState::measure(&[sample(false, 400)]);
+        assert_eq!(radius.check(&steady, &slow).unwrap().metric, "p95 latency");
+        assert!(
+            radius
+                .check(&steady, &SteadyState::measure(&[sample(false, 300)]))
+                .is_none()
+        );
+
+        // Fast baselines get slack rather than aborting on a few milliseconds
+        let fast = SteadyState::measure(&[sample(false, 2)]);
+        let noisy = SteadyState::measure(&[sample(false, 40)]);
+        assert!(radius.check(&fast, &noisy).is_none());
+    }
+
+    #[test]
+    fn test_monitor_aborts_once() {
+        let steady = SteadyState::measure(&[sample(false, 10)]);
+        let mut monitor = Monitor::new(steady, BlastRadius::default());
+
+        for _ in 0..MIN_SAMPLES - 1 {
+            assert!(monitor.observe(sample(true, 10)).is_none());
+        }
+        assert!(monitor.observe(sample(true, 10)).is_some());
+        assert!(monitor.observe(sample(true, 10)).is_none());
+
+        let abort = monitor.aborted().unwrap();
+        assert_eq!(abort.after, MIN_SAMPLES);
+        assert_eq!(abort.window.error_rate, 1.0);
+    }
+}
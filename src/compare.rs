@@ -0,0 +1,441 @@
+//! Comparing two captures of the same service
+//!
+//! Traffic recorded before and after a deploy is compared per endpoint
+//! pattern without replaying anything: endpoints that appeared or went away,
+//! shifts in status codes, latency percentiles and response schemas, and
+//! dependency calls (SQL, Redis, ...) that started or stopped.
+
+use crate::models::{CapturedRequest, Protocol};
+use crate::parsers::http::HttpParser;
+use crate::parsers::sql::SqlParser;
+use crate::schema::{self, SchemaChange};
+use hyper::Uri;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Status shares moving less than this (10 points) are noise
+const STATUS_SHIFT: f64 = 0.1;
+
+/// Percentiles moving less than this share of the old value are noise...
+const LATENCY_SHIFT: f64 = 0.2;
+
+/// ...and so are moves of fewer milliseconds than this
+const LATENCY_SLACK_MS: u64 = 10;
+
+const PERCENTILES: [u8; 3] = [50, 95, 99];
+
+/// Traffic seen on one endpoint in one capture
+#[derive(Debug, Default)]
+struct Profile {
+    count: usize,
+    statuses: BTreeMap<u16, usize>,
+    /// Sorted
+    latencies: Vec<u64>,
+    bodies: Vec<Value>,
+}
+
+impl Profile {
+    fn answered(&self) -> usize {
+        self.statuses.values().sum()
+    }
+
+    fn share(&self, status: u16) -> f64 {
+        match self.answered() {
+            0 => 0.0,
+            answered => *self.statuses.get(&status).unwrap_or(&0) as f64 / answered as f64,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StatusShift {
+    pub status: u16,
+    /// Share of answered requests, 0 to 1
+    pub before: f64,
+    pub after: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LatencyShift {
+    pub percentile: u8,
+    pub before_ms: u64,
+    pub after_ms: u64,
+}
+
+/// How an endpoint present in both captures changed
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EndpointDiff {
+    pub endpoint: String,
+    pub before_count: usize,
+    pub after_count: usize,
+    pub statuses: Vec<StatusShift>,
+    pub latency: Vec<LatencyShift>,
+    pub schema: Vec<SchemaChange>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct CaptureDiff {
+    /// Endpoints only in the new capture, with their request counts
+    pub added: BTreeMap<String, usize>,
+    /// Endpoints only in the old capture
+    pub removed: BTreeMap<String, usize>,
+    pub changed: Vec<EndpointDiff>,
+    /// Dependency calls only in the new capture, with their counts
+    pub new_dependencies: BTreeMap<String, usize>,
+    /// Dependency calls only in the old capture
+    pub dropped_dependencies: BTreeMap<String, usize>,
+}
+
+impl CaptureDiff {
+    pub fn compare(old: &[CapturedRequest], new: &[CapturedRequest]) -> Self {
+        let (before, after) = (profiles(old), profiles(new));
+        let mut diff = Self::default();
+
+        for (endpoint, profile) in &after {
+            if !before.contains_key(endpoint) {
+                diff.added.insert(endpoint.clone(), profile.count);
+            }
+        }
+        for (endpoint, old_profile) in &before {
+            match after.get(endpoint) {
+                None => {
+                    diff.removed.insert(endpoint.clone(), old_profile.count);
+                }
+                Some(new_profile) => {
+                    let changes = endpoint_diff(endpoint, old_profile, new_profile);
+                    if !changes.statuses.is_empty()
+                        || !changes.latency.is_empty()
+                        || !changes.schema.is_empty()
+                    {
+                        diff.changed.push(changes);
+                    }
+                }
+            }
+        }
+
+        let (before, after) = (dependencies(old), dependencies(new));
+        diff.new_dependencies = after
+            .iter()
+            .filter(|(call, _)| !before.contains_key(*call))
+            .map(|(call, count)| (call.clone(), *count))
+            .collect();
+        diff.dropped_dependencies = before
+            .iter()
+            .filter(|(call, _)| !after.contains_key(*call))
+            .map(|(call, count)| (call.clone(), *count))
+            .collect();
+
+        diff
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.changed.is_empty()
+            && self.new_dependencies.is_empty()
+            && self.dropped_dependencies.is_empty()
+    }
+
+    pub fn print(&self) {
+        println!("\n=== Capture Diff ===\n");
+        println!("Added endpoints: {}", self.added.len());
+        println!("Removed endpoints: {}", self.removed.len());
+        println!("Changed endpoints: {}", self.changed.len());
+        println!("New dependency calls: {}", self.new_dependencies.len());
+        println!(
+            "Dropped dependency calls: {}",
+            self.dropped_dependencies.len()
+        );
+
+        if self.is_empty() {
+            println!("\nNo differences");
+        }
+        if !self.added.is_empty() {
+            println!("\nAdded endpoints:");
+            for (endpoint, count) in &self.added {
+                println!("  + {} ({} requests)", endpoint, count);
+            }
+        }
+        if !self.removed.is_empty() {
+            println!("\nRemoved endpoints:");
+            for (endpoint, count) in &self.removed {
+                println!("  - {} ({} requests)", endpoint, count);
+            }
+        }
+        if !self.changed.is_empty() {
+            println!("\nChanged endpoints:");
+            for endpoint in &self.changed {
+                println!(
+                    "  {} ({} -> {} requests)",
+                    endpoint.endpoint, endpoint.before_count, endpoint.after_count
+                );
+                for shift in &endpoint.statuses {
+                    println!(
+                        "    status {}: {:.1}% -> {:.1}%",
+                        shift.status,
+                        shift.before * 100.0,
+                        shift.after * 100.0
+                    );
+                }
+                for shift in &endpoint.latency {
+                    println!(
+                        "    p{} latency: {}ms -> {}ms",
+                        shift.percentile, shift.before_ms, shift.after_ms
+                    );
+                }
+                for change in &endpoint.schema {
+                    println!("    schema {}", change);
+                }
+            }
+        }
+        if !self.new_dependencies.is_empty() {
+            println!("\nNew dependency calls:");
+            for (call, count) in &self.new_dependencies {
+                println!("  + {} ({} calls)", call, count);
+            }
+        }
+        if !self.dropped_dependencies.is_empty() {
+            println!("\nDependency calls no longer made:");
+            for (call, count) in &self.dropped_dependencies {
+                println!("  - {} ({} calls)", call, count);
+            }
+        }
+
+        println!("\n");
+    }
+}
+
+/// Nearest-rank percentile of sorted values
+pub fn percentile(sorted: &[u64], p: u8) -> Option<u64> {
+    if sorted.is_empty() {
+        return None;
+    }
+    let rank = (p as f64 / 100.0 * sorted.len() as f64).ceil() as usize;
+    Some(sorted[rank.clamp(1, sorted.len()) - 1])
+}
+
+fn endpoint_diff(endpoint: &str, before: &Profile, after: &Profile) -> EndpointDiff {
+    let statuses: BTreeSet<u16> = before
+        .statuses
+        .keys()
+        .chain(after.statuses.keys())
+        .copied()
+        .collect();
+    let statuses = statuses
+        .into_iter()
+        .map(|status| StatusShift {
+            status,
+            before: before.share(status),
+            after: after.share(status),
+        })
+        .filter(|shift| (shift.after - shift.before).abs() >= STATUS_SHIFT)
+        .collect();
+
+    let latency = PERCENTILES
+        .iter()
+        .filter_map(|&p| {
+            let before_ms = percentile(&before.latencies, p)?;
+            let after_ms = percentile(&after.latencies, p)?;
+            let moved = before_ms.abs_diff(after_ms);
+            (moved >= LATENCY_SLACK_MS && moved as f64 >= before_ms as f64 * LATENCY_SHIFT)
+                .then_some(LatencyShift {
+                    percentile: p,
+                    before_ms,
+                    after_ms,
+                })
+        })
+        .collect();
+
+    let schema = match (
+        schema::infer_all(&before.bodies),
+        schema::infer_all(&after.bodies),
+    ) {
+        (Some(old), Some(new)) => schema::diff(&old, &new),
+        _ => Vec::new(),
+    };
+
+    EndpointDiff {
+        endpoint: endpoint.to_string(),
+        before_count: before.count,
+        after_count: after.count,
+        statuses,
+        latency,
+        schema,
+    }
+}
+
+/// HTTP traffic by `METHOD pattern`
+fn profiles(requests: &[CapturedRequest]) -> BTreeMap<String, Profile> {
+    let mut profiles: BTreeMap<String, Profile> = BTreeMap::new();
+
+    for req in requests {
+        if !matches!(req.protocol, Protocol::Http | Protocol::Https) {
+            continue;
+        }
+        let Ok(uri) = req.request.uri.parse::<Uri>() else {
+            continue;
+        };
+        let endpoint = format!(
+            "{} {}",
+            req.request.method,
+            HttpParser::extract_endpoint_pattern(&uri)
+        );
+
+        let profile = profiles.entry(endpoint).or_default();
+        profile.count += 1;
+        if let Some(duration) = req.duration_ms {
+            profile.latencies.push(duration);
+        }
+        if let Some(response) = &req.response {
+            *profile.statuses.entry(response.status_code).or_default() += 1;
+            if let Some(body) = response.body.as_deref()
+                && let Ok(body) = serde_json::from_slice(body)
+            {
+                profile.bodies.push(body);
+            }
+        }
+    }
+
+    for profile in profiles.values_mut() {
+        profile.latencies.sort_unstable();
+    }
+    profiles
+}
+
+/// Calls to databases, caches and other services, e.g. `SQL SELECT users`
+/// or `Redis GET session:*`, with how often each was made
+fn dependencies(requests: &[CapturedRequest]) -> BTreeMap<String, usize> {
+    let mut calls: BTreeMap<String, usize> = BTreeMap::new();
+
+    for req in requests {
+        let call = match req.protocol {
+            Protocol::Http | Protocol::Https => continue,
+            Protocol::Sql => {
+                let tables = SqlParser::extract_table_names(&req.request.uri);
+                format!("SQL {} {}", req.request.method, tables.join(","))
+            }
+            Protocol::Redis => {
+                let key = req
+                    .request
+                    .uri
+                    .split_whitespace()
+                    .next()
+                    .unwrap_or_default();
+                match key.split_once(':') {
+                    Some((prefix, _)) => format!("Redis {} {}:*", req.request.method, prefix),
+                    None => format!("Redis {}", req.request.method),
+                }
+            }
+            Protocol::Kafka => format!("Kafka {} {}", req.request.method, req.request.uri),
+            Protocol::Grpc => format!("gRPC {}", req.request.uri),
+        };
+        *calls.entry(call.trim_end().to_string()).or_default() += 1;
+    }
+
+    calls
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::captured;
+
+    fn timed(method: &str, uri: &str, status: u16, body: &str, ms: u64) -> CapturedRequest {
+        let mut req = captured(method, uri, "", status, body);
+        req.duration_ms = Some(ms);
+        req
+    }
+
+    fn dependency(protocol: Protocol, method: &str, uri: &str) -> CapturedRequest {
+        let mut req = captured(method, uri, "", 200, "");
+        req.protocol = protocol;
+        req
+    }
+
+    #[test]
+    fn test_percentile() {
+        let values: Vec<u64> = (1..=100).collect();
+        assert_eq!(percentile(&values, 50), Some(50));
+        assert_eq!(percentile(&values, 95), Some(95));
+        assert_eq!(percentile(&[7], 99), Some(7));
+        assert_eq!(percentile(&[], 50), None);
+    }
+
+    #[test]
+    fn test_endpoints_added_removed_and_changed() {
+        let old = vec![
+            timed("GET", "/api/users/1", 200, r#"{"id":1,"name":"a"}"#, 10),
+            timed("GET", "/api/users/2", 200, r#"{"id":2,"name":"b"}"#, 12),
+            timed("GET", "/api/legacy", 200, "{}", 5),
+            timed("GET", "/health", 200, "ok", 1),
+        ];
+        let new = vec![
+            timed("GET", "/api/users/1", 200, r#"{"id":"1"}"#, 80),
+            timed("GET", "/api/users/3", 500, r#"{"id":"3"}"#, 90),
+            timed("GET", "/api/v2/users", 200, "[]", 5),
+            timed("GET", "/health", 200, "ok", 2),
+        ];
+
+        let diff = CaptureDiff::compare(&old, &new);
+        assert_eq!(
+            diff.added,
+            BTreeMap::from([("GET /api/v2/users".to_string(), 1)])
+        );
+        assert_eq!(
+            diff.removed,
+            BTreeMap::from([("GET /api/legacy".to_string(), 1)])
+        );
+        assert_eq!(diff.changed.len(), 1);
+
+        let users = &diff.changed[0];
+        assert_eq!(users.endpoint, "GET /api/users/{id}");
+        assert_eq!(
+            users.statuses,
+            [
+                StatusShift {
+                    status: 200,
+                    before: 1.0,
+                    after: 0.5
+                },
+                StatusShift {
+                    status: 500,
+                    before: 0.0,
+                    after: 0.5
+                },
+            ]
+        );
+        assert_eq!(users.latency[0].percentile, 50);
+        assert_eq!(
+            (users.latency[0].before_ms, users.latency[0].after_ms),
+            (10, 80)
+        );
+        let schema: Vec<String> = users.schema.iter().map(|c| c.to_string()).collect();
+        assert_eq!(
+            schema,
+            ["$.id: integer -> string", "$.name: string -> missing"]
+        );
+    }
+
+    #[test]
+    fn test_dependency_calls() {
+        let old = vec![
+            dependency(Protocol::Sql, "SELECT", "SELECT * FROM users WHERE id = 1"),
+            dependency(Protocol::Redis, "GET", "session:abc"),
+        ];
+        let new = vec![
+            dependency(Protocol::Sql, "SELECT", "SELECT * FROM users WHERE id = 2"),
+            dependency(Protocol::Sql, "INSERT", "INSERT INTO audit (x) VALUES (1)"),
+            dependency(Protocol::Redis, "PING", ""),
+        ];
+
+        let diff = CaptureDiff::compare(&old, &new);
+        let new_calls: Vec<&String> = diff.new_dependencies.keys().collect();
+        assert_eq!(new_calls, ["Redis PING", "SQL INSERT audit"]);
+        let dropped: Vec<&String> = diff.dropped_dependencies.keys().collect();
+        assert_eq!(dropped, ["Redis GET session:*"]);
+        assert!(diff.changed.is_empty());
+        assert!(!diff.is_empty());
+        assert!(CaptureDiff::compare(&old, &old).is_empty());
+    }
+}
@@ -0,0 +1,553 @@
+//! Contract validation of captured traffic against an OpenAPI 3 document
+//!
+//! Every captured HTTP exchange is matched to a spec operation and checked for
+//! undeclared paths and status codes, missing parameters and body schema
+//! violations. Findings are grouped per endpoint into a drift report.
+
+use crate::models::{CapturedRequest, Protocol};
+use crate::openapi::{MediaType, OpenApi, Operation, Parameter};
+use crate::parsers::http::HttpParser;
+use crate::schema;
+use hyper::Uri;
+use serde::de::DeserializeOwned;
+use serde_json::{Value, json};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Distinct violations of one endpoint with their occurrence counts
+type Findings = BTreeMap<(ViolationKind, String), usize>;
+
+/// Header parameters that OpenAPI says must be ignored when declared
+const IGNORED_HEADERS: [&str; 3] = ["accept", "content-type", "authorization"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ViolationKind {
+    UnknownPath,
+    UnknownMethod,
+    UndeclaredStatus,
+    MissingParameter,
+    InvalidParameter,
+    MissingRequestBody,
+    RequestBodySchema,
+    ResponseBodySchema,
+}
+
+#[derive(Debug, Clone)]
+pub struct Violation {
+    pub kind: ViolationKind,
+    pub message: String,
+    pub occurrences: usize,
+}
+
+#[derive(Debug)]
+pub struct EndpointDrift {
+    pub endpoint: String,
+    pub requests: usize,
+    pub violations: Vec<Violation>,
+}
+
+#[derive(Debug, Default)]
+pub struct ContractReport {
+    pub total_requests: usize,
+    pub conforming_requests: usize,
+    pub endpoints: Vec<EndpointDrift>,
+    /// Spec operations that no captured request exercised
+    pub uncovered_operations: Vec<String>,
+}
+
+pub struct ContractValidator<'a> {
+    spec: &'a OpenApi,
+    root: Value,
+    base_paths: Vec<String>,
+}
+
+impl<'a> ContractValidator<'a> {
+    pub fn new(spec: &'a OpenApi) -> Self {
+        let root = json!({ "components": spec.components.clone().unwrap_or_default() });
+
+        let mut base_paths: Vec<String> = spec
+            .servers
+            .iter()
+            .filter_map(|s| {
+                let path = match s.url.parse::<Uri>() {
+                    Ok(uri) if uri.scheme().is_some() => uri.path().to_string(),
+                    _ => s.url.clone(),
+                };
+                let path = path.trim_end_matches('/');
+                (!path.is_empty()).then(|| path.to_string())
+            })
+            .collect();
+        base_paths.push(String::new());
+
+        Self {
+            spec,
+            root,
+            base_paths,
+        }
+    }
+
+    pub fn validate(&self, requests: &[CapturedRequest]) -> ContractReport {
+        let mut report = ContractReport::default();
+        let mut endpoints: BTreeMap<String, (usize, Findings)> = BTreeMap::new();
+        let mut covered = BTreeSet::new();
+
+        for captured in requests {
+            if !matches!(captured.protocol, Protocol::Http | Protocol::Https) {
+                continue;
+            }
+            let Ok(uri) = captured.request.uri.parse::<Uri>() else {
+                continue;
+            };
+            report.total_requests += 1;
+
+            let method = captured.request.method.to_uppercase();
+            let (endpoint, violations) = match self.find_path(uri.path()) {
+                Some((template, path_values)) => {
+                    let endpoint = format!("{} {}", method, template);
+                    let violations = match self.spec.paths[template].operation(&method) {
+                        Some(operation) => {
+                            covered.insert(endpoint.clone());
+                            self.check_operation(template, operation, captured, &path_values)
+                        }
+                        None => vec![(
+                            ViolationKind::UnknownMethod,
+                            format!("{} is not declared for {}", method, template),
+                        )],
+                    };
+                    (endpoint, violations)
+                }
+                None => {
+                    let pattern = HttpParser::extract_endpoint_pattern(&uri);
+                    (
+                        format!("{} {}", method, pattern),
+                        vec![(
+                            ViolationKind::UnknownPath,
+                            "path is not declared in the spec".to_string(),
+                        )],
+                    )
+                }
+            };
+
+            if violations.is_empty() {
+                report.conforming_requests += 1;
+            }
+
+            let (count, found) = endpoints.entry(endpoint).or_default();
+            *count += 1;
+            for violation in violations {
+                *found.entry(violation).or_insert(0) += 1;
+            }
+        }
+
+        report.endpoints = endpoints
+            .into_iter()
+            .map(|(endpoint, (requests, found))| EndpointDrift {
+                endpoint,
+                requests,
+                violations: found
+                    .into_iter()
+                    .map(|((kind, message), occurrences)| Violation {
+                        kind,
+                        message,
+                        occurrences,
+                    })
+                    .collect(),
+            })
+            .collect();
+
+        for (template, item) in &self.spec.paths {
+            for method in [
+                "GET", "PUT", "POST", "DELETE", "OPTIONS", "HEAD", "PATCH", "TRACE",
+            ] {
+                let endpoint = format!("{} {}", method, template);
+                if item.operation(method).is_some() && !covered.contains(&endpoint) {
+                    report.uncovered_operations.push(endpoint);
+                }
+            }
+        }
+
+        report
+    }
+
+    /// Find the most specific path template matching `path`
+    fn find_path(&self, path: &str) -> Option<(&'a String, BTreeMap<String, String>)> {
+        let mut best: Option<(usize, &'a String, BTreeMap<String, String>)> = None;
+
+        for base in &self.base_paths {
+            let Some(relative) = path.strip_prefix(base.as_str()) else {
+                continue;
+            };
+            let segments: Vec<&str> = relative.split('/').collect();
+
+            for template in self.spec.paths.keys() {
+                let parts: Vec<&str> = template.split('/').collect();
+                if parts.len() != segments.len() {
+                    continue;
+                }
+
+                let mut values = BTreeMap::new();
+                let mut literals = 0;
+                let matched = parts.iter().zip(&segments).all(|(part, segment)| {
+                    match part.strip_prefix('{').and_then(|p| p.strip_suffix('}')) {
+                        Some(name) if !segment.is_empty() => {
+                            values.insert(name.to_string(), segment.to_string());
+                            true
+                        }
+                        Some(_) => false,
+                        None => {
+                            literals += 1;
+                            part == segment
+                        }
+                    }
+                });
+
+                if matched && best.as_ref().is_none_or(|(score, _, _)| literals > *score) {
+                    best = Some((literals, template, values));
+                }
+            }
+        }
+
+        best.map(|(_, template, values)| (template, values))
+    }
+
+    fn check_operation(
+        &self,
+        template: &str,
+        operation: &Operation,
+        captured: &CapturedRequest,
+        path_values: &BTreeMap<String, String>,
+    ) -> Vec<(ViolationKind, String)> {
+        let mut violations = Vec::new();
+        let request = &captured.request;
+
+        // Operation-level parameters override path-level ones with the same name
+        let mut parameters: BTreeMap<(String, String), Parameter> = BTreeMap::new();
+        for parameter in self.spec.paths[template]
+            .parameters
+            .iter()
+            .chain(&operation.parameters)
+        {
+            if let Some(parameter) = self.resolve(parameter, |p| &p.reference) {
+                parameters.insert(
+                    (parameter.location.clone(), parameter.name.clone()),
+                    parameter,
+                );
+            }
+        }
+
+        for ((location, name), parameter) in &parameters {
+            let value = match location.as_str() {
+                "path" => path_values.get(name),
+                "query" => request.query_params.get(name),
+                "header" if IGNORED_HEADERS.contains(&name.to_lowercase().as_str()) => continue,
+                "header" => request.headers.get(&name.to_lowercase()),
+                _ => continue,
+            };
+
+            match (value, &parameter.schema) {
+                (None, _) if parameter.required => violations.push((
+                    ViolationKind::MissingParameter,
+                    format!("missing required {} parameter '{}'", location, name),
+                )),
+                (Some(value), Some(schema)) => {
+                    for error in schema::validate(&coerce(value, schema), schema, &self.root) {
+                        violations.push((
+                            ViolationKind::InvalidParameter,
+                            format!("{} parameter '{}' {}", location, name, error),
+                        ));
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if let Some(body) = operation
+            .request_body
+            .as_ref()
+            .and_then(|b| self.resolve(b, |b| &b.reference))
+        {
+            match &request.body {
+                None if body.required => violations.push((
+                    ViolationKind::MissingRequestBody,
+                    "required request body is missing".to_string(),
+                )),
+                Some(data) => {
+                    for error in self.check_body(&body.content, data) {
+                        violations.push((
+                            ViolationKind::RequestBodySchema,
+                            format!("request body {}", error),
+                        ));
+                    }
+                }
+                None => {}
+            }
+        }
+
+        if let Some(response) = &captured.response {
+            let status = response.status_code.to_string();
+            let class = format!("{}XX", &status[..1]);
+            let declared = operation
+                .responses
+                .get(&status)
+                .or_else(|| operation.responses.get(&class))
+                .or_else(|| operation.responses.get(&class.to_lowercase()))
+                .or_else(|| operation.responses.get("default"))
+                .and_then(|r| self.resolve(r, |r| &r.reference));
+
+            match declared {
+                None => violations.push((
+                    ViolationKind::UndeclaredStatus,
+                    format!("status {} is not declared", status),
+                )),
+                Some(declared) => {
+                    if let Some(data) = &response.body {
+                        for error in self.check_body(&declared.content, data) {
+                            violations.push((
+                                ViolationKind::ResponseBodySchema,
+                                format!("{} response body {}", status, error),
+                            ));
+                        }
+                    }
+                }
+            }
+        }
+
+        violations
+    }
+
+    /// Validate a JSON body against the JSON media type of `content`
+    fn check_body(&self, content: &BTreeMap<String, MediaType>, data: &[u8]) -> Vec<String> {
+        let Some(schema) = content
+            .iter()
+            .find(|(mime, _)| mime.contains("json"))
+            .and_then(|(_, media)| media.schema.as_ref())
+        else {
+            return Vec::new();
+        };
+
+        if data.is_empty() {
+            return Vec::new();
+        }
+
+        match serde_json::from_slice::<Value>(data) {
+            Ok(value) => schema::validate(&value, schema, &self.root),
+            Err(_) => vec!["is not valid JSON".to_string()],
+        }
+    }
+
+    /// Follow a `$ref` into `components`, or return the object itself
+    fn resolve<T: Clone + DeserializeOwned>(
+        &self,
+        item: &T,
+        reference: impl Fn(&T) -> &Option<String>,
+    ) -> Option<T> {
+        match reference(item) {
+            Some(pointer) => {
+                let target = self.root.pointer(pointer.trim_start_matches('#'))?;
+                serde_json::from_value(target.clone()).ok()
+            }
+            None => Some(item.clone()),
+        }
+    }
+}
+
+/// Interpret a path, query or header string as the type its schema declares
+fn coerce(value: &str, schema: &Value) -> Value {
+    let parsed = match schema.get("type").and_then(Value::as_str) {
+        Some("integer" | "number" | "boolean") => serde_json::from_str(value).ok(),
+        _ => None,
+    };
+    parsed.unwrap_or_else(|| Value::String(value.to_string()))
+}
+
+impl ContractReport {
+    pub fn drift_count(&self) -> usize {
+        self.endpoints
+            .iter()
+            .flat_map(|e| &e.violations)
+            .map(|v| v.occurrences)
+            .sum()
+    }
+
+    pub fn print(&self) {
+        println!("\n=== Contract Drift Report ===\n");
+
+        println!("Overview:");
+        println!("  Requests Checked: {}", self.total_requests);
+        println!(
+            "  Conforming: {} ({:.1}%)",
+            self.conforming_requests,
+            (self.conforming_requests as f64 / self.total_requests.max(1) as f64) * 100.0
+        );
+        println!("  Violations: {}", self.drift_count());
+
+        let drifting: Vec<&EndpointDrift> = self
+            .endpoints
+            .iter()
+            .filter(|e| !e.violations.is_empty())
+            .collect();
+
+        if !drifting.is_empty() {
+            println!("\nDrifting Endpoints:");
+            for (i, endpoint) in drifting.iter().enumerate() {
+                println!(
+                    "\n{}. {} ({} requests)",
+                    i + 1,
+                    endpoint.endpoint,
+                    endpoint.requests
+                );
+                for violation in &endpoint.violations {
+                    println!(
+                        "   - {:?}: {} (x{})",
+                        violation.kind, violation.message, violation.occurrences
+                    );
+                }
+            }
+        }
+
+        if !self.uncovered_operations.is_empty() {
+            println!("\nOperations Without Captured Traffic:");
+            for operation in &self.uncovered_operations {
+                println!("  {}", operation);
+            }
+        }
+
+        println!("\n");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::captured;
+    use crate::openapi::SpecFormat;
+
+    const SPEC: &str = r##"
+openapi: 3.0.3
+info: { title: Orders, version: "1.0" }
+servers:
+  - url: https://api.example.com/v1
+paths:
+  /orders:
+    post:
+      parameters:
+        - name: X-Request-Id
+          in: header
+          required: true
+      requestBody:
+        $ref: "#/components/requestBodies/Order"
+      responses:
+        "201":
+          description: Created
+  /orders/{id}:
+    parameters:
+      - name: id
+        in: path
+        required: true
+        schema: { type: integer }
+    get:
+      responses:
+        "200":
+          description: OK
+          content:
+            application/json:
+              schema: { $ref: "#/components/schemas/Order" }
+        4XX:
+          description: Client error
+components:
+  schemas:
+    Order:
+      type: object
+      required: [id, item]
+      properties:
+        id: { type: integer }
+        item: { type: string }
+  requestBodies:
+    Order:
+      required: true
+      content:
+        application/json:
+          schema: { $ref: "#/components/schemas/Order" }
+"##;
+
+    fn validate(requests: &[CapturedRequest]) -> ContractReport {
+        let spec = OpenApi::parse(SPEC, SpecFormat::Yaml).unwrap();
+        ContractValidator::new(&spec).validate(requests)
+    }
+
+    fn kinds(report: &ContractReport, endpoint: &str) -> Vec<ViolationKind> {
+        report
+            .endpoints
+            .iter()
+            .find(|e| e.endpoint == endpoint)
+            .map(|e| e.violations.iter().map(|v| v.kind).collect())
+            .unwrap_or_default()
+    }
+
+    #[test]
+    fn test_conforming_traffic() {
+        let report = validate(&[
+            captured("GET", "/v1/orders/7", "", 200, r#"{"id":7,"item":"x"}"#),
+            captured("GET", "/v1/orders/8", "", 404, ""),
+        ]);
+
+        assert_eq!(report.total_requests, 2);
+        assert_eq!(report.conforming_requests, 2);
+        assert_eq!(report.drift_count(), 0);
+        assert_eq!(report.uncovered_operations, vec!["POST /orders"]);
+    }
+
+    #[test]
+    fn test_detects_drift() {
+        let report = validate(&[
+            captured("GET", "/v1/orders/abc", "", 500, ""),
+            captured("GET", "/v1/orders/7", "", 200, r#"{"id":"7"}"#),
+            captured("POST", "/v1/orders", "", 201, ""),
+            captured("DELETE", "/v1/orders/7", "", 204, ""),
+            captured("GET", "/v1/customers", "", 200, "[]"),
+        ]);
+
+        assert_eq!(report.conforming_requests, 0);
+        assert_eq!(
+            kinds(&report, "GET /orders/{id}"),
+            vec![
+                ViolationKind::UndeclaredStatus,
+                ViolationKind::InvalidParameter,
+                ViolationKind::ResponseBodySchema,
+                ViolationKind::ResponseBodySchema,
+            ]
+        );
+        assert_eq!(
+            kinds(&report, "POST /orders"),
+            vec![
+                ViolationKind::MissingParameter,
+                ViolationKind::MissingRequestBody
+            ]
+        );
+        assert_eq!(
+            kinds(&report, "DELETE /orders/{id}"),
+            vec![ViolationKind::UnknownMethod]
+        );
+        assert_eq!(
+            kinds(&report, "GET /v1/customers"),
+            vec![ViolationKind::UnknownPath]
+        );
+    }
+
+    #[test]
+    fn test_request_body_schema() {
+        let mut request = captured("POST", "/v1/orders", r#"{"id":1}"#, 201, "");
+        request
+            .request
+            .headers
+            .insert("x-request-id".to_string(), "abc".to_string());
+
+        let report = validate(&[request]);
+        let drift = &report.endpoints[0];
+        assert_eq!(drift.violations.len(), 1);
+        assert_eq!(drift.violations[0].kind, ViolationKind::RequestBodySchema);
+        assert_eq!(
+            drift.violations[0].message,
+            "request body $: missing required property 'item'"
+        );
+    }
+}
@@ -0,0 +1,783 @@
+//! Regression diffs between captured and replayed responses
+//!
+//! Replaying a capture against a new build and comparing every response with
+//! the recorded one shows what changed: status, headers, JSON body values by
+//! path and latency. Each change is graded, so CI can fail on backward
+//! incompatible changes only.
+
+use crate::chaos;
+use crate::generators::flow::Key;
+use crate::mocks;
+use crate::models::{CapturedRequest, Protocol, ResponseData};
+use crate::redact::{self, JsonPath};
+use anyhow::Result;
+use serde::Serialize;
+use serde_json::{Value, json};
+use std::collections::{BTreeSet, HashSet};
+use std::fmt;
+use std::time::{Duration, Instant};
+use tracing::info;
+
+/// Response headers that change from one response to the next
+const VOLATILE_HEADERS: [&str; 8] = [
+    "age",
+    "date",
+    "etag",
+    "expires",
+    "last-modified",
+    "server",
+    "set-cookie",
+    "x-request-id",
+];
+
+/// How much a change matters to existing clients, least first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// Something new, e.g. an extra field
+    Added,
+    /// A different value of the same shape, or a slower response
+    Changed,
+    /// A different status or content type, a missing field or header, a
+    /// value of another type, or no response at all
+    Breaking,
+}
+
+impl Severity {
+    /// The `--fail-on` value: the least severe change that fails the run,
+    /// or `None` to never fail
+    pub fn from_fail_on(value: &str) -> Result<Option<Self>> {
+        match value {
+            "breaking" => Ok(Some(Self::Breaking)),
+            "changed" => Ok(Some(Self::Changed)),
+            "any" => Ok(Some(Self::Added)),
+            "never" => Ok(None),
+            other => anyhow::bail!(
+                "Unsupported --fail-on: {} (expected breaking, changed, any or never)",
+                other
+            ),
+        }
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Added => "added",
+            Self::Changed => "changed",
+            Self::Breaking => "breaking",
+        };
+        f.write_str(name)
+    }
+}
+
+/// One difference between a captured response and its replay
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum Change {
+    Status {
+        expected: u16,
+        actual: u16,
+    },
+    Header {
+        name: String,
+        expected: Option<String>,
+        actual: Option<String>,
+    },
+    /// A JSON value at `path`, or the whole text body at `$`
+    Body {
+        path: String,
+        expected: Option<Value>,
+        actual: Option<Value>,
+    },
+    Latency {
+        expected_ms: u64,
+        actual_ms: u64,
+    },
+}
+
+impl Change {
+    pub fn severity(&self) -> Severity {
+        match self {
+            Self::Status { .. } => Severity::Breaking,
+            Self::Header { expected: None, .. } | Self::Body { expected: None, .. } => {
+                Severity::Added
+            }
+            Self::Header { actual: None, .. } | Self::Body { actual: None, .. } => {
+                Severity::Breaking
+            }
+            Self::Header {
+                name,
+                expected: Some(expected),
+                actual: Some(actual),
+            } if name == "content-type" && media_type(expected) != media_type(actual) => {
+                Severity::Breaking
+            }
+            Self::Body {
+                expected: Some(expected),
+                actual: Some(actual),
+                ..
+            } if json_type(expected) != json_type(actual) => Severity::Breaking,
+            Self::Header { .. } | Self::Body { .. } | Self::Latency { .. } => Severity::Changed,
+        }
+    }
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn or_missing<T: fmt::Display>(value: &Option<T>) -> String {
+            value
+                .as_ref()
+                .map(|v| v.to_string())
+                .unwrap_or_else(|| "missing".to_string())
+        }
+
+        match self {
+            Self::Status { expected, actual } => write!(f, "status: {} -> {}", expected, actual),
+            Self::Header {
+                name,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "header {}: {} -> {}",
+                name,
+                or_missing(&expected.as_ref().map(|v| format!("{:?}", v))),
+                or_missing(&actual.as_ref().map(|v| format!("{:?}", v)))
+            ),
+            Self::Body {
+                path,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "body {}: {} -> {}",
+                path,
+                or_missing(expected),
+                or_missing(actual)
+            ),
+            Self::Latency {
+                expected_ms,
+                actual_ms,
+            } => write!(f, "latency: {}ms -> {}ms", expected_ms, actual_ms),
+        }
+    }
+}
+
+/// What to compare and what to leave out
+#[derive(Debug, Clone)]
+pub struct DiffRules {
+    /// Compare headers, bodies and latency rather than just the status
+    detailed: bool,
+    ignored_headers: HashSet<String>,
+    ignored_paths: Vec<JsonPath>,
+    /// A response is a latency regression when it takes this many times
+    /// as long as the captured one...
+    latency_factor: f64,
+    /// ...and at least this many milliseconds longer
+    latency_slack_ms: u64,
+}
+
+impl Default for DiffRules {
+    fn default() -> Self {
+        Self {
+            detailed: true,
+            ignored_headers: mocks::HOP_HEADERS
+                .iter()
+                .chain(&VOLATILE_HEADERS)
+                .map(|h| h.to_string())
+                .collect(),
+            ignored_paths: Vec::new(),
+            latency_factor: 2.0,
+            latency_slack_ms: 100,
+        }
+    }
+}
+
+impl DiffRules {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_detail(mut self, detailed: bool) -> Self {
+        self.detailed = detailed;
+        self
+    }
+
+    pub fn with_ignored_header(mut self, name: &str) -> Self {
+        self.ignored_headers.insert(name.to_lowercase());
+        self
+    }
+
+    /// Leave JSON body values out of the comparison, e.g. `$.meta.requestId`
+    /// or `$..updatedAt`
+    pub fn with_ignored_path(mut self, path: &str) -> Result<Self> {
+        self.ignored_paths.push(JsonPath::parse(path)?);
+        Ok(self)
+    }
+
+    pub fn with_latency_budget(mut self, factor: f64, slack_ms: u64) -> Self {
+        self.latency_factor = factor;
+        self.latency_slack_ms = slack_ms;
+        self
+    }
+
+    /// Differences from the captured response, in status, header, body and
+    /// latency order
+    ///
+    /// Redacted captured values match anything, since the original was
+    /// never stored.
+    pub fn compare(
+        &self,
+        expected: &ResponseData,
+        expected_ms: Option<u64>,
+        actual: &ResponseData,
+        actual_ms: u64,
+    ) -> Vec<Change> {
+        let mut changes = Vec::new();
+
+        if expected.status_code != actual.status_code {
+            changes.push(Change::Status {
+                expected: expected.status_code,
+                actual: actual.status_code,
+            });
+        }
+        if !self.detailed {
+            return changes;
+        }
+
+        self.compare_headers(expected, actual, &mut changes);
+        self.compare_bodies(expected, actual, &mut changes);
+
+        if let Some(expected_ms) = expected_ms
+            && actual_ms as f64 > expected_ms as f64 * self.latency_factor
+            && actual_ms.saturating_sub(expected_ms) >= self.latency_slack_ms
+        {
+            changes.push(Change::Latency {
+                expected_ms,
+                actual_ms,
+            });
+        }
+
+        changes
+    }
+
+    fn compare_headers(
+        &self,
+        expected: &ResponseData,
+        actual: &ResponseData,
+        changes: &mut Vec<Change>,
+    ) {
+        let lower = |response: &ResponseData| {
+            response
+                .headers
+                .iter()
+                .map(|(k, v)| (k.to_lowercase(), v.clone()))
+                .filter(|(k, _)| !self.ignored_headers.contains(k))
+                .collect::<std::collections::BTreeMap<String, String>>()
+        };
+        let expected = lower(expected);
+        let actual = lower(actual);
+
+        let names: BTreeSet<&String> = expected.keys().chain(actual.keys()).collect();
+        for name in names {
+            let (before, after) = (expected.get(name), actual.get(name));
+            let same = match (before, after) {
+                (Some(before), Some(after)) => before == after || redact::is_redacted(before),
+                (None, None) => true,
+                _ => false,
+            };
+            if !same {
+                changes.push(Change::Header {
+                    name: name.clone(),
+                    expected: before.cloned(),
+                    actual: after.cloned(),
+                });
+            }
+        }
+    }
+
+    fn compare_bodies(
+        &self,
+        expected: &ResponseData,
+        actual: &ResponseData,
+        changes: &mut Vec<Change>,
+    ) {
+        let before = expected.body.as_deref().unwrap_or_default();
+        let after = actual.body.as_deref().unwrap_or_default();
+        if before == after {
+            return;
+        }
+
+        match (
+            serde_json::from_slice::<Value>(before),
+            serde_json::from_slice::<Value>(after),
+        ) {
+            (Ok(before), Ok(after)) => self.compare_json(&mut Vec::new(), &before, &after, changes),
+            _ => {
+                let text = |body: &[u8]| match serde_json::from_slice::<Value>(body) {
+                    Ok(value) => value,
+                    Err(_) => Value::String(String::from_utf8_lossy(body).into_owned()),
+                };
+                let (before, after) = (text(before), text(after));
+                if matches!(&before, Value::String(s) if redact::is_redacted(s)) {
+                    return;
+                }
+                changes.push(Change::Body {
+                    path: "$".to_string(),
+                    expected: Some(before),
+                    actual: Some(after),
+                });
+            }
+        }
+    }
+
+    fn compare_json(
+        &self,
+        path: &mut Vec<Key>,
+        before: &Value,
+        after: &Value,
+        changes: &mut Vec<Change>,
+    ) {
+        if self.ignored_paths.iter().any(|p| p.covers(path)) {
+            return;
+        }
+
+        match (before, after) {
+            (Value::Object(before), Value::Object(after)) => {
+                let keys: BTreeSet<&String> = before.keys().chain(after.keys()).collect();
+                for key in keys {
+                    path.push(Key::Field(key.clone()));
+                    self.compare_entry(path, before.get(key), after.get(key), changes);
+                    path.pop();
+                }
+            }
+            (Value::Array(before), Value::Array(after)) => {
+                for i in 0..before.len().max(after.len()) {
+                    path.push(Key::Index(i));
+                    self.compare_entry(path, before.get(i), after.get(i), changes);
+                    path.pop();
+                }
+            }
+            (Value::String(s), _) if redact::is_redacted(s) => {}
+            _ if before == after => {}
+            _ => changes.push(Change::Body {
+                path: path_text(path),
+                expected: Some(before.clone()),
+                actual: Some(after.clone()),
+            }),
+        }
+    }
+
+    fn compare_entry(
+        &self,
+        path: &mut Vec<Key>,
+        before: Option<&Value>,
+        after: Option<&Value>,
+        changes: &mut Vec<Change>,
+    ) {
+        match (before, after) {
+            (Some(before), Some(after)) => self.compare_json(path, before, after, changes),
+            (None, None) => {}
+            _ if self.ignored_paths.iter().any(|p| p.covers(path)) => {}
+            _ => changes.push(Change::Body {
+                path: path_text(path),
+                expected: before.cloned(),
+                actual: after.cloned(),
+            }),
+        }
+    }
+}
+
+/// The outcome of replaying one captured request
+#[derive(Debug, Clone)]
+pub struct RequestDiff {
+    pub method: String,
+    pub uri: String,
+    pub changes: Vec<Change>,
+    /// Why no response arrived
+    pub error: Option<String>,
+}
+
+impl RequestDiff {
+    /// The most severe change, `None` when the response matched
+    pub fn severity(&self) -> Option<Severity> {
+        match self.error {
+            Some(_) => Some(Severity::Breaking),
+            None => self.changes.iter().map(Change::severity).max(),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct DiffReport {
+    pub requests: Vec<RequestDiff>,
+}
+
+impl DiffReport {
+    /// Requests with a change at least as severe as `threshold`
+    pub fn failing(&self, threshold: Severity) -> usize {
+        self.requests
+            .iter()
+            .filter(|r| r.severity() >= Some(threshold))
+            .count()
+    }
+
+    /// Requests whose most severe change is `severity`
+    pub fn count(&self, severity: Option<Severity>) -> usize {
+        self.requests
+            .iter()
+            .filter(|r| r.severity() == severity)
+            .count()
+    }
+
+    pub fn print(&self) {
+        println!("\n=== Replay Diff Report ===\n");
+        println!("Requests: {}", self.requests.len());
+        println!("Unchanged: {}", self.count(None));
+        println!("Added: {}", self.count(Some(Severity::Added)));
+        println!("Changed: {}", self.count(Some(Severity::Changed)));
+        println!("Breaking: {}", self.count(Some(Severity::Breaking)));
+
+        for request in &self.requests {
+            let Some(severity) = request.severity() else {
+                continue;
+            };
+            println!("\n[{}] {} {}", severity, request.method, request.uri);
+            if let Some(error) = &request.error {
+                println!("  request failed: {}", error);
+            }
+            for change in &request.changes {
+                println!("  [{}] {}", change.severity(), change);
+            }
+        }
+
+        println!("\n");
+    }
+
+    pub fn to_json(&self) -> Value {
+        let requests: Vec<Value> = self
+            .requests
+            .iter()
+            .map(|request| {
+                let changes: Vec<Value> = request
+                    .changes
+                    .iter()
+                    .map(|change| {
+                        let mut value = serde_json::to_value(change).unwrap_or_default();
+                        value["severity"] = json!(change.severity());
+                        value
+                    })
+                    .collect();
+                json!({
+                    "method": request.method,
+                    "uri": request.uri,
+                    "severity": request.severity(),
+                    "error": request.error,
+                    "changes": changes,
+                })
+            })
+            .collect();
+
+        json!({
+            "summary": {
+                "requests": self.requests.len(),
+                "unchanged": self.count(None),
+                "added": self.count(Some(Severity::Added)),
+                "changed": self.count(Some(Severity::Changed)),
+                "breaking": self.count(Some(Severity::Breaking)),
+            },
+            "requests": requests,
+        })
+    }
+}
+
+/// Replay every answered HTTP request against `target_url` in capture order
+/// and compare the responses
+pub async fn replay(
+    requests: &[CapturedRequest],
+    target_url: &str,
+    rules: &DiffRules,
+) -> Result<DiffReport> {
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(30))
+        .build()?;
+    let mut report = DiffReport::default();
+
+    for request in requests {
+        if !matches!(request.protocol, Protocol::Http | Protocol::Https) {
+            continue;
+        }
+        let Some(expected) = &request.response else {
+            continue;
+        };
+
+        let mut diff = RequestDiff {
+            method: request.request.method.clone(),
+            uri: request.request.uri.clone(),
+            changes: Vec::new(),
+            error: None,
+        };
+
+        let start = Instant::now();
+        match send(&client, target_url, request).await {
+            Ok(actual) => {
+                let actual_ms = start.elapsed().as_millis() as u64;
+                diff.changes = rules.compare(expected, request.duration_ms, &actual, actual_ms);
+            }
+            Err(e) => diff.error = Some(e.to_string()),
+        }
+
+        info!(
+            "{} {}: {}",
+            diff.method,
+            diff.uri,
+            diff.severity()
+                .map(|s| s.to_string())
+                .unwrap_or_else(|| "unchanged".to_string())
+        );
+        report.requests.push(diff);
+    }
+
+    Ok(report)
+}
+
+async fn send(
+    client: &reqwest::Client,
+    target_url: &str,
+    request: &CapturedRequest,
+) -> Result<ResponseData> {
+    let response = chaos::build_request(client, target_url, request)
+        .send()
+        .await?;
+    let status_code = response.status().as_u16();
+    let headers = response
+        .headers()
+        .iter()
+        .map(|(k, v)| (k.as_str().to_string(), v.to_str().unwrap_or("").to_string()))
+        .collect();
+    let body = response.bytes().await?;
+
+    Ok(ResponseData {
+        status_code,
+        headers,
+        body: Some(body.to_vec()),
+    })
+}
+
+/// `$.items[0].name`, with `['odd key']` for keys that aren't identifiers
+fn path_text(path: &[Key]) -> String {
+    let mut text = "$".to_string();
+    for key in path {
+        match key {
+            Key::Field(name)
+                if !name.is_empty()
+                    && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') =>
+            {
+                text.push('.');
+                text.push_str(name);
+            }
+            Key::Field(name) => text.push_str(&format!("['{}']", name)),
+            Key::Index(i) => text.push_str(&format!("[{}]", i)),
+        }
+    }
+    text
+}
+
+fn media_type(content_type: &str) -> String {
+    content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_lowercase()
+}
+
+fn json_type(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn response(status: u16, headers: &[(&str, &str)], body: &str) -> ResponseData {
+        ResponseData {
+            status_code: status,
+            headers: headers
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect::<HashMap<_, _>>(),
+            body: Some(body.as_bytes().to_vec()),
+        }
+    }
+
+    #[test]
+    fn test_identical_responses() {
+        let rules = DiffRules::new();
+        let expected = response(
+            200,
+            &[("Date", "Mon"), ("content-type", "application/json")],
+            "{\"a\":1}",
+        );
+        let actual = response(
+            200,
+            &[("date", "Tue"), ("content-type", "application/json")],
+            "{ \"a\": 1 }",
+        );
+
+        assert!(rules.compare(&expected, Some(10), &actual, 15).is_empty());
+    }
+
+    #[test]
+    fn test_json_changes_by_path() {
+        let rules = DiffRules::new();
+        let expected = response(
+            200,
+            &[],
+            r#"{"id":1,"name":"ann","tags":["a","b"],"owner":{"id":7},"token":"__REDACTED_TOKEN__"}"#,
+        );
+        let actual = response(
+            200,
+            &[],
+            r#"{"id":"1","name":"bob","tags":["a"],"owner":{"id":7,"role":"x"},"token":"t"}"#,
+        );
+
+        let changes = rules.compare(&expected, None, &actual, 0);
+        let summary: Vec<(String, Severity)> = changes
+            .iter()
+            .map(|c| (c.to_string(), c.severity()))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("body $.id: 1 -> \"1\"".to_string(), Severity::Breaking),
+                (
+                    "body $.name: \"ann\" -> \"bob\"".to_string(),
+                    Severity::Changed
+                ),
+                (
+                    "body $.owner.role: missing -> \"x\"".to_string(),
+                    Severity::Added
+                ),
+                (
+                    "body $.tags[1]: \"b\" -> missing".to_string(),
+                    Severity::Breaking
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_ignore_rules() {
+        let rules = DiffRules::new()
+            .with_ignored_header("X-Trace")
+            .with_ignored_path("$..updatedAt")
+            .unwrap()
+            .with_ignored_path("$.meta")
+            .unwrap();
+        let expected = response(
+            200,
+            &[("x-trace", "1")],
+            r#"{"item":{"updatedAt":"mon"},"meta":{"page":1}}"#,
+        );
+        let actual = response(
+            200,
+            &[("X-Trace", "2")],
+            r#"{"item":{"updatedAt":"tue"},"meta":{"page":2,"next":3}}"#,
+        );
+
+        assert!(rules.compare(&expected, None, &actual, 0).is_empty());
+    }
+
+    #[test]
+    fn test_status_headers_and_latency() {
+        let rules = DiffRules::new().with_latency_budget(2.0, 100);
+        let expected = response(
+            200,
+            &[
+                ("content-type", "application/json"),
+                ("cache-control", "no-store"),
+            ],
+            "ok",
+        );
+        let actual = response(500, &[("content-type", "text/plain"), ("x-new", "1")], "ok");
+
+        let changes = rules.compare(&expected, Some(100), &actual, 250);
+        let severities: Vec<Severity> = changes.iter().map(Change::severity).collect();
+        assert_eq!(
+            severities,
+            [
+                Severity::Breaking, // status
+                Severity::Breaking, // cache-control removed
+                Severity::Breaking, // content type
+                Severity::Added,    // x-new
+                Severity::Changed,  // latency
+            ]
+        );
+        assert_eq!(changes[4].to_string(), "latency: 100ms -> 250ms");
+
+        // Within the budget
+        assert_eq!(rules.compare(&expected, Some(100), &actual, 150).len(), 4);
+        // Status only
+        let rules = DiffRules::new().with_detail(false);
+        assert_eq!(rules.compare(&expected, Some(100), &actual, 900).len(), 1);
+    }
+
+    #[test]
+    fn test_report_severity_and_json() {
+        let report = DiffReport {
+            requests: vec![
+                RequestDiff {
+                    method: "GET".to_string(),
+                    uri: "/a".to_string(),
+                    changes: Vec::new(),
+                    error: None,
+                },
+                RequestDiff {
+                    method: "GET".to_string(),
+                    uri: "/b".to_string(),
+                    changes: vec![Change::Latency {
+                        expected_ms: 1,
+                        actual_ms: 500,
+                    }],
+                    error: None,
+                },
+            ],
+        };
+
+        assert_eq!(report.failing(Severity::Changed), 1);
+        assert_eq!(report.failing(Severity::Breaking), 0);
+        let json = report.to_json();
+        assert_eq!(json["summary"]["unchanged"], 1);
+        assert_eq!(json["summary"]["changed"], 1);
+        assert_eq!(json["requests"][1]["changes"][0]["kind"], "latency");
+        assert_eq!(json["requests"][1]["changes"][0]["severity"], "changed");
+        assert_eq!(json["requests"][0]["severity"], Value::Null);
+
+        assert_eq!(
+            Severity::from_fail_on("any").unwrap(),
+            Some(Severity::Added)
+        );
+        assert_eq!(Severity::from_fail_on("never").unwrap(), None);
+        assert!(Severity::from_fail_on("sometimes").is_err());
+    }
+
+    #[test]
+    fn test_path_text() {
+        let path = [
+            Key::Field("items".to_string()),
+            Key::Index(0),
+            Key::Field("first name".to_string()),
+        ];
+        assert_eq!(path_text(&path), "$.items[0]['first name']");
+    }
+}
@@ -0,0 +1,229 @@
+//! HAR 1.2 export
+//!
+//! Writes captured HTTP requests as an HTTP Archive that can be opened in browser
+//! devtools and other HAR tooling.
+
+use crate::exporters::Exporter;
+use crate::har::{
+    Har, HarContent, HarCreator, HarEntry, HarLog, HarNameValue, HarPostData, HarRequest,
+    HarResponse, HarTimings,
+};
+use crate::models::{CapturedRequest, Protocol};
+use anyhow::Result;
+use base64::Engine as _;
+use base64::engine::general_purpose::STANDARD;
+use hyper::StatusCode;
+use std::collections::HashMap;
+
+pub struct HarExporter;
+
+impl HarExporter {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Exporter for HarExporter {
+    fn export(&self, requests: &[CapturedRequest]) -> Result<String> {
+        let entries = requests
+            .iter()
+            .filter(|r| matches!(r.protocol, Protocol::Http | Protocol::Https))
+            .map(request_to_entry)
+            .collect();
+
+        let har = Har {
+            log: HarLog {
+                version: "1.2".to_string(),
+                creator: HarCreator {
+                    name: env!("CARGO_PKG_NAME").to_string(),
+                    version: env!("CARGO_PKG_VERSION").to_string(),
+                },
+                entries,
+            },
+        };
+
+        Ok(serde_json::to_string_pretty(&har)?)
+    }
+}
+
+fn request_to_entry(captured: &CapturedRequest) -> HarEntry {
+    let request = &captured.request;
+    let duration = captured.duration_ms.unwrap_or(0) as f64;
+
+    let url = if request.uri.starts_with("http://") || request.uri.starts_with("https://") {
+        request.uri.clone()
+    } else {
+        let scheme = match captured.protocol {
+            Protocol::Https => "https",
+            _ => "http",
+        };
+        let host = request
+            .headers
+            .get("host")
+            .map(String::as_str)
+            .unwrap_or("localhost");
+        format!("{}://{}{}", scheme, host, request.uri)
+    };
+
+    let post_data = request.body.as_ref().map(|body| HarPostData {
+        mime_type: header(&request.headers, "content-type"),
+        text: String::from_utf8_lossy(body).to_string(),
+    });
+
+    let response = match &captured.response {
+        Some(response) => {
+            let body = response.body.as_deref().unwrap_or_default();
+            let (text, encoding) = match std::str::from_utf8(body) {
+                Ok(text) => (text.to_string(), None),
+                Err(_) => (STANDARD.encode(body), Some("base64".to_string())),
+            };
+
+            HarResponse {
+                status: response.status_code,
+                status_text: StatusCode::from_u16(response.status_code)
+                    .ok()
+                    .and_then(|s| s.canonical_reason())
+                    .unwrap_or_default()
+                    .to_string(),
+                http_version: "HTTP/1.1".to_string(),
+                cookies: Vec::new(),
+                headers: name_values(&response.headers),
+                content: HarContent {
+                    size: body.len() as i64,
+                    mime_type: header(&response.headers, "content-type"),
+                    text: (!body.is_empty()).then_some(text),
+                    encoding,
+                },
+                redirect_url: header(&response.headers, "location"),
+                headers_size: -1,
+                body_size: body.len() as i64,
+            }
+        }
+        None => HarResponse {
+            status: 0,
+            status_text: String::new(),
+            http_version: String::new(),
+            cookies: Vec::new(),
+            headers: Vec::new(),
+            content: HarContent {
+                size: 0,
+                mime_type: String::new(),
+                text: None,
+                encoding: None,
+            },
+            redirect_url: String::new(),
+            headers_size: -1,
+            body_size: -1,
+        },
+    };
+
+    HarEntry {
+        started_date_time: captured.timestamp.to_rfc3339(),
+        time: duration,
+        request: HarRequest {
+            method: request.method.clone(),
+            url,
+            http_version: "HTTP/1.1".to_string(),
+            cookies: Vec::new(),
+            headers: name_values(&request.headers),
+            query_string: name_values(&request.query_params),
+            post_data,
+            headers_size: -1,
+            body_size: request.body.as_ref().map(|b| b.len() as i64).unwrap_or(0),
+        },
+        response,
+        cache: serde_json::json!({}),
+        timings: HarTimings {
+            send: 0.0,
+            wait: duration,
+            receive: 0.0,
+        },
+    }
+}
+
+fn name_values(map: &HashMap<String, String>) -> Vec<HarNameValue> {
+    let mut pairs: Vec<HarNameValue> = map
+        .iter()
+        .map(|(name, value)| HarNameValue {
+            name: name.clone(),
+            value: value.clone(),
+        })
+        .collect();
+    pairs.sort_by(|a, b| a.name.cmp(&b.name));
+    pairs
+}
+
+fn header(headers: &HashMap<String, String>, name: &str) -> String {
+    headers.get(name).cloned().unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::importers::{HarImporter, Importer};
+    use crate::models::ResponseData;
+
+    fn captured(uri: &str, body: Option<Vec<u8>>) -> CapturedRequest {
+        let mut captured = crate::fixtures::captured("GET", uri, "", 200, "");
+        captured
+            .request
+            .headers
+            .insert("host".to_string(), "localhost:9000".to_string());
+        captured.response = Some(ResponseData {
+            status_code: 200,
+            headers: HashMap::from([(
+                "content-type".to_string(),
+                "application/octet-stream".to_string(),
+            )]),
+            body,
+        });
+        captured.duration_ms = Some(12);
+        captured
+    }
+
+    #[test]
+    fn test_export_har_document() {
+        let output = HarExporter::new()
+            .export(&[captured("/api/users?page=2", Some(b"[]".to_vec()))])
+            .unwrap();
+        let har: serde_json::Value = serde_json::from_str(&output).unwrap();
+
+        let entry = &har["log"]["entries"][0];
+        assert_eq!(har["log"]["version"], "1.2");
+        assert_eq!(
+            entry["request"]["url"],
+            "http://localhost:9000/api/users?page=2"
+        );
+        assert_eq!(entry["request"]["queryString"][0]["name"], "page");
+        assert_eq!(entry["response"]["status"], 200);
+        assert_eq!(entry["response"]["statusText"], "OK");
+        assert_eq!(entry["response"]["content"]["text"], "[]");
+        assert_eq!(entry["time"], 12.0);
+    }
+
+    #[test]
+    fn test_export_skips_non_http() {
+        let mut query = captured("SELECT 1", None);
+        query.protocol = Protocol::Sql;
+        let output = HarExporter::new().export(&[query]).unwrap();
+        assert!(output.contains("\"entries\": []"));
+    }
+
+    #[test]
+    fn test_round_trip_binary_body() {
+        let body = vec![0xFF, 0x00, 0x10];
+        let original = captured("/blob", Some(body.clone()));
+        let output = HarExporter::new().export(&[original]).unwrap();
+
+        let imported = HarImporter::new().import(output.as_bytes()).unwrap();
+        assert_eq!(imported.len(), 1);
+        assert_eq!(imported[0].request.uri, "/blob");
+        assert_eq!(
+            imported[0].request.headers.get("host"),
+            Some(&"localhost:9000".to_string())
+        );
+        let response = imported[0].response.as_ref().unwrap();
+        assert_eq!(response.body.as_deref(), Some(&body[..]));
+        assert_eq!(imported[0].duration_ms, Some(12));
+    }
+}
@@ -0,0 +1,12 @@
+pub mod har;
+pub mod openapi;
+
+use crate::models::CapturedRequest;
+use anyhow::Result;
+
+pub use har::HarExporter;
+pub use openapi::OpenApiExporter;
+
+pub trait Exporter {
+    fn export(&self, requests: &[CapturedRequest]) -> Result<String>;
+}
@@ -0,0 +1,372 @@
+//! OpenAPI 3 export
+//!
+//! Infers an API description from captured HTTP traffic: one operation per
+//! method and endpoint pattern, with parameters, body schemas and the status
+//! codes that were actually observed.
+
+use crate::exporters::Exporter;
+use crate::models::{CapturedRequest, Protocol};
+use crate::openapi::{
+    Info, MediaType, OpenApi, Operation, Parameter, PathItem, RequestBody, Response, Server,
+    SpecFormat,
+};
+use crate::parsers::http::HttpParser;
+use crate::schema;
+use anyhow::Result;
+use hyper::{StatusCode, Uri};
+use serde_json::{Value, json};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+/// A captured request with the raw values of its path placeholders
+type Sample<'a> = (&'a CapturedRequest, Vec<String>);
+
+/// A captured body with the headers describing it
+type Body<'a> = (&'a HashMap<String, String>, &'a [u8]);
+
+pub struct OpenApiExporter {
+    format: SpecFormat,
+}
+
+impl OpenApiExporter {
+    pub fn new(format: SpecFormat) -> Self {
+        Self { format }
+    }
+}
+
+impl Exporter for OpenApiExporter {
+    fn export(&self, requests: &[CapturedRequest]) -> Result<String> {
+        let mut operations: BTreeMap<(String, String), Vec<Sample>> = BTreeMap::new();
+        let mut servers = BTreeSet::new();
+
+        for captured in requests {
+            let scheme = match captured.protocol {
+                Protocol::Http => "http",
+                Protocol::Https => "https",
+                _ => continue,
+            };
+            let Ok(uri) = captured.request.uri.parse::<Uri>() else {
+                continue;
+            };
+
+            if let Some(host) = captured.request.headers.get("host") {
+                servers.insert(format!("{}://{}", scheme, host));
+            }
+
+            let pattern = HttpParser::extract_endpoint_pattern(&uri);
+            let (template, _) = path_template(&pattern);
+            let values = placeholder_values(&pattern, uri.path());
+
+            operations
+                .entry((template, captured.request.method.to_uppercase()))
+                .or_default()
+                .push((captured, values));
+        }
+
+        let mut paths: BTreeMap<String, PathItem> = BTreeMap::new();
+        for ((template, method), samples) in &operations {
+            let item = paths.entry(template.clone()).or_default();
+            if let Some(slot) = item.operation_mut(method) {
+                *slot = Some(build_operation(template, method, samples));
+            }
+        }
+
+        let spec = OpenApi {
+            openapi: "3.0.3".to_string(),
+            info: Info {
+                title: "Captured API".to_string(),
+                version: "1.0.0".to_string(),
+                description: Some(format!(
+                    "Inferred by {} from {} captured requests",
+                    env!("CARGO_PKG_NAME"),
+                    requests.len()
+                )),
+            },
+            servers: servers.into_iter().map(|url| Server { url }).collect(),
+            paths,
+            components: None,
+        };
+
+        spec.to_string(self.format)
+    }
+}
+
+/// Give every placeholder in an endpoint pattern a unique parameter name
+///
+/// `/users/{id}/orders/{id}` becomes `/users/{id}/orders/{id2}`.
+fn path_template(pattern: &str) -> (String, Vec<String>) {
+    let mut seen: HashMap<&str, usize> = HashMap::new();
+    let mut names = Vec::new();
+
+    let segments: Vec<String> = pattern
+        .split('/')
+        .map(
+            |segment| match segment.strip_prefix('{').and_then(|s| s.strip_suffix('}')) {
+                Some(base) => {
+                    let count = seen.entry(base).or_insert(0);
+                    *count += 1;
+                    let name = if *count == 1 {
+                        base.to_string()
+                    } else {
+                        format!("{}{}", base, count)
+                    };
+                    names.push(name.clone());
+                    format!("{{{}}}", name)
+                }
+                None => segment.to_string(),
+            },
+        )
+        .collect();
+
+    (segments.join("/"), names)
+}
+
+/// Raw path segments that were replaced by placeholders
+fn placeholder_values(pattern: &str, path: &str) -> Vec<String> {
+    pattern
+        .split('/')
+        .zip(path.split('/'))
+        .filter(|(p, _)| p.starts_with('{'))
+        .map(|(_, value)| value.to_string())
+        .collect()
+}
+
+fn build_operation(template: &str, method: &str, samples: &[Sample]) -> Operation {
+    let (_, path_params) = path_template(template);
+    let mut parameters: Vec<Parameter> = path_params
+        .iter()
+        .enumerate()
+        .map(|(i, name)| Parameter {
+            name: name.clone(),
+            location: "path".to_string(),
+            required: true,
+            schema: Some(scalar_schema(
+                samples.iter().filter_map(|(_, values)| values.get(i)),
+            )),
+            ..Default::default()
+        })
+        .collect();
+
+    let query_names: BTreeSet<&String> = samples
+        .iter()
+        .flat_map(|(r, _)| r.request.query_params.keys())
+        .collect();
+    for name in query_names {
+        let values: Vec<&String> = samples
+            .iter()
+            .filter_map(|(r, _)| r.request.query_params.get(name))
+            .collect();
+        parameters.push(Parameter {
+            name: name.clone(),
+            location: "query".to_string(),
+            required: values.len() == samples.len(),
+            schema: Some(scalar_schema(values)),
+            ..Default::default()
+        });
+    }
+
+    let bodies: Vec<Body> = samples
+        .iter()
+        .filter_map(|(r, _)| Some((&r.request.headers, r.request.body.as_deref()?)))
+        .collect();
+    let request_body = (!bodies.is_empty()).then(|| RequestBody {
+        required: bodies.len() == samples.len(),
+        content: content(&bodies),
+        ..Default::default()
+    });
+
+    let mut by_status: BTreeMap<u16, Vec<Body>> = BTreeMap::new();
+    for (captured, _) in samples {
+        if let Some(response) = &captured.response {
+            let bodies = by_status.entry(response.status_code).or_default();
+            if let Some(body) = response.body.as_deref().filter(|b| !b.is_empty()) {
+                bodies.push((&response.headers, body));
+            }
+        }
+    }
+
+    let responses = by_status
+        .into_iter()
+        .map(|(status, bodies)| {
+            let description = StatusCode::from_u16(status)
+                .ok()
+                .and_then(|s| s.canonical_reason())
+                .unwrap_or("Observed response")
+                .to_string();
+            (
+                status.to_string(),
+                Response {
+                    description,
+                    content: content(&bodies),
+                    ..Default::default()
+                },
+            )
+        })
+        .collect();
+
+    Operation {
+        operation_id: Some(operation_id(method, template)),
+        summary: Some(format!(
+            "{} {} ({} captured)",
+            method,
+            template,
+            samples.len()
+        )),
+        parameters,
+        request_body,
+        responses,
+    }
+}
+
+/// Describe captured bodies per media type, inferring schemas for JSON
+fn content(bodies: &[Body]) -> BTreeMap<String, MediaType> {
+    let mut by_type: BTreeMap<String, Vec<&[u8]>> = BTreeMap::new();
+    for (headers, body) in bodies {
+        by_type
+            .entry(media_type(headers, body))
+            .or_default()
+            .push(body);
+    }
+
+    by_type
+        .into_iter()
+        .map(|(mime, bodies)| {
+            let schema = if mime.contains("json") {
+                let values: Vec<Value> = bodies
+                    .iter()
+                    .filter_map(|b| serde_json::from_slice(b).ok())
+                    .collect();
+                schema::infer_all(&values)
+            } else {
+                Some(json!({ "type": "string" }))
+            };
+            (mime, MediaType { schema })
+        })
+        .collect()
+}
+
+fn media_type(headers: &HashMap<String, String>, body: &[u8]) -> String {
+    match headers.get("content-type") {
+        Some(value) => value
+            .split(';')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_lowercase(),
+        None if serde_json::from_slice::<Value>(body).is_ok() => "application/json".to_string(),
+        None => "application/octet-stream".to_string(),
+    }
+}
+
+/// Infer the schema of path and query parameter values
+fn scalar_schema<'a>(values: impl IntoIterator<Item = &'a String>) -> Value {
+    values
+        .into_iter()
+        .map(|value| {
+            let parsed = if value.parse::<i64>().is_ok() || value.parse::<f64>().is_ok() {
+                serde_json::from_str(value).unwrap_or(Value::Null)
+            } else if value == "true" || value == "false" {
+                Value::Bool(value == "true")
+            } else {
+                Value::String(value.clone())
+            };
+            schema::infer(&parsed)
+        })
+        .reduce(|a, b| schema::merge(&a, &b))
+        .unwrap_or_else(|| json!({ "type": "string" }))
+}
+
+fn operation_id(method: &str, template: &str) -> String {
+    let path: String = template
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    let path = path
+        .split('_')
+        .filter(|s| !s.is_empty())
+        .collect::<Vec<_>>()
+        .join("_");
+
+    if path.is_empty() {
+        method.to_lowercase()
+    } else {
+        format!("{}_{}", method.to_lowercase(), path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::captured;
+
+    fn export(requests: &[CapturedRequest]) -> Value {
+        let output = OpenApiExporter::new(SpecFormat::Json)
+            .export(requests)
+            .unwrap();
+        serde_json::from_str(&output).unwrap()
+    }
+
+    #[test]
+    fn test_path_template_names_are_unique() {
+        let (template, names) = path_template("/users/{id}/orders/{id}");
+        assert_eq!(template, "/users/{id}/orders/{id2}");
+        assert_eq!(names, vec!["id", "id2"]);
+    }
+
+    #[test]
+    fn test_export_paths_and_parameters() {
+        let mut user = captured(
+            "GET",
+            "/api/users/1?verbose=true",
+            "",
+            200,
+            r#"{"id":1,"name":"a"}"#,
+        );
+        user.request
+            .headers
+            .insert("host".to_string(), "localhost:9000".to_string());
+        let spec = export(&[
+            user,
+            captured("GET", "/api/users/2", "", 404, r#"{"error":"missing"}"#),
+        ]);
+
+        assert_eq!(spec["openapi"], "3.0.3");
+        assert_eq!(spec["servers"][0]["url"], "http://localhost:9000");
+
+        let op = &spec["paths"]["/api/users/{id}"]["get"];
+        assert_eq!(op["operationId"], "get_api_users_id");
+        assert_eq!(op["parameters"][0]["name"], "id");
+        assert_eq!(op["parameters"][0]["in"], "path");
+        assert_eq!(op["parameters"][0]["schema"]["type"], "integer");
+        assert_eq!(op["parameters"][1]["name"], "verbose");
+        assert_eq!(op["parameters"][1]["in"], "query");
+        assert!(op["parameters"][1].get("required").is_none());
+        assert_eq!(op["parameters"][1]["schema"]["type"], "boolean");
+
+        let ok = &op["responses"]["200"]["content"]["application/json"]["schema"];
+        assert_eq!(ok["properties"]["name"]["type"], "string");
+        assert_eq!(op["responses"]["404"]["description"], "Not Found");
+    }
+
+    #[test]
+    fn test_export_request_body_schema() {
+        let spec = export(&[
+            captured("POST", "/api/orders", r#"{"item":"a","qty":1}"#, 201, "{}"),
+            captured("POST", "/api/orders", r#"{"item":"b"}"#, 201, "{}"),
+        ]);
+
+        let body = &spec["paths"]["/api/orders"]["post"]["requestBody"];
+        assert_eq!(body["required"], true);
+        let schema = &body["content"]["application/json"]["schema"];
+        assert_eq!(schema["properties"]["qty"]["type"], "integer");
+        assert_eq!(schema["required"], json!(["item"]));
+    }
+
+    #[test]
+    fn test_export_yaml() {
+        let output = OpenApiExporter::new(SpecFormat::Yaml)
+            .export(&[captured("GET", "/health", "", 200, "{}")])
+            .unwrap();
+        assert!(output.starts_with("openapi: 3.0.3"));
+        assert!(output.contains("/health:"));
+    }
+}
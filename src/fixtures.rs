@@ -0,0 +1,42 @@
+//! Captured traffic shared by the tests
+
+use crate::models::{CapturedRequest, Protocol, RequestData, ResponseData};
+use crate::parsers::http::HttpParser;
+use chrono::Utc;
+use hyper::Uri;
+use std::collections::HashMap;
+
+/// An answered HTTP request with a JSON response; an empty `body` means the
+/// request had none
+pub(crate) fn captured(
+    method: &str,
+    uri: &str,
+    body: &str,
+    status: u16,
+    response: &str,
+) -> CapturedRequest {
+    CapturedRequest {
+        id: uri.to_string(),
+        timestamp: Utc::now(),
+        protocol: Protocol::Http,
+        request: RequestData {
+            method: method.to_string(),
+            uri: uri.to_string(),
+            headers: HashMap::new(),
+            body: (!body.is_empty()).then(|| body.as_bytes().to_vec()),
+            query_params: uri
+                .parse::<Uri>()
+                .map(|uri| HttpParser::parse_query_params(&uri))
+                .unwrap_or_default(),
+        },
+        response: Some(ResponseData {
+            status_code: status,
+            headers: HashMap::from([
+                ("content-type".to_string(), "application/json".to_string()),
+                ("content-length".to_string(), response.len().to_string()),
+            ]),
+            body: Some(response.as_bytes().to_vec()),
+        }),
+        duration_ms: Some(5),
+    }
+}
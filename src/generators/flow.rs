@@ -0,0 +1,768 @@
+//! Stateful flow detection
+//!
+//! Finds values that a response handed to the client (an order ID, a session
+//! token) and that later requests sent back in their path, query, headers or
+//! body. Requests linked that way form a scenario; generators replay it by
+//! extracting each value from the live response instead of hard-coding the
+//! captured one.
+
+use crate::models::{CapturedRequest, Protocol};
+use crate::parsers::http::HttpParser;
+use crate::redact::{self, Segment};
+use hyper::Uri;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// Deepest response nesting searched for values
+const MAX_DEPTH: usize = 4;
+/// Array elements searched per response array
+const MAX_ITEMS: usize = 10;
+/// Opaque strings at least this long are tracked even without an ID-like key
+const MIN_TOKEN_LEN: usize = 8;
+/// Shorter path segments, such as the `1` of `/pages/1`, are too common to
+/// link requests on
+const MIN_SEGMENT_LEN: usize = 2;
+/// Locals, fixtures, helpers and imports the generated tests already use
+const RESERVED: &str = "api base_url body client data env err expect fetch field fmt given http \
+    httpx io json json_text os path payload process pytest req request requests response self \
+    send session status strings t test testing text unittest";
+/// Keywords of the generated languages; a value under such a key is named
+/// `<key>_value`, e.g. `type_value` or `typeValue`
+const KEYWORDS: &str = "abstract and as assert async await boolean break byte case catch chan \
+    char class const continue crate debugger def default defer del delete do double dyn elif \
+    else enum except export extends extern false fallthrough final finally float fn for func \
+    function global go goto if impl implements import in instanceof int interface is lambda let \
+    long loop map match mod move mut new nil none nonlocal not null or package pass private \
+    protected pub public raise range ref return select short static struct super switch \
+    synchronized this throw throws trait transient true try type typeof unsafe use var void \
+    volatile where while with yield";
+
+/// Step in a path into a JSON response
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Key {
+    Field(String),
+    Index(usize),
+}
+
+/// Literal text interleaved with variables
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Part {
+    Text(String),
+    Var(String),
+    /// A redacted value, read from this environment variable
+    Env(String),
+}
+
+pub type Template = Vec<Part>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FlowBody {
+    /// JSON body with variables at the given JSON pointers
+    Json(Value, BTreeMap<String, String>),
+    Text(Template),
+}
+
+/// A value read from a step's response into a variable
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Extract {
+    pub variable: String,
+    pub path: Vec<Key>,
+}
+
+#[derive(Debug)]
+pub struct Step<'a> {
+    pub request: &'a CapturedRequest,
+    pub endpoint: String,
+    pub uri: Template,
+    pub headers: Vec<(&'a String, Template)>,
+    pub body: Option<FlowBody>,
+    pub extracts: Vec<Extract>,
+}
+
+#[derive(Debug)]
+pub struct Flow<'a> {
+    pub steps: Vec<Step<'a>>,
+}
+
+impl Flow<'_> {
+    /// Endpoint of the step that starts the scenario
+    pub fn name(&self) -> &str {
+        &self.steps[0].endpoint
+    }
+
+    /// Step endpoints joined into a one-line summary
+    pub fn summary(&self) -> String {
+        self.steps
+            .iter()
+            .map(|s| s.endpoint.as_str())
+            .collect::<Vec<_>>()
+            .join(" -> ")
+    }
+}
+
+/// A response value later requests may reuse
+#[derive(Debug, Clone)]
+struct Provided {
+    step: usize,
+    path: Vec<Key>,
+    value: Value,
+}
+
+/// Where a request reused a provided value
+#[derive(Debug, Clone)]
+enum Location {
+    Segment(usize),
+    Query(String),
+    Header(String),
+    Body(String),
+    Text,
+}
+
+#[derive(Debug, Clone)]
+struct Use {
+    text: String,
+    provider: (usize, Vec<Key>),
+    location: Location,
+}
+
+/// Detect flows in requests ordered by capture time
+pub fn detect(requests: &[CapturedRequest]) -> Vec<Flow<'_>> {
+    let mut http: Vec<&CapturedRequest> = requests
+        .iter()
+        .filter(|r| matches!(r.protocol, Protocol::Http | Protocol::Https))
+        .collect();
+    http.sort_by_key(|r| r.timestamp);
+
+    let mut known: HashMap<String, Provided> = HashMap::new();
+    let mut client_values: HashSet<String> = HashSet::new();
+    let mut uses: Vec<Vec<Use>> = Vec::with_capacity(http.len());
+
+    for (index, req) in http.iter().enumerate() {
+        let found = find_uses(req, &known);
+
+        // Values the client sent on its own are not server-provided, even if
+        // a response echoes them back later
+        for token in request_tokens(req) {
+            if !found.iter().any(|u| u.text == token) {
+                client_values.insert(token);
+            }
+        }
+
+        if let Some(body) = response_json(req) {
+            let mut candidates = Vec::new();
+            collect_candidates(&body, &mut Vec::new(), None, &mut candidates);
+            for (path, value) in candidates {
+                let text = value_text(&value);
+                // An echo of what this request sent keeps its original source
+                if client_values.contains(&text) || found.iter().any(|u| u.text == text) {
+                    continue;
+                }
+                known.insert(
+                    text,
+                    Provided {
+                        step: index,
+                        path,
+                        value,
+                    },
+                );
+            }
+        }
+        uses.push(found);
+    }
+
+    // Requests linked through reused values form one flow
+    let mut parent: Vec<usize> = (0..http.len()).collect();
+    for (index, found) in uses.iter().enumerate() {
+        for u in found {
+            union(&mut parent, u.provider.0, index);
+        }
+    }
+
+    let mut components: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+    for index in 0..http.len() {
+        let root = find(&mut parent, index);
+        components.entry(root).or_default().push(index);
+    }
+
+    let mut flows: Vec<(usize, Flow)> = components
+        .into_values()
+        .filter(|members| members.len() > 1)
+        .map(|members| (members[0], build_flow(&http, &members, &uses)))
+        .collect();
+    flows.sort_by_key(|(first, _)| *first);
+
+    flows.into_iter().map(|(_, flow)| flow).collect()
+}
+
+fn build_flow<'a>(http: &[&'a CapturedRequest], members: &[usize], uses: &[Vec<Use>]) -> Flow<'a> {
+    // Name every extracted value once, in order of first use
+    let mut variables: HashMap<(usize, Vec<Key>), String> = HashMap::new();
+    let mut taken: HashSet<String> = RESERVED.split_whitespace().map(str::to_string).collect();
+    let mut extracts: BTreeMap<usize, Vec<Extract>> = BTreeMap::new();
+
+    for &index in members {
+        for u in &uses[index] {
+            if variables.contains_key(&u.provider) {
+                continue;
+            }
+            let (step, path) = &u.provider;
+            let base = variable_name(http[*step], path);
+            let mut name = base.clone();
+            let mut n = 1;
+            while !taken.insert(name.clone()) {
+                n += 1;
+                name = format!("{}_{}", base, n);
+            }
+            extracts.entry(*step).or_default().push(Extract {
+                variable: name.clone(),
+                path: path.clone(),
+            });
+            variables.insert(u.provider.clone(), name);
+        }
+    }
+
+    let steps = members
+        .iter()
+        .map(|&index| {
+            let req = http[index];
+            let bindings: Vec<(&Use, &String)> = uses[index]
+                .iter()
+                .map(|u| (u, &variables[&u.provider]))
+                .collect();
+
+            Step {
+                request: req,
+                endpoint: endpoint(req),
+                uri: uri_template(&req.request.uri, &bindings),
+                headers: header_templates(req, &bindings),
+                body: body_template(req, &bindings),
+                extracts: extracts.remove(&index).unwrap_or_default(),
+            }
+        })
+        .collect();
+
+    Flow { steps }
+}
+
+/// `METHOD pattern`, e.g. `GET /users/{id}`
+pub fn endpoint(req: &CapturedRequest) -> String {
+    let pattern = match req.request.uri.parse::<Uri>() {
+        Ok(uri) => HttpParser::extract_endpoint_pattern(&uri),
+        Err(_) => req.request.uri.clone(),
+    };
+    format!("{} {}", req.request.method, pattern)
+}
+
+/// `order_id` for the `id` of `POST /orders`, otherwise the snake_cased key
+fn variable_name(provider: &CapturedRequest, path: &[Key]) -> String {
+    let key = path
+        .iter()
+        .rev()
+        .find_map(|k| match k {
+            Key::Field(name) => Some(name.as_str()),
+            Key::Index(_) => None,
+        })
+        .unwrap_or("value");
+
+    let name = if key.eq_ignore_ascii_case("id") {
+        let path = provider.request.uri.split('?').next().unwrap_or_default();
+        let resource = path
+            .split('/')
+            .rev()
+            .find(|s| !s.is_empty() && !s.chars().all(|c| c.is_ascii_digit()) && s.len() <= 20)
+            .map(singular)
+            .unwrap_or_else(|| "resource".to_string());
+        format!("{}_id", resource)
+    } else {
+        key.to_string()
+    };
+
+    let snake = snake_case(&name);
+    if snake.is_empty() || snake.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("value_{}", snake)
+    } else if KEYWORDS.split_whitespace().any(|k| k == snake) {
+        format!("{}_value", snake)
+    } else {
+        snake
+    }
+}
+
+fn singular(word: &str) -> String {
+    if let Some(stem) = word.strip_suffix("ies") {
+        format!("{}y", stem)
+    } else if word.len() > 3 && word.ends_with('s') && !word.ends_with("ss") {
+        word[..word.len() - 1].to_string()
+    } else {
+        word.to_string()
+    }
+}
+
+fn snake_case(name: &str) -> String {
+    let mut out = String::new();
+    let mut previous_lower = false;
+    for c in name.chars() {
+        if c.is_ascii_uppercase() && previous_lower {
+            out.push('_');
+        }
+        previous_lower = c.is_ascii_lowercase() || c.is_ascii_digit();
+        if c.is_ascii_alphanumeric() {
+            out.push(c.to_ascii_lowercase());
+        } else if !out.ends_with('_') {
+            out.push('_');
+        }
+    }
+    out.trim_matches('_').to_string()
+}
+
+fn is_id_key(key: &str) -> bool {
+    let lower = key.to_lowercase();
+    lower == "id"
+        || lower == "key"
+        || lower == "slug"
+        || lower.ends_with("_id")
+        || lower.ends_with("_key")
+        || lower.contains("token")
+        || lower.contains("uuid")
+        || lower.contains("session")
+        || (key.ends_with("Id") && key.len() > 2)
+}
+
+/// Scalars of a response worth tracking: ID-like keys and opaque tokens
+fn collect_candidates(
+    value: &Value,
+    path: &mut Vec<Key>,
+    key: Option<&str>,
+    out: &mut Vec<(Vec<Key>, Value)>,
+) {
+    if path.len() > MAX_DEPTH {
+        return;
+    }
+
+    match value {
+        Value::Object(map) => {
+            for (name, child) in map {
+                path.push(Key::Field(name.clone()));
+                collect_candidates(child, path, Some(name), out);
+                path.pop();
+            }
+        }
+        Value::Array(items) => {
+            for (i, child) in items.iter().take(MAX_ITEMS).enumerate() {
+                path.push(Key::Index(i));
+                collect_candidates(child, path, key, out);
+                path.pop();
+            }
+        }
+        Value::String(s) => {
+            let id_key = key.is_some_and(is_id_key);
+            let opaque = s.len() >= MIN_TOKEN_LEN && !s.chars().any(char::is_whitespace);
+            if !s.is_empty() && (id_key || opaque) {
+                out.push((path.clone(), value.clone()));
+            }
+        }
+        Value::Number(n) if (n.is_i64() || n.is_u64()) && key.is_some_and(is_id_key) => {
+            out.push((path.clone(), value.clone()));
+        }
+        _ => {}
+    }
+}
+
+fn value_text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+fn response_json(req: &CapturedRequest) -> Option<Value> {
+    let body = req.response.as_ref()?.body.as_deref()?;
+    serde_json::from_slice(body).ok()
+}
+
+fn request_json(req: &CapturedRequest) -> Option<Value> {
+    serde_json::from_slice(req.request.body.as_deref()?).ok()
+}
+
+fn is_skipped_header(key: &str) -> bool {
+    key == "host" || key == "content-length"
+}
+
+/// Every value the client sent: path segments, query values, headers and
+/// body scalars
+fn request_tokens(req: &CapturedRequest) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let (path, query) = split_uri(&req.request.uri);
+
+    tokens.extend(
+        path.split('/')
+            .filter(|s| !s.is_empty())
+            .map(str::to_string),
+    );
+    tokens.extend(query_pairs(query).into_iter().map(|(_, v)| v.to_string()));
+    tokens.extend(
+        req.request
+            .headers
+            .iter()
+            .filter(|(k, _)| !is_skipped_header(k))
+            .map(|(_, v)| v.clone()),
+    );
+    if let Some(body) = request_json(req) {
+        let mut leaves = Vec::new();
+        json_leaves(&body, String::new(), &mut leaves);
+        tokens.extend(leaves.into_iter().map(|(_, v)| value_text(v)));
+    }
+
+    tokens
+}
+
+fn find_uses(req: &CapturedRequest, known: &HashMap<String, Provided>) -> Vec<Use> {
+    let mut found = Vec::new();
+    let mut record = |text: &str, provided: &Provided, location: Location| {
+        found.push(Use {
+            text: text.to_string(),
+            provider: (provided.step, provided.path.clone()),
+            location,
+        });
+    };
+
+    let (path, query) = split_uri(&req.request.uri);
+    for (i, segment) in path.split('/').enumerate() {
+        if segment.len() < MIN_SEGMENT_LEN {
+            continue;
+        }
+        if let Some(provided) = known.get(segment) {
+            record(segment, provided, Location::Segment(i));
+        }
+    }
+    // Only ID-like parameters link short values; `?page=2` is not a reuse
+    for (name, value) in query_pairs(query) {
+        if !is_id_key(name) && value.len() < MIN_TOKEN_LEN {
+            continue;
+        }
+        if let Some(provided) = known.get(value) {
+            record(value, provided, Location::Query(name.to_string()));
+        }
+    }
+
+    let mut headers: Vec<(&String, &String)> = req.request.headers.iter().collect();
+    headers.sort();
+    for (name, value) in headers {
+        if is_skipped_header(name) {
+            continue;
+        }
+        if let Some(provided) = known.get(value.as_str()) {
+            record(value, provided, Location::Header(name.clone()));
+            continue;
+        }
+        // Tokens embedded in a longer value, e.g. `Bearer <token>`
+        if let Some((text, provided)) = longest_contained(value, known) {
+            record(&text, provided, Location::Header(name.clone()));
+        }
+    }
+
+    match request_json(req) {
+        Some(body) => {
+            let mut leaves = Vec::new();
+            json_leaves(&body, String::new(), &mut leaves);
+            for (pointer, leaf) in leaves {
+                let text = value_text(leaf);
+                if let Some(provided) = known.get(&text).filter(|p| &p.value == leaf) {
+                    record(&text, provided, Location::Body(pointer));
+                }
+            }
+        }
+        None => {
+            let body = req
+                .request
+                .body
+                .as_deref()
+                .map(String::from_utf8_lossy)
+                .unwrap_or_default();
+            if let Some((text, provided)) = longest_contained(&body, known) {
+                record(&text, provided, Location::Text);
+            }
+        }
+    }
+
+    found
+}
+
+fn longest_contained<'k>(
+    haystack: &str,
+    known: &'k HashMap<String, Provided>,
+) -> Option<(String, &'k Provided)> {
+    known
+        .iter()
+        .filter(|(text, _)| text.len() >= MIN_TOKEN_LEN && haystack.contains(text.as_str()))
+        .max_by(|a, b| a.0.len().cmp(&b.0.len()).then_with(|| b.0.cmp(a.0)))
+        .map(|(text, provided)| (text.clone(), provided))
+}
+
+fn json_leaves<'v>(value: &'v Value, pointer: String, out: &mut Vec<(String, &'v Value)>) {
+    match value {
+        Value::Object(map) => {
+            for (key, child) in map {
+                let escaped = key.replace('~', "~0").replace('/', "~1");
+                json_leaves(child, format!("{}/{}", pointer, escaped), out);
+            }
+        }
+        Value::Array(items) => {
+            for (i, child) in items.iter().enumerate() {
+                json_leaves(child, format!("{}/{}", pointer, i), out);
+            }
+        }
+        Value::Null | Value::Bool(_) => {}
+        scalar => out.push((pointer, scalar)),
+    }
+}
+
+fn split_uri(uri: &str) -> (&str, &str) {
+    match uri.split_once('?') {
+        Some((path, query)) => (path, query),
+        None => (uri, ""),
+    }
+}
+
+fn query_pairs(query: &str) -> Vec<(&str, &str)> {
+    query
+        .split('&')
+        .filter(|p| !p.is_empty())
+        .map(|pair| pair.split_once('=').unwrap_or((pair, "")))
+        .collect()
+}
+
+fn uri_template(uri: &str, bindings: &[(&Use, &String)]) -> Template {
+    let (path, query) = split_uri(uri);
+    let mut template = Template::new();
+
+    for (i, segment) in path.split('/').enumerate() {
+        if i > 0 {
+            push_text(&mut template, "/");
+        }
+        let var = bindings
+            .iter()
+            .find(|(u, _)| matches!(u.location, Location::Segment(s) if s == i));
+        match var {
+            Some((_, var)) => template.push(Part::Var((*var).clone())),
+            None => push_text(&mut template, segment),
+        }
+    }
+
+    for (i, (name, value)) in query_pairs(query).into_iter().enumerate() {
+        push_text(&mut template, if i == 0 { "?" } else { "&" });
+        push_text(&mut template, &format!("{}=", name));
+        let var = bindings
+            .iter()
+            .find(|(u, _)| matches!(&u.location, Location::Query(q) if q == name));
+        match var {
+            Some((_, var)) => template.push(Part::Var((*var).clone())),
+            None => push_text(&mut template, value),
+        }
+    }
+
+    template
+}
+
+fn header_templates<'a>(
+    req: &'a CapturedRequest,
+    bindings: &[(&Use, &String)],
+) -> Vec<(&'a String, Template)> {
+    let mut headers: Vec<(&String, &String)> = req
+        .request
+        .headers
+        .iter()
+        .filter(|(k, _)| !is_skipped_header(k))
+        .collect();
+    headers.sort();
+
+    headers
+        .into_iter()
+        .map(|(name, value)| {
+            let var = bindings
+                .iter()
+                .find(|(u, _)| matches!(&u.location, Location::Header(h) if h == name));
+            let template = match var {
+                Some((u, var)) => substitute(value, &u.text, var),
+                None => vec![Part::Text(value.clone())],
+            };
+            (name, template)
+        })
+        .collect()
+}
+
+fn body_template(req: &CapturedRequest, bindings: &[(&Use, &String)]) -> Option<FlowBody> {
+    let raw = req.request.body.as_deref().filter(|b| !b.is_empty())?;
+
+    if let Some(body) = request_json(req) {
+        let vars = bindings
+            .iter()
+            .filter_map(|(u, var)| match &u.location {
+                Location::Body(pointer) => Some((pointer.clone(), (*var).clone())),
+                _ => None,
+            })
+            .collect();
+        return Some(FlowBody::Json(body, vars));
+    }
+
+    let text = String::from_utf8_lossy(raw);
+    let template = match bindings
+        .iter()
+        .find(|(u, _)| matches!(u.location, Location::Text))
+    {
+        Some((u, var)) => substitute(&text, &u.text, var),
+        None => vec![Part::Text(text.into_owned())],
+    };
+    Some(FlowBody::Text(template))
+}
+
+fn substitute(text: &str, value: &str, var: &str) -> Template {
+    let mut template = Template::new();
+    for (i, chunk) in text.split(value).enumerate() {
+        if i > 0 {
+            template.push(Part::Var(var.to_string()));
+        }
+        push_text(&mut template, chunk);
+    }
+    template
+}
+
+/// Captured text as a template, with redacted values as `Part::Env`
+pub fn template(text: &str) -> Template {
+    let mut template = Template::new();
+    push_text(&mut template, text);
+    template
+}
+
+fn push_text(template: &mut Template, text: &str) {
+    for segment in redact::split(text) {
+        match (segment, template.last_mut()) {
+            (Segment::Text(text), Some(Part::Text(existing))) => existing.push_str(text),
+            (Segment::Text(text), _) => template.push(Part::Text(text.to_string())),
+            (Segment::Env(name), _) => template.push(Part::Env(name.to_string())),
+        }
+    }
+}
+
+/// Render a JSON body in a language's literal syntax, emitting `var` for
+/// every leaf bound to a variable
+pub fn render_json(
+    value: &Value,
+    vars: &BTreeMap<String, String>,
+    syntax: &dyn Fn(&Value) -> Option<String>,
+    var: &dyn Fn(&str) -> String,
+    object: &dyn Fn(Vec<(String, String)>) -> String,
+    array: &dyn Fn(Vec<String>) -> String,
+) -> String {
+    fn walk(
+        value: &Value,
+        pointer: String,
+        vars: &BTreeMap<String, String>,
+        syntax: &dyn Fn(&Value) -> Option<String>,
+        var: &dyn Fn(&str) -> String,
+        object: &dyn Fn(Vec<(String, String)>) -> String,
+        array: &dyn Fn(Vec<String>) -> String,
+    ) -> String {
+        if let Some(name) = vars.get(&pointer) {
+            return var(name);
+        }
+        match value {
+            Value::Object(map) => object(
+                map.iter()
+                    .map(|(key, child)| {
+                        let escaped = key.replace('~', "~0").replace('/', "~1");
+                        let child = walk(
+                            child,
+                            format!("{}/{}", pointer, escaped),
+                            vars,
+                            syntax,
+                            var,
+                            object,
+                            array,
+                        );
+                        (key.clone(), child)
+                    })
+                    .collect(),
+            ),
+            Value::Array(items) => array(
+                items
+                    .iter()
+                    .enumerate()
+                    .map(|(i, child)| {
+                        walk(
+                            child,
+                            format!("{}/{}", pointer, i),
+                            vars,
+                            syntax,
+                            var,
+                            object,
+                            array,
+                        )
+                    })
+                    .collect(),
+            ),
+            scalar => syntax(scalar).unwrap_or_else(|| scalar.to_string()),
+        }
+    }
+
+    walk(value, String::new(), vars, syntax, var, object, array)
+}
+
+/// Split a JSON body into literal JSON text and variables, for languages
+/// that build bodies by string concatenation
+pub fn json_parts(value: &Value, vars: &BTreeMap<String, String>) -> Template {
+    const MARK: char = '\u{1}';
+
+    let text = render_json(
+        value,
+        vars,
+        &|_| None,
+        &|name| format!("{}{}{}", MARK, name, MARK),
+        &|fields| {
+            let fields: Vec<String> = fields
+                .into_iter()
+                .map(|(k, v)| format!("{}:{}", Value::String(k), v))
+                .collect();
+            format!("{{{}}}", fields.join(","))
+        },
+        &|items| format!("[{}]", items.join(",")),
+    );
+
+    let mut template = Template::new();
+    for (i, chunk) in text.split(MARK).enumerate() {
+        if i % 2 == 1 {
+            template.push(Part::Var(chunk.to_string()));
+        } else {
+            push_text(&mut template, chunk);
+        }
+    }
+    template
+}
+
+/// `order_id` -> `orderId`, for languages with camelCase locals
+pub fn camel_case(name: &str) -> String {
+    let mut out = String::new();
+    for (i, word) in name.split('_').filter(|w| !w.is_empty()).enumerate() {
+        let mut chars = word.chars();
+        match chars.next() {
+            Some(c) if i > 0 => out.extend(c.to_uppercase().chain(chars)),
+            Some(_) => out.push_str(word),
+            None => {}
+        }
+    }
+    out
+}
+
+fn find(parent: &mut [usize], mut x: usize) -> usize {
+    while parent[x] != x {
+        parent[x] = parent[parent[x]];
+        x = parent[x];
+    }
+    x
+}
+
+fn union(parent: &mut [usize], a: usize, b: usize) {
+    let (a, b) = (find(parent, a), find(parent, b));
+    if a != b {
+        parent[a.max(b)] = a.min(b);
+    }
+}
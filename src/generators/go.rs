@@ -1,7 +1,99 @@
-use crate::generators::TestGenerator;
+use crate::generators::flow::{self, Flow, FlowBody, Key, Part, Template};
+use crate::generators::{
+    SuiteConfig, TestGenerator, Varying, group_by_endpoint, request_headers, unique_name, variants,
+};
 use crate::models::CapturedRequest;
 use anyhow::Result;
-use std::collections::HashMap;
+use std::collections::HashSet;
+
+/// Settings lookup shared by every generated file
+const ENV_HELPER: &str = r#"// env reads a setting for the current TEST_ENV, e.g. STAGING_BASE_URL over BASE_URL
+func env(name, fallback string) string {
+	if prefix := []byte(os.Getenv("TEST_ENV")); len(prefix) > 0 {
+		for i, c := range prefix {
+			if 'a' <= c && c <= 'z' {
+				prefix[i] = c - 'a' + 'A'
+			}
+		}
+		if value, ok := os.LookupEnv(string(prefix) + "_" + name); ok {
+			return value
+		}
+	}
+	if value, ok := os.LookupEnv(name); ok {
+		return value
+	}
+	return fallback
+}
+
+"#;
+
+/// Helpers shared by scenario tests
+const FLOW_HELPERS: &str = r#"// send issues one scenario request and decodes any JSON response
+func send(t *testing.T, method, url, body string, headers map[string]string) (int, any) {
+	t.Helper()
+	var reader io.Reader
+	if body != "" {
+		reader = strings.NewReader(body)
+	}
+	req, err := http.NewRequest(method, url, reader)
+	if err != nil {
+		t.Fatal(err)
+	}
+	for key, value := range headers {
+		req.Header.Set(key, value)
+	}
+
+	client := &http.Client{}
+	resp, err := client.Do(req)
+	if err != nil {
+		t.Fatal(err)
+	}
+	defer resp.Body.Close()
+
+	var data any
+	decoder := json.NewDecoder(resp.Body)
+	decoder.UseNumber()
+	_ = decoder.Decode(&data)
+	return resp.StatusCode, data
+}
+
+// field walks object keys and array indexes into a decoded response
+func field(t *testing.T, data any, path ...any) any {
+	t.Helper()
+	for _, key := range path {
+		switch k := key.(type) {
+		case string:
+			object, ok := data.(map[string]any)
+			if !ok {
+				t.Fatalf("expected an object holding %q", k)
+			}
+			data = object[k]
+		case int:
+			array, ok := data.([]any)
+			if !ok || k >= len(array) {
+				t.Fatalf("expected an array with index %d", k)
+			}
+			data = array[k]
+		}
+	}
+	return data
+}
+
+// jsonText encodes an extracted value for a JSON request body
+func jsonText(value any) string {
+	encoded, _ := json.Marshal(value)
+	return string(encoded)
+}
+
+"#;
+
+/// Imports a generated file needs
+#[derive(Default)]
+struct Imports {
+    fmt: bool,
+    strings: bool,
+    flows: bool,
+}
 
 pub struct GoGenerator;
 
@@ -10,24 +102,11 @@ impl GoGenerator {
         Self
     }
 
-    fn group_by_endpoint<'a>(
-        &self,
-        requests: &'a [CapturedRequest],
-    ) -> HashMap<String, Vec<&'a CapturedRequest>> {
-        let mut grouped: HashMap<String, Vec<&'a CapturedRequest>> = HashMap::new();
-
-        for req in requests {
-            let key = format!("{} {}", req.request.method, req.request.uri);
-            grouped.entry(key).or_default().push(req);
-        }
-
-        grouped
-    }
-
     fn sanitize_test_name(&self, name: &str) -> String {
-        name.split_whitespace()
+        name.split(|c: char| !c.is_ascii_alphanumeric())
+            .filter(|s| !s.is_empty())
             .map(|s| {
-                let s = s.replace(['/', '-'], "_");
+                let s = s.to_lowercase();
                 let mut chars = s.chars();
                 match chars.next() {
                     None => String::new(),
@@ -37,71 +116,313 @@ impl GoGenerator {
             .collect::<Vec<_>>()
             .join("")
     }
-}
 
-impl TestGenerator for GoGenerator {
-    fn generate(&self, requests: &[CapturedRequest]) -> Result<String> {
-        let mut output = String::new();
+    /// Build, send and check one request; `path`, `body` and `status` are Go
+    /// expressions
+    fn write_request(
+        &self,
+        output: &mut String,
+        indent: &str,
+        req: &CapturedRequest,
+        path: &str,
+        body: Option<&str>,
+        status: Option<&str>,
+    ) {
+        let body = body
+            .map(|b| format!("strings.NewReader({})", b))
+            .unwrap_or_else(|| "nil".to_string());
+        output.push_str(&format!(
+            "{}req, err := http.NewRequest(\"{}\", baseURL+{}, {})\n",
+            indent, req.request.method, path, body
+        ));
+        output.push_str(&format!("{}if err != nil {{\n", indent));
+        output.push_str(&format!("{}\tt.Fatal(err)\n", indent));
+        output.push_str(&format!("{}}}\n\n", indent));
 
-        output.push_str("package main\n\n");
-        output.push_str("import (\n");
-        output.push_str("\t\"net/http\"\n");
-        output.push_str("\t\"testing\"\n");
-        output.push_str(")\n\n");
-        output.push_str("const baseURL = \"http://localhost:8080\"\n\n");
+        let headers = request_headers(req);
+        for (key, value) in &headers {
+            output.push_str(&format!(
+                "{}req.Header.Set({}, {})\n",
+                indent,
+                string_literal(key),
+                concat(&flow::template(value), "")
+            ));
+        }
+        if !headers.is_empty() {
+            output.push('\n');
+        }
 
-        let grouped = self.group_by_endpoint(requests);
+        output.push_str(&format!("{}client := &http.Client{{}}\n", indent));
+        output.push_str(&format!("{}resp, err := client.Do(req)\n", indent));
+        output.push_str(&format!("{}if err != nil {{\n", indent));
+        output.push_str(&format!("{}\tt.Fatal(err)\n", indent));
+        output.push_str(&format!("{}}}\n", indent));
+        output.push_str(&format!("{}defer resp.Body.Close()\n\n", indent));
 
-        for (endpoint, reqs) in grouped.iter() {
-            let first_req = reqs[0];
-            let test_name = self.sanitize_test_name(endpoint);
+        match status {
+            Some(status) => {
+                output.push_str(&format!("{}if resp.StatusCode != {} {{\n", indent, status));
+                output.push_str(&format!(
+                    "{}\tt.Errorf(\"expected status %d, got %d\", {}, resp.StatusCode)\n",
+                    indent, status
+                ));
+                output.push_str(&format!("{}}}\n", indent));
+            }
+            None => {
+                output.push_str(&format!("{}if resp.StatusCode >= 500 {{\n", indent));
+                output.push_str(&format!(
+                    "{}\tt.Errorf(\"server error: %d\", resp.StatusCode)\n",
+                    indent
+                ));
+                output.push_str(&format!("{}}}\n", indent));
+            }
+        }
+    }
+
+    /// Scenario test replaying a flow, passing extracted values forward;
+    /// returns whether it formats values with `fmt`
+    fn write_flow(&self, output: &mut String, flow: &Flow, test_name: &str) -> bool {
+        let mut uses_fmt = false;
+
+        output.push_str(&format!("func {}(t *testing.T) {{\n", test_name));
+        output.push_str(&format!("\t// Scenario: {}\n", flow.summary()));
 
-            output.push_str(&format!("func Test{}(t *testing.T) {{\n", test_name));
-            output.push_str(&format!("\t// Test {} endpoint\n", endpoint));
+        for (i, step) in flow.steps.iter().enumerate() {
+            if i > 0 {
+                output.push('\n');
+            }
+
+            let url = concat(&step.uri, "fmt.Sprint");
+            let headers = match step.headers.is_empty() {
+                true => "nil".to_string(),
+                false => {
+                    let entries: Vec<String> = step
+                        .headers
+                        .iter()
+                        .map(|(key, value)| {
+                            format!("{}: {}", string_literal(key), concat(value, "fmt.Sprint"))
+                        })
+                        .collect();
+                    format!("map[string]string{{{}}}", entries.join(", "))
+                }
+            };
+            let body = match &step.body {
+                Some(FlowBody::Json(value, vars)) => {
+                    concat(&flow::json_parts(value, vars), "jsonText")
+                }
+                Some(FlowBody::Text(text)) => concat(text, "fmt.Sprint"),
+                None => "\"\"".to_string(),
+            };
+            uses_fmt |= url.contains("fmt.") || headers.contains("fmt.") || body.contains("fmt.");
+
+            let assign = if i == 0 { ":=" } else { "=" };
             output.push_str(&format!(
-                "\treq, err := http.NewRequest(\"{}\", baseURL+\"{}\", nil)\n",
-                first_req.request.method, first_req.request.uri
+                "\tstatus, data {} send(t, \"{}\", baseURL+{}, {}, {})\n",
+                assign, step.request.request.method, url, body, headers
             ));
-            output.push_str("\tif err != nil {\n");
-            output.push_str("\t\tt.Fatal(err)\n");
-            output.push_str("\t}\n\n");
 
-            for (key, value) in &first_req.request.headers {
-                if key != "host" && key != "content-length" {
-                    output.push_str(&format!("\treq.Header.Set(\"{}\", \"{}\")\n", key, value));
+            match step.request.response.as_ref().map(|r| r.status_code) {
+                Some(status) => {
+                    output.push_str(&format!("\tif status != {} {{\n", status));
+                    output.push_str(&format!(
+                        "\t\tt.Errorf(\"expected status %d, got %d\", {}, status)\n",
+                        status
+                    ));
+                }
+                None => {
+                    output.push_str("\tif status >= 500 {\n");
+                    output.push_str("\t\tt.Errorf(\"server error: %d\", status)\n");
                 }
             }
-
-            output.push_str("\n\tclient := &http.Client{}\n");
-            output.push_str("\tresp, err := client.Do(req)\n");
-            output.push_str("\tif err != nil {\n");
-            output.push_str("\t\tt.Fatal(err)\n");
             output.push_str("\t}\n");
-            output.push_str("\tdefer resp.Body.Close()\n\n");
 
-            if let Some(response) = &first_req.response {
-                output.push_str(&format!(
-                    "\tif resp.StatusCode != {} {{\n",
-                    response.status_code
-                ));
+            for extract in &step.extracts {
+                let path: Vec<String> = extract
+                    .path
+                    .iter()
+                    .map(|key| match key {
+                        Key::Field(name) => string_literal(name),
+                        Key::Index(i) => i.to_string(),
+                    })
+                    .collect();
                 output.push_str(&format!(
-                    "\t\tt.Errorf(\"expected status {}, got %d\", resp.StatusCode)\n",
-                    response.status_code
+                    "\t{} := field(t, data, {})\n",
+                    go_ident(&extract.variable),
+                    path.join(", ")
                 ));
-                output.push_str("\t}\n");
-            } else {
-                output.push_str("\tif resp.StatusCode >= 500 {\n");
-                output.push_str("\t\tt.Errorf(\"server error: %d\", resp.StatusCode)\n");
-                output.push_str("\t}\n");
             }
+        }
+
+        output.push_str("}\n\n");
+
+        uses_fmt
+    }
 
-            output.push_str(&format!("\t// Called {} times in capture\n", reqs.len()));
-            output.push_str("}\n\n");
+    /// Emit a test; returns whether it sends a body through `strings`
+    fn write_test(
+        &self,
+        output: &mut String,
+        test_name: &str,
+        endpoint: &str,
+        reqs: &[&CapturedRequest],
+    ) -> bool {
+        let first_req = reqs[0];
+
+        let variants = variants(reqs);
+        let varying = Varying::of(&variants);
+        let first = &variants[0];
+
+        output.push_str(&format!("func {}(t *testing.T) {{\n", test_name));
+        output.push_str(&format!("\t// Test {} endpoint\n", endpoint));
+
+        let fixed_body = first.body.map(body_literal);
+        let fixed_status = first.status.map(|s| s.to_string());
+
+        if !varying.any() {
+            self.write_request(
+                output,
+                "\t",
+                first_req,
+                &concat(&flow::template(first.uri), ""),
+                fixed_body.as_deref(),
+                fixed_status.as_deref(),
+            );
+        } else {
+            // Table-driven test over the captured variants
+            let mut fields = vec![("name", "string")];
+            if varying.uri {
+                fields.push(("path", "string"));
+            }
+            if varying.body {
+                fields.push(("body", "string"));
+            }
+            if varying.status {
+                fields.push(("expectedStatus", "int"));
+            }
+            let width = fields.iter().map(|(name, _)| name.len()).max().unwrap_or(0);
+
+            output.push_str("\tcases := []struct {\n");
+            for (name, ty) in &fields {
+                output.push_str(&format!("\t\t{:width$} {}\n", name, ty, width = width));
+            }
+            output.push_str("\t}{\n");
+
+            for (i, variant) in variants.iter().enumerate() {
+                let mut label = Vec::new();
+                let mut values = Vec::new();
+                if varying.uri {
+                    label.push(variant.uri.to_string());
+                    values.push(format!(
+                        "path: {}",
+                        concat(&flow::template(variant.uri), "")
+                    ));
+                }
+                if varying.body {
+                    values.push(format!(
+                        "body: {}",
+                        variant.body.map(body_literal).unwrap_or("\"\"".to_string())
+                    ));
+                }
+                if let (true, Some(status)) = (varying.status, variant.status) {
+                    label.push(status.to_string());
+                    values.push(format!("expectedStatus: {}", status));
+                }
+                if label.is_empty() {
+                    label.push(format!("case {}", i + 1));
+                }
+                values.insert(0, format!("name: {}", string_literal(&label.join(" "))));
+                output.push_str(&format!("\t\t{{{}}},\n", values.join(", ")));
+            }
+            output.push_str("\t}\n\n");
+
+            output.push_str("\tfor _, tc := range cases {\n");
+            output.push_str("\t\tt.Run(tc.name, func(t *testing.T) {\n");
+
+            let path = match varying.uri {
+                true => "tc.path".to_string(),
+                false => concat(&flow::template(first.uri), ""),
+            };
+            let body = match varying.body {
+                true => Some("tc.body".to_string()),
+                false => fixed_body.clone(),
+            };
+            let status = match varying.status {
+                true => Some("tc.expectedStatus".to_string()),
+                false => fixed_status,
+            };
+            self.write_request(
+                output,
+                "\t\t\t",
+                first_req,
+                &path,
+                body.as_deref(),
+                status.as_deref(),
+            );
+
+            output.push_str("\t\t})\n");
+            output.push_str("\t}\n");
         }
 
-        if output
-            == "package main\n\nimport (\n\t\"net/http\"\n\t\"testing\"\n)\n\nconst baseURL = \"http://localhost:8080\"\n\n"
-        {
+        output.push_str(&format!("\t// Called {} times in capture\n", reqs.len()));
+        output.push_str("}\n\n");
+
+        varying.body || fixed_body.is_some()
+    }
+}
+
+impl TestGenerator for GoGenerator {
+    fn generate_with(&self, requests: &[CapturedRequest], config: &SuiteConfig) -> Result<String> {
+        let mut tests = String::new();
+        let mut imports = Imports::default();
+
+        let grouped = group_by_endpoint(requests);
+        let mut names = HashSet::new();
+
+        for (endpoint, reqs) in grouped.iter() {
+            let base = format!("Test{}", self.sanitize_test_name(endpoint));
+            let test_name = unique_name(&mut names, base, "");
+            imports.strings |= self.write_test(&mut tests, &test_name, endpoint, reqs);
+        }
+
+        for flow in flow::detect(requests) {
+            let base = format!("TestFlow{}", self.sanitize_test_name(flow.name()));
+            let test_name = unique_name(&mut names, base, "");
+            imports.fmt |= self.write_flow(&mut tests, &flow, &test_name);
+            imports.flows = true;
+            imports.strings = true;
+        }
+
+        let mut output = String::new();
+
+        output.push_str("package main\n\n");
+        output.push_str("import (\n");
+        if imports.flows {
+            output.push_str("\t\"encoding/json\"\n");
+        }
+        if imports.fmt {
+            output.push_str("\t\"fmt\"\n");
+        }
+        if imports.flows {
+            output.push_str("\t\"io\"\n");
+        }
+        output.push_str("\t\"net/http\"\n");
+        output.push_str("\t\"os\"\n");
+        if imports.strings {
+            output.push_str("\t\"strings\"\n");
+        }
+        output.push_str("\t\"testing\"\n");
+        output.push_str(")\n\n");
+        output.push_str(ENV_HELPER);
+        output.push_str(&format!(
+            "var baseURL = env(\"BASE_URL\", {})\n\n",
+            string_literal(&config.base_url)
+        ));
+        if imports.flows {
+            output.push_str(FLOW_HELPERS);
+        }
+        output.push_str(&tests);
+
+        if grouped.is_empty() {
             output.push_str("// No requests captured\n");
         }
 
@@ -112,3 +433,38 @@ impl TestGenerator for GoGenerator {
         "go"
     }
 }
+
+/// Quote a string as a Go interpreted string literal
+fn string_literal(value: &str) -> String {
+    serde_json::to_string(value).unwrap_or_else(|_| "\"\"".to_string())
+}
+
+/// Template as a Go string expression, converting variables with `convert`
+fn concat(template: &Template, convert: &str) -> String {
+    if template.is_empty() {
+        return "\"\"".to_string();
+    }
+
+    template
+        .iter()
+        .map(|part| match part {
+            Part::Text(text) => string_literal(text),
+            Part::Var(name) => format!("{}({})", convert, go_ident(name)),
+            Part::Env(name) => format!("env({}, \"\")", string_literal(name)),
+        })
+        .collect::<Vec<_>>()
+        .join("+")
+}
+
+/// camelCase with Go's initialism for IDs, e.g. `order_id` -> `orderID`
+fn go_ident(name: &str) -> String {
+    let ident = flow::camel_case(name);
+    match ident.strip_suffix("Id") {
+        Some(stem) => format!("{}ID", stem),
+        None => ident,
+    }
+}
+
+fn body_literal(body: &[u8]) -> String {
+    concat(&flow::template(&String::from_utf8_lossy(body)), "")
+}
@@ -0,0 +1,623 @@
+use crate::generators::flow::{self, Flow, FlowBody, Key, Part, Template};
+use crate::generators::{
+    SuiteConfig, TestGenerator, Varying, common_json, group_by_endpoint, unique_name, variants,
+};
+use crate::models::CapturedRequest;
+use anyhow::Result;
+use serde_json::Value;
+use std::collections::HashSet;
+
+/// Headers that `java.net.http.HttpClient` refuses to set explicitly
+const RESTRICTED_HEADERS: [&str; 5] = ["connection", "content-length", "expect", "host", "upgrade"];
+
+/// Settings lookup shared by every generated file
+const ENV_HELPER: &str = r#"
+    /** Setting for the current TEST_ENV, e.g. STAGING_BASE_URL over BASE_URL */
+    static String env(String name, String fallback) {
+        String prefix = System.getenv("TEST_ENV");
+        if (prefix != null && !prefix.isEmpty()) {
+            String scoped = System.getenv(prefix.toUpperCase() + "_" + name);
+            if (scoped != null) {
+                return scoped;
+            }
+        }
+        String value = System.getenv(name);
+        return value == null ? fallback : value;
+    }
+"#;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JavaClient {
+    RestAssured,
+    HttpClient,
+}
+
+pub struct JavaGenerator {
+    client: JavaClient,
+}
+
+impl JavaGenerator {
+    pub fn new(framework: &str) -> Result<Self> {
+        let client = match framework.to_lowercase().as_str() {
+            "auto" | "junit" | "junit5" | "restassured" | "rest-assured" => JavaClient::RestAssured,
+            "httpclient" | "http-client" => JavaClient::HttpClient,
+            other => anyhow::bail!("Unsupported Java framework: {}", other),
+        };
+
+        Ok(Self { client })
+    }
+
+    /// camelCase method name, e.g. `GET /api/users/{id}` -> `getApiUsersId`
+    fn sanitize_test_name(&self, name: &str) -> String {
+        let words: Vec<String> = name
+            .split(|c: char| !c.is_ascii_alphanumeric())
+            .filter(|s| !s.is_empty())
+            .map(|s| s.to_lowercase())
+            .collect();
+
+        let mut method = String::new();
+        for (i, word) in words.iter().enumerate() {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(c) if i > 0 => method.extend(c.to_uppercase().chain(chars)),
+                Some(_) => method.push_str(word),
+                None => {}
+            }
+        }
+
+        if method.starts_with(|c: char| c.is_ascii_digit()) || method.is_empty() {
+            format!("test{}", method)
+        } else {
+            method
+        }
+    }
+
+    /// Emit the request; `path`, `body` and `status` are Java expressions
+    fn write_rest_assured(
+        &self,
+        output: &mut String,
+        req: &CapturedRequest,
+        headers: &[(&String, String)],
+        request: (&str, Option<&str>, Option<&str>),
+        assertions: &[(String, String)],
+    ) {
+        let (path, body, status) = request;
+        output.push_str("        given()\n");
+        for (key, value) in headers {
+            output.push_str(&format!(
+                "            .header({}, {})\n",
+                string_literal(key),
+                value
+            ));
+        }
+        if let Some(body) = body {
+            output.push_str(&format!("            .body({})\n", body));
+        }
+
+        output.push_str("        .when()\n");
+        output.push_str(&format!(
+            "            .request({}, {})\n",
+            string_literal(&req.request.method),
+            path
+        ));
+        output.push_str("        .then()\n");
+
+        match status {
+            Some(status) => output.push_str(&format!("            .statusCode({})", status)),
+            None => output.push_str("            .statusCode(lessThan(500))"),
+        }
+
+        for (path, value) in assertions {
+            output.push_str(&format!(
+                "\n            .body({}, {})",
+                string_literal(path),
+                value
+            ));
+        }
+        output.push_str(";\n");
+    }
+
+    fn write_http_client(
+        &self,
+        output: &mut String,
+        req: &CapturedRequest,
+        headers: &[(&String, String)],
+        request: (&str, Option<&str>, Option<&str>),
+        assertions: &[(String, String)],
+    ) {
+        let (path, body, status) = request;
+        output.push_str(&format!(
+            "        HttpRequest request = HttpRequest.newBuilder(URI.create(BASE_URL + {}))\n",
+            path
+        ));
+        for (key, value) in headers {
+            output.push_str(&format!(
+                "            .header({}, {})\n",
+                string_literal(key),
+                value
+            ));
+        }
+
+        let publisher = match body {
+            Some(body) => format!("HttpRequest.BodyPublishers.ofString({})", body),
+            None => "HttpRequest.BodyPublishers.noBody()".to_string(),
+        };
+        output.push_str(&format!(
+            "            .method({}, {})\n",
+            string_literal(&req.request.method),
+            publisher
+        ));
+        output.push_str("            .build();\n");
+        output.push_str(
+            "        HttpResponse<String> response = client.send(request, HttpResponse.BodyHandlers.ofString());\n\n",
+        );
+
+        match status {
+            Some(status) => output.push_str(&format!(
+                "        assertEquals({}, response.statusCode());\n",
+                status
+            )),
+            None => output.push_str("        assertTrue(response.statusCode() < 500);\n"),
+        }
+
+        for (path, value) in assertions {
+            output.push_str(&format!(
+                "        assertEquals({}, JsonPath.read(response.body(), {}));\n",
+                value,
+                string_literal(path)
+            ));
+        }
+    }
+
+    /// Scalar JSON fields shared by all responses as (path, Java expression)
+    fn json_assertions(&self, reqs: &[&CapturedRequest]) -> Vec<(String, String)> {
+        let value = common_json(reqs);
+
+        let mut leaves = Vec::new();
+        if let Some(value @ Value::Object(_)) = &value {
+            collect_leaves(value, &mut Vec::new(), &mut leaves);
+        }
+
+        leaves
+            .into_iter()
+            .map(|(path, value)| match self.client {
+                JavaClient::RestAssured => {
+                    let matcher = match value {
+                        Value::Null => "nullValue()".to_string(),
+                        // RestAssured parses JSON decimals as floats by default
+                        Value::Number(n) if n.is_f64() => format!("equalTo({}f)", n),
+                        other => format!("equalTo({})", java_literal(other)),
+                    };
+                    (path.join("."), matcher)
+                }
+                JavaClient::HttpClient => {
+                    let expected = match value {
+                        Value::Null => "(Object) null".to_string(),
+                        other => java_literal(other),
+                    };
+                    (format!("$.{}", path.join(".")), expected)
+                }
+            })
+            .collect()
+    }
+
+    /// Scenario test replaying a flow, passing extracted values forward
+    fn write_flow(&self, output: &mut String, flow: &Flow, test_name: &str) {
+        output.push_str("\n    @Test\n");
+        output.push_str(&format!(
+            "    @DisplayName({})\n",
+            string_literal(&format!("scenario: {}", flow.summary()))
+        ));
+        let throws = match self.client {
+            JavaClient::RestAssured => "",
+            JavaClient::HttpClient => " throws Exception",
+        };
+        output.push_str(&format!("    void {}(){} {{\n", test_name, throws));
+
+        let mut response_declared = false;
+        for (i, step) in flow.steps.iter().enumerate() {
+            if i > 0 {
+                output.push('\n');
+            }
+
+            let headers: Vec<(&String, String)> = step
+                .headers
+                .iter()
+                .filter(|(key, _)| !RESTRICTED_HEADERS.contains(&key.to_lowercase().as_str()))
+                .map(|(key, value)| (*key, concat(value, false)))
+                .collect();
+            let body = match &step.body {
+                Some(FlowBody::Json(value, vars)) => {
+                    Some(concat(&flow::json_parts(value, vars), true))
+                }
+                Some(FlowBody::Text(text)) => Some(concat(text, false)),
+                None => None,
+            };
+            let status = step
+                .request
+                .response
+                .as_ref()
+                .map(|r| r.status_code.to_string());
+            let path = concat(&step.uri, false);
+            let request = (path.as_str(), body.as_deref(), status.as_deref());
+
+            let mut call = String::new();
+            match self.client {
+                JavaClient::RestAssured => {
+                    self.write_rest_assured(&mut call, step.request, &headers, request, &[]);
+                    if !step.extracts.is_empty() {
+                        // Keep the response around to read values from it
+                        call.truncate(call.len() - ";\n".len());
+                        call.push_str("\n            .extract().response();\n");
+                        let binding = match response_declared {
+                            true => "response = ",
+                            false => "Response response = ",
+                        };
+                        call.insert_str("        ".len(), binding);
+                        response_declared = true;
+                    }
+                }
+                JavaClient::HttpClient => {
+                    self.write_http_client(&mut call, step.request, &headers, request, &[]);
+                    // Steps share one request and response, reassigned after the first
+                    if i > 0 {
+                        call = call.replacen("HttpRequest request", "request", 1).replacen(
+                            "HttpResponse<String> response",
+                            "response",
+                            1,
+                        );
+                    }
+                }
+            }
+            output.push_str(&call);
+
+            for extract in &step.extracts {
+                let read = match self.client {
+                    JavaClient::RestAssured => {
+                        format!("response.path({})", string_literal(&gpath(&extract.path)))
+                    }
+                    JavaClient::HttpClient => format!(
+                        "JsonPath.read(response.body(), {})",
+                        string_literal(&json_path(&extract.path))
+                    ),
+                };
+                output.push_str(&format!(
+                    "        Object {} = {};\n",
+                    flow::camel_case(&extract.variable),
+                    read
+                ));
+            }
+        }
+
+        output.push_str("    }\n");
+    }
+
+    fn header(&self, config: &SuiteConfig, parametrized: bool, flows: bool) -> String {
+        let mut output = String::new();
+
+        match self.client {
+            JavaClient::RestAssured => {
+                output.push_str("import static io.restassured.RestAssured.given;\n");
+                output.push_str("import static org.hamcrest.Matchers.*;\n\n");
+                output.push_str("import io.restassured.RestAssured;\n");
+                if flows {
+                    output.push_str("import io.restassured.response.Response;\n");
+                }
+                output.push_str("import org.junit.jupiter.api.BeforeAll;\n");
+            }
+            JavaClient::HttpClient => {
+                output.push_str("import static org.junit.jupiter.api.Assertions.*;\n\n");
+                output.push_str("import com.jayway.jsonpath.JsonPath;\n");
+                output.push_str("import java.net.URI;\n");
+                output.push_str("import java.net.http.HttpClient;\n");
+                output.push_str("import java.net.http.HttpRequest;\n");
+                output.push_str("import java.net.http.HttpResponse;\n");
+            }
+        }
+        if parametrized {
+            output.push_str("import java.util.stream.Stream;\n");
+        }
+        output.push_str("import org.junit.jupiter.api.DisplayName;\n");
+        output.push_str("import org.junit.jupiter.api.Test;\n");
+        if parametrized {
+            output.push_str("import org.junit.jupiter.params.ParameterizedTest;\n");
+            output.push_str("import org.junit.jupiter.params.provider.Arguments;\n");
+            output.push_str("import org.junit.jupiter.params.provider.MethodSource;\n");
+        }
+        output.push('\n');
+
+        output.push_str("class GeneratedApiTest {\n");
+        output.push_str(&format!(
+            "    static final String BASE_URL = env(\"BASE_URL\", {});\n",
+            string_literal(&config.base_url)
+        ));
+        match self.client {
+            JavaClient::RestAssured => {
+                output.push_str("\n    @BeforeAll\n");
+                output.push_str("    static void setUp() {\n");
+                output.push_str("        RestAssured.baseURI = BASE_URL;\n");
+                output.push_str("    }\n");
+            }
+            JavaClient::HttpClient => {
+                output
+                    .push_str("    static final HttpClient client = HttpClient.newHttpClient();\n");
+            }
+        }
+        output.push_str(ENV_HELPER);
+        if flows {
+            output.push_str("\n    /** Extracted value as it appears in a JSON body */\n");
+            output.push_str("    static String json(Object value) {\n");
+            output.push_str("        if (value instanceof String s) {\n");
+            output.push_str(
+                r#"            return "\"" + s.replace("\\", "\\\\").replace("\"", "\\\"") + "\"";"#,
+            );
+            output.push('\n');
+            output.push_str("        }\n");
+            output.push_str("        return String.valueOf(value);\n");
+            output.push_str("    }\n");
+        }
+
+        output
+    }
+}
+
+impl TestGenerator for JavaGenerator {
+    fn generate_with(&self, requests: &[CapturedRequest], config: &SuiteConfig) -> Result<String> {
+        let mut tests = String::new();
+        let mut flows = String::new();
+        let mut method_names = HashSet::new();
+        let mut parametrized = false;
+
+        let grouped = group_by_endpoint(requests);
+
+        for (endpoint, reqs) in grouped.iter() {
+            let first_req = reqs[0];
+
+            let base = self.sanitize_test_name(endpoint);
+            let test_name = unique_name(&mut method_names, base, "");
+
+            let variants = variants(reqs);
+            let varying = Varying::of(&variants);
+            let first = &variants[0];
+
+            let mut params = Vec::new();
+            if varying.any() {
+                parametrized = true;
+
+                // Captured variants become the arguments of a parameterized test
+                let cases: Vec<String> = variants
+                    .iter()
+                    .map(|variant| {
+                        let mut values = Vec::new();
+                        if varying.uri {
+                            values.push(concat(&flow::template(variant.uri), false));
+                        }
+                        if varying.body {
+                            values.push(variant.body.map(body_literal).unwrap_or("\"\"".into()));
+                        }
+                        if varying.status {
+                            values.push(variant.status.unwrap_or_default().to_string());
+                        }
+                        format!("            Arguments.of({})", values.join(", "))
+                    })
+                    .collect();
+
+                tests.push_str(&format!(
+                    "\n    static Stream<Arguments> {}Cases() {{\n",
+                    test_name
+                ));
+                tests.push_str("        return Stream.of(\n");
+                tests.push_str(&cases.join(",\n"));
+                tests.push_str("\n        );\n");
+                tests.push_str("    }\n");
+
+                if varying.uri {
+                    params.push("String path");
+                }
+                if varying.body {
+                    params.push("String body");
+                }
+                if varying.status {
+                    params.push("int expectedStatus");
+                }
+
+                tests.push_str("\n    @ParameterizedTest\n");
+                tests.push_str(&format!("    @MethodSource(\"{}Cases\")\n", test_name));
+            } else {
+                tests.push_str("\n    @Test\n");
+            }
+
+            tests.push_str(&format!("    @DisplayName({})\n", string_literal(endpoint)));
+            let throws = match self.client {
+                JavaClient::RestAssured => "",
+                JavaClient::HttpClient => " throws Exception",
+            };
+            tests.push_str(&format!(
+                "    void {}({}){} {{\n",
+                test_name,
+                params.join(", "),
+                throws
+            ));
+
+            let mut headers: Vec<(&String, &String)> = first_req
+                .request
+                .headers
+                .iter()
+                .filter(|(key, _)| !RESTRICTED_HEADERS.contains(&key.to_lowercase().as_str()))
+                .collect();
+            headers.sort();
+            let headers: Vec<(&String, String)> = headers
+                .into_iter()
+                .map(|(key, value)| (key, concat(&flow::template(value), false)))
+                .collect();
+
+            let path = match varying.uri {
+                true => "path".to_string(),
+                false => concat(&flow::template(first.uri), false),
+            };
+            let body = match varying.body {
+                true => Some("body".to_string()),
+                false => first.body.map(body_literal),
+            };
+            let status = match varying.status {
+                true => Some("expectedStatus".to_string()),
+                false => first.status.map(|s| s.to_string()),
+            };
+            let request = (path.as_str(), body.as_deref(), status.as_deref());
+            let assertions = self.json_assertions(reqs);
+
+            match self.client {
+                JavaClient::RestAssured => {
+                    self.write_rest_assured(&mut tests, first_req, &headers, request, &assertions)
+                }
+                JavaClient::HttpClient => {
+                    self.write_http_client(&mut tests, first_req, &headers, request, &assertions)
+                }
+            }
+
+            tests.push_str(&format!(
+                "        // Called {} times in capture\n",
+                reqs.len()
+            ));
+            tests.push_str("    }\n");
+        }
+
+        for flow in flow::detect(requests) {
+            let base = format!("flow{}", capitalize(&self.sanitize_test_name(flow.name())));
+            let test_name = unique_name(&mut method_names, base, "");
+            self.write_flow(&mut flows, &flow, &test_name);
+        }
+
+        let mut output = self.header(config, parametrized, !flows.is_empty());
+        output.push_str(&tests);
+        output.push_str(&flows);
+
+        if grouped.is_empty() {
+            output.push_str("\n    // No requests captured\n");
+        }
+
+        output.push_str("}\n");
+
+        Ok(output)
+    }
+
+    fn file_extension(&self) -> &str {
+        "java"
+    }
+
+    fn file_name(&self) -> String {
+        // Surefire and Gradle only run classes named `*Test`, and the file
+        // has to match the class name
+        "GeneratedApiTest.java".to_string()
+    }
+}
+
+/// Scalar leaves of nested JSON objects with identifier-safe keys
+fn collect_leaves<'a>(
+    value: &'a Value,
+    path: &mut Vec<String>,
+    leaves: &mut Vec<(Vec<String>, &'a Value)>,
+) {
+    match value {
+        Value::Object(map) => {
+            for (key, child) in map {
+                if !is_identifier(key) {
+                    continue;
+                }
+                path.push(key.clone());
+                collect_leaves(child, path, leaves);
+                path.pop();
+            }
+        }
+        Value::Array(_) => {}
+        scalar => leaves.push((path.clone(), scalar)),
+    }
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(c) => c.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// RestAssured GPath to a response value, e.g. `items[0].id`
+fn gpath(path: &[Key]) -> String {
+    let mut out = String::new();
+    for key in path {
+        match key {
+            Key::Field(name) if is_identifier(name) => {
+                if !out.is_empty() {
+                    out.push('.');
+                }
+                out.push_str(name);
+            }
+            Key::Field(name) => {
+                if !out.is_empty() {
+                    out.push('.');
+                }
+                out.push_str(&format!("'{}'", name));
+            }
+            Key::Index(i) => out.push_str(&format!("[{}]", i)),
+        }
+    }
+    out
+}
+
+/// JsonPath to a response value, e.g. `$.items[0].id`
+fn json_path(path: &[Key]) -> String {
+    let mut out = String::from("$");
+    for key in path {
+        match key {
+            Key::Field(name) if is_identifier(name) => out.push_str(&format!(".{}", name)),
+            Key::Field(name) => out.push_str(&format!("['{}']", name)),
+            Key::Index(i) => out.push_str(&format!("[{}]", i)),
+        }
+    }
+    out
+}
+
+/// Template as a Java string concatenation, wrapping variables in
+/// `json(...)` when they land in a JSON body
+fn concat(template: &Template, json: bool) -> String {
+    if template.is_empty() {
+        return "\"\"".to_string();
+    }
+
+    template
+        .iter()
+        .map(|part| match part {
+            Part::Text(text) => string_literal(text),
+            Part::Var(name) if json => format!("json({})", flow::camel_case(name)),
+            Part::Var(name) => flow::camel_case(name),
+            Part::Env(name) => format!("env({}, \"\")", string_literal(name)),
+        })
+        .collect::<Vec<_>>()
+        .join(" + ")
+}
+
+/// Quote a string as a Java literal
+fn string_literal(value: &str) -> String {
+    serde_json::to_string(value).unwrap_or_else(|_| "\"\"".to_string())
+}
+
+fn body_literal(body: &[u8]) -> String {
+    concat(&flow::template(&String::from_utf8_lossy(body)), false)
+}
+
+fn java_literal(value: &Value) -> String {
+    match value {
+        Value::String(s) => string_literal(s),
+        Value::Number(n) if n.as_i64().is_some_and(|i| i32::try_from(i).is_err()) => {
+            format!("{}L", n)
+        }
+        other => other.to_string(),
+    }
+}
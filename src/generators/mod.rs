@@ -1,29 +1,260 @@
+pub mod flow;
 pub mod go;
+pub mod java;
 pub mod python;
 pub mod rust_gen;
+pub mod typescript;
 
 #[cfg(test)]
 mod tests;
 
-use crate::models::CapturedRequest;
+use crate::models::{CapturedRequest, Protocol};
+use crate::parsers::http::HttpParser;
+use crate::redact;
 use anyhow::Result;
 use go::GoGenerator;
+use hyper::Uri;
+use java::JavaGenerator;
 use python::PythonGenerator;
 use rust_gen::RustGenerator;
+use serde_json::{Map, Value};
+use std::collections::{BTreeMap, HashSet};
+use typescript::TypeScriptGenerator;
+
+/// Base URL baked into generated suites when the capture doesn't say where
+/// it came from
+pub const DEFAULT_BASE_URL: &str = "http://localhost:8080";
+
+/// Settings a generated suite is built with
+///
+/// Generated code reads every setting through an `env` helper that prefers
+/// `{TEST_ENV}_NAME` over `NAME`, so one suite serves local, CI and staging
+/// runs, e.g. `TEST_ENV=staging` picks up `STAGING_BASE_URL`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SuiteConfig {
+    /// Default for the `BASE_URL` environment variable
+    pub base_url: String,
+}
+
+impl Default for SuiteConfig {
+    fn default() -> Self {
+        Self {
+            base_url: DEFAULT_BASE_URL.to_string(),
+        }
+    }
+}
 
 pub trait TestGenerator {
-    fn generate(&self, requests: &[CapturedRequest]) -> Result<String>;
+    fn generate_with(&self, requests: &[CapturedRequest], config: &SuiteConfig) -> Result<String>;
     fn file_extension(&self) -> &str;
+
+    fn file_name(&self) -> String {
+        format!("test_generated.{}", self.file_extension())
+    }
 }
 
 pub fn get_generator(language: &str, framework: Option<&str>) -> Result<Box<dyn TestGenerator>> {
     match language.to_lowercase().as_str() {
         "python" | "py" | "auto" => {
             let framework = framework.unwrap_or("pytest");
-            Ok(Box::new(PythonGenerator::new(framework)))
+            Ok(Box::new(PythonGenerator::new(framework)?))
         }
-        "go" | "golang" => Ok(Box::new(GoGenerator::new())),
-        "rust" | "rs" => Ok(Box::new(RustGenerator::new())),
+        // Go and Rust suites only come in one flavour, named here so a
+        // framework meant for another language is not silently ignored
+        "go" | "golang" => match framework.unwrap_or("testing").to_lowercase().as_str() {
+            "auto" | "testing" => Ok(Box::new(GoGenerator::new())),
+            other => anyhow::bail!("Unsupported Go framework: {} (expected testing)", other),
+        },
+        "rust" | "rs" => match framework.unwrap_or("tokio").to_lowercase().as_str() {
+            "auto" | "tokio" | "reqwest" => Ok(Box::new(RustGenerator::new())),
+            other => anyhow::bail!(
+                "Unsupported Rust framework: {} (expected tokio or reqwest)",
+                other
+            ),
+        },
+        "java" => Ok(Box::new(JavaGenerator::new(framework.unwrap_or("junit5"))?)),
+        "typescript" | "ts" => Ok(Box::new(TypeScriptGenerator::new(
+            framework.unwrap_or("jest"),
+        )?)),
+        "javascript" | "js" | "node" => anyhow::bail!(
+            "Plain JavaScript suites are not generated; use --language typescript (Vitest runs it directly, Jest through ts-jest)"
+        ),
         _ => anyhow::bail!("Unsupported language: {}", language),
     }
 }
+
+/// Group requests by method and endpoint pattern, so `/users/1` and
+/// `/users/2` become variants of one test
+///
+/// Endpoints come back sorted so generated files are reproducible.
+pub fn group_by_endpoint(requests: &[CapturedRequest]) -> BTreeMap<String, Vec<&CapturedRequest>> {
+    let mut grouped: BTreeMap<String, Vec<&CapturedRequest>> = BTreeMap::new();
+
+    for req in requests {
+        let pattern = match req.request.uri.parse::<Uri>() {
+            Ok(uri) => HttpParser::extract_endpoint_pattern(&uri),
+            Err(_) => req.request.uri.clone(),
+        };
+        let key = format!("{} {}", req.request.method, pattern);
+        grouped.entry(key).or_default().push(req);
+    }
+
+    grouped
+}
+
+/// Base URL of the server a capture was recorded against, guessed from the
+/// most common `Host` header; `https` when most requests to it were
+pub fn captured_base_url(requests: &[CapturedRequest]) -> Option<String> {
+    let mut hosts: BTreeMap<&str, (usize, usize)> = BTreeMap::new();
+    for req in requests {
+        if let Some(host) = req.request.headers.get("host") {
+            let (count, https) = hosts.entry(host).or_default();
+            *count += 1;
+            if matches!(req.protocol, Protocol::Https) {
+                *https += 1;
+            }
+        }
+    }
+
+    let (host, (count, https)) = hosts.iter().max_by_key(|(_, (count, _))| *count)?;
+    let scheme = if https * 2 > *count { "https" } else { "http" };
+    Some(format!("{}://{}", scheme, host))
+}
+
+/// Send `headers` with every request, each read from the environment like a
+/// redacted value, e.g. `Authorization` from `AUTHORIZATION`
+pub fn inject_auth_headers(requests: &mut [CapturedRequest], headers: &[String]) {
+    for req in requests {
+        for name in headers {
+            let name = name.to_lowercase();
+            req.request
+                .headers
+                .retain(|key, _| key.to_lowercase() != name);
+            let value = redact::placeholder(&redact::env_name(&name));
+            req.request.headers.insert(name, value);
+        }
+    }
+}
+
+/// Headers worth replaying, sorted by name
+pub fn request_headers(req: &CapturedRequest) -> Vec<(&String, &String)> {
+    let mut headers: Vec<(&String, &String)> = req
+        .request
+        .headers
+        .iter()
+        .filter(|(key, _)| *key != "host" && *key != "content-length")
+        .collect();
+    headers.sort();
+    headers
+}
+
+/// `base`, or `base` with the first free numeric suffix when a sanitized
+/// name collides with one already taken
+pub fn unique_name(taken: &mut HashSet<String>, base: String, separator: &str) -> String {
+    let mut name = base.clone();
+    let mut n = 1;
+    while !taken.insert(name.clone()) {
+        n += 1;
+        name = format!("{}{}{}", base, separator, n);
+    }
+    name
+}
+
+/// One distinct observation of an endpoint: the concrete URI and body that
+/// were sent and the status they produced
+#[derive(Debug, Clone, PartialEq)]
+pub struct Variant<'a> {
+    pub uri: &'a str,
+    pub body: Option<&'a [u8]>,
+    pub status: Option<u16>,
+}
+
+/// Distinct variants of an endpoint in capture order
+///
+/// Requests that never got a response are dropped when others did, since
+/// they carry no expected status.
+pub fn variants<'a>(reqs: &[&'a CapturedRequest]) -> Vec<Variant<'a>> {
+    let any_response = reqs.iter().any(|r| r.response.is_some());
+    let mut variants: Vec<Variant> = Vec::new();
+
+    for req in reqs {
+        if any_response && req.response.is_none() {
+            continue;
+        }
+        let variant = Variant {
+            uri: &req.request.uri,
+            body: req.request.body.as_deref().filter(|b| !b.is_empty()),
+            status: req.response.as_ref().map(|r| r.status_code),
+        };
+        if !variants.contains(&variant) {
+            variants.push(variant);
+        }
+    }
+
+    variants
+}
+
+/// Which variant fields differ and therefore become test parameters
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Varying {
+    pub uri: bool,
+    pub body: bool,
+    pub status: bool,
+}
+
+impl Varying {
+    pub fn of(variants: &[Variant]) -> Self {
+        let Some(first) = variants.first() else {
+            return Self::default();
+        };
+
+        Self {
+            uri: variants.iter().any(|v| v.uri != first.uri),
+            body: variants.iter().any(|v| v.body != first.body),
+            status: variants.iter().any(|v| v.status != first.status),
+        }
+    }
+
+    pub fn any(&self) -> bool {
+        self.uri || self.body || self.status
+    }
+}
+
+/// The part of the JSON response bodies that every request agrees on
+///
+/// Objects keep only keys present with a common value in all bodies; other
+/// values must be equal. Redacted values are never shared, so tests don't
+/// assert on placeholders. Returns `None` when nothing is shared.
+pub fn common_json(reqs: &[&CapturedRequest]) -> Option<Value> {
+    let mut bodies = reqs.iter().filter_map(|r| {
+        let body = r.response.as_ref()?.body.as_deref()?;
+        serde_json::from_slice::<Value>(body).ok()
+    });
+
+    let first = bodies.next()?;
+    let first = intersect(&first, &first)?;
+    bodies.try_fold(first, |common, body| intersect(&common, &body))
+}
+
+fn intersect(a: &Value, b: &Value) -> Option<Value> {
+    match (a, b) {
+        (Value::Object(a), Value::Object(b)) => {
+            let common: Map<String, Value> = a
+                .iter()
+                .filter_map(|(key, value)| Some((key.clone(), intersect(value, b.get(key)?)?)))
+                .collect();
+            (!common.is_empty()).then_some(Value::Object(common))
+        }
+        _ if a == b && !contains_redacted(a) => Some(a.clone()),
+        _ => None,
+    }
+}
+
+fn contains_redacted(value: &Value) -> bool {
+    match value {
+        Value::String(s) => redact::is_redacted(s),
+        Value::Array(items) => items.iter().any(contains_redacted),
+        Value::Object(map) => map.values().any(contains_redacted),
+        _ => false,
+    }
+}
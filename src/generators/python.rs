@@ -1,96 +1,546 @@
-use crate::generators::TestGenerator;
+use crate::generators::flow::{self, Flow, FlowBody, Key, Part, Template};
+use crate::generators::{
+    SuiteConfig, TestGenerator, Varying, group_by_endpoint, request_headers, unique_name, variants,
+};
 use crate::models::CapturedRequest;
 use anyhow::Result;
-use std::collections::HashMap;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashSet};
 
-pub struct PythonGenerator;
+/// Settings lookup shared by every generated file
+const ENV_HELPER: &str = r#"def env(name, default=""):
+    """Setting for the current TEST_ENV, e.g. STAGING_BASE_URL over BASE_URL"""
+    prefix = os.environ.get("TEST_ENV", "").upper()
+    if prefix and f"{prefix}_{name}" in os.environ:
+        return os.environ[f"{prefix}_{name}"]
+    return os.environ.get(name, default)
 
-impl PythonGenerator {
-    pub fn new(_framework: &str) -> Self {
-        Self
-    }
 
-    fn group_by_endpoint<'a>(
-        &self,
-        requests: &'a [CapturedRequest],
-    ) -> HashMap<String, Vec<&'a CapturedRequest>> {
-        let mut grouped: HashMap<String, Vec<&'a CapturedRequest>> = HashMap::new();
+"#;
 
-        for req in requests {
-            let key = format!("{} {}", req.request.method, req.request.uri);
-            grouped.entry(key).or_default().push(req);
-        }
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PythonFramework {
+    Pytest,
+    Unittest,
+    HttpxAsync,
+}
+
+pub struct PythonGenerator {
+    framework: PythonFramework,
+}
 
-        grouped
+impl PythonGenerator {
+    pub fn new(framework: &str) -> Result<Self> {
+        let framework = match framework.to_lowercase().as_str() {
+            "auto" | "pytest" => PythonFramework::Pytest,
+            "unittest" => PythonFramework::Unittest,
+            "httpx" | "httpx-async" | "asyncio" | "pytest-asyncio" => PythonFramework::HttpxAsync,
+            other => anyhow::bail!("Unsupported Python framework: {}", other),
+        };
+
+        Ok(Self { framework })
     }
 
     fn sanitize_test_name(&self, name: &str) -> String {
         name.to_lowercase()
-            .replace(['/', '-'], "_")
-            .replace('?', "")
-            .replace(['&', '='], "_")
-            .trim_matches('_')
-            .to_string()
+            .split(|c: char| !c.is_ascii_alphanumeric())
+            .filter(|s| !s.is_empty())
+            .collect::<Vec<_>>()
+            .join("_")
     }
-}
 
-impl TestGenerator for PythonGenerator {
-    fn generate(&self, requests: &[CapturedRequest]) -> Result<String> {
+    fn header(&self, config: &SuiteConfig) -> String {
         let mut output = String::new();
 
-        output.push_str("import pytest\n");
-        output.push_str("import requests\n");
-        output.push_str("from typing import Dict, Any\n\n");
-        output.push_str("BASE_URL = \"http://localhost:8080\"\n\n");
+        match self.framework {
+            PythonFramework::Pytest => {
+                output.push_str("import os\n\n");
+                output.push_str("import pytest\n");
+                output.push_str("import requests\n\n");
+            }
+            PythonFramework::Unittest => {
+                output.push_str("import os\n");
+                output.push_str("import unittest\n\n");
+                output.push_str("import requests\n\n");
+            }
+            PythonFramework::HttpxAsync => {
+                output.push_str("import os\n\n");
+                output.push_str("import httpx\n");
+                output.push_str("import pytest\n");
+                output.push_str("import pytest_asyncio\n\n");
+            }
+        }
 
-        let grouped = self.group_by_endpoint(requests);
+        output.push_str(ENV_HELPER);
+        output.push_str(&format!(
+            "BASE_URL = env(\"BASE_URL\", {})\n\n\n",
+            string_literal(&config.base_url)
+        ));
 
-        for (endpoint, reqs) in grouped.iter() {
-            let first_req = reqs[0];
-            let test_name = self.sanitize_test_name(endpoint);
+        match self.framework {
+            PythonFramework::Pytest => {
+                output.push_str("@pytest.fixture(scope=\"session\")\n");
+                output.push_str("def base_url():\n");
+                output.push_str("    return BASE_URL\n\n\n");
+                output.push_str("@pytest.fixture\n");
+                output.push_str("def session():\n");
+                output.push_str("    with requests.Session() as s:\n");
+                output.push_str("        yield s\n\n\n");
+            }
+            PythonFramework::Unittest => {
+                output.push_str("class TestCapturedApi(unittest.TestCase):\n");
+                output.push_str("    @classmethod\n");
+                output.push_str("    def setUpClass(cls):\n");
+                output.push_str("        cls.session = requests.Session()\n\n");
+                output.push_str("    @classmethod\n");
+                output.push_str("    def tearDownClass(cls):\n");
+                output.push_str("        cls.session.close()\n\n");
+            }
+            PythonFramework::HttpxAsync => {
+                output.push_str("@pytest_asyncio.fixture\n");
+                output.push_str("async def client():\n");
+                output.push_str("    async with httpx.AsyncClient(base_url=BASE_URL) as client:\n");
+                output.push_str("        yield client\n\n\n");
+            }
+        }
 
-            output.push_str(&format!("def test_{}():\n", test_name));
-            output.push_str(&format!("    \"\"\"Test {} endpoint\"\"\"\n", endpoint));
+        output
+    }
 
-            let method_lower = first_req.request.method.to_lowercase();
+    /// The request call, e.g. `session.post(f"{base_url}/users", json=payload)`
+    ///
+    /// `url` is f-string text after the base URL and `path` the equivalent
+    /// expression for clients that take a path; header values are Python
+    /// expressions
+    fn request_call(
+        &self,
+        method: &str,
+        (url, path): (&str, &str),
+        headers: &[(&String, String)],
+        payload: Option<&str>,
+        json: bool,
+        indent: &str,
+    ) -> String {
+        let mut call = match self.framework {
+            PythonFramework::Pytest => {
+                format!("session.{}(f\"{{base_url}}{}\"", method.to_lowercase(), url)
+            }
+            PythonFramework::Unittest => format!(
+                "self.session.{}(f\"{{BASE_URL}}{}\"",
+                method.to_lowercase(),
+                url
+            ),
+            PythonFramework::HttpxAsync => {
+                format!("await client.request({}, {}", string_literal(method), path)
+            }
+        };
+
+        if !headers.is_empty() {
+            call.push_str(&format!(",\n{}    headers={{\n", indent));
+            for (key, value) in headers {
+                call.push_str(&format!(
+                    "{}        {}: {},\n",
+                    indent,
+                    string_literal(key),
+                    value
+                ));
+            }
+            call.push_str(&format!("{}    }}", indent));
+        }
+
+        if let Some(payload) = payload {
+            let keyword = match (self.framework, json) {
+                (_, true) => "json",
+                (PythonFramework::HttpxAsync, false) => "content",
+                (_, false) => "data",
+            };
+            call.push_str(&format!(",\n{}    {}={}", indent, keyword, payload));
+        }
+
+        call.push(')');
+        call
+    }
+
+    fn status_assertion(&self, expected: Option<&str>) -> String {
+        match (self.framework, expected) {
+            (PythonFramework::Unittest, Some(status)) => {
+                format!("self.assertEqual(response.status_code, {})", status)
+            }
+            (PythonFramework::Unittest, None) => {
+                "self.assertLess(response.status_code, 500)".to_string()
+            }
+            (_, Some(status)) => format!("assert response.status_code == {}", status),
+            (_, None) => "assert response.status_code < 500".to_string(),
+        }
+    }
+
+    /// Scenario test replaying a flow, passing extracted values forward
+    fn write_flow(&self, output: &mut String, flow: &Flow, test_name: &str) {
+        let (signature, indent) = match self.framework {
+            PythonFramework::Pytest => (format!("def {}(session, base_url):\n", test_name), "    "),
+            PythonFramework::Unittest => (format!("    def {}(self):\n", test_name), "        "),
+            PythonFramework::HttpxAsync => (
+                format!("@pytest.mark.asyncio\nasync def {}(client):\n", test_name),
+                "    ",
+            ),
+        };
+        output.push_str(&signature);
+        output.push_str(&format!(
+            "{}\"\"\"Scenario: {}\"\"\"\n",
+            indent,
+            flow.summary()
+        ));
+
+        for (i, step) in flow.steps.iter().enumerate() {
+            if i > 0 {
+                output.push('\n');
+            }
+
+            let headers: Vec<(&String, String)> = step
+                .headers
+                .iter()
+                .map(|(key, value)| (*key, fstring(value)))
+                .collect();
+            let (payload, json) = match &step.body {
+                Some(FlowBody::Json(value, vars)) => (Some(python_json(value, vars)), true),
+                Some(FlowBody::Text(text)) => (Some(fstring(text)), false),
+                None => (None, false),
+            };
+            let call = self.request_call(
+                &step.request.request.method,
+                (&fstring_text(&step.uri), &fstring(&step.uri)),
+                &headers,
+                payload.as_deref(),
+                json,
+                indent,
+            );
+            output.push_str(&format!("{}response = {}\n", indent, call));
+
+            let status = step
+                .request
+                .response
+                .as_ref()
+                .map(|r| r.status_code.to_string());
             output.push_str(&format!(
-                "    response = requests.{}(f\"{{BASE_URL}}{}\"",
-                method_lower, first_req.request.uri
+                "{}{}\n",
+                indent,
+                self.status_assertion(status.as_deref())
             ));
 
-            if !first_req.request.headers.is_empty() {
-                output.push_str(",\n        headers={\n");
-                for (key, value) in &first_req.request.headers {
-                    if key != "host" && key != "content-length" {
-                        output.push_str(&format!("            \"{}\": \"{}\",\n", key, value));
-                    }
-                }
-                output.push_str("        }");
+            for extract in &step.extracts {
+                let accessor: String = extract
+                    .path
+                    .iter()
+                    .map(|key| match key {
+                        Key::Field(name) => format!("[{}]", string_literal(name)),
+                        Key::Index(i) => format!("[{}]", i),
+                    })
+                    .collect();
+                output.push_str(&format!(
+                    "{}{} = response.json(){}\n",
+                    indent, extract.variable, accessor
+                ));
             }
+        }
+
+        match self.framework {
+            PythonFramework::Unittest => output.push('\n'),
+            _ => output.push_str("\n\n"),
+        }
+    }
+
+    fn write_test(
+        &self,
+        output: &mut String,
+        test_name: &str,
+        endpoint: &str,
+        reqs: &[&CapturedRequest],
+    ) {
+        let first_req = reqs[0];
+
+        let variants = variants(reqs);
+        let varying = Varying::of(&variants);
+        let json = variants.iter().all(|v| {
+            v.body
+                .is_none_or(|b| serde_json::from_slice::<Value>(b).is_ok())
+        });
+
+        // Only fields that differ between variants become test parameters
+        let mut names = Vec::new();
+        if varying.uri {
+            names.push("path");
+        }
+        if varying.body {
+            names.push("payload");
+        }
+        if varying.status {
+            names.push("expected_status");
+        }
+        let cases: Vec<String> = variants
+            .iter()
+            .map(|variant| {
+                let mut values = Vec::new();
+                if varying.uri {
+                    values.push(fstring(&flow::template(variant.uri)));
+                }
+                if varying.body {
+                    values.push(payload_literal(variant.body, json));
+                }
+                if varying.status {
+                    values.push(
+                        variant
+                            .status
+                            .map(|s| s.to_string())
+                            .unwrap_or_else(|| "None".to_string()),
+                    );
+                }
+                match values.len() {
+                    1 => values.remove(0),
+                    _ => format!("({})", values.join(", ")),
+                }
+            })
+            .collect();
 
-            output.push_str(")\n\n");
+        let parametrized = varying.any();
+        let indent = match self.framework {
+            PythonFramework::Unittest if parametrized => "                ",
+            PythonFramework::Unittest => "        ",
+            _ => "    ",
+        };
 
-            if let Some(response) = &first_req.response {
+        // Decorators and signature
+        let mut params = match self.framework {
+            PythonFramework::Pytest => vec!["session", "base_url"],
+            PythonFramework::Unittest => vec!["self"],
+            PythonFramework::HttpxAsync => vec!["client"],
+        };
+        if parametrized && self.framework != PythonFramework::Unittest {
+            output.push_str("@pytest.mark.parametrize(\n");
+            output.push_str(&format!("    \"{}\",\n", names.join(", ")));
+            output.push_str("    [\n");
+            for case in &cases {
+                output.push_str(&format!("        {},\n", case));
+            }
+            output.push_str("    ],\n");
+            output.push_str(")\n");
+            params.extend(&names);
+        }
+
+        match self.framework {
+            PythonFramework::Pytest => {
+                output.push_str(&format!("def {}({}):\n", test_name, params.join(", ")))
+            }
+            PythonFramework::Unittest => {
+                output.push_str(&format!("    def {}({}):\n", test_name, params.join(", ")))
+            }
+            PythonFramework::HttpxAsync => {
+                output.push_str("@pytest.mark.asyncio\n");
                 output.push_str(&format!(
-                    "    assert response.status_code == {}\n",
-                    response.status_code
+                    "async def {}({}):\n",
+                    test_name,
+                    params.join(", ")
                 ));
-            } else {
-                output.push_str("    assert response.status_code < 500\n");
             }
+        }
+
+        let doc_indent = match self.framework {
+            PythonFramework::Unittest => "        ",
+            _ => "    ",
+        };
+        output.push_str(&format!(
+            "{}\"\"\"Test {} endpoint\"\"\"\n",
+            doc_indent, endpoint
+        ));
 
-            output.push_str(&format!("    # Called {} times in capture\n", reqs.len()));
-            output.push_str("\n\n");
+        // unittest has no parametrize, so variants become subtests
+        if parametrized && self.framework == PythonFramework::Unittest {
+            output.push_str("        cases = [\n");
+            for case in &cases {
+                output.push_str(&format!("            {},\n", case));
+            }
+            output.push_str("        ]\n");
+            output.push_str(&format!("        for {} in cases:\n", names.join(", ")));
+            let subtest: Vec<String> = names.iter().map(|n| format!("{}={}", n, n)).collect();
+            output.push_str(&format!(
+                "            with self.subTest({}):\n",
+                subtest.join(", ")
+            ));
         }
 
-        if output.is_empty() {
+        let first = variants.first();
+        let path = varying.uri.then_some("path");
+        let fixed_payload = first
+            .and_then(|v| v.body)
+            .map(|b| payload_literal(Some(b), json));
+        let payload = match varying.body {
+            true => Some("payload"),
+            false => fixed_payload.as_deref(),
+        };
+        let url = match path {
+            Some(path) => (format!("{{{}}}", path), path.to_string()),
+            None => {
+                let uri = flow::template(&first_req.request.uri);
+                (fstring_text(&uri), fstring(&uri))
+            }
+        };
+        let headers: Vec<(&String, String)> = request_headers(first_req)
+            .into_iter()
+            .map(|(key, value)| (key, fstring(&flow::template(value))))
+            .collect();
+        let call = self.request_call(
+            &first_req.request.method,
+            (&url.0, &url.1),
+            &headers,
+            payload,
+            json,
+            indent,
+        );
+        output.push_str(&format!("{}response = {}\n", indent, call));
+        output.push('\n');
+
+        let status = first.and_then(|v| v.status);
+        let assertion = match (self.framework, varying.status, status) {
+            (PythonFramework::Unittest, true, _) => {
+                "self.assertEqual(response.status_code, expected_status)".to_string()
+            }
+            (_, true, _) => "assert response.status_code == expected_status".to_string(),
+            (_, false, status) => self.status_assertion(status.map(|s| s.to_string()).as_deref()),
+        };
+        output.push_str(&format!("{}{}\n", indent, assertion));
+
+        output.push_str(&format!(
+            "{}# Called {} times in capture\n",
+            doc_indent,
+            reqs.len()
+        ));
+        match self.framework {
+            PythonFramework::Unittest => output.push('\n'),
+            _ => output.push_str("\n\n"),
+        }
+    }
+}
+
+impl TestGenerator for PythonGenerator {
+    fn generate_with(&self, requests: &[CapturedRequest], config: &SuiteConfig) -> Result<String> {
+        let mut output = String::new();
+
+        let grouped = group_by_endpoint(requests);
+        let mut names = HashSet::new();
+
+        for (endpoint, reqs) in grouped.iter() {
+            let base = format!("test_{}", self.sanitize_test_name(endpoint));
+            let test_name = unique_name(&mut names, base, "_");
+            self.write_test(&mut output, &test_name, endpoint, reqs);
+        }
+
+        for flow in flow::detect(requests) {
+            let base = format!("test_flow_{}", self.sanitize_test_name(flow.name()));
+            let test_name = unique_name(&mut names, base, "_");
+            self.write_flow(&mut output, &flow, &test_name);
+        }
+
+        if grouped.is_empty() {
             output.push_str("# No requests captured\n");
         }
 
-        Ok(output)
+        if self.framework == PythonFramework::Unittest {
+            output.push_str("\nif __name__ == \"__main__\":\n");
+            output.push_str("    unittest.main()\n");
+        }
+
+        let header = self.header(config);
+        Ok(header + &output)
     }
 
     fn file_extension(&self) -> &str {
         "py"
     }
 }
+
+/// Request body as a Python literal, a dict/list for JSON bodies
+fn payload_literal(body: Option<&[u8]>, json: bool) -> String {
+    let Some(body) = body else {
+        return "None".to_string();
+    };
+
+    match serde_json::from_slice::<Value>(body) {
+        Ok(value) if json => python_literal(&value),
+        _ => fstring(&flow::template(&String::from_utf8_lossy(body))),
+    }
+}
+
+fn python_literal(value: &Value) -> String {
+    match value {
+        Value::Null => "None".to_string(),
+        Value::Bool(true) => "True".to_string(),
+        Value::Bool(false) => "False".to_string(),
+        Value::Number(n) => n.to_string(),
+        Value::String(s) => fstring(&flow::template(s)),
+        Value::Array(items) => format!(
+            "[{}]",
+            items
+                .iter()
+                .map(python_literal)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        Value::Object(map) => format!(
+            "{{{}}}",
+            map.iter()
+                .map(|(k, v)| format!("{}: {}", string_literal(k), python_literal(v)))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    }
+}
+
+/// JSON body as a Python literal with variables in place of bound leaves
+fn python_json(value: &Value, vars: &BTreeMap<String, String>) -> String {
+    flow::render_json(
+        value,
+        vars,
+        &|scalar| Some(python_literal(scalar)),
+        &|name| name.to_string(),
+        &|fields| {
+            let fields: Vec<String> = fields
+                .into_iter()
+                .map(|(k, v)| format!("{}: {}", string_literal(&k), v))
+                .collect();
+            format!("{{{}}}", fields.join(", "))
+        },
+        &|items| format!("[{}]", items.join(", ")),
+    )
+}
+
+/// Template text for inside an f-string
+fn fstring_text(template: &Template) -> String {
+    template
+        .iter()
+        .map(|part| match part {
+            Part::Text(text) => {
+                let quoted = string_literal(text);
+                quoted[1..quoted.len() - 1]
+                    .replace('{', "{{")
+                    .replace('}', "}}")
+            }
+            Part::Var(name) => format!("{{{}}}", name),
+            Part::Env(name) => format!("{{env('{}')}}", name),
+        })
+        .collect()
+}
+
+/// Template as a Python string expression, an f-string only when needed
+fn fstring(template: &Template) -> String {
+    match template.as_slice() {
+        [] => "\"\"".to_string(),
+        [Part::Text(text)] => string_literal(text),
+        [Part::Var(name)] => format!("str({})", name),
+        [Part::Env(name)] => format!("env(\"{}\")", name),
+        _ => format!("f\"{}\"", fstring_text(template)),
+    }
+}
+
+/// Quote a string as a Python literal
+fn string_literal(value: &str) -> String {
+    serde_json::to_string(value).unwrap_or_else(|_| "\"\"".to_string())
+}
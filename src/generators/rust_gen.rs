@@ -1,7 +1,22 @@
-use crate::generators::TestGenerator;
+use crate::generators::flow::{self, Flow, FlowBody, Key, Part, Template};
+use crate::generators::{
+    SuiteConfig, TestGenerator, Varying, group_by_endpoint, request_headers, unique_name, variants,
+};
 use crate::models::CapturedRequest;
 use anyhow::Result;
-use std::collections::HashMap;
+use std::collections::HashSet;
+
+/// Settings lookup shared by every generated file
+const ENV_HELPER: &str = r#"    /// Setting for the current TEST_ENV, e.g. STAGING_BASE_URL over BASE_URL
+    fn env(name: &str, default: &str) -> String {
+        let prefix = std::env::var("TEST_ENV").unwrap_or_default().to_uppercase();
+        match std::env::var(format!("{}_{}", prefix, name)) {
+            Ok(value) if !prefix.is_empty() => value,
+            _ => std::env::var(name).unwrap_or_else(|_| default.to_string()),
+        }
+    }
+
+"#;
 
 pub struct RustGenerator;
 
@@ -10,76 +25,257 @@ impl RustGenerator {
         Self
     }
 
-    fn group_by_endpoint<'a>(
+    fn sanitize_test_name(&self, name: &str) -> String {
+        name.to_lowercase()
+            .split(|c: char| !c.is_ascii_alphanumeric())
+            .filter(|s| !s.is_empty())
+            .collect::<Vec<_>>()
+            .join("_")
+    }
+
+    /// Send one request and check its status; `path`, `body` and `status`
+    /// are Rust expressions
+    fn write_request(
         &self,
-        requests: &'a [CapturedRequest],
-    ) -> HashMap<String, Vec<&'a CapturedRequest>> {
-        let mut grouped: HashMap<String, Vec<&'a CapturedRequest>> = HashMap::new();
+        output: &mut String,
+        indent: &str,
+        req: &CapturedRequest,
+        path: &str,
+        body: Option<&str>,
+        status: Option<&str>,
+    ) {
+        let method_lower = req.request.method.to_lowercase();
+        output.push_str(&format!(
+            "{}let response = client.{}(format!(\"{{}}{{}}\", base_url(), {}))\n",
+            indent, method_lower, path
+        ));
 
-        for req in requests {
-            let key = format!("{} {}", req.request.method, req.request.uri);
-            grouped.entry(key).or_default().push(req);
+        for (key, value) in request_headers(req) {
+            output.push_str(&format!(
+                "{}    .header({:?}, {})\n",
+                indent,
+                key,
+                text_expr(value)
+            ));
         }
 
-        grouped
+        if let Some(body) = body {
+            output.push_str(&format!("{}    .body({})\n", indent, body));
+        }
+
+        output.push_str(&format!("{}    .send()\n", indent));
+        output.push_str(&format!("{}    .await\n", indent));
+        output.push_str(&format!(
+            "{}    .expect(\"Failed to send request\");\n\n",
+            indent
+        ));
+
+        match status {
+            Some(status) => output.push_str(&format!(
+                "{}assert_eq!(response.status().as_u16(), {});\n",
+                indent, status
+            )),
+            None => output.push_str(&format!(
+                "{}assert!(response.status().as_u16() < 500);\n",
+                indent
+            )),
+        }
     }
+}
 
-    fn sanitize_test_name(&self, name: &str) -> String {
-        name.to_lowercase()
-            .replace(['/', '-'], "_")
-            .replace('?', "")
-            .replace(['&', '='], "_")
-            .trim_matches('_')
-            .to_string()
+impl RustGenerator {
+    /// Scenario test replaying a flow, passing extracted values forward
+    fn write_flow(&self, output: &mut String, flow: &Flow, test_name: &str) {
+        output.push_str("    #[tokio::test]\n");
+        output.push_str(&format!("    async fn {}() {{\n", test_name));
+        output.push_str(&format!("        // Scenario: {}\n", flow.summary()));
+        output.push_str("        let client = reqwest::Client::new();\n");
+
+        for step in &flow.steps {
+            let mut url = vec![Part::Var("BASE_URL".to_string())];
+            url.extend(step.uri.iter().cloned());
+
+            output.push('\n');
+            output.push_str(&format!(
+                "        let response = client\n            .{}({})\n",
+                step.request.request.method.to_lowercase(),
+                format_expr(&url)
+            ));
+            for (key, value) in &step.headers {
+                output.push_str(&format!(
+                    "            .header({:?}, {})\n",
+                    key,
+                    format_expr(value)
+                ));
+            }
+            match &step.body {
+                Some(FlowBody::Json(value, vars)) => {
+                    let body = flow::render_json(
+                        value,
+                        vars,
+                        &|scalar| scalar.as_str().map(|s| format!("{:?}", s)),
+                        &str::to_string,
+                        &|fields| {
+                            let fields: Vec<String> = fields
+                                .into_iter()
+                                .map(|(k, v)| format!("{:?}: {}", k, v))
+                                .collect();
+                            format!("{{{}}}", fields.join(", "))
+                        },
+                        &|items| format!("[{}]", items.join(", ")),
+                    );
+                    output.push_str(&format!(
+                        "            .json(&serde_json::json!({}))\n",
+                        body
+                    ));
+                }
+                Some(FlowBody::Text(text)) => {
+                    output.push_str(&format!("            .body({})\n", format_expr(text)));
+                }
+                None => {}
+            }
+            output.push_str("            .send()\n");
+            output.push_str("            .await\n");
+            output.push_str("            .expect(\"Failed to send request\");\n");
+
+            match step.request.response.as_ref().map(|r| r.status_code) {
+                Some(status) => output.push_str(&format!(
+                    "        assert_eq!(response.status().as_u16(), {});\n",
+                    status
+                )),
+                None => output.push_str("        assert!(response.status().as_u16() < 500);\n"),
+            }
+
+            if step.extracts.is_empty() {
+                continue;
+            }
+            output.push_str("        let data: serde_json::Value = response\n");
+            output.push_str("            .json()\n");
+            output.push_str("            .await\n");
+            output.push_str("            .expect(\"Failed to parse response\");\n");
+            for extract in &step.extracts {
+                let accessor: String = extract
+                    .path
+                    .iter()
+                    .map(|key| match key {
+                        Key::Field(name) => format!("[{:?}]", name),
+                        Key::Index(i) => format!("[{}]", i),
+                    })
+                    .collect();
+                output.push_str(&format!(
+                    "        let {} = data{}.clone();\n",
+                    extract.variable, accessor
+                ));
+            }
+        }
+
+        output.push_str("    }\n\n");
     }
 }
 
 impl TestGenerator for RustGenerator {
-    fn generate(&self, requests: &[CapturedRequest]) -> Result<String> {
+    fn generate_with(&self, requests: &[CapturedRequest], config: &SuiteConfig) -> Result<String> {
         let mut output = String::new();
 
-        output.push_str("#[cfg(test)]\n");
-        output.push_str("mod tests {\n");
-        output.push_str("    use reqwest;\n\n");
-        output.push_str("    const BASE_URL: &str = \"http://localhost:8080\";\n\n");
-
-        let grouped = self.group_by_endpoint(requests);
+        let grouped = group_by_endpoint(requests);
+        let mut test_names = HashSet::new();
 
         for (endpoint, reqs) in grouped.iter() {
             let first_req = reqs[0];
-            let test_name = self.sanitize_test_name(endpoint);
+            let base = format!("test_{}", self.sanitize_test_name(endpoint));
+            let test_name = unique_name(&mut test_names, base, "_");
+
+            let variants = variants(reqs);
+            let varying = Varying::of(&variants);
+            let first = &variants[0];
 
             output.push_str("    #[tokio::test]\n");
-            output.push_str(&format!("    async fn test_{}() {{\n", test_name));
+            output.push_str(&format!("    async fn {}() {{\n", test_name));
             output.push_str(&format!("        // Test {} endpoint\n", endpoint));
 
-            let method_lower = first_req.request.method.to_lowercase();
-            output.push_str("        let client = reqwest::Client::new();\n");
-            output.push_str(&format!(
-                "        let response = client.{}(format!(\"{{}}{})\", BASE_URL))\n",
-                method_lower, first_req.request.uri
-            ));
+            let fixed_body = first.body.map(|b| text_expr(&String::from_utf8_lossy(b)));
+            let fixed_status = first.status.map(|s| s.to_string());
 
-            for (key, value) in &first_req.request.headers {
-                if key != "host" && key != "content-length" {
-                    output.push_str(&format!(
-                        "            .header(\"{}\", \"{}\")\n",
-                        key, value
-                    ));
+            if !varying.any() {
+                output.push_str("        let client = reqwest::Client::new();\n");
+                self.write_request(
+                    &mut output,
+                    "        ",
+                    first_req,
+                    &text_expr(first.uri),
+                    fixed_body.as_deref(),
+                    fixed_status.as_deref(),
+                );
+            } else {
+                // Case loop over the captured variants
+                let mut names = Vec::new();
+                let mut types = Vec::new();
+                if varying.uri {
+                    names.push("path");
+                    types.push("&str");
+                }
+                if varying.body {
+                    names.push("body");
+                    types.push("&str");
+                }
+                if varying.status {
+                    names.push("expected_status");
+                    types.push("u16");
                 }
-            }
 
-            output.push_str("            .send()\n");
-            output.push_str("            .await\n");
-            output.push_str("            .expect(\"Failed to send request\");\n\n");
+                let (pattern, ty) = match names.len() {
+                    1 => (names[0].to_string(), types[0].to_string()),
+                    _ => (
+                        format!("({})", names.join(", ")),
+                        format!("({})", types.join(", ")),
+                    ),
+                };
 
-            if let Some(response) = &first_req.response {
-                output.push_str(&format!(
-                    "        assert_eq!(response.status().as_u16(), {});\n",
-                    response.status_code
-                ));
-            } else {
-                output.push_str("        assert!(response.status().as_u16() < 500);\n");
+                output.push_str(&format!("        let cases: &[{}] = &[\n", ty));
+                for variant in &variants {
+                    let mut values = Vec::new();
+                    if varying.uri {
+                        values.push(case_expr(variant.uri));
+                    }
+                    if varying.body {
+                        let body = variant.body.map(String::from_utf8_lossy);
+                        values.push(case_expr(&body.unwrap_or_default()));
+                    }
+                    if varying.status {
+                        values.push(variant.status.unwrap_or_default().to_string());
+                    }
+                    let case = match values.len() {
+                        1 => values.remove(0),
+                        _ => format!("({})", values.join(", ")),
+                    };
+                    output.push_str(&format!("            {},\n", case));
+                }
+                output.push_str("        ];\n\n");
+
+                output.push_str("        let client = reqwest::Client::new();\n");
+                output.push_str(&format!("        for &{} in cases {{\n", pattern));
+
+                let path = match varying.uri {
+                    true => "path".to_string(),
+                    false => text_expr(first.uri),
+                };
+                let body = match varying.body {
+                    true => Some("body".to_string()),
+                    false => fixed_body,
+                };
+                let status = match varying.status {
+                    true => Some("expected_status".to_string()),
+                    false => fixed_status,
+                };
+                self.write_request(
+                    &mut output,
+                    "            ",
+                    first_req,
+                    &path,
+                    body.as_deref(),
+                    status.as_deref(),
+                );
+                output.push_str("        }\n");
             }
 
             output.push_str(&format!(
@@ -89,12 +285,95 @@ impl TestGenerator for RustGenerator {
             output.push_str("    }\n\n");
         }
 
+        let flows = flow::detect(requests);
+        if !flows.is_empty() {
+            output.push_str("    /// Extracted value as it appears in a URL or header\n");
+            output.push_str("    fn text(value: &serde_json::Value) -> String {\n");
+            output.push_str("        match value {\n");
+            output.push_str("            serde_json::Value::String(s) => s.clone(),\n");
+            output.push_str("            other => other.to_string(),\n");
+            output.push_str("        }\n");
+            output.push_str("    }\n\n");
+        }
+
+        for flow in &flows {
+            let base = format!("test_flow_{}", self.sanitize_test_name(flow.name()));
+            let test_name = unique_name(&mut test_names, base, "_");
+            self.write_flow(&mut output, flow, &test_name);
+        }
+
         output.push_str("}\n");
 
-        Ok(output)
+        let mut header = String::new();
+        header.push_str("#[cfg(test)]\n");
+        header.push_str("mod tests {\n");
+        header.push_str("    use reqwest;\n\n");
+        header.push_str(ENV_HELPER);
+        header.push_str("    fn base_url() -> String {\n");
+        header.push_str(&format!(
+            "        env(\"BASE_URL\", {:?})\n",
+            config.base_url
+        ));
+        header.push_str("    }\n\n");
+
+        Ok(header + &output)
     }
 
     fn file_extension(&self) -> &str {
         "rs"
     }
 }
+
+/// Template as a Rust string expression, a `format!` only when needed
+fn format_expr(template: &Template) -> String {
+    match template.as_slice() {
+        [] => return "\"\"".to_string(),
+        [Part::Text(text)] => return format!("{:?}", text),
+        [Part::Env(name)] => return format!("env({:?}, \"\")", name),
+        _ => {}
+    }
+
+    let mut pattern = String::new();
+    let mut args = Vec::new();
+    for part in template {
+        match part {
+            Part::Text(text) => {
+                let quoted = format!("{:?}", text);
+                pattern.push_str(
+                    &quoted[1..quoted.len() - 1]
+                        .replace('{', "{{")
+                        .replace('}', "}}"),
+                );
+            }
+            Part::Var(name) if name == "BASE_URL" => {
+                pattern.push_str("{}");
+                args.push("base_url()".to_string());
+            }
+            Part::Var(name) => {
+                pattern.push_str("{}");
+                args.push(format!("text(&{})", name));
+            }
+            Part::Env(name) => {
+                pattern.push_str("{}");
+                args.push(format!("env({:?}, \"\")", name));
+            }
+        }
+    }
+
+    format!("format!(\"{}\", {})", pattern, args.join(", "))
+}
+
+/// Captured text as a Rust string expression, reading redacted values from
+/// the environment
+fn text_expr(text: &str) -> String {
+    format_expr(&flow::template(text))
+}
+
+/// `&str` entry of a case table, borrowing any `format!` result
+fn case_expr(text: &str) -> String {
+    let expr = text_expr(text);
+    match expr.starts_with('"') {
+        true => expr,
+        false => format!("&{}", expr),
+    }
+}
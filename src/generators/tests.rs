@@ -1,36 +1,28 @@
 use super::*;
-use crate::models::{CapturedRequest, Protocol, RequestData, ResponseData};
+use crate::fixtures::captured;
+use crate::models::CapturedRequest;
 use chrono::Utc;
 
-fn create_test_request(method: &str, uri: &str, status: u16) -> CapturedRequest {
-    CapturedRequest {
-        id: "test-123".to_string(),
-        timestamp: Utc::now(),
-        protocol: Protocol::Http,
-        request: RequestData {
-            method: method.to_string(),
-            uri: uri.to_string(),
-            headers: Default::default(),
-            body: Some(b"{\"test\":\"data\"}".to_vec()),
-            query_params: Default::default(),
-        },
-        response: Some(ResponseData {
-            status_code: status,
-            headers: Default::default(),
-            body: Some(b"{\"result\":\"ok\"}".to_vec()),
-        }),
-        duration_ms: Some(42),
+/// Suites with the default settings
+trait GenerateDefault: TestGenerator {
+    fn generate(&self, requests: &[CapturedRequest]) -> anyhow::Result<String> {
+        self.generate_with(requests, &SuiteConfig::default())
     }
 }
 
+impl<T: TestGenerator + ?Sized> GenerateDefault for T {}
+
+const BODY: &str = r#"{"test":"data"}"#;
+const RESPONSE: &str = r#"{"result":"ok"}"#;
+
 #[test]
 fn test_python_generator() {
     let requests = vec![
-        create_test_request("GET", "/api/users", 200),
-        create_test_request("POST", "/api/users", 201),
+        captured("GET", "/api/users", BODY, 200, RESPONSE),
+        captured("POST", "/api/users", BODY, 201, RESPONSE),
     ];
 
-    let generator = PythonGenerator;
+    let generator = PythonGenerator::new("pytest").unwrap();
     let code = generator.generate(&requests).unwrap();
 
     assert!(code.contains("import requests"));
@@ -44,8 +36,8 @@ fn test_python_generator() {
 #[test]
 fn test_go_generator() {
     let requests = vec![
-        create_test_request("GET", "/api/products", 200),
-        create_test_request("DELETE", "/api/products/1", 204),
+        captured("GET", "/api/products", BODY, 200, RESPONSE),
+        captured("DELETE", "/api/products/1", BODY, 204, RESPONSE),
     ];
 
     let generator = GoGenerator;
@@ -63,8 +55,8 @@ fn test_go_generator() {
 #[test]
 fn test_rust_generator() {
     let requests = vec![
-        create_test_request("PUT", "/api/orders/1", 200),
-        create_test_request("PATCH", "/api/orders/1", 200),
+        captured("PUT", "/api/orders/1", BODY, 200, RESPONSE),
+        captured("PATCH", "/api/orders/1", BODY, 200, RESPONSE),
     ];
 
     let generator = RustGenerator;
@@ -79,6 +71,423 @@ fn test_rust_generator() {
     assert!(code.contains("assert_eq!(response.status().as_u16(), 200)"));
 }
 
+#[test]
+fn test_typescript_generator_jest_fetch() {
+    let requests = vec![
+        captured("GET", "/api/users", BODY, 200, RESPONSE),
+        captured("POST", "/api/users", BODY, 201, RESPONSE),
+    ];
+
+    let generator = TypeScriptGenerator::new("jest").unwrap();
+    let code = generator.generate(&requests).unwrap();
+
+    assert!(!code.contains("from \"vitest\""));
+    assert!(code.contains("test(\"POST /api/users\", async () => {"));
+    assert!(code.contains("type PostApiUsersPayload = {\n  test: string;\n};"));
+    assert!(code.contains("const payload: PostApiUsersPayload = {"));
+    assert!(code.contains("await fetch(`${BASE_URL}/api/users`"));
+    assert!(code.contains("method: \"POST\""));
+    assert!(code.contains("body: JSON.stringify(payload)"));
+    assert!(!code.contains("type GetApiUsersPayload"));
+    assert!(code.contains("expect(response.status).toBe(201);"));
+    assert!(code.contains("const body = await response.json();"));
+    assert!(code.contains("expect(body).toMatchObject({\n    \"result\": \"ok\"\n  });"));
+    assert_eq!(generator.file_extension(), "test.ts");
+}
+
+#[test]
+fn test_typescript_generator_vitest_supertest() {
+    let mut request = captured("DELETE", "/api/users/1", BODY, 204, RESPONSE);
+    request.request.body = None;
+    request.response.as_mut().unwrap().body = None;
+    request
+        .request
+        .headers
+        .insert("authorization".to_string(), "Bearer abc".to_string());
+
+    let generator = TypeScriptGenerator::new("vitest-supertest").unwrap();
+    let code = generator.generate(&[request]).unwrap();
+
+    assert!(code.contains("import { expect, test } from \"vitest\";"));
+    assert!(code.contains("import request from \"supertest\";"));
+    assert!(code.contains("const api = request(BASE_URL);"));
+    assert!(
+        code.contains(
+            "    .delete(\"/api/users/1\")\n    .set(\"authorization\", \"Bearer abc\");"
+        )
+    );
+    assert!(code.contains("expect(response.status).toBe(204);"));
+    assert!(!code.contains("type "));
+    assert!(!code.contains("response.body"));
+}
+
+#[test]
+fn test_typescript_payload_types() {
+    let mut request = captured("PUT", "/api/orders/1", BODY, 200, RESPONSE);
+    request.request.body = Some(
+        br#"{"id":1,"note":null,"tags":["a"],"items":[{"sku":"x"}],"content-type":"a"}"#.to_vec(),
+    );
+
+    let code = TypeScriptGenerator::new("jest")
+        .unwrap()
+        .generate(&[request])
+        .unwrap();
+
+    assert!(code.contains("type PutApiOrdersIdPayload = {"));
+    assert!(code.contains("  \"content-type\": string;"));
+    assert!(code.contains("  id: number;"));
+    assert!(code.contains("  note: unknown | null;"));
+    assert!(code.contains("  tags: string[];"));
+    assert!(code.contains("  items: Array<{\n    sku: string;\n  }>;"));
+}
+
+#[test]
+fn test_get_generator_typescript() {
+    assert!(get_generator("typescript", None).is_ok());
+    assert!(get_generator("ts", Some("vitest")).is_ok());
+    assert!(get_generator("ts", Some("supertest")).is_ok());
+    assert!(get_generator("js", None).is_err());
+    assert!(get_generator("typescript", Some("mocha")).is_err());
+}
+
+#[test]
+fn test_java_generator_rest_assured() {
+    let mut request = captured("GET", "/api/users/42", BODY, 200, RESPONSE);
+    request.response.as_mut().unwrap().body = Some(
+        br#"{"id":42,"score":1.5,"profile":{"active":true},"tags":["a"],"note":null}"#.to_vec(),
+    );
+    let requests = vec![
+        request,
+        captured("GET", "/api/users/7", BODY, 200, RESPONSE),
+        captured("POST", "/api/users", BODY, 201, RESPONSE),
+    ];
+
+    let generator = JavaGenerator::new("junit5").unwrap();
+    let code = generator.generate(&requests).unwrap();
+
+    assert!(code.contains("import static io.restassured.RestAssured.given;"));
+    assert!(code.contains("class GeneratedApiTest {"));
+    assert_eq!(code.matches("    @Test\n").count(), 1);
+    assert!(code.contains("@ParameterizedTest\n    @MethodSource(\"getApiUsersIdCases\")"));
+    assert!(code.contains("@DisplayName(\"GET /api/users/{id}\")"));
+    assert!(code.contains(
+        "        return Stream.of(\n            Arguments.of(\"/api/users/42\"),\n            Arguments.of(\"/api/users/7\")\n        );"
+    ));
+    assert!(code.contains("void getApiUsersId(String path) {"));
+    assert!(code.contains(".request(\"GET\", path)"));
+    assert!(code.contains("void postApiUsers() {"));
+    assert!(code.contains(".body(\"{\\\"test\\\":\\\"data\\\"}\")"));
+    assert!(code.contains(".request(\"POST\", \"/api/users\")"));
+    assert!(code.contains(".statusCode(201)"));
+    assert!(code.contains(".body(\"result\", equalTo(\"ok\"))"));
+    assert!(code.contains("// Called 2 times in capture"));
+    assert_eq!(generator.file_name(), "GeneratedApiTest.java");
+}
+
+#[test]
+fn test_java_generator_json_paths() {
+    let mut request = captured("GET", "/api/users/42", BODY, 200, RESPONSE);
+    request.response.as_mut().unwrap().body = Some(
+        br#"{"id":42,"score":1.5,"big":9000000000,"profile":{"active":true},"tags":["a"],"note":null}"#
+            .to_vec(),
+    );
+
+    let rest_assured = JavaGenerator::new("restassured")
+        .unwrap()
+        .generate(std::slice::from_ref(&request))
+        .unwrap();
+    assert!(rest_assured.contains(".body(\"id\", equalTo(42))"));
+    assert!(rest_assured.contains(".body(\"score\", equalTo(1.5f))"));
+    assert!(rest_assured.contains(".body(\"profile.active\", equalTo(true))"));
+    assert!(rest_assured.contains(".body(\"note\", nullValue())"));
+    assert!(!rest_assured.contains("tags"));
+
+    let http_client = JavaGenerator::new("httpclient")
+        .unwrap()
+        .generate(&[request])
+        .unwrap();
+    assert!(http_client.contains("import java.net.http.HttpClient;"));
+    assert!(http_client.contains("void getApiUsersId() throws Exception {"));
+    assert!(http_client.contains(".method(\"GET\", HttpRequest.BodyPublishers.ofString("));
+    assert!(http_client.contains("assertEquals(200, response.statusCode());"));
+    assert!(
+        http_client
+            .contains("assertEquals(9000000000L, JsonPath.read(response.body(), \"$.big\"));")
+    );
+    assert!(
+        http_client
+            .contains("assertEquals(true, JsonPath.read(response.body(), \"$.profile.active\"));")
+    );
+}
+
+#[test]
+fn test_java_generator_skips_restricted_headers() {
+    let mut request = captured("DELETE", "/api/users/1", BODY, 204, RESPONSE);
+    for (key, value) in [
+        ("host", "example.com"),
+        ("Connection", "close"),
+        ("x-trace", "1"),
+    ] {
+        request
+            .request
+            .headers
+            .insert(key.to_string(), value.to_string());
+    }
+
+    let code = JavaGenerator::new("httpclient")
+        .unwrap()
+        .generate(&[request])
+        .unwrap();
+
+    assert!(code.contains(".header(\"x-trace\", \"1\")"));
+    assert!(!code.contains("\"host\""));
+    assert!(!code.contains("\"Connection\""));
+}
+
+#[test]
+fn test_get_generator_java() {
+    assert!(get_generator("java", None).is_ok());
+    assert!(get_generator("java", Some("httpclient")).is_ok());
+    assert!(get_generator("java", Some("testng")).is_err());
+}
+
+#[test]
+fn test_python_pytest_fixtures() {
+    let requests = vec![captured("GET", "/api/users", BODY, 200, RESPONSE)];
+
+    let code = PythonGenerator::new("pytest")
+        .unwrap()
+        .generate(&requests)
+        .unwrap();
+
+    assert!(code.contains("@pytest.fixture\ndef session():"));
+    assert!(code.contains("def test_get_api_users(session, base_url):"));
+    assert!(code.contains(
+        "response = session.get(f\"{base_url}/api/users\",\n        json={\"test\": \"data\"})"
+    ));
+    assert!(!code.contains("parametrize"));
+}
+
+#[test]
+fn test_python_pytest_parametrize_variants() {
+    let mut invalid = captured("POST", "/api/users", BODY, 400, RESPONSE);
+    invalid.request.body = Some(br#"{"name":null,"admin":false}"#.to_vec());
+    let requests = vec![
+        captured("POST", "/api/users", BODY, 201, RESPONSE),
+        invalid,
+        captured("POST", "/api/users", BODY, 201, RESPONSE),
+    ];
+
+    let code = PythonGenerator::new("pytest")
+        .unwrap()
+        .generate(&requests)
+        .unwrap();
+
+    assert!(code.contains(
+        "@pytest.mark.parametrize(\n    \"payload, expected_status\",\n    [\n        ({\"test\": \"data\"}, 201),\n        ({\"admin\": False, \"name\": None}, 400),\n    ],\n)\n"
+    ));
+    assert!(code.contains("def test_post_api_users(session, base_url, payload, expected_status):"));
+    assert!(code.contains("json=payload)"));
+    assert!(code.contains("assert response.status_code == expected_status"));
+    assert!(code.contains("# Called 3 times in capture"));
+}
+
+#[test]
+fn test_python_unittest() {
+    let requests = vec![
+        captured("GET", "/api/users", BODY, 200, RESPONSE),
+        captured("DELETE", "/api/users", BODY, 204, RESPONSE),
+        captured("DELETE", "/api/users", BODY, 404, RESPONSE),
+    ];
+
+    let code = PythonGenerator::new("unittest")
+        .unwrap()
+        .generate(&requests)
+        .unwrap();
+
+    assert!(code.contains("import unittest"));
+    assert!(!code.contains("import pytest"));
+    assert!(code.contains("class TestCapturedApi(unittest.TestCase):"));
+    assert!(code.contains("    def test_get_api_users(self):"));
+    assert!(code.contains("        self.assertEqual(response.status_code, 200)"));
+    assert!(code.contains("        cases = [\n            204,\n            404,\n        ]\n"));
+    assert!(code.contains("        for expected_status in cases:"));
+    assert!(code.contains("            with self.subTest(expected_status=expected_status):"));
+    assert!(
+        code.contains("                response = self.session.delete(f\"{BASE_URL}/api/users\",\n                    json={\"test\": \"data\"})"
+    ));
+    assert!(
+        code.contains("                self.assertEqual(response.status_code, expected_status)")
+    );
+    assert!(code.ends_with("if __name__ == \"__main__\":\n    unittest.main()\n"));
+}
+
+#[test]
+fn test_python_httpx_async() {
+    let mut request = captured("PUT", "/api/users/1", BODY, 200, RESPONSE);
+    request.request.body = Some(b"plain text".to_vec());
+
+    let code = PythonGenerator::new("httpx")
+        .unwrap()
+        .generate(&[request])
+        .unwrap();
+
+    assert!(code.contains("import httpx"));
+    assert!(code.contains("@pytest_asyncio.fixture\nasync def client():"));
+    assert!(code.contains("@pytest.mark.asyncio\nasync def test_put_api_users_id(client):"));
+    assert!(code.contains("response = await client.request(\"PUT\", \"/api/users/1\",\n        content=\"plain text\")"));
+    assert!(code.contains("assert response.status_code == 200"));
+}
+
+#[test]
+fn test_get_generator_python_frameworks() {
+    assert!(get_generator("python", Some("pytest")).is_ok());
+    assert!(get_generator("python", Some("unittest")).is_ok());
+    assert!(get_generator("python", Some("httpx")).is_ok());
+    assert!(get_generator("python", Some("nose")).is_err());
+    assert!(get_generator("auto", Some("behave")).is_err());
+}
+
+#[test]
+fn test_get_generator_go_and_rust_frameworks() {
+    assert!(get_generator("go", None).is_ok());
+    assert!(get_generator("go", Some("testing")).is_ok());
+    assert!(get_generator("go", Some("pytest")).is_err());
+    assert!(get_generator("rust", Some("tokio")).is_ok());
+    assert!(get_generator("rs", Some("jest")).is_err());
+}
+
+fn variant_requests() -> Vec<CapturedRequest> {
+    let mut invalid = captured("POST", "/api/users", BODY, 400, RESPONSE);
+    invalid.request.body = Some(br#"{"name":1}"#.to_vec());
+    vec![
+        captured("GET", "/api/users/1", BODY, 200, RESPONSE),
+        captured("GET", "/api/users/2", BODY, 404, RESPONSE),
+        captured("GET", "/api/users/1", BODY, 200, RESPONSE),
+        captured("POST", "/api/users", BODY, 201, RESPONSE),
+        invalid,
+    ]
+}
+
+#[test]
+fn test_variants_of_endpoint_pattern() {
+    let requests = variant_requests();
+    let grouped = group_by_endpoint(&requests);
+    assert_eq!(grouped.len(), 2);
+
+    let users = &grouped["GET /api/users/{id}"];
+    assert_eq!(users.len(), 3);
+
+    let found = variants(users);
+    assert_eq!(found.len(), 2);
+    assert_eq!(found[0].uri, "/api/users/1");
+    assert_eq!(found[1].status, Some(404));
+    assert_eq!(
+        Varying::of(&found),
+        Varying {
+            uri: true,
+            body: false,
+            status: true
+        }
+    );
+}
+
+#[test]
+fn test_common_json() {
+    let mut other = captured("GET", "/api/users/2", BODY, 200, RESPONSE);
+    other.response.as_mut().unwrap().body = Some(br#"{"result":"ok","id":2}"#.to_vec());
+    let first = captured("GET", "/api/users/1", BODY, 200, RESPONSE);
+
+    assert_eq!(
+        common_json(&[&first, &other]),
+        Some(serde_json::json!({ "result": "ok" }))
+    );
+
+    other.response.as_mut().unwrap().body = Some(br#"{"result":"error"}"#.to_vec());
+    assert_eq!(common_json(&[&first, &other]), None);
+}
+
+#[test]
+fn test_python_parametrize_paths() {
+    let code = PythonGenerator::new("pytest")
+        .unwrap()
+        .generate(&variant_requests())
+        .unwrap();
+
+    assert!(code.contains(
+        "@pytest.mark.parametrize(\n    \"path, expected_status\",\n    [\n        (\"/api/users/1\", 200),\n        (\"/api/users/2\", 404),\n    ],\n)\n"
+    ));
+    assert!(code.contains("def test_get_api_users_id(session, base_url, path, expected_status):"));
+    assert!(code.contains("response = session.get(f\"{base_url}{path}\""));
+    assert!(code.contains("# Called 3 times in capture"));
+}
+
+#[test]
+fn test_go_table_tests() {
+    let code = GoGenerator.generate(&variant_requests()).unwrap();
+
+    assert!(code.contains("\t\"strings\"\n"));
+    assert!(code.contains(
+        "\tcases := []struct {\n\t\tname           string\n\t\tpath           string\n\t\texpectedStatus int\n\t}{\n"
+    ));
+    assert!(code.contains(
+        "\t\t{name: \"/api/users/2 404\", path: \"/api/users/2\", expectedStatus: 404},\n"
+    ));
+    assert!(
+        code.contains("\t\t{name: \"400\", body: \"{\\\"name\\\":1}\", expectedStatus: 400},\n")
+    );
+    assert!(code.contains("\t\tt.Run(tc.name, func(t *testing.T) {\n"));
+    assert!(code.contains("http.NewRequest(\"GET\", baseURL+tc.path, "));
+    assert!(
+        code.contains(
+            "http.NewRequest(\"POST\", baseURL+\"/api/users\", strings.NewReader(tc.body))"
+        )
+    );
+    assert!(code.contains("\t\t\tif resp.StatusCode != tc.expectedStatus {\n"));
+}
+
+#[test]
+fn test_go_single_request_without_body() {
+    let mut request = captured("GET", "/health", BODY, 200, RESPONSE);
+    request.request.body = None;
+
+    let code = GoGenerator.generate(&[request]).unwrap();
+
+    assert!(!code.contains("strings"));
+    assert!(code.contains("http.NewRequest(\"GET\", baseURL+\"/health\", nil)"));
+    assert!(!code.contains("cases"));
+}
+
+#[test]
+fn test_rust_case_loop() {
+    let code = RustGenerator.generate(&variant_requests()).unwrap();
+
+    assert!(code.contains(
+        "        let cases: &[(&str, u16)] = &[\n            (\"/api/users/1\", 200),\n            (\"/api/users/2\", 404),\n        ];\n"
+    ));
+    assert!(code.contains("        for &(path, expected_status) in cases {\n"));
+    assert!(code.contains("client.get(format!(\"{}{}\", base_url(), path))"));
+    assert!(code.contains("            (\"{\\\"name\\\":1}\", 400),\n"));
+    assert!(code.contains("                .body(body)\n"));
+    assert!(code.contains("assert_eq!(response.status().as_u16(), expected_status);"));
+}
+
+#[test]
+fn test_typescript_test_each() {
+    let code = TypeScriptGenerator::new("jest")
+        .unwrap()
+        .generate(&variant_requests())
+        .unwrap();
+
+    assert!(code.contains(
+        "test.each<{ path: string; expectedStatus: number }>([\n  { path: \"/api/users/1\", expectedStatus: 200 },\n  { path: \"/api/users/2\", expectedStatus: 404 },\n])(\"GET /api/users/{id} $path $expectedStatus\", async ({ path, expectedStatus }) => {"
+    ));
+    assert!(code.contains("await fetch(`${BASE_URL}${path}`"));
+    assert!(code.contains("type PostApiUsersPayload = {\n  name?: number;\n  test?: string;\n};"));
+    assert!(code.contains("  { payload: {\"name\":1}, expectedStatus: 400 },"));
+    assert!(code.contains("body: JSON.stringify(payload),"));
+}
+
 #[test]
 fn test_get_generator_auto_detection() {
     let result = get_generator("auto", None);
@@ -112,7 +521,7 @@ fn test_get_generator_unknown() {
 #[test]
 fn test_empty_requests() {
     let requests = vec![];
-    let generator = PythonGenerator;
+    let generator = PythonGenerator::new("pytest").unwrap();
     let code = generator.generate(&requests).unwrap();
     assert!(code.contains("import requests"));
 }
@@ -120,14 +529,542 @@ fn test_empty_requests() {
 #[test]
 fn test_multiple_methods_same_endpoint() {
     let requests = vec![
-        create_test_request("GET", "/api/users", 200),
-        create_test_request("POST", "/api/users", 201),
-        create_test_request("PUT", "/api/users", 200),
-        create_test_request("DELETE", "/api/users", 204),
+        captured("GET", "/api/users", BODY, 200, RESPONSE),
+        captured("POST", "/api/users", BODY, 201, RESPONSE),
+        captured("PUT", "/api/users", BODY, 200, RESPONSE),
+        captured("DELETE", "/api/users", BODY, 204, RESPONSE),
     ];
 
-    let generator = PythonGenerator;
+    let generator = PythonGenerator::new("pytest").unwrap();
     let code = generator.generate(&requests).unwrap();
 
     assert!(code.matches("def test_").count() >= 4);
 }
+
+fn flow_requests() -> Vec<CapturedRequest> {
+    let start = Utc::now();
+    let step = |i: i64, method: &str, uri: &str, body: Option<&str>, status, response: &str| {
+        let mut req = captured(method, uri, BODY, status, RESPONSE);
+        req.timestamp = start + chrono::Duration::milliseconds(i);
+        req.request.body = body.map(|b| b.as_bytes().to_vec());
+        req.response.as_mut().unwrap().body = Some(response.as_bytes().to_vec());
+        req
+    };
+
+    let mut me = step(3, "GET", "/api/me", None, 200, r#"{"name":"ada"}"#);
+    me.request.headers.insert(
+        "authorization".to_string(),
+        "Bearer tok_8f3a9c2e".to_string(),
+    );
+
+    vec![
+        step(
+            0,
+            "POST",
+            "/api/orders",
+            Some(r#"{"item":"book"}"#),
+            201,
+            r#"{"id":42,"status":"new"}"#,
+        ),
+        step(
+            1,
+            "GET",
+            "/api/orders/42",
+            None,
+            200,
+            r#"{"id":42,"item":"book"}"#,
+        ),
+        step(
+            2,
+            "POST",
+            "/api/login",
+            Some(r#"{"user":"ada"}"#),
+            200,
+            r#"{"token":"tok_8f3a9c2e"}"#,
+        ),
+        me,
+        step(
+            4,
+            "POST",
+            "/api/payments",
+            Some(r#"{"order_id":42,"amount":10}"#),
+            201,
+            r#"{"ok":true}"#,
+        ),
+        step(5, "GET", "/api/health", None, 200, r#"{"status":"up"}"#),
+    ]
+}
+
+#[test]
+fn test_detect_flows() {
+    let requests = flow_requests();
+    let flows = flow::detect(&requests);
+
+    assert_eq!(flows.len(), 2);
+    assert_eq!(
+        flows[0].summary(),
+        "POST /api/orders -> GET /api/orders/{id} -> POST /api/payments"
+    );
+    assert_eq!(flows[1].summary(), "POST /api/login -> GET /api/me");
+
+    let order = &flows[0].steps;
+    assert_eq!(order[0].extracts.len(), 1);
+    assert_eq!(order[0].extracts[0].variable, "order_id");
+    assert_eq!(
+        order[0].extracts[0].path,
+        vec![flow::Key::Field("id".into())]
+    );
+    assert_eq!(
+        order[1].uri,
+        vec![
+            flow::Part::Text("/api/orders/".into()),
+            flow::Part::Var("order_id".into())
+        ]
+    );
+    match &order[2].body {
+        Some(flow::FlowBody::Json(_, vars)) => {
+            assert_eq!(vars.get("/order_id").map(String::as_str), Some("order_id"))
+        }
+        other => panic!("expected JSON body, got {:?}", other),
+    }
+
+    let login = &flows[1].steps;
+    assert_eq!(login[0].extracts[0].variable, "token");
+    assert_eq!(
+        login[1].headers[0].1,
+        vec![
+            flow::Part::Text("Bearer ".into()),
+            flow::Part::Var("token".into())
+        ]
+    );
+}
+
+#[test]
+fn test_detect_flows_ignores_echoed_client_values() {
+    let mut create = captured("POST", "/api/users", BODY, 201, RESPONSE);
+    create.request.body = Some(br#"{"username":"ada_lovelace"}"#.to_vec());
+    create.response.as_mut().unwrap().body = Some(br#"{"username":"ada_lovelace"}"#.to_vec());
+    let mut fetch = captured("GET", "/api/users/ada_lovelace", BODY, 200, RESPONSE);
+    fetch.timestamp = create.timestamp + chrono::Duration::milliseconds(1);
+
+    assert!(flow::detect(&[create, fetch]).is_empty());
+}
+
+#[test]
+fn test_flow_variables_avoid_keywords() {
+    let mut create = captured("POST", "/api/widgets", BODY, 201, RESPONSE);
+    create.response.as_mut().unwrap().body = Some(br#"{"type":"kind_7f3e9a"}"#.to_vec());
+    let mut fetch = captured("GET", "/api/widgets/kind_7f3e9a", BODY, 200, RESPONSE);
+    fetch.timestamp = create.timestamp + chrono::Duration::milliseconds(1);
+    let requests = [create, fetch];
+
+    let flows = flow::detect(&requests);
+    assert_eq!(flows.len(), 1);
+    assert_eq!(flows[0].steps[0].extracts[0].variable, "type_value");
+
+    let code = GoGenerator::new().generate(&requests).unwrap();
+    assert!(code.contains("typeValue := field(t, data, \"type\")"));
+    let code = RustGenerator::new().generate(&requests).unwrap();
+    assert!(code.contains("let type_value = data[\"type\"].clone();"));
+    let code = JavaGenerator::new("junit5")
+        .unwrap()
+        .generate(&requests)
+        .unwrap();
+    assert!(code.contains("Object typeValue = response.path(\"type\");"));
+    let code = TypeScriptGenerator::new("jest")
+        .unwrap()
+        .generate(&requests)
+        .unwrap();
+    assert!(code.contains("const typeValue = data.type;"));
+}
+
+#[test]
+fn test_detect_flows_ignores_short_values() {
+    let mut create = captured("POST", "/api/orders", BODY, 201, RESPONSE);
+    create.response.as_mut().unwrap().body = Some(br#"{"id":1}"#.to_vec());
+    let mut list = captured("GET", "/api/items?page=1", BODY, 200, RESPONSE);
+    list.timestamp = create.timestamp + chrono::Duration::milliseconds(1);
+    let mut page = captured("GET", "/api/pages/1", BODY, 200, RESPONSE);
+    page.timestamp = create.timestamp + chrono::Duration::milliseconds(2);
+    assert!(flow::detect(&[create.clone(), list, page]).is_empty());
+
+    let mut payment = captured("GET", "/api/payments?order_id=1", BODY, 200, RESPONSE);
+    payment.timestamp = create.timestamp + chrono::Duration::milliseconds(1);
+    assert_eq!(flow::detect(&[create, payment]).len(), 1);
+}
+
+#[test]
+fn test_python_flow_scenario() {
+    let generator = PythonGenerator::new("pytest").unwrap();
+    let code = generator.generate(&flow_requests()).unwrap();
+
+    assert!(code.contains("def test_flow_post_api_orders(session, base_url):"));
+    assert!(code.contains("order_id = response.json()[\"id\"]"));
+    assert!(code.contains("session.get(f\"{base_url}/api/orders/{order_id}\")"));
+    assert!(code.contains("json={\"amount\": 10, \"order_id\": order_id}"));
+    assert!(code.contains("token = response.json()[\"token\"]"));
+    assert!(code.contains("\"authorization\": f\"Bearer {token}\""));
+}
+
+#[test]
+fn test_go_flow_scenario() {
+    let code = GoGenerator::new().generate(&flow_requests()).unwrap();
+
+    assert!(code.contains("\t\"encoding/json\"\n\t\"fmt\"\n\t\"io\"\n"));
+    assert!(code.contains("func TestFlowPostApiOrders(t *testing.T) {"));
+    assert!(code.contains("orderID := field(t, data, \"id\")"));
+    assert!(code.contains("baseURL+\"/api/orders/\"+fmt.Sprint(orderID)"));
+    assert!(code.contains("\"{\\\"amount\\\":10,\\\"order_id\\\":\"+jsonText(orderID)+\"}\""));
+    assert!(code.contains("map[string]string{\"authorization\": \"Bearer \"+fmt.Sprint(token)}"));
+}
+
+#[test]
+fn test_go_without_flows_skips_helpers() {
+    let requests = vec![captured("GET", "/api/users", BODY, 200, RESPONSE)];
+    let code = GoGenerator::new().generate(&requests).unwrap();
+
+    assert!(!code.contains("func send("));
+    assert!(!code.contains("\"encoding/json\""));
+}
+
+#[test]
+fn test_rust_flow_scenario() {
+    let code = RustGenerator::new().generate(&flow_requests()).unwrap();
+
+    assert!(code.contains("fn text(value: &serde_json::Value) -> String {"));
+    assert!(code.contains("async fn test_flow_post_api_orders() {"));
+    assert!(code.contains("let order_id = data[\"id\"].clone();"));
+    assert!(code.contains(".get(format!(\"{}/api/orders/{}\", base_url(), text(&order_id)))"));
+    assert!(code.contains(".json(&serde_json::json!({\"amount\": 10, \"order_id\": order_id}))"));
+    assert!(code.contains(".header(\"authorization\", format!(\"Bearer {}\", text(&token)))"));
+}
+
+#[test]
+fn test_typescript_flow_scenario() {
+    let generator = TypeScriptGenerator::new("jest").unwrap();
+    let code = generator.generate(&flow_requests()).unwrap();
+
+    assert!(code.contains("test(\"scenario: POST /api/login -> GET /api/me\", async () => {"));
+    assert!(code.contains("let data = await response.json();\n  const orderId = data.id;"));
+    assert!(code.contains("response = await fetch(`${BASE_URL}/api/orders/${orderId}`, {"));
+    assert!(code.contains("JSON.stringify({ \"amount\": 10, \"order_id\": orderId })"));
+    assert!(code.contains("\"authorization\": `Bearer ${token}`,"));
+
+    let generator = TypeScriptGenerator::new("supertest").unwrap();
+    let code = generator.generate(&flow_requests()).unwrap();
+    assert!(code.contains("const orderId = response.body.id;"));
+    assert!(code.contains(".get(`/api/orders/${orderId}`)"));
+}
+
+#[test]
+fn test_java_flow_scenario() {
+    let generator = JavaGenerator::new("junit5").unwrap();
+    let code = generator.generate(&flow_requests()).unwrap();
+
+    assert!(code.contains("import io.restassured.response.Response;"));
+    assert!(code.contains("void flowPostApiOrders() {"));
+    assert!(
+        code.contains(".extract().response();\n        Object orderId = response.path(\"id\");")
+    );
+    assert!(code.contains(".request(\"GET\", \"/api/orders/\" + orderId)"));
+    assert!(
+        code.contains(".body(\"{\\\"amount\\\":10,\\\"order_id\\\":\" + json(orderId) + \"}\")")
+    );
+
+    let generator = JavaGenerator::new("httpclient").unwrap();
+    let code = generator.generate(&flow_requests()).unwrap();
+    assert!(code.contains("Object token = JsonPath.read(response.body(), \"$.token\");"));
+    assert!(code.contains(
+        "\n        request = HttpRequest.newBuilder(URI.create(BASE_URL + \"/api/me\"))"
+    ));
+}
+
+/// A capture exercising headers, variants, colliding names and a flow
+fn golden_requests() -> Vec<CapturedRequest> {
+    let start = chrono::TimeZone::with_ymd_and_hms(&Utc, 2024, 1, 1, 0, 0, 0).unwrap();
+    let request = |i: i64, method: &str, uri: &str, body: Option<&str>, status, response: &str| {
+        let mut req = captured(method, uri, BODY, status, RESPONSE);
+        req.id = format!("req-{}", i);
+        req.timestamp = start + chrono::Duration::seconds(i);
+        req.request.body = body.map(|b| b.as_bytes().to_vec());
+        req.response.as_mut().unwrap().body = Some(response.as_bytes().to_vec());
+        for (key, value) in [
+            ("host", "api.example.com"),
+            ("accept", "application/json"),
+            ("x-request-source", "capture"),
+            ("user-agent", "chaos-testing"),
+        ] {
+            req.request
+                .headers
+                .insert(key.to_string(), value.to_string());
+        }
+        req
+    };
+
+    vec![
+        request(
+            0,
+            "GET",
+            "/api/users/1",
+            None,
+            200,
+            r#"{"id":1,"name":"ada"}"#,
+        ),
+        request(
+            1,
+            "GET",
+            "/api/users/2",
+            None,
+            404,
+            r#"{"error":"not found"}"#,
+        ),
+        request(
+            2,
+            "POST",
+            "/api/orders",
+            Some(r#"{"item":"book","qty":1}"#),
+            201,
+            r#"{"id":42,"status":"new"}"#,
+        ),
+        request(
+            3,
+            "GET",
+            "/api/orders/42",
+            None,
+            200,
+            r#"{"id":42,"status":"new"}"#,
+        ),
+        request(4, "DELETE", "/api/orders/42", None, 204, ""),
+        request(5, "GET", "/api/user-list", None, 200, "[]"),
+        request(6, "GET", "/api/user_list", None, 200, "[]"),
+        request(
+            7,
+            "PUT",
+            "/api/users/1",
+            Some(r#"{"name":"grace"}"#),
+            200,
+            r#"{"id":1,"name":"grace"}"#,
+        ),
+    ]
+}
+
+/// The same capture with every header map rebuilt, so iteration order differs
+fn reshuffled(requests: &[CapturedRequest]) -> Vec<CapturedRequest> {
+    requests
+        .iter()
+        .map(|req| {
+            let mut req = req.clone();
+            req.request.headers = req.request.headers.into_iter().collect();
+            req
+        })
+        .collect()
+}
+
+/// Compare against `src/generators/golden/<name>`; set `UPDATE_GOLDEN=1` to
+/// rewrite the file after an intended change
+fn assert_golden(name: &str, language: &str, framework: Option<&str>) {
+    let generator = get_generator(language, framework).unwrap();
+    let requests = golden_requests();
+    let actual = generator.generate(&requests).unwrap();
+
+    for _ in 0..5 {
+        let again = generator.generate(&reshuffled(&requests)).unwrap();
+        assert_eq!(actual, again, "{} output is not deterministic", name);
+    }
+
+    let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("src/generators/golden")
+        .join(name);
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        std::fs::write(&path, &actual).unwrap();
+    }
+    let expected = std::fs::read_to_string(&path)
+        .unwrap_or_else(|e| panic!("cannot read {}: {}", path.display(), e));
+    assert_eq!(
+        actual, expected,
+        "{} is out of date; rerun with UPDATE_GOLDEN=1 if the change is intended",
+        name
+    );
+}
+
+#[test]
+fn test_golden_python() {
+    assert_golden("python_pytest.golden", "python", Some("pytest"));
+}
+
+#[test]
+fn test_golden_go() {
+    assert_golden("go.golden", "go", None);
+}
+
+#[test]
+fn test_golden_rust() {
+    assert_golden("rust.golden", "rust", None);
+}
+
+#[test]
+fn test_golden_typescript() {
+    assert_golden("typescript_jest.golden", "typescript", Some("jest"));
+}
+
+#[test]
+fn test_golden_java() {
+    assert_golden("java_junit5.golden", "java", Some("junit5"));
+}
+
+#[test]
+fn test_colliding_test_names_are_numbered() {
+    let requests = vec![
+        captured("GET", "/api/user-list", BODY, 200, RESPONSE),
+        captured("GET", "/api/user_list", BODY, 200, RESPONSE),
+    ];
+
+    let code = PythonGenerator::new("pytest")
+        .unwrap()
+        .generate(&requests)
+        .unwrap();
+    assert!(code.contains("def test_get_api_user_list(session, base_url):"));
+    assert!(code.contains("def test_get_api_user_list_2(session, base_url):"));
+
+    let code = GoGenerator::new().generate(&requests).unwrap();
+    assert!(code.contains("func TestGetApiUserList(t *testing.T)"));
+    assert!(code.contains("func TestGetApiUserList2(t *testing.T)"));
+
+    let code = RustGenerator::new().generate(&requests).unwrap();
+    assert!(code.contains("async fn test_get_api_user_list()"));
+    assert!(code.contains("async fn test_get_api_user_list_2()"));
+}
+
+fn redacted_requests() -> Vec<CapturedRequest> {
+    let mut request = captured("POST", "/api/login", BODY, 200, RESPONSE);
+    request
+        .request
+        .headers
+        .insert("authorization".to_string(), "Bearer abc123".to_string());
+    request.request.body = Some(b"{\"user\":\"ann\",\"password\":\"hunter2\"}".to_vec());
+    request.response.as_mut().unwrap().body = Some(b"{\"token\":\"t0k3n-value\"}".to_vec());
+
+    let mut requests = vec![request];
+    crate::redact::Redactor::new().redact_all(&mut requests);
+    requests
+}
+
+#[test]
+fn test_redacted_values_read_from_environment() {
+    let requests = redacted_requests();
+
+    let code = PythonGenerator::new("pytest")
+        .unwrap()
+        .generate(&requests)
+        .unwrap();
+    assert!(code.contains("\"authorization\": env(\"AUTHORIZATION\")"));
+    assert!(code.contains("\"password\": env(\"PASSWORD\")"));
+    assert!(!code.contains("hunter2"));
+    assert!(!code.contains("__REDACTED_"));
+
+    let code = GoGenerator::new().generate(&requests).unwrap();
+    assert!(code.contains("req.Header.Set(\"authorization\", env(\"AUTHORIZATION\", \"\"))"));
+    assert!(!code.contains("__REDACTED_"));
+
+    let code = RustGenerator::new().generate(&requests).unwrap();
+    assert!(code.contains(".header(\"authorization\", env(\"AUTHORIZATION\", \"\"))"));
+    assert!(!code.contains("__REDACTED_"));
+
+    let code = TypeScriptGenerator::new("jest")
+        .unwrap()
+        .generate(&requests)
+        .unwrap();
+    assert!(code.contains("\"authorization\": env(\"AUTHORIZATION\")"));
+    assert!(code.contains("\"password\": env(\"PASSWORD\")"));
+    assert!(!code.contains("__REDACTED_"));
+
+    let code = JavaGenerator::new("junit5")
+        .unwrap()
+        .generate(&requests)
+        .unwrap();
+    assert!(code.contains(".header(\"authorization\", env(\"AUTHORIZATION\", \"\"))"));
+    assert!(!code.contains("__REDACTED_"));
+}
+
+#[test]
+fn test_base_url_defaults_to_configured_target() {
+    let requests = vec![captured("GET", "/api/users", BODY, 200, RESPONSE)];
+    let config = SuiteConfig {
+        base_url: "https://staging.example.com".to_string(),
+    };
+
+    let code = PythonGenerator::new("pytest")
+        .unwrap()
+        .generate_with(&requests, &config)
+        .unwrap();
+    assert!(code.contains("BASE_URL = env(\"BASE_URL\", \"https://staging.example.com\")"));
+    assert!(code.contains("prefix = os.environ.get(\"TEST_ENV\", \"\").upper()"));
+
+    let code = GoGenerator::new()
+        .generate_with(&requests, &config)
+        .unwrap();
+    assert!(code.contains("var baseURL = env(\"BASE_URL\", \"https://staging.example.com\")"));
+
+    let code = RustGenerator::new()
+        .generate_with(&requests, &config)
+        .unwrap();
+    assert!(code.contains("env(\"BASE_URL\", \"https://staging.example.com\")"));
+
+    let code = TypeScriptGenerator::new("jest")
+        .unwrap()
+        .generate_with(&requests, &config)
+        .unwrap();
+    assert!(code.contains("const BASE_URL = env(\"BASE_URL\", \"https://staging.example.com\");"));
+
+    let code = JavaGenerator::new("junit5")
+        .unwrap()
+        .generate_with(&requests, &config)
+        .unwrap();
+    assert!(code.contains(
+        "static final String BASE_URL = env(\"BASE_URL\", \"https://staging.example.com\");"
+    ));
+}
+
+#[test]
+fn test_captured_base_url_uses_most_common_host() {
+    let mut requests = vec![
+        captured("GET", "/a", BODY, 200, RESPONSE),
+        captured("GET", "/b", BODY, 200, RESPONSE),
+        captured("GET", "/c", BODY, 200, RESPONSE),
+    ];
+    assert_eq!(captured_base_url(&requests), None);
+
+    for (req, host) in requests.iter_mut().zip(["api:9000", "cdn:80", "api:9000"]) {
+        req.request
+            .headers
+            .insert("host".to_string(), host.to_string());
+    }
+    assert_eq!(
+        captured_base_url(&requests).as_deref(),
+        Some("http://api:9000")
+    );
+
+    for req in &mut requests {
+        req.protocol = Protocol::Https;
+    }
+    assert_eq!(
+        captured_base_url(&requests).as_deref(),
+        Some("https://api:9000")
+    );
+}
+
+#[test]
+fn test_injected_auth_headers_read_from_environment() {
+    let mut requests = vec![captured("GET", "/api/users", BODY, 200, RESPONSE)];
+    requests[0]
+        .request
+        .headers
+        .insert("X-Api-Key".to_string(), "live-key".to_string());
+
+    inject_auth_headers(&mut requests, &["x-api-key".to_string()]);
+
+    assert_eq!(requests[0].request.headers.len(), 1);
+    let code = GoGenerator::new().generate(&requests).unwrap();
+    assert!(code.contains("req.Header.Set(\"x-api-key\", env(\"X_API_KEY\", \"\"))"));
+    assert!(!code.contains("live-key"));
+}
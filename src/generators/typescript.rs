@@ -0,0 +1,677 @@
+use crate::generators::{
+    SuiteConfig, TestGenerator, Varying, common_json, flow, group_by_endpoint, request_headers,
+    unique_name, variants,
+};
+use crate::models::CapturedRequest;
+use crate::{redact, schema};
+use anyhow::Result;
+use flow::{Flow, FlowBody, Key, Part, Template};
+use regex::Regex;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashSet};
+use std::sync::LazyLock;
+
+/// Settings lookup shared by every generated file
+const ENV_HELPER: &str = r#"/** Setting for the current TEST_ENV, e.g. STAGING_BASE_URL over BASE_URL */
+function env(name: string, fallback = ""): string {
+  const prefix = process.env.TEST_ENV?.toUpperCase();
+  const scoped = prefix ? process.env[`${prefix}_${name}`] : undefined;
+  return scoped ?? process.env[name] ?? fallback;
+}
+
+"#;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Runner {
+    Jest,
+    Vitest,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Client {
+    Fetch,
+    Supertest,
+}
+
+pub struct TypeScriptGenerator {
+    runner: Runner,
+    client: Client,
+}
+
+impl TypeScriptGenerator {
+    /// Accepts a runner and/or client, e.g. `jest`, `vitest`, `supertest` or
+    /// `vitest-supertest`. Defaults to Jest with `fetch`.
+    pub fn new(framework: &str) -> Result<Self> {
+        let mut runner = Runner::Jest;
+        let mut client = Client::Fetch;
+
+        for part in framework.to_lowercase().split(['-', '+', ',']) {
+            match part.trim() {
+                "" | "auto" => {}
+                "jest" => runner = Runner::Jest,
+                "vitest" => runner = Runner::Vitest,
+                "fetch" => client = Client::Fetch,
+                "supertest" => client = Client::Supertest,
+                other => anyhow::bail!("Unsupported TypeScript framework: {}", other),
+            }
+        }
+
+        Ok(Self { runner, client })
+    }
+
+    /// PascalCase identifier for the payload type of an endpoint
+    fn type_name(&self, endpoint: &str) -> String {
+        let name: String = endpoint
+            .split(|c: char| !c.is_ascii_alphanumeric())
+            .filter(|s| !s.is_empty())
+            .map(|s| {
+                let s = s.to_lowercase();
+                let mut chars = s.chars();
+                match chars.next() {
+                    None => String::new(),
+                    Some(c) => c.to_uppercase().chain(chars).collect(),
+                }
+            })
+            .collect();
+
+        if name.starts_with(|c: char| c.is_ascii_digit()) {
+            format!("Endpoint{}Payload", name)
+        } else {
+            format!("{}Payload", name)
+        }
+    }
+
+    fn header(&self, config: &SuiteConfig) -> String {
+        let mut output = String::new();
+
+        if self.runner == Runner::Vitest {
+            output.push_str("import { expect, test } from \"vitest\";\n");
+        }
+        if self.client == Client::Supertest {
+            output.push_str("import request from \"supertest\";\n");
+        }
+        if !output.is_empty() {
+            output.push('\n');
+        }
+
+        output.push_str(ENV_HELPER);
+        output.push_str(&format!(
+            "const BASE_URL = env(\"BASE_URL\", {});\n",
+            string_literal(&config.base_url)
+        ));
+        if self.client == Client::Supertest {
+            output.push_str("const api = request(BASE_URL);\n");
+        }
+        output.push('\n');
+
+        output
+    }
+
+    /// `url` is a TypeScript expression for the request URL or path
+    fn write_fetch(
+        &self,
+        output: &mut String,
+        indent: &str,
+        req: &CapturedRequest,
+        url: &str,
+        headers: &[(&String, String)],
+        payload: Option<&str>,
+    ) {
+        output.push_str(&format!(
+            "{}const response = await fetch({}, {{\n",
+            indent, url
+        ));
+        output.push_str(&format!(
+            "{}  method: {},\n",
+            indent,
+            string_literal(&req.request.method)
+        ));
+
+        if !headers.is_empty() {
+            output.push_str(&format!("{}  headers: {{\n", indent));
+            for (key, value) in headers {
+                output.push_str(&format!(
+                    "{}    {}: {},\n",
+                    indent,
+                    string_literal(key),
+                    value
+                ));
+            }
+            output.push_str(&format!("{}  }},\n", indent));
+        }
+
+        if let Some(payload) = payload {
+            output.push_str(&format!("{}  body: {},\n", indent, payload));
+        }
+
+        output.push_str(&format!("{}}});\n\n", indent));
+    }
+
+    fn write_supertest(
+        &self,
+        output: &mut String,
+        indent: &str,
+        req: &CapturedRequest,
+        url: &str,
+        headers: &[(&String, String)],
+        payload: Option<&str>,
+    ) {
+        let method = req.request.method.to_lowercase();
+        output.push_str(&format!("{}const response = await api\n", indent));
+        output.push_str(&format!("{}  .{}({})\n", indent, method, url));
+
+        for (key, value) in headers {
+            output.push_str(&format!(
+                "{}  .set({}, {})\n",
+                indent,
+                string_literal(key),
+                value
+            ));
+        }
+
+        if let Some(payload) = payload {
+            output.push_str(&format!("{}  .send({})\n", indent, payload));
+        }
+
+        output.truncate(output.trim_end().len());
+        output.push_str(";\n\n");
+    }
+
+    /// Status check plus a body check against what all responses share
+    fn write_assertions(
+        &self,
+        output: &mut String,
+        indent: &str,
+        status: Option<&str>,
+        expected: Option<Value>,
+    ) {
+        let Some(status) = status else {
+            output.push_str(&format!(
+                "{}expect(response.status).toBeLessThan(500);\n",
+                indent
+            ));
+            return;
+        };
+
+        output.push_str(&format!(
+            "{}expect(response.status).toBe({});\n",
+            indent, status
+        ));
+
+        let Some(expected) = expected else {
+            return;
+        };
+
+        let body = match self.client {
+            Client::Fetch => {
+                output.push_str(&format!("{}const body = await response.json();\n", indent));
+                "body"
+            }
+            Client::Supertest => "response.body",
+        };
+
+        match expected {
+            Value::Object(_) => output.push_str(&format!(
+                "{}expect({}).toMatchObject({});\n",
+                indent,
+                body,
+                reindent(
+                    &serde_json::to_string_pretty(&expected).unwrap_or_default(),
+                    indent
+                )
+            )),
+            Value::Array(_) => output.push_str(&format!(
+                "{}expect(Array.isArray({})).toBe(true);\n",
+                indent, body
+            )),
+            scalar => output.push_str(&format!(
+                "{}expect({}).toEqual({});\n",
+                indent, body, scalar
+            )),
+        }
+    }
+
+    /// Scenario test replaying a flow, passing extracted values forward
+    fn write_flow(&self, output: &mut String, flow: &Flow) {
+        output.push_str(&format!(
+            "test({}, async () => {{\n",
+            string_literal(&format!("scenario: {}", flow.summary()))
+        ));
+
+        let mut data_declared = false;
+        for (i, step) in flow.steps.iter().enumerate() {
+            if i > 0 {
+                output.push('\n');
+            }
+
+            let headers: Vec<(&String, String)> = step
+                .headers
+                .iter()
+                .map(|(key, value)| (*key, template_literal(value)))
+                .collect();
+            let payload = match (&step.body, self.client) {
+                (Some(FlowBody::Json(value, vars)), Client::Fetch) => {
+                    Some(format!("JSON.stringify({})", ts_json(value, vars)))
+                }
+                (Some(FlowBody::Json(value, vars)), Client::Supertest) => {
+                    Some(ts_json(value, vars))
+                }
+                (Some(FlowBody::Text(text)), _) => Some(template_literal(text)),
+                (None, _) => None,
+            };
+
+            let mut call = String::new();
+            match self.client {
+                Client::Fetch => {
+                    let mut url = vec![Part::Var("BASE_URL".to_string())];
+                    url.extend(step.uri.iter().cloned());
+                    let url = template_literal(&url);
+                    self.write_fetch(
+                        &mut call,
+                        "  ",
+                        step.request,
+                        &url,
+                        &headers,
+                        payload.as_deref(),
+                    )
+                }
+                Client::Supertest => self.write_supertest(
+                    &mut call,
+                    "  ",
+                    step.request,
+                    &template_literal(&step.uri),
+                    &headers,
+                    payload.as_deref(),
+                ),
+            }
+            // Steps share one `response`, reassigned after the first request
+            let binding = if i == 0 { "let response" } else { "response" };
+            output.push_str(&call.replacen("const response", binding, 1));
+
+            let status = step
+                .request
+                .response
+                .as_ref()
+                .map(|r| r.status_code.to_string());
+            self.write_assertions(output, "  ", status.as_deref(), None);
+
+            if step.extracts.is_empty() {
+                continue;
+            }
+            let data = match self.client {
+                Client::Fetch => {
+                    let binding = if data_declared { "data" } else { "let data" };
+                    output.push_str(&format!("  {} = await response.json();\n", binding));
+                    data_declared = true;
+                    "data"
+                }
+                Client::Supertest => "response.body",
+            };
+            for extract in &step.extracts {
+                let accessor: String = extract
+                    .path
+                    .iter()
+                    .map(|key| match key {
+                        Key::Field(name) if is_identifier(name) => format!(".{}", name),
+                        Key::Field(name) => format!("[{}]", string_literal(name)),
+                        Key::Index(i) => format!("[{}]", i),
+                    })
+                    .collect();
+                output.push_str(&format!(
+                    "  const {} = {}{};\n",
+                    flow::camel_case(&extract.variable),
+                    data,
+                    accessor
+                ));
+            }
+        }
+
+        output.push_str("});\n\n");
+    }
+
+    fn unique_type_name(&self, endpoint: &str, type_names: &mut HashSet<String>) -> String {
+        unique_name(type_names, self.type_name(endpoint), "")
+    }
+}
+
+impl TestGenerator for TypeScriptGenerator {
+    fn generate_with(&self, requests: &[CapturedRequest], config: &SuiteConfig) -> Result<String> {
+        let mut output = self.header(config);
+        let mut type_names = HashSet::new();
+
+        let grouped = group_by_endpoint(requests);
+
+        for (endpoint, reqs) in grouped.iter() {
+            let first_req = reqs[0];
+
+            let headers: Vec<(&String, String)> = request_headers(first_req)
+                .into_iter()
+                .map(|(key, value)| (key, template_literal(&flow::template(value))))
+                .collect();
+
+            // fetch rejects GET and HEAD requests that carry a body
+            let sends_body = !matches!(first_req.request.method.as_str(), "GET" | "HEAD");
+            let mut variants = variants(reqs);
+            if !sends_body {
+                variants.iter_mut().for_each(|v| v.body = None);
+                variants.dedup();
+            }
+            let varying = Varying::of(&variants);
+            let first = &variants[0];
+
+            // JSON payloads get a type inferred from the captured bodies
+            let bodies: Vec<Option<Value>> = variants
+                .iter()
+                .map(|v| v.body.and_then(|b| serde_json::from_slice(b).ok()))
+                .collect();
+            let json = variants
+                .iter()
+                .zip(&bodies)
+                .all(|(v, parsed)| v.body.is_none() || parsed.is_some());
+            let json_values: Vec<&Value> = bodies.iter().flatten().collect();
+            let payload_type = match schema::infer_all(json_values.iter().copied()) {
+                Some(schema) if json => {
+                    let type_name = self.unique_type_name(endpoint, &mut type_names);
+                    output.push_str(&format!(
+                        "type {} = {};\n\n",
+                        type_name,
+                        ts_type(&schema, 0)
+                    ));
+                    Some(type_name)
+                }
+                _ => None,
+            };
+
+            let payload_literal = |i: usize, pretty: bool| -> Option<String> {
+                match (&payload_type, &bodies[i], variants[i].body) {
+                    (Some(_), Some(value), _) if pretty => Some(redacted_strings(
+                        &serde_json::to_string_pretty(value).unwrap_or_default(),
+                    )),
+                    (Some(_), Some(value), _) => Some(redacted_strings(&value.to_string())),
+                    (_, _, Some(body)) => Some(template_literal(&flow::template(
+                        &String::from_utf8_lossy(body),
+                    ))),
+                    _ => None,
+                }
+            };
+
+            let indent = if varying.any() {
+                // One row per captured variant, fed to test.each
+                let mut fields = Vec::new();
+                let mut title = vec![endpoint.clone()];
+                if varying.uri {
+                    fields.push("path: string".to_string());
+                    title.push("$path".to_string());
+                }
+                if varying.body {
+                    let ty = payload_type.as_deref().unwrap_or("string");
+                    let optional = if variants.iter().any(|v| v.body.is_none()) {
+                        "?"
+                    } else {
+                        ""
+                    };
+                    fields.push(format!("payload{}: {}", optional, ty));
+                }
+                if varying.status {
+                    fields.push("expectedStatus: number".to_string());
+                    title.push("$expectedStatus".to_string());
+                }
+
+                output.push_str(&format!("test.each<{{ {} }}>([\n", fields.join("; ")));
+                for (i, variant) in variants.iter().enumerate() {
+                    let mut values = Vec::new();
+                    if varying.uri {
+                        values.push(format!(
+                            "path: {}",
+                            template_literal(&flow::template(variant.uri))
+                        ));
+                    }
+                    if let (true, Some(payload)) = (varying.body, payload_literal(i, false)) {
+                        values.push(format!("payload: {}", payload));
+                    }
+                    if let (true, Some(status)) = (varying.status, variant.status) {
+                        values.push(format!("expectedStatus: {}", status));
+                    }
+                    output.push_str(&format!("  {{ {} }},\n", values.join(", ")));
+                }
+
+                let mut params = Vec::new();
+                if varying.uri {
+                    params.push("path");
+                }
+                if varying.body {
+                    params.push("payload");
+                }
+                if varying.status {
+                    params.push("expectedStatus");
+                }
+                output.push_str(&format!(
+                    "])({}, async ({{ {} }}) => {{\n",
+                    string_literal(&title.join(" ")),
+                    params.join(", ")
+                ));
+                "  "
+            } else {
+                output.push_str(&format!(
+                    "test({}, async () => {{\n",
+                    string_literal(endpoint)
+                ));
+                "  "
+            };
+
+            if let (false, Some(type_name), Some(payload)) =
+                (varying.body, &payload_type, payload_literal(0, true))
+            {
+                output.push_str(&format!(
+                    "{}const payload: {} = {};\n\n",
+                    indent,
+                    type_name,
+                    reindent(&payload, indent)
+                ));
+            }
+
+            let raw_payload = payload_literal(0, false);
+            let payload = match (varying.body, &payload_type, &raw_payload) {
+                (true, Some(_), _) | (false, Some(_), _) if self.client == Client::Fetch => {
+                    Some("JSON.stringify(payload)")
+                }
+                (true, _, _) | (false, Some(_), _) => Some("payload"),
+                (false, None, raw) => raw.as_deref(),
+            };
+
+            let url = match (varying.uri, self.client) {
+                (true, Client::Fetch) => "`${BASE_URL}${path}`".to_string(),
+                (false, Client::Fetch) => {
+                    let mut url = vec![Part::Var("BASE_URL".to_string())];
+                    url.extend(flow::template(first.uri));
+                    template_literal(&url)
+                }
+                (true, Client::Supertest) => "path".to_string(),
+                (false, Client::Supertest) => template_literal(&flow::template(first.uri)),
+            };
+            match self.client {
+                Client::Fetch => {
+                    self.write_fetch(&mut output, indent, first_req, &url, &headers, payload)
+                }
+                Client::Supertest => {
+                    self.write_supertest(&mut output, indent, first_req, &url, &headers, payload)
+                }
+            }
+
+            let status = match varying.status {
+                true => Some("expectedStatus".to_string()),
+                false => first.status.map(|s| s.to_string()),
+            };
+            self.write_assertions(&mut output, indent, status.as_deref(), common_json(reqs));
+
+            output.push_str(&format!("  // Called {} times in capture\n", reqs.len()));
+            output.push_str("});\n\n");
+        }
+
+        for flow in flow::detect(requests) {
+            self.write_flow(&mut output, &flow);
+        }
+
+        if grouped.is_empty() {
+            output.push_str("// No requests captured\n");
+        }
+
+        Ok(output)
+    }
+
+    fn file_extension(&self) -> &str {
+        // Jest and Vitest only pick up `*.test.ts` files by default
+        "test.ts"
+    }
+}
+
+/// Quote a string as a TypeScript literal
+fn string_literal(value: &str) -> String {
+    serde_json::to_string(value).unwrap_or_else(|_| "\"\"".to_string())
+}
+
+/// Template as a TypeScript string, a template literal only when needed
+fn template_literal(template: &Template) -> String {
+    match template.as_slice() {
+        [Part::Text(text)] => return string_literal(text),
+        [Part::Env(name)] => return format!("env({})", string_literal(name)),
+        _ => {}
+    }
+
+    let mut out = String::from("`");
+    for part in template {
+        match part {
+            Part::Text(text) => out.push_str(
+                &text
+                    .replace('\\', "\\\\")
+                    .replace('`', "\\`")
+                    .replace("${", "\\${"),
+            ),
+            Part::Var(name) if name == "BASE_URL" => out.push_str("${BASE_URL}"),
+            Part::Var(name) => out.push_str(&format!("${{{}}}", flow::camel_case(name))),
+            Part::Env(name) => out.push_str(&format!("${{env({})}}", string_literal(name))),
+        }
+    }
+    out.push('`');
+    out
+}
+
+/// JSON body as an object literal with variables in place of bound leaves
+fn ts_json(value: &Value, vars: &BTreeMap<String, String>) -> String {
+    flow::render_json(
+        value,
+        vars,
+        &|scalar| {
+            scalar
+                .as_str()
+                .map(|s| template_literal(&flow::template(s)))
+        },
+        &flow::camel_case,
+        &|fields| {
+            let fields: Vec<String> = fields
+                .into_iter()
+                .map(|(k, v)| format!("{}: {}", string_literal(&k), v))
+                .collect();
+            match fields.is_empty() {
+                true => "{}".to_string(),
+                false => format!("{{ {} }}", fields.join(", ")),
+            }
+        },
+        &|items| format!("[{}]", items.join(", ")),
+    )
+}
+
+/// Swap JSON string tokens holding redacted values for expressions reading
+/// them from the environment
+fn redacted_strings(json: &str) -> String {
+    static STRING: LazyLock<Regex> =
+        LazyLock::new(|| Regex::new(r#""(?:[^"\\]|\\.)*""#).expect("valid string regex"));
+
+    STRING
+        .replace_all(json, |caps: &regex::Captures| {
+            let token = &caps[0];
+            match serde_json::from_str::<String>(token) {
+                Ok(text) if redact::is_redacted(&text) => template_literal(&flow::template(&text)),
+                _ => token.to_string(),
+            }
+        })
+        .into_owned()
+}
+
+/// Indent continuation lines of a multi-line literal
+fn reindent(code: &str, indent: &str) -> String {
+    code.replace('\n', &format!("\n{}", indent))
+}
+
+/// Render an inferred JSON schema as a TypeScript type
+fn ts_type(schema: &Value, depth: usize) -> String {
+    let base = match schema.get("type").and_then(Value::as_str) {
+        Some("string") => "string".to_string(),
+        Some("integer" | "number") => "number".to_string(),
+        Some("boolean") => "boolean".to_string(),
+        Some("array") => {
+            let items = schema
+                .get("items")
+                .map(|i| ts_type(i, depth))
+                .unwrap_or_default();
+            if items.contains(' ') {
+                format!("Array<{}>", items)
+            } else if items.is_empty() {
+                "unknown[]".to_string()
+            } else {
+                format!("{}[]", items)
+            }
+        }
+        Some("object") => {
+            let properties = schema.get("properties").and_then(Value::as_object);
+            let required: HashSet<&str> = schema
+                .get("required")
+                .and_then(Value::as_array)
+                .map(|r| r.iter().filter_map(Value::as_str).collect())
+                .unwrap_or_default();
+
+            match properties.filter(|p| !p.is_empty()) {
+                None => "Record<string, unknown>".to_string(),
+                Some(properties) => {
+                    let pad = "  ".repeat(depth + 1);
+                    let mut fields = String::from("{\n");
+                    for (name, property) in properties {
+                        let key = if is_identifier(name) {
+                            name.clone()
+                        } else {
+                            string_literal(name)
+                        };
+                        let optional = if required.contains(name.as_str()) {
+                            ""
+                        } else {
+                            "?"
+                        };
+                        fields.push_str(&format!(
+                            "{}{}{}: {};\n",
+                            pad,
+                            key,
+                            optional,
+                            ts_type(property, depth + 1)
+                        ));
+                    }
+                    fields.push_str(&"  ".repeat(depth));
+                    fields.push('}');
+                    fields
+                }
+            }
+        }
+        _ => "unknown".to_string(),
+    };
+
+    if schema.get("nullable").and_then(Value::as_bool) == Some(true) {
+        format!("{} | null", base)
+    } else {
+        base
+    }
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_' || c == '$')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$')
+}
@@ -0,0 +1,219 @@
+//! HAR 1.2 import
+//!
+//! Converts HTTP Archive entries recorded by browsers or API gateways into
+//! captured requests.
+
+use crate::har::{Har, HarContent, HarEntry, HarNameValue};
+use crate::importers::Importer;
+use crate::models::{CapturedRequest, Protocol};
+use crate::parsers::http::HttpParser;
+use anyhow::{Context, Result};
+use base64::Engine as _;
+use base64::engine::general_purpose::STANDARD;
+use chrono::{DateTime, Utc};
+use hyper::header::{HeaderName, HeaderValue};
+use hyper::{HeaderMap, Method, Uri};
+use tracing::warn;
+use uuid::Uuid;
+
+pub struct HarImporter;
+
+impl HarImporter {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Importer for HarImporter {
+    fn import(&self, data: &[u8]) -> Result<Vec<CapturedRequest>> {
+        let har: Har = serde_json::from_slice(data).context("Invalid HAR document")?;
+
+        let mut requests = Vec::with_capacity(har.log.entries.len());
+        for (i, entry) in har.log.entries.iter().enumerate() {
+            match entry_to_request(entry) {
+                Ok(request) => requests.push(request),
+                Err(e) => warn!("Skipping HAR entry #{}: {:#}", i + 1, e),
+            }
+        }
+
+        Ok(requests)
+    }
+}
+
+fn entry_to_request(entry: &HarEntry) -> Result<CapturedRequest> {
+    let url: Uri = entry.request.url.parse()?;
+    let method = Method::from_bytes(entry.request.method.as_bytes())?;
+    let origin: Uri = url
+        .path_and_query()
+        .map(|p| p.as_str())
+        .unwrap_or("/")
+        .parse()?;
+
+    let protocol = match url.scheme_str() {
+        Some("https") => Protocol::Https,
+        _ => Protocol::Http,
+    };
+
+    let mut headers = to_header_map(&entry.request.headers);
+    // HTTP/2 entries carry the host in the `:authority` pseudo-header only
+    if !headers.contains_key("host")
+        && let Some(authority) = url.authority()
+        && let Ok(value) = HeaderValue::from_str(authority.as_str())
+    {
+        headers.insert("host", value);
+    }
+
+    let body = entry
+        .request
+        .post_data
+        .as_ref()
+        .filter(|p| !p.text.is_empty())
+        .map(|p| p.text.as_bytes().to_vec());
+
+    let response = if entry.response.status == 0 {
+        // Aborted or blocked requests are recorded with status 0
+        None
+    } else {
+        Some(HttpParser::parse_response(
+            entry.response.status,
+            &to_header_map(&entry.response.headers),
+            content_body(&entry.response.content)?,
+        ))
+    };
+
+    let timestamp: DateTime<Utc> = DateTime::parse_from_rfc3339(&entry.started_date_time)?.into();
+
+    Ok(CapturedRequest {
+        id: Uuid::new_v4().to_string(),
+        timestamp,
+        protocol,
+        request: HttpParser::parse_request(&method, &origin, &headers, body),
+        response,
+        duration_ms: (entry.time >= 0.0).then(|| entry.time.round() as u64),
+    })
+}
+
+fn to_header_map(headers: &[HarNameValue]) -> HeaderMap {
+    headers
+        .iter()
+        .filter(|h| !h.name.starts_with(':'))
+        .filter_map(|h| {
+            Some((
+                HeaderName::from_bytes(h.name.as_bytes()).ok()?,
+                HeaderValue::from_str(&h.value).ok()?,
+            ))
+        })
+        .collect()
+}
+
+fn content_body(content: &HarContent) -> Result<Option<Vec<u8>>> {
+    let Some(text) = content.text.as_deref().filter(|t| !t.is_empty()) else {
+        return Ok(None);
+    };
+
+    if content.encoding.as_deref() == Some("base64") {
+        Ok(Some(STANDARD.decode(text).context("Invalid base64 body")?))
+    } else {
+        Ok(Some(text.as_bytes().to_vec()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = r#"{
+        "log": {
+            "version": "1.2",
+            "creator": {"name": "Firefox", "version": "130.0"},
+            "entries": [
+                {
+                    "startedDateTime": "2026-03-01T10:00:00.000+01:00",
+                    "time": 41.6,
+                    "request": {
+                        "method": "POST",
+                        "url": "https://api.example.com/api/orders?dry=1",
+                        "httpVersion": "HTTP/2",
+                        "headers": [
+                            {"name": ":authority", "value": "api.example.com"},
+                            {"name": "Content-Type", "value": "application/json"}
+                        ],
+                        "queryString": [{"name": "dry", "value": "1"}],
+                        "postData": {"mimeType": "application/json", "text": "{\"item\":\"widget\"}"},
+                        "headersSize": -1,
+                        "bodySize": 17
+                    },
+                    "response": {
+                        "status": 201,
+                        "statusText": "Created",
+                        "httpVersion": "HTTP/2",
+                        "headers": [{"name": "content-type", "value": "application/json"}],
+                        "content": {"size": 8, "mimeType": "application/json", "text": "eyJpZCI6N30=", "encoding": "base64"},
+                        "redirectURL": "",
+                        "headersSize": -1,
+                        "bodySize": 8
+                    },
+                    "cache": {},
+                    "timings": {"send": 1, "wait": 40, "receive": 0.6}
+                },
+                {
+                    "startedDateTime": "2026-03-01T09:00:01Z",
+                    "time": -1,
+                    "request": {"method": "GET", "url": "http://localhost:9000/health", "headers": []},
+                    "response": {"status": 0, "content": {"size": 0}}
+                }
+            ]
+        }
+    }"#;
+
+    #[test]
+    fn test_import_har_entries() {
+        let requests = HarImporter::new().import(SAMPLE.as_bytes()).unwrap();
+        assert_eq!(requests.len(), 2);
+
+        let order = &requests[0];
+        assert!(matches!(order.protocol, Protocol::Https));
+        assert_eq!(order.request.method, "POST");
+        assert_eq!(order.request.uri, "/api/orders?dry=1");
+        assert_eq!(
+            order.request.query_params.get("dry"),
+            Some(&"1".to_string())
+        );
+        assert_eq!(
+            order.request.headers.get("host"),
+            Some(&"api.example.com".to_string())
+        );
+        assert!(!order.request.headers.contains_key(":authority"));
+        assert_eq!(
+            order.request.body.as_deref(),
+            Some(&b"{\"item\":\"widget\"}"[..])
+        );
+        assert_eq!(order.duration_ms, Some(42));
+        assert_eq!(order.timestamp.to_rfc3339(), "2026-03-01T09:00:00+00:00");
+
+        let response = order.response.as_ref().unwrap();
+        assert_eq!(response.status_code, 201);
+        assert_eq!(response.body.as_deref(), Some(&b"{\"id\":7}"[..]));
+
+        let aborted = &requests[1];
+        assert!(aborted.response.is_none());
+        assert_eq!(aborted.duration_ms, None);
+    }
+
+    #[test]
+    fn test_import_skips_invalid_entries() {
+        let mut har: serde_json::Value = serde_json::from_str(SAMPLE).unwrap();
+        har["log"]["entries"][0]["request"]["url"] = "http://bad host/orders".into();
+
+        let requests = HarImporter::new()
+            .import(har.to_string().as_bytes())
+            .unwrap();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].request.uri, "/health");
+    }
+
+    #[test]
+    fn test_import_invalid_har() {
+        assert!(HarImporter::new().import(b"{\"log\": {}}").is_err());
+    }
+}
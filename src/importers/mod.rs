@@ -0,0 +1,14 @@
+pub mod har;
+pub mod pcap;
+mod reassembly;
+mod streams;
+
+use crate::models::CapturedRequest;
+use anyhow::Result;
+
+pub use har::HarImporter;
+pub use pcap::PcapImporter;
+
+pub trait Importer {
+    fn import(&self, data: &[u8]) -> Result<Vec<CapturedRequest>>;
+}
@@ -0,0 +1,513 @@
+//! pcap/pcapng capture import
+//!
+//! Reads classic pcap and pcapng files, decodes the link, IP and TCP layers and
+//! hands the TCP segments to the stream reassembler.
+
+use crate::importers::Importer;
+use crate::importers::reassembly::Reassembler;
+use crate::importers::streams;
+use crate::models::CapturedRequest;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use tracing::debug;
+
+const LINKTYPE_NULL: u32 = 0;
+const LINKTYPE_ETHERNET: u32 = 1;
+const LINKTYPE_RAW: u32 = 101;
+const LINKTYPE_LOOP: u32 = 108;
+const LINKTYPE_LINUX_SLL: u32 = 113;
+const LINKTYPE_IPV4: u32 = 228;
+const LINKTYPE_IPV6: u32 = 229;
+const LINKTYPE_LINUX_SLL2: u32 = 276;
+
+const TCP_FIN: u8 = 0x01;
+const TCP_SYN: u8 = 0x02;
+const TCP_RST: u8 = 0x04;
+const TCP_ACK: u8 = 0x10;
+
+pub struct PcapImporter;
+
+impl PcapImporter {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Importer for PcapImporter {
+    fn import(&self, data: &[u8]) -> Result<Vec<CapturedRequest>> {
+        let frames = read_frames(data)?;
+        debug!("Read {} frames from capture", frames.len());
+
+        let mut reassembler = Reassembler::new();
+        for frame in &frames {
+            if let Some(segment) = decode_frame(frame) {
+                reassembler.push(segment);
+            }
+        }
+
+        let conversations = reassembler.finish();
+        debug!("Reassembled {} TCP conversations", conversations.len());
+
+        let mut requests: Vec<CapturedRequest> =
+            conversations.iter().flat_map(streams::decode).collect();
+        requests.sort_by_key(|r| r.timestamp);
+
+        Ok(requests)
+    }
+}
+
+/// A link-layer frame read from the capture file
+struct Frame<'a> {
+    timestamp: DateTime<Utc>,
+    linktype: u32,
+    data: &'a [u8],
+}
+
+/// A decoded TCP segment
+#[derive(Debug, Clone)]
+pub(super) struct TcpSegment {
+    pub timestamp: DateTime<Utc>,
+    pub src: SocketAddr,
+    pub dst: SocketAddr,
+    pub seq: u32,
+    pub flags: u8,
+    pub payload: Vec<u8>,
+}
+
+impl TcpSegment {
+    pub fn is_syn(&self) -> bool {
+        self.flags & TCP_SYN != 0
+    }
+
+    pub fn is_syn_ack(&self) -> bool {
+        self.is_syn() && self.flags & TCP_ACK != 0
+    }
+
+    pub fn is_close(&self) -> bool {
+        self.flags & (TCP_FIN | TCP_RST) != 0
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Endian {
+    Little,
+    Big,
+}
+
+impl Endian {
+    fn u16(self, data: &[u8], pos: usize) -> Option<u16> {
+        let bytes: [u8; 2] = data.get(pos..pos + 2)?.try_into().ok()?;
+        Some(match self {
+            Self::Little => u16::from_le_bytes(bytes),
+            Self::Big => u16::from_be_bytes(bytes),
+        })
+    }
+
+    fn u32(self, data: &[u8], pos: usize) -> Option<u32> {
+        let bytes: [u8; 4] = data.get(pos..pos + 4)?.try_into().ok()?;
+        Some(match self {
+            Self::Little => u32::from_le_bytes(bytes),
+            Self::Big => u32::from_be_bytes(bytes),
+        })
+    }
+}
+
+fn read_frames(data: &[u8]) -> Result<Vec<Frame<'_>>> {
+    if data.len() < 4 {
+        anyhow::bail!("File too short to be a packet capture");
+    }
+
+    match u32::from_le_bytes([data[0], data[1], data[2], data[3]]) {
+        0x0A0D0D0A => read_pcapng(data),
+        0xA1B2C3D4 => read_pcap(data, Endian::Little, 1_000),
+        0xD4C3B2A1 => read_pcap(data, Endian::Big, 1_000),
+        0xA1B23C4D => read_pcap(data, Endian::Little, 1),
+        0x4D3CB2A1 => read_pcap(data, Endian::Big, 1),
+        magic => anyhow::bail!("Unrecognized capture file magic: {:#010x}", magic),
+    }
+}
+
+/// Read a classic pcap file; `nanos_per_unit` is 1000 for microsecond files
+fn read_pcap(data: &[u8], endian: Endian, nanos_per_unit: u32) -> Result<Vec<Frame<'_>>> {
+    let linktype = endian
+        .u32(data, 20)
+        .ok_or_else(|| anyhow::anyhow!("Truncated pcap header"))?;
+
+    let mut frames = Vec::new();
+    let mut pos = 24;
+
+    while let (Some(secs), Some(frac), Some(caplen)) = (
+        endian.u32(data, pos),
+        endian.u32(data, pos + 4),
+        endian.u32(data, pos + 8),
+    ) {
+        let start = pos + 16;
+        let Some(packet) = data.get(start..start + caplen as usize) else {
+            debug!("Truncated pcap record at offset {}", pos);
+            break;
+        };
+
+        frames.push(Frame {
+            timestamp: timestamp(secs as i64, frac.saturating_mul(nanos_per_unit)),
+            linktype,
+            data: packet,
+        });
+        pos = start + caplen as usize;
+    }
+
+    Ok(frames)
+}
+
+fn read_pcapng(data: &[u8]) -> Result<Vec<Frame<'_>>> {
+    // (linktype, timestamp units per second) per interface of the current section
+    let mut interfaces: Vec<(u32, u64)> = Vec::new();
+    let mut endian = Endian::Little;
+    let mut frames = Vec::new();
+    let mut pos = 0;
+
+    while pos + 12 <= data.len() {
+        if data[pos..pos + 4] == [0x0A, 0x0D, 0x0D, 0x0A] {
+            endian = match data[pos + 8..pos + 12] {
+                [0x4D, 0x3C, 0x2B, 0x1A] => Endian::Little,
+                [0x1A, 0x2B, 0x3C, 0x4D] => Endian::Big,
+                _ => anyhow::bail!("Invalid pcapng byte-order magic"),
+            };
+            interfaces.clear();
+        }
+
+        let block_type = endian.u32(data, pos).unwrap_or_default();
+        let block_len = endian.u32(data, pos + 4).unwrap_or_default() as usize;
+        if block_len < 12 || pos + block_len > data.len() {
+            debug!("Truncated pcapng block at offset {}", pos);
+            break;
+        }
+        let body = &data[pos + 8..pos + block_len - 4];
+
+        match block_type {
+            // Interface Description Block
+            1 => {
+                let linktype = endian.u16(body, 0).unwrap_or_default() as u32;
+                let resolution = pcapng_ts_resolution(body.get(8..).unwrap_or(&[]), endian);
+                interfaces.push((linktype, resolution));
+            }
+            // Enhanced Packet Block
+            6 => {
+                let (Some(iface), Some(high), Some(low), Some(caplen)) = (
+                    endian.u32(body, 0),
+                    endian.u32(body, 4),
+                    endian.u32(body, 8),
+                    endian.u32(body, 12),
+                ) else {
+                    break;
+                };
+                if let (Some(&(linktype, resolution)), Some(packet)) = (
+                    interfaces.get(iface as usize),
+                    body.get(20..20 + caplen as usize),
+                ) {
+                    let ticks = ((high as u64) << 32) | low as u64;
+                    frames.push(Frame {
+                        timestamp: ticks_to_timestamp(ticks, resolution),
+                        linktype,
+                        data: packet,
+                    });
+                }
+            }
+            // Simple Packet Block, which carries no timestamp
+            3 => {
+                if let (Some(&(linktype, _)), Some(packet)) = (interfaces.first(), body.get(4..)) {
+                    frames.push(Frame {
+                        timestamp: DateTime::UNIX_EPOCH,
+                        linktype,
+                        data: packet,
+                    });
+                }
+            }
+            _ => {}
+        }
+
+        pos += block_len;
+    }
+
+    Ok(frames)
+}
+
+/// Parse the `if_tsresol` option of an Interface Description Block
+fn pcapng_ts_resolution(options: &[u8], endian: Endian) -> u64 {
+    let mut pos = 0;
+
+    while let (Some(code), Some(len)) = (endian.u16(options, pos), endian.u16(options, pos + 2)) {
+        if code == 0 {
+            break;
+        }
+        if code == 9
+            && let Some(&value) = options.get(pos + 4)
+        {
+            let exponent = (value & 0x7F) as u32;
+            return if value & 0x80 == 0 {
+                10u64.checked_pow(exponent).unwrap_or(1_000_000)
+            } else {
+                2u64.checked_pow(exponent).unwrap_or(1_000_000)
+            };
+        }
+        pos += 4 + (len as usize).div_ceil(4) * 4;
+    }
+
+    1_000_000
+}
+
+fn ticks_to_timestamp(ticks: u64, units_per_sec: u64) -> DateTime<Utc> {
+    let secs = ticks / units_per_sec;
+    let nanos = (ticks % units_per_sec) as u128 * 1_000_000_000 / units_per_sec as u128;
+    timestamp(secs as i64, nanos as u32)
+}
+
+fn timestamp(secs: i64, nanos: u32) -> DateTime<Utc> {
+    DateTime::from_timestamp(secs, nanos).unwrap_or_default()
+}
+
+fn decode_frame(frame: &Frame<'_>) -> Option<TcpSegment> {
+    let data = frame.data;
+
+    let ip = match frame.linktype {
+        LINKTYPE_ETHERNET => {
+            let mut ethertype = u16::from_be_bytes([*data.get(12)?, *data.get(13)?]);
+            let mut offset = 14;
+            // 802.1Q / 802.1ad VLAN tags
+            while ethertype == 0x8100 || ethertype == 0x88A8 {
+                ethertype = u16::from_be_bytes([*data.get(offset + 2)?, *data.get(offset + 3)?]);
+                offset += 4;
+            }
+            if ethertype != 0x0800 && ethertype != 0x86DD {
+                return None;
+            }
+            data.get(offset..)?
+        }
+        LINKTYPE_NULL | LINKTYPE_LOOP => data.get(4..)?,
+        LINKTYPE_RAW | LINKTYPE_IPV4 | LINKTYPE_IPV6 => data,
+        LINKTYPE_LINUX_SLL => data.get(16..)?,
+        LINKTYPE_LINUX_SLL2 => data.get(20..)?,
+        other => {
+            debug!("Skipping frame with unsupported link type {}", other);
+            return None;
+        }
+    };
+
+    let (src_ip, dst_ip, tcp) = decode_ip(ip)?;
+    decode_tcp(frame.timestamp, src_ip, dst_ip, tcp)
+}
+
+fn decode_ip(data: &[u8]) -> Option<(IpAddr, IpAddr, &[u8])> {
+    match data.first()? >> 4 {
+        4 => {
+            let header_len = (data[0] & 0x0F) as usize * 4;
+            let total_len = u16::from_be_bytes([*data.get(2)?, *data.get(3)?]) as usize;
+            let fragment = u16::from_be_bytes([*data.get(6)?, *data.get(7)?]);
+            // Fragmented datagrams are not reassembled
+            if *data.get(9)? != 6 || fragment & 0x3FFF != 0 {
+                return None;
+            }
+            let src: [u8; 4] = data.get(12..16)?.try_into().ok()?;
+            let dst: [u8; 4] = data.get(16..20)?.try_into().ok()?;
+            // A header claiming more bytes than were captured is malformed
+            // or truncated by the snap length
+            if header_len < 20 || header_len > data.len() {
+                return None;
+            }
+            let end = total_len.clamp(header_len, data.len());
+            Some((
+                IpAddr::V4(Ipv4Addr::from(src)),
+                IpAddr::V4(Ipv4Addr::from(dst)),
+                data.get(header_len..end)?,
+            ))
+        }
+        6 => {
+            let payload_len = u16::from_be_bytes([*data.get(4)?, *data.get(5)?]) as usize;
+            let mut next_header = *data.get(6)?;
+            let src: [u8; 16] = data.get(8..24)?.try_into().ok()?;
+            let dst: [u8; 16] = data.get(24..40)?.try_into().ok()?;
+            let end = (40 + payload_len).min(data.len());
+            let mut offset = 40;
+
+            // Hop-by-hop, routing and destination options extension headers
+            while matches!(next_header, 0 | 43 | 60) {
+                next_header = *data.get(offset)?;
+                offset += (*data.get(offset + 1)? as usize + 1) * 8;
+            }
+            if next_header != 6 {
+                return None;
+            }
+
+            Some((
+                IpAddr::V6(Ipv6Addr::from(src)),
+                IpAddr::V6(Ipv6Addr::from(dst)),
+                data.get(offset..end)?,
+            ))
+        }
+        _ => None,
+    }
+}
+
+fn decode_tcp(
+    timestamp: DateTime<Utc>,
+    src_ip: IpAddr,
+    dst_ip: IpAddr,
+    data: &[u8],
+) -> Option<TcpSegment> {
+    let src_port = u16::from_be_bytes([*data.first()?, *data.get(1)?]);
+    let dst_port = u16::from_be_bytes([*data.get(2)?, *data.get(3)?]);
+    let seq = u32::from_be_bytes(data.get(4..8)?.try_into().ok()?);
+    let header_len = (*data.get(12)? >> 4) as usize * 4;
+    let flags = *data.get(13)?;
+
+    Some(TcpSegment {
+        timestamp,
+        src: SocketAddr::new(src_ip, src_port),
+        dst: SocketAddr::new(dst_ip, dst_port),
+        seq,
+        flags,
+        payload: data.get(header_len..)?.to_vec(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Build an Ethernet/IPv4/TCP frame
+    fn tcp_frame(src: u16, dst: u16, seq: u32, flags: u8, payload: &[u8]) -> Vec<u8> {
+        let mut frame = vec![0u8; 12];
+        frame.extend_from_slice(&[0x08, 0x00]);
+
+        let total_len = (20 + 20 + payload.len()) as u16;
+        frame.extend_from_slice(&[0x45, 0]);
+        frame.extend_from_slice(&total_len.to_be_bytes());
+        frame.extend_from_slice(&[0, 0, 0x40, 0, 64, 6, 0, 0]);
+        frame.extend_from_slice(&[127, 0, 0, 1, 127, 0, 0, 1]);
+
+        frame.extend_from_slice(&src.to_be_bytes());
+        frame.extend_from_slice(&dst.to_be_bytes());
+        frame.extend_from_slice(&seq.to_be_bytes());
+        frame.extend_from_slice(&[0, 0, 0, 0, 0x50, flags, 0xFF, 0xFF, 0, 0, 0, 0]);
+        frame.extend_from_slice(payload);
+        frame
+    }
+
+    /// Build a microsecond pcap file from (timestamp in ms, frame) pairs
+    fn pcap_file(frames: &[(u32, Vec<u8>)]) -> Vec<u8> {
+        let mut file = Vec::new();
+        file.extend_from_slice(&0xA1B2C3D4u32.to_le_bytes());
+        file.extend_from_slice(&[2, 0, 4, 0]);
+        file.extend_from_slice(&[0; 8]);
+        file.extend_from_slice(&65535u32.to_le_bytes());
+        file.extend_from_slice(&LINKTYPE_ETHERNET.to_le_bytes());
+
+        for (ms, frame) in frames {
+            file.extend_from_slice(&(1_700_000_000 + ms / 1000).to_le_bytes());
+            file.extend_from_slice(&((ms % 1000) * 1000).to_le_bytes());
+            file.extend_from_slice(&(frame.len() as u32).to_le_bytes());
+            file.extend_from_slice(&(frame.len() as u32).to_le_bytes());
+            file.extend_from_slice(frame);
+        }
+        file
+    }
+
+    fn http_exchange() -> Vec<(u32, Vec<u8>)> {
+        let request = b"GET /api/users/42?verbose=true HTTP/1.1\r\nHost: localhost\r\n\r\n";
+        let response = b"HTTP/1.1 200 OK\r\nContent-Length: 11\r\n\r\n{\"id\": 42}\n";
+
+        vec![
+            (0, tcp_frame(50000, 8080, 1000, TCP_SYN, b"")),
+            (1, tcp_frame(8080, 50000, 5000, TCP_SYN | TCP_ACK, b"")),
+            (2, tcp_frame(50000, 8080, 1001, TCP_ACK, request)),
+            (40, tcp_frame(8080, 50000, 5001, TCP_ACK, response)),
+            (
+                41,
+                tcp_frame(50000, 8080, 1001 + request.len() as u32, TCP_FIN, b""),
+            ),
+        ]
+    }
+
+    #[test]
+    fn test_import_pcap_http() {
+        let file = pcap_file(&http_exchange());
+        let requests = PcapImporter::new().import(&file).unwrap();
+
+        assert_eq!(requests.len(), 1);
+        let captured = &requests[0];
+        assert_eq!(captured.request.method, "GET");
+        assert_eq!(captured.request.uri, "/api/users/42?verbose=true");
+        assert_eq!(
+            captured.request.query_params.get("verbose"),
+            Some(&"true".to_string())
+        );
+        let response = captured.response.as_ref().unwrap();
+        assert_eq!(response.status_code, 200);
+        assert_eq!(response.body.as_deref(), Some(&b"{\"id\": 42}\n"[..]));
+        assert_eq!(captured.duration_ms, Some(38));
+    }
+
+    #[test]
+    fn test_import_pcapng_http() {
+        let mut file = Vec::new();
+        // Section Header Block
+        file.extend_from_slice(&0x0A0D0D0Au32.to_le_bytes());
+        file.extend_from_slice(&28u32.to_le_bytes());
+        file.extend_from_slice(&0x1A2B3C4Du32.to_le_bytes());
+        file.extend_from_slice(&[1, 0, 0, 0]);
+        file.extend_from_slice(&(-1i64).to_le_bytes());
+        file.extend_from_slice(&28u32.to_le_bytes());
+        // Interface Description Block with if_tsresol = 10^-3
+        file.extend_from_slice(&1u32.to_le_bytes());
+        file.extend_from_slice(&32u32.to_le_bytes());
+        file.extend_from_slice(&(LINKTYPE_ETHERNET as u16).to_le_bytes());
+        file.extend_from_slice(&[0, 0]);
+        file.extend_from_slice(&65535u32.to_le_bytes());
+        file.extend_from_slice(&[9, 0, 1, 0, 3, 0, 0, 0, 0, 0, 0, 0]);
+        file.extend_from_slice(&32u32.to_le_bytes());
+
+        for (ms, frame) in http_exchange() {
+            let padded = frame.len().div_ceil(4) * 4;
+            let block_len = (32 + padded) as u32;
+            let ticks = 1_700_000_000_000u64 + ms as u64;
+            file.extend_from_slice(&6u32.to_le_bytes());
+            file.extend_from_slice(&block_len.to_le_bytes());
+            file.extend_from_slice(&0u32.to_le_bytes());
+            file.extend_from_slice(&((ticks >> 32) as u32).to_le_bytes());
+            file.extend_from_slice(&(ticks as u32).to_le_bytes());
+            file.extend_from_slice(&(frame.len() as u32).to_le_bytes());
+            file.extend_from_slice(&(frame.len() as u32).to_le_bytes());
+            file.extend_from_slice(&frame);
+            file.resize(file.len() + padded - frame.len(), 0);
+            file.extend_from_slice(&block_len.to_le_bytes());
+        }
+
+        let requests = PcapImporter::new().import(&file).unwrap();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].request.uri, "/api/users/42?verbose=true");
+        assert_eq!(requests[0].duration_ms, Some(38));
+    }
+
+    #[test]
+    fn test_skips_truncated_ip_header() {
+        // IHL of 15 (60 bytes) on a frame cut to 40 bytes of IP
+        let mut truncated = tcp_frame(50000, 8080, 7000, TCP_ACK, b"GET / HTTP/1.1\r\n");
+        truncated[14] = 0x4F;
+        truncated.truncate(14 + 40);
+        assert!(decode_ip(&truncated[14..]).is_none());
+
+        let mut short = tcp_frame(50000, 8080, 7000, TCP_ACK, b"");
+        short[14] = 0x44;
+        assert!(decode_ip(&short[14..]).is_none());
+
+        let mut frames = http_exchange();
+        frames.insert(2, (2, truncated));
+        let requests = PcapImporter::new().import(&pcap_file(&frames)).unwrap();
+        assert_eq!(requests.len(), 1);
+    }
+
+    #[test]
+    fn test_rejects_unknown_format() {
+        assert!(PcapImporter::new().import(b"not a capture").is_err());
+    }
+}
@@ -0,0 +1,237 @@
+//! TCP stream reassembly
+//!
+//! Groups segments into conversations, orders them by sequence number and drops
+//! retransmitted bytes so each direction becomes a contiguous byte stream.
+
+use crate::importers::pcap::TcpSegment;
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use tracing::debug;
+
+/// One direction of a TCP connection
+#[derive(Debug, Default)]
+pub(super) struct Stream {
+    pub data: Vec<u8>,
+    /// Stream offset at which each captured segment starts, with its capture time
+    chunks: Vec<(usize, DateTime<Utc>)>,
+}
+
+impl Stream {
+    pub fn push(&mut self, timestamp: DateTime<Utc>, bytes: &[u8]) {
+        self.chunks.push((self.data.len(), timestamp));
+        self.data.extend_from_slice(bytes);
+    }
+
+    /// Capture time of the segment that carried the byte at `offset`
+    pub fn time_at(&self, offset: usize) -> Option<DateTime<Utc>> {
+        let idx = self.chunks.partition_point(|(start, _)| *start <= offset);
+        idx.checked_sub(1).map(|i| self.chunks[i].1)
+    }
+}
+
+/// A reassembled TCP connection
+#[derive(Debug)]
+pub(super) struct Conversation {
+    pub client: SocketAddr,
+    pub server: SocketAddr,
+    pub to_server: Stream,
+    pub to_client: Stream,
+}
+
+#[derive(Default)]
+struct Pending {
+    first_seen: Option<DateTime<Utc>>,
+    closed: bool,
+    segments: Vec<TcpSegment>,
+}
+
+pub(super) struct Reassembler {
+    open: HashMap<(SocketAddr, SocketAddr), Pending>,
+    finished: Vec<(DateTime<Utc>, Conversation)>,
+}
+
+impl Reassembler {
+    pub fn new() -> Self {
+        Self {
+            open: HashMap::new(),
+            finished: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, segment: TcpSegment) {
+        let key = if segment.src < segment.dst {
+            (segment.src, segment.dst)
+        } else {
+            (segment.dst, segment.src)
+        };
+
+        // A fresh SYN on a used 4-tuple starts a new connection
+        let reused = segment.is_syn()
+            && !segment.is_syn_ack()
+            && self
+                .open
+                .get(&key)
+                .is_some_and(|p| p.closed || p.segments.iter().any(|s| !s.payload.is_empty()));
+        if reused && let Some(pending) = self.open.remove(&key) {
+            self.close(pending);
+        }
+
+        let pending = self.open.entry(key).or_default();
+        pending.first_seen.get_or_insert(segment.timestamp);
+        pending.closed |= segment.is_close();
+        pending.segments.push(segment);
+    }
+
+    /// Finish reassembly, returning conversations ordered by their first packet
+    pub fn finish(mut self) -> Vec<Conversation> {
+        let open: Vec<Pending> = self.open.drain().map(|(_, p)| p).collect();
+        for pending in open {
+            self.close(pending);
+        }
+
+        self.finished.sort_by_key(|(first_seen, _)| *first_seen);
+        self.finished.into_iter().map(|(_, c)| c).collect()
+    }
+
+    fn close(&mut self, pending: Pending) {
+        let Some(first) = pending.segments.first() else {
+            return;
+        };
+
+        let (client, server) = if let Some(syn) = pending
+            .segments
+            .iter()
+            .find(|s| s.is_syn() && !s.is_syn_ack())
+        {
+            (syn.src, syn.dst)
+        } else if let Some(syn_ack) = pending.segments.iter().find(|s| s.is_syn_ack()) {
+            (syn_ack.dst, syn_ack.src)
+        } else if first.src.port() < first.dst.port() {
+            // Capture started mid-connection: assume the lower port is the server
+            (first.dst, first.src)
+        } else {
+            (first.src, first.dst)
+        };
+
+        let conversation = Conversation {
+            client,
+            server,
+            to_server: reassemble_direction(&pending.segments, client),
+            to_client: reassemble_direction(&pending.segments, server),
+        };
+
+        self.finished
+            .push((pending.first_seen.unwrap_or_default(), conversation));
+    }
+}
+
+fn reassemble_direction(segments: &[TcpSegment], src: SocketAddr) -> Stream {
+    let syn_seq = segments
+        .iter()
+        .find(|s| s.src == src && s.is_syn())
+        .map(|s| s.seq.wrapping_add(1));
+
+    let mut data: Vec<&TcpSegment> = segments
+        .iter()
+        .filter(|s| s.src == src && !s.payload.is_empty())
+        .collect();
+
+    let Some(first) = data.first() else {
+        return Stream::default();
+    };
+
+    let base = syn_seq.unwrap_or(first.seq);
+    let relative = |s: &TcpSegment| s.seq.wrapping_sub(base) as i32 as i64;
+    // Without a SYN the earliest byte may arrive after a later one
+    let shift = if syn_seq.is_some() {
+        0
+    } else {
+        data.iter().map(|s| relative(s)).min().unwrap_or(0)
+    };
+
+    data.retain(|s| relative(s) >= shift);
+    data.sort_by_key(|s| (relative(s), s.timestamp));
+
+    let mut stream = Stream::default();
+    let mut cursor = shift;
+
+    for segment in data {
+        let start = relative(segment);
+        let end = start + segment.payload.len() as i64;
+        if end <= cursor {
+            continue;
+        }
+        if start > cursor {
+            debug!(
+                "Missing {} bytes in stream from {}",
+                start - cursor,
+                segment.src
+            );
+        }
+
+        let skip = (cursor - start).max(0) as usize;
+        stream.push(segment.timestamp, &segment.payload[skip..]);
+        cursor = end;
+    }
+
+    stream
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segment(src: u16, dst: u16, seq: u32, flags: u8, payload: &[u8], ms: i64) -> TcpSegment {
+        TcpSegment {
+            timestamp: DateTime::from_timestamp_millis(ms).unwrap(),
+            src: SocketAddr::from(([10, 0, 0, 1], src)),
+            dst: SocketAddr::from(([10, 0, 0, 2], dst)),
+            seq,
+            flags,
+            payload: payload.to_vec(),
+        }
+    }
+
+    #[test]
+    fn test_reorders_and_drops_retransmissions() {
+        let mut reassembler = Reassembler::new();
+        reassembler.push(segment(40000, 80, 99, 0x02, b"", 0));
+        reassembler.push(segment(40000, 80, 106, 0x10, b"world", 2));
+        reassembler.push(segment(40000, 80, 100, 0x10, b"hello ", 1));
+        reassembler.push(segment(40000, 80, 100, 0x10, b"hello ", 3));
+
+        let conversations = reassembler.finish();
+        assert_eq!(conversations.len(), 1);
+
+        let stream = &conversations[0].to_server;
+        assert_eq!(stream.data, b"hello world");
+        assert_eq!(stream.time_at(0).unwrap().timestamp_millis(), 1);
+        assert_eq!(stream.time_at(8).unwrap().timestamp_millis(), 2);
+    }
+
+    #[test]
+    fn test_identifies_server_without_handshake() {
+        let mut reassembler = Reassembler::new();
+        reassembler.push(segment(6379, 51000, 10, 0x10, b"+OK\r\n", 0));
+
+        let conversations = reassembler.finish();
+        assert_eq!(conversations[0].server.port(), 6379);
+        assert_eq!(conversations[0].to_client.data, b"+OK\r\n");
+        assert!(conversations[0].to_server.data.is_empty());
+    }
+
+    #[test]
+    fn test_splits_reused_four_tuple() {
+        let mut reassembler = Reassembler::new();
+        reassembler.push(segment(40000, 80, 0, 0x02, b"", 0));
+        reassembler.push(segment(40000, 80, 1, 0x11, b"first", 1));
+        reassembler.push(segment(40000, 80, 500, 0x02, b"", 2));
+        reassembler.push(segment(40000, 80, 501, 0x10, b"second", 3));
+
+        let conversations = reassembler.finish();
+        assert_eq!(conversations.len(), 2);
+        assert_eq!(conversations[0].to_server.data, b"first");
+        assert_eq!(conversations[1].to_server.data, b"second");
+    }
+}
@@ -0,0 +1,376 @@
+//! Protocol decoding of reassembled TCP conversations
+//!
+//! Detects the application protocol of a conversation and runs both directions
+//! through the matching parser. Database and cache replies have no status code,
+//! so successful replies are stored as 200 and error replies as 500.
+
+use crate::importers::reassembly::Conversation;
+use crate::models::{CapturedRequest, Protocol, RequestData, ResponseData};
+use crate::parsers::http::HttpParser;
+use crate::parsers::postgres::PostgresParser;
+use crate::parsers::redis::RedisParser;
+use crate::parsers::sql::SqlParser;
+use chrono::{DateTime, Utc};
+use tracing::debug;
+use uuid::Uuid;
+
+const REDIS_PORT: u16 = 6379;
+const POSTGRES_PORT: u16 = 5432;
+
+pub(super) fn decode(conversation: &Conversation) -> Vec<CapturedRequest> {
+    let client_data = &conversation.to_server.data;
+    let port = conversation.server.port();
+
+    if client_data.is_empty() {
+        Vec::new()
+    } else if looks_like_http(client_data) {
+        decode_http(conversation)
+    } else if port == REDIS_PORT || client_data.first() == Some(&b'*') {
+        decode_redis(conversation)
+    } else if port == POSTGRES_PORT || PostgresParser::startup_len(client_data).is_some() {
+        decode_postgres(conversation)
+    } else {
+        debug!(
+            "Skipping unrecognized stream {} -> {}",
+            conversation.client, conversation.server
+        );
+        Vec::new()
+    }
+}
+
+fn looks_like_http(data: &[u8]) -> bool {
+    let line_end = data
+        .windows(2)
+        .position(|w| w == b"\r\n")
+        .unwrap_or(data.len());
+    let line = String::from_utf8_lossy(&data[..line_end]);
+    line.ends_with("HTTP/1.1") || line.ends_with("HTTP/1.0")
+}
+
+fn decode_http(conversation: &Conversation) -> Vec<CapturedRequest> {
+    let requests = &conversation.to_server;
+    let responses = &conversation.to_client;
+    let mut captured = Vec::new();
+    let (mut req_pos, mut resp_pos) = (0, 0);
+
+    while let Some((request, len)) = HttpParser::parse_raw_request(&requests.data[req_pos..]) {
+        let started = requests.time_at(req_pos).unwrap_or_default();
+        req_pos += len;
+
+        let mut response = None;
+        let mut finished = None;
+        while let Some((resp, len)) =
+            HttpParser::parse_raw_response(&responses.data[resp_pos..], &request.method)
+        {
+            resp_pos += len;
+            finished = responses.time_at(resp_pos.saturating_sub(1));
+            // Interim responses such as 100 Continue precede the real one
+            if (100..200).contains(&resp.status_code) && resp.status_code != 101 {
+                continue;
+            }
+            response = Some(resp);
+            break;
+        }
+
+        let upgraded = response.as_ref().is_some_and(|r| r.status_code == 101);
+        captured.push(capture(
+            Protocol::Http,
+            request,
+            response,
+            started,
+            finished,
+        ));
+
+        if upgraded {
+            break;
+        }
+    }
+
+    captured
+}
+
+fn decode_redis(conversation: &Conversation) -> Vec<CapturedRequest> {
+    let commands = &conversation.to_server;
+    let replies = &conversation.to_client;
+    let mut captured = Vec::new();
+    let (mut cmd_pos, mut reply_pos) = (0, 0);
+
+    while let Some(len) = RedisParser::frame_len(&commands.data[cmd_pos..]) {
+        let frame = &commands.data[cmd_pos..cmd_pos + len];
+        let started = commands.time_at(cmd_pos).unwrap_or_default();
+        cmd_pos += len;
+
+        let Some(command) = RedisParser::parse(frame) else {
+            continue;
+        };
+
+        let response = RedisParser::frame_len(&replies.data[reply_pos..]).map(|len| {
+            let reply = &replies.data[reply_pos..reply_pos + len];
+            reply_pos += len;
+            ResponseData {
+                status_code: if RedisParser::is_error_reply(reply) {
+                    500
+                } else {
+                    200
+                },
+                headers: Default::default(),
+                body: Some(reply.to_vec()),
+            }
+        });
+        let finished = response
+            .as_ref()
+            .and_then(|_| replies.time_at(reply_pos.saturating_sub(1)));
+
+        let request = RequestData {
+            method: command.command,
+            uri: command.args.join(" "),
+            headers: Default::default(),
+            body: None,
+            query_params: Default::default(),
+        };
+        captured.push(capture(
+            Protocol::Redis,
+            request,
+            response,
+            started,
+            finished,
+        ));
+    }
+
+    captured
+}
+
+/// Result of one query cycle, terminated by ReadyForQuery
+#[derive(Default)]
+struct PostgresReply {
+    error: Option<String>,
+    tag: Option<String>,
+    finished: Option<DateTime<Utc>>,
+}
+
+fn decode_postgres(conversation: &Conversation) -> Vec<CapturedRequest> {
+    let client = &conversation.to_server;
+    let server = &conversation.to_client;
+    let (mut pos, mut server_pos) = (0, 0);
+    let mut in_startup = false;
+
+    while let Some(len) = PostgresParser::startup_len(&client.data[pos..]) {
+        if PostgresParser::is_ssl_request(&client.data[pos..]) {
+            if server.data.get(server_pos) != Some(&b'N') {
+                debug!("Skipping TLS-encrypted PostgreSQL stream");
+                return Vec::new();
+            }
+            server_pos += 1;
+        } else {
+            in_startup = true;
+        }
+        pos += len;
+    }
+
+    // Each simple query or extended-protocol Sync yields one query cycle
+    let mut queries: Vec<(Option<String>, DateTime<Utc>)> = Vec::new();
+    let mut pending: Option<(String, DateTime<Utc>)> = None;
+
+    while let Some(len) = PostgresParser::message_len(&client.data[pos..]) {
+        let message = &client.data[pos..pos + len];
+        let sent = client.time_at(pos).unwrap_or_default();
+        pos += len;
+
+        match message[0] {
+            b'Q' => {
+                let query = PostgresParser::parse_simple_query(message).map(|q| q.query);
+                queries.push((query, sent));
+            }
+            b'P' => {
+                if let Some((_, parsed)) = PostgresParser::parse_prepared_statement(message) {
+                    pending = Some((parsed.query, sent));
+                }
+            }
+            b'S' => match pending.take() {
+                Some((query, started)) => queries.push((Some(query), started)),
+                None => queries.push((None, sent)),
+            },
+            b'X' => break,
+            _ => {}
+        }
+    }
+
+    let mut replies = Vec::new();
+    let mut reply = PostgresReply::default();
+
+    while let Some(len) = PostgresParser::message_len(&server.data[server_pos..]) {
+        let message = &server.data[server_pos..server_pos + len];
+        server_pos += len;
+        let text = || {
+            String::from_utf8_lossy(&message[5..])
+                .trim_end_matches('\0')
+                .to_string()
+        };
+
+        match message[0] {
+            b'C' => reply.tag = Some(text()),
+            b'E' => reply.error = Some(postgres_error_message(&message[5..])),
+            b'Z' => {
+                reply.finished = server.time_at(server_pos - 1);
+                let done = std::mem::take(&mut reply);
+                // The first ReadyForQuery ends authentication, not a query
+                if in_startup {
+                    in_startup = false;
+                } else {
+                    replies.push(done);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let mut replies = replies.into_iter();
+    queries
+        .into_iter()
+        .filter_map(|(query, started)| {
+            let reply = replies.next();
+            let query = query?;
+
+            let response = reply.as_ref().map(|r| ResponseData {
+                status_code: if r.error.is_some() { 500 } else { 200 },
+                headers: Default::default(),
+                body: r
+                    .error
+                    .clone()
+                    .or_else(|| r.tag.clone())
+                    .map(String::into_bytes),
+            });
+
+            let request = RequestData {
+                method: format!("{:?}", SqlParser::classify_query(&query)).to_uppercase(),
+                uri: query,
+                headers: Default::default(),
+                body: None,
+                query_params: Default::default(),
+            };
+
+            Some(capture(
+                Protocol::Sql,
+                request,
+                response,
+                started,
+                reply.and_then(|r| r.finished),
+            ))
+        })
+        .collect()
+}
+
+/// Extract the human-readable `M` field from an ErrorResponse body
+fn postgres_error_message(fields: &[u8]) -> String {
+    fields
+        .split(|&b| b == 0)
+        .find(|field| field.first() == Some(&b'M'))
+        .map(|field| String::from_utf8_lossy(&field[1..]).to_string())
+        .unwrap_or_else(|| "error".to_string())
+}
+
+fn capture(
+    protocol: Protocol,
+    request: RequestData,
+    response: Option<ResponseData>,
+    started: DateTime<Utc>,
+    finished: Option<DateTime<Utc>>,
+) -> CapturedRequest {
+    CapturedRequest {
+        id: Uuid::new_v4().to_string(),
+        timestamp: started,
+        protocol,
+        request,
+        response,
+        duration_ms: finished.map(|f| (f - started).num_milliseconds().max(0) as u64),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::importers::reassembly::Stream;
+    use std::net::SocketAddr;
+
+    fn conversation(port: u16, client: &[u8], server: &[u8]) -> Conversation {
+        let mut to_server = Stream::default();
+        to_server.push(DateTime::from_timestamp_millis(0).unwrap(), client);
+        let mut to_client = Stream::default();
+        to_client.push(DateTime::from_timestamp_millis(7).unwrap(), server);
+
+        Conversation {
+            client: SocketAddr::from(([127, 0, 0, 1], 50000)),
+            server: SocketAddr::from(([127, 0, 0, 1], port)),
+            to_server,
+            to_client,
+        }
+    }
+
+    fn pg_message(tag: u8, body: &[u8]) -> Vec<u8> {
+        let mut message = vec![tag];
+        message.extend_from_slice(&((body.len() + 4) as u32).to_be_bytes());
+        message.extend_from_slice(body);
+        message
+    }
+
+    #[test]
+    fn test_decode_redis_pipeline() {
+        let conv = conversation(
+            6379,
+            b"*2\r\n$3\r\nGET\r\n$3\r\nkey\r\n*3\r\n$3\r\nSET\r\n$1\r\nk\r\n$1\r\nv\r\n",
+            b"$5\r\nvalue\r\n-ERR readonly\r\n",
+        );
+
+        let captured = decode(&conv);
+        assert_eq!(captured.len(), 2);
+        assert_eq!(captured[0].request.method, "GET");
+        assert_eq!(captured[0].request.uri, "key");
+        assert_eq!(captured[0].response.as_ref().unwrap().status_code, 200);
+        assert_eq!(captured[1].request.method, "SET");
+        assert_eq!(captured[1].request.uri, "k v");
+        assert_eq!(captured[1].response.as_ref().unwrap().status_code, 500);
+        assert_eq!(captured[1].duration_ms, Some(7));
+    }
+
+    #[test]
+    fn test_decode_postgres_simple_query() {
+        let mut client = Vec::new();
+        let startup = b"\0\x03\0\0user\0app\0\0";
+        client.extend_from_slice(&((startup.len() + 4) as u32).to_be_bytes());
+        client.extend_from_slice(startup);
+        client.extend(pg_message(b'Q', b"SELECT * FROM users\0"));
+        client.extend(pg_message(b'Q', b"DELETE FROM nope\0"));
+
+        let mut server = Vec::new();
+        server.extend(pg_message(b'R', &[0, 0, 0, 0]));
+        server.extend(pg_message(b'Z', b"I"));
+        server.extend(pg_message(b'C', b"SELECT 3\0"));
+        server.extend(pg_message(b'Z', b"I"));
+        server.extend(pg_message(
+            b'E',
+            b"SERROR\0Mrelation \"nope\" does not exist\0\0",
+        ));
+        server.extend(pg_message(b'Z', b"I"));
+
+        let captured = decode(&conversation(5432, &client, &server));
+        assert_eq!(captured.len(), 2);
+        assert_eq!(captured[0].request.method, "SELECT");
+        assert_eq!(captured[0].request.uri, "SELECT * FROM users");
+
+        let ok = captured[0].response.as_ref().unwrap();
+        assert_eq!(ok.status_code, 200);
+        assert_eq!(ok.body.as_deref(), Some(&b"SELECT 3"[..]));
+
+        let failed = captured[1].response.as_ref().unwrap();
+        assert_eq!(failed.status_code, 500);
+        assert_eq!(
+            failed.body.as_deref(),
+            Some(&b"relation \"nope\" does not exist"[..])
+        );
+    }
+
+    #[test]
+    fn test_skips_unknown_protocol() {
+        assert!(decode(&conversation(443, b"\x16\x03\x01\x02\x00", b"")).is_empty());
+    }
+}
@@ -1,14 +1,18 @@
+use crate::chaos::mutate::{Mutations, Mutator};
 use crate::models::{CapturedRequest, Protocol, ResponseData};
 use crate::parsers::HttpParser;
+use crate::redact::Redactor;
+use crate::replay::{self, ReplayMode, Replayer};
 use crate::storage::Storage;
 use anyhow::Result;
 use chrono::Utc;
-use hyper::body::Incoming;
+use http_body_util::{BodyExt, Full};
+use hyper::body::{Bytes, Incoming};
 use hyper::server::conn::http1;
 use hyper::service::service_fn;
 use hyper::{Request, Response, StatusCode};
 use hyper_util::rt::TokioIo;
-use std::net::SocketAddr;
+use std::net::{IpAddr, SocketAddr};
 use std::sync::Arc;
 use tokio::net::TcpListener;
 use tracing::{debug, error, info, warn};
@@ -18,6 +22,9 @@ pub struct HttpInterceptor {
     port: u16,
     storage_path: String,
     target_url: Option<String>,
+    redactor: Arc<Redactor>,
+    replay: Option<Arc<Replayer>>,
+    mutator: Option<Arc<Mutator>>,
 }
 
 impl HttpInterceptor {
@@ -26,6 +33,9 @@ impl HttpInterceptor {
             port,
             storage_path,
             target_url: None,
+            redactor: Arc::new(Redactor::new()),
+            replay: None,
+            mutator: None,
         }
     }
 
@@ -34,25 +44,75 @@ impl HttpInterceptor {
         self
     }
 
+    /// Rules applied to every request before it is stored; forwarded
+    /// traffic is left untouched
+    pub fn with_redactor(mut self, redactor: Redactor) -> Self {
+        self.redactor = Arc::new(redactor);
+        self
+    }
+
+    /// Answer from captured traffic; in VCR mode requests without a
+    /// recording are still forwarded and recorded, otherwise nothing is
+    /// stored
+    pub fn with_replay(mut self, replayer: Replayer) -> Self {
+        self.replay = Some(Arc::new(replayer));
+        self
+    }
+
+    /// Corrupt the responses clients receive; captures keep the real ones
+    pub fn with_mutations(mut self, mutations: Mutations) -> Self {
+        self.mutator = (!mutations.is_empty()).then(|| Arc::new(Mutator::new(mutations)));
+        self
+    }
+
     pub async fn start(&self) -> Result<()> {
         let addr = SocketAddr::from(([127, 0, 0, 1], self.port));
         let listener = TcpListener::bind(addr).await?;
         let storage = Arc::new(Storage::new(&self.storage_path)?);
+        if let Some(target) = &self.target_url {
+            // Generated suites default to the server the traffic came from
+            storage.set_metadata("target", target)?;
+        }
         let target_url = Arc::new(self.target_url.clone());
 
         info!("HTTP interceptor listening on {}", addr);
-        info!("Storing captures in: {}", self.storage_path);
-        if let Some(target) = &self.target_url {
+        if let Some(replayer) = &self.replay {
+            info!(
+                "Replaying {} captured responses from: {}",
+                replayer.len(),
+                self.storage_path
+            );
+            match (replayer.mode(), &self.target_url) {
+                (ReplayMode::Vcr, Some(target)) => {
+                    info!("Recording requests forwarded to: {}", target)
+                }
+                (ReplayMode::Vcr, None) => {
+                    warn!("No target URL - unrecorded responses will be mocked")
+                }
+                (ReplayMode::ReplayOnly, _) => {
+                    warn!("Replay only - requests without a recording will fail")
+                }
+                (ReplayMode::Nearest, _) => {}
+            }
+        } else if let Some(target) = &self.target_url {
+            info!("Storing captures in: {}", self.storage_path);
             info!("Forwarding requests to: {}", target);
         } else {
+            info!("Storing captures in: {}", self.storage_path);
             warn!("No target URL - responses will be mocked");
         }
 
         loop {
-            let (stream, client_addr) = listener.accept().await?;
+            let (stream, client_addr) = tokio::select! {
+                accepted = listener.accept() => accepted?,
+                _ = tokio::signal::ctrl_c() => break,
+            };
             let io = TokioIo::new(stream);
             let storage = Arc::clone(&storage);
             let target_url = Arc::clone(&target_url);
+            let redactor = Arc::clone(&self.redactor);
+            let replay = self.replay.clone();
+            let mutator = self.mutator.clone();
 
             debug!("Connection from {}", client_addr);
 
@@ -63,7 +123,18 @@ impl HttpInterceptor {
                         service_fn(move |req| {
                             let storage = Arc::clone(&storage);
                             let target_url = Arc::clone(&target_url);
-                            handle_request(req, storage, target_url)
+                            let redactor = Arc::clone(&redactor);
+                            let replay = replay.clone();
+                            let mutator = mutator.clone();
+                            handle_request(
+                                req,
+                                client_addr.ip(),
+                                storage,
+                                target_url,
+                                redactor,
+                                replay,
+                                mutator,
+                            )
                         }),
                     )
                     .await
@@ -72,32 +143,108 @@ impl HttpInterceptor {
                 }
             });
         }
+
+        if let Some(mutator) = &self.mutator {
+            mutator.report().print();
+        }
+        if let Some(replayer) = &self.replay {
+            let report = replayer.report();
+            report.print();
+            if replayer.mode() == ReplayMode::ReplayOnly && !report.unmatched.is_empty() {
+                anyhow::bail!(
+                    "{} distinct requests had no recording",
+                    report.unmatched.len()
+                );
+            }
+        }
+        Ok(())
     }
 }
 
 async fn handle_request(
     req: Request<Incoming>,
+    client: IpAddr,
     storage: Arc<Storage>,
     target_url: Arc<Option<String>>,
-) -> Result<Response<String>, hyper::Error> {
+    redactor: Arc<Redactor>,
+    replay: Option<Arc<Replayer>>,
+    mutator: Option<Arc<Mutator>>,
+) -> Result<Response<Full<Bytes>>, hyper::Error> {
     let start = std::time::Instant::now();
 
-    let method = req.method().clone();
-    let uri = req.uri().clone();
-    let headers = req.headers().clone();
+    let (parts, body) = req.into_parts();
+    let body = body.collect().await?.to_bytes();
+    let method = parts.method;
+    let uri = parts.uri;
+    let headers = parts.headers;
 
-    debug!("Request: {} {} {:?}", method, uri, req.version());
+    debug!("Request: {} {} {:?}", method, uri, parts.version);
+    if let Some(mutator) = &mutator {
+        mutator.observe(client, method.as_str(), &uri);
+    }
 
     let is_json = HttpParser::is_json_content(&headers);
     let endpoint_pattern = HttpParser::extract_endpoint_pattern(&uri);
 
     debug!("Endpoint pattern: {} (JSON: {})", endpoint_pattern, is_json);
 
-    let request_data = HttpParser::parse_request(&method, &uri, &headers, None);
+    let request_data = HttpParser::parse_request(
+        &method,
+        &uri,
+        &headers,
+        (!body.is_empty()).then(|| body.to_vec()),
+    );
     let request_id = Uuid::new_v4().to_string();
 
+    let mut captured = CapturedRequest {
+        id: request_id.clone(),
+        timestamp: Utc::now(),
+        protocol: Protocol::Http,
+        request: request_data,
+        response: None,
+        duration_ms: None,
+    };
+    // The request is redacted before the lookup, as recordings were
+    let mut redacted = redactor.redact(&mut captured);
+
+    if let Some(replayer) = &replay {
+        let recording = match replayer.mode() {
+            ReplayMode::Nearest => replayer.find(method.as_str(), &uri, &body),
+            // Recordings were redacted before storing, so the lookup is too
+            ReplayMode::Vcr | ReplayMode::ReplayOnly => replayer.find_exact(&captured.request),
+        };
+
+        match (recording, replayer.mode()) {
+            (Some(recording), _) => {
+                let mutated = mutator.as_ref().and_then(|mutator| {
+                    mutator.mutate(client, method.as_str(), &uri, recording.response.as_ref()?)
+                });
+                return Ok(replayed(replayer, &method, &uri, &recording, mutated).await);
+            }
+            (None, ReplayMode::Nearest) => {
+                warn!("Unmatched: {} {}", method, uri);
+                replayer.record_unmatched(method.as_str(), &uri);
+                return Ok(text_response(
+                    StatusCode::NOT_FOUND,
+                    format!("No captured response for {} {}\n", method, uri),
+                ));
+            }
+            (None, ReplayMode::ReplayOnly) => {
+                error!("No recording for {} {} (replay-only)", method, uri);
+                replayer.record_unmatched(method.as_str(), &uri);
+                return Ok(text_response(
+                    StatusCode::BAD_GATEWAY,
+                    format!("No recording for {} {} in replay-only mode\n", method, uri),
+                ));
+            }
+            (None, ReplayMode::Vcr) => {
+                debug!("No recording for {} {}, forwarding", method, uri);
+            }
+        }
+    }
+
     let (response_data, response_body) = if let Some(target) = target_url.as_ref() {
-        match forward_request(&method, &uri, &headers, target).await {
+        match forward_request(&method, &uri, &headers, body, target).await {
             Ok((resp_data, body)) => (Some(resp_data), body),
             Err(e) => {
                 error!("Failed to forward request: {}", e);
@@ -107,7 +254,7 @@ async fn handle_request(
                         headers: Default::default(),
                         body: None,
                     }),
-                    "Bad Gateway: Failed to reach target".to_string(),
+                    Bytes::from_static(b"Bad Gateway: Failed to reach target"),
                 )
             }
         }
@@ -118,47 +265,107 @@ async fn handle_request(
                 headers: Default::default(),
                 body: None,
             }),
-            format!(
+            Bytes::from(format!(
                 "Intercepted: {} {}\nStored with ID: {}",
                 method, uri, request_id
-            ),
+            )),
         )
     };
 
     let duration_ms = start.elapsed().as_millis() as u64;
 
-    let captured = CapturedRequest {
-        id: request_id.clone(),
-        timestamp: Utc::now(),
-        protocol: Protocol::Http,
-        request: request_data,
-        response: response_data.clone(),
-        duration_ms: Some(duration_ms),
-    };
+    captured.response = response_data.clone();
+    captured.duration_ms = Some(duration_ms);
+    // The response only exists now; the client still gets it unredacted
+    redacted += redactor.redact(&mut captured);
+    if redacted > 0 {
+        debug!("Redacted {} values before storing", redacted);
+    }
 
     if let Err(e) = storage.store_request(&captured) {
         error!("Failed to store request: {}", e);
     } else {
         info!("Captured: {} {} ({}ms)", method, uri, duration_ms);
+        if let Some(replayer) = &replay {
+            replayer.record(captured);
+        }
     }
 
-    let status = response_data
+    let mutated = mutator
         .as_ref()
-        .map(|r| StatusCode::from_u16(r.status_code).unwrap_or(StatusCode::OK))
-        .unwrap_or(StatusCode::OK);
+        .and_then(|mutator| mutator.mutate(client, method.as_str(), &uri, response_data.as_ref()?));
+    let (response_data, response_body) = match mutated {
+        Some(mutated) => {
+            warn!("Mutated response to {} {}", method, uri);
+            let body = Bytes::from(mutated.body.clone().unwrap_or_default());
+            (Some(mutated), body)
+        }
+        None => (response_data, response_body),
+    };
+
+    let mut builder = Response::builder();
+    if let Some(response) = &response_data {
+        builder =
+            builder.status(StatusCode::from_u16(response.status_code).unwrap_or(StatusCode::OK));
+        for (name, value) in replay::replay_headers(response) {
+            builder = builder.header(name.as_str(), value.as_str());
+        }
+    }
+    Ok(builder.body(Full::new(response_body)).unwrap_or_else(|_| {
+        text_response(
+            StatusCode::BAD_GATEWAY,
+            "Target response has invalid headers\n".to_string(),
+        )
+    }))
+}
 
-    Ok(Response::builder()
+/// A recorded response, after its recorded latency when that is replayed
+async fn replayed(
+    replayer: &Replayer,
+    method: &hyper::Method,
+    uri: &hyper::Uri,
+    recording: &CapturedRequest,
+    mutated: Option<ResponseData>,
+) -> Response<Full<Bytes>> {
+    let (response, delay) = replayer.answer(recording);
+    let response = mutated.as_ref().unwrap_or(response);
+    if !delay.is_zero() {
+        tokio::time::sleep(delay).await;
+    }
+    info!(
+        "Replayed: {} {} -> {} (captured {})",
+        method, uri, response.status_code, recording.request.uri
+    );
+
+    let mut builder = Response::builder()
+        .status(StatusCode::from_u16(response.status_code).unwrap_or(StatusCode::OK));
+    for (name, value) in replay::replay_headers(response) {
+        builder = builder.header(name.as_str(), value.as_str());
+    }
+    let body = Bytes::from(response.body.clone().unwrap_or_default());
+
+    builder.body(Full::new(body)).unwrap_or_else(|_| {
+        text_response(
+            StatusCode::BAD_GATEWAY,
+            "Captured response has invalid headers\n".to_string(),
+        )
+    })
+}
+
+fn text_response(status: StatusCode, body: String) -> Response<Full<Bytes>> {
+    Response::builder()
         .status(status)
-        .body(response_body)
-        .unwrap())
+        .body(Full::new(Bytes::from(body)))
+        .unwrap()
 }
 
 async fn forward_request(
     method: &hyper::Method,
     uri: &hyper::Uri,
     headers: &hyper::HeaderMap,
+    body: Bytes,
     target: &str,
-) -> Result<(ResponseData, String)> {
+) -> Result<(ResponseData, Bytes)> {
     let client = reqwest::Client::new();
     let url = format!(
         "{}{}",
@@ -181,6 +388,9 @@ async fn forward_request(
             req_builder = req_builder.header(key.as_str(), value_str);
         }
     }
+    if !body.is_empty() {
+        req_builder = req_builder.body(body);
+    }
 
     let response = req_builder.send().await?;
     let status = response.status().as_u16();
@@ -190,14 +400,159 @@ async fn forward_request(
         .map(|(k, v)| (k.as_str().to_string(), v.to_str().unwrap_or("").to_string()))
         .collect();
 
-    let body = response.text().await?;
+    let body = response.bytes().await?;
 
     Ok((
         ResponseData {
             status_code: status,
             headers: resp_headers,
-            body: Some(body.as_bytes().to_vec()),
+            body: Some(body.to_vec()),
         },
         body,
     ))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    /// A backend answering every connection with one canned response
+    async fn backend(head: &str, body: &[u8]) -> String {
+        let response = [
+            format!(
+                "HTTP/1.1 200 OK\r\n{}Content-Length: {}\r\nConnection: close\r\n\r\n",
+                head,
+                body.len()
+            )
+            .into_bytes(),
+            body.to_vec(),
+        ]
+        .concat();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut buf = [0; 4096];
+                let _ = stream.read(&mut buf).await;
+                let _ = stream.write_all(&response).await;
+            }
+        });
+        format!("http://{}", addr)
+    }
+
+    fn temp_db() -> String {
+        std::env::temp_dir()
+            .join(format!("interceptor-{}.db", Uuid::new_v4()))
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    /// Run the interceptor on a free port for one GET of `path`
+    async fn fetch(
+        interceptor: impl FnOnce(u16) -> HttpInterceptor,
+        path: &str,
+    ) -> (hyper::HeaderMap, Bytes) {
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let interceptor = interceptor(port);
+        let proxy = tokio::spawn(async move { interceptor.start().await });
+
+        let url = format!("http://127.0.0.1:{}{}", port, path);
+        let client = reqwest::Client::new();
+        for _ in 0..50 {
+            if let Ok(response) = client.get(&url).send().await {
+                let headers = response.headers().clone();
+                let body = response.bytes().await.unwrap();
+                proxy.abort();
+                return (headers, body);
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+        panic!("interceptor never answered");
+    }
+
+    #[tokio::test]
+    async fn test_responses_are_redacted_in_storage() {
+        let body = br#"{"access_token":"tok-SECRET-123","user":"ada"}"#;
+        let target = backend(
+            "Content-Type: application/json\r\nSet-Cookie: sid=SECRETCOOKIE\r\n",
+            body,
+        )
+        .await;
+
+        let path = temp_db();
+        let (headers, answered) = fetch(
+            |port| HttpInterceptor::new(port, path.clone()).with_target(target),
+            "/login",
+        )
+        .await;
+        // Forwarded traffic is left untouched
+        assert_eq!(&answered[..], body);
+        assert_eq!(headers["set-cookie"], "sid=SECRETCOOKIE");
+
+        let stored = Storage::new(&path).unwrap().get_all_requests().unwrap();
+        let _ = std::fs::remove_file(&path);
+        let response = stored[0].response.as_ref().unwrap();
+        let cookie = response
+            .headers
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case("set-cookie"))
+            .map(|(_, value)| value.as_str());
+        assert_eq!(cookie, Some("__REDACTED_SET_COOKIE__"));
+        let body = String::from_utf8(response.body.clone().unwrap()).unwrap();
+        assert!(!body.contains("SECRET"), "{}", body);
+        assert!(body.contains("__REDACTED_ACCESS_TOKEN__"));
+    }
+
+    #[tokio::test]
+    async fn test_binary_bodies_pass_through() {
+        let png = [0x89, b'P', b'N', b'G', 0xff, 0x00, 0xfe];
+        let target = backend("Content-Type: image/png\r\n", &png).await;
+
+        let path = temp_db();
+        let (headers, forwarded) = fetch(
+            |port| HttpInterceptor::new(port, path.clone()).with_target(target),
+            "/logo.png",
+        )
+        .await;
+        assert_eq!(&forwarded[..], png);
+        assert_eq!(headers["content-type"], "image/png");
+
+        let stored = Storage::new(&path).unwrap().get_all_requests().unwrap();
+        assert_eq!(
+            stored[0].response.as_ref().unwrap().body.as_deref(),
+            Some(&png[..])
+        );
+        let (_, replayed) = fetch(
+            |port| HttpInterceptor::new(port, path.clone()).with_replay(Replayer::new(stored)),
+            "/logo.png",
+        )
+        .await;
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(&replayed[..], png);
+    }
+
+    #[tokio::test]
+    async fn test_served_responses_are_mutated() {
+        let recording = crate::fixtures::captured("GET", "/users/7", "", 200, r#"{"id":7}"#);
+        let mutations = Mutations::new()
+            .with_rule("GET /users/{id}=drop-field")
+            .unwrap();
+        let path = temp_db();
+        let (_, served) = fetch(
+            |port| {
+                HttpInterceptor::new(port, path.clone())
+                    .with_replay(Replayer::new(vec![recording]))
+                    .with_mutations(mutations)
+            },
+            "/users/7",
+        )
+        .await;
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(&served[..], b"{}");
+    }
+}
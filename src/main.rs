@@ -1,13 +1,26 @@
 use anyhow::Result;
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand};
 use tracing::{Level, info};
 
 mod analyzer;
 mod chaos;
+mod compare;
+mod contract;
+mod diff;
+mod exporters;
+#[cfg(test)]
+mod fixtures;
 mod generators;
+mod har;
+mod importers;
 mod interceptor;
+mod mocks;
 mod models;
+mod openapi;
 mod parsers;
+mod redact;
+mod replay;
+mod schema;
 mod storage;
 mod utils;
 
@@ -41,6 +54,21 @@ enum Commands {
 
         #[arg(short, long)]
         target: Option<String>,
+
+        /// `record` forwards and records everything, `vcr` replays requests
+        /// recorded in the output before and records the rest, `replay-only`
+        /// replays and fails on requests without a recording
+        #[arg(short, long, default_value = "record")]
+        mode: String,
+
+        /// Corrupt successful responses the client receives, e.g.
+        /// `GET /users/{id}=drop-field:20%`; every response in scope without
+        /// a chance (repeatable)
+        #[arg(long = "mutate", value_name = "ENDPOINT=MUTATION")]
+        mutations: Vec<String>,
+
+        #[command(flatten)]
+        redaction: RedactArgs,
     },
 
     /// Generate tests from captured traffic
@@ -56,6 +84,41 @@ enum Commands {
 
         #[arg(short, long, default_value = "tests")]
         output: String,
+
+        /// `test` for a test suite, `mock` for a mock server answering with
+        /// the captured responses (`--language wiremock`, `mockserver` or `rust`)
+        #[arg(short, long, default_value = "test")]
+        mode: String,
+
+        /// Default base URL of the generated suite (BASE_URL still overrides
+        /// it at run time); defaults to the capture's target
+        #[arg(long)]
+        base_url: Option<String>,
+
+        /// Send a header read from the environment with every request, e.g.
+        /// `Authorization` from AUTHORIZATION or STAGING_AUTHORIZATION (repeatable)
+        #[arg(long = "auth-header", value_name = "NAME")]
+        auth_headers: Vec<String>,
+
+        #[command(flatten)]
+        redaction: RedactArgs,
+    },
+
+    /// Serve captured responses as a fake backend
+    Serve {
+        #[arg(short, long, default_value = "chaos-capture.db")]
+        input: String,
+
+        #[arg(short = 'P', long, default_value = "8080")]
+        port: u16,
+
+        /// Delay each response by the time the captured one took
+        #[arg(long)]
+        latency: bool,
+
+        /// Corrupt served responses, as `observe --mutate` (repeatable)
+        #[arg(long = "mutate", value_name = "ENDPOINT=MUTATION")]
+        mutations: Vec<String>,
     },
 
     /// Run chaos testing scenarios
@@ -68,6 +131,135 @@ enum Commands {
 
         #[arg(short, long)]
         url: String,
+
+        /// Requests in flight at once
+        #[arg(short, long, default_value_t = 1)]
+        workers: usize,
+
+        /// Requests started per second across all workers (unlimited by default)
+        #[arg(long, conflicts_with = "preserve_timing")]
+        rps: Option<f64>,
+
+        /// Bring workers in one by one over this period, e.g. `30s`
+        #[arg(long, value_parser = chaos::parse_duration, default_value = "0s")]
+        ramp_up: std::time::Duration,
+
+        /// Replay with the captured gaps between requests
+        #[arg(long)]
+        preserve_timing: bool,
+
+        /// What an endpoint must do to pass under chaos, e.g.
+        /// `GET /users/{id}=retry:3`, `/search=fallback`,
+        /// `*=unavailable:500ms` or `*=no-500` (repeatable)
+        #[arg(long = "expect", value_name = "ENDPOINT=RULE")]
+        expectations: Vec<String>,
+
+        /// Time-based fault profile replacing the level's failure rate, e.g.
+        /// `ramp:to=30%,over=5m`, `burst:rate=50%,every=1m,for=10s`,
+        /// `outage:from=2m,until=3m,endpoint=/users/{id}` or
+        /// `degrade:to=500ms,over=5m` (repeatable)
+        #[arg(long = "schedule", value_name = "PROFILE")]
+        schedules: Vec<String>,
+
+        /// TCP-level fault between the replayer and the target, e.g.
+        /// `bandwidth:rate=64kb`, `stall:chance=5%,for=2s`,
+        /// `reset:after=4kb,chance=20%`, `half-open:chance=5%`,
+        /// `delay-accept:for=200ms` or `delay-connect:for=200ms` (repeatable)
+        #[arg(long = "net-fault", value_name = "FAULT")]
+        net_faults: Vec<String>,
+
+        /// Corrupt successful responses of an endpoint, e.g.
+        /// `GET /users/{id}=drop-field`, `*=truncate:10%`; also `change-type`,
+        /// `content-type`, `duplicate`, `reorder` and `stale` (repeatable)
+        #[arg(long = "mutate", value_name = "ENDPOINT=MUTATION")]
+        mutations: Vec<String>,
+
+        /// After the run, re-send writes to POST and PATCH endpoints as this
+        /// many concurrent copies and as a retry after a lost response, and
+        /// fail on endpoints that create duplicates
+        #[arg(long, value_name = "COPIES")]
+        retry_storm: Option<usize>,
+
+        /// Measure a steady state from the `capture` or a fault-free
+        /// `baseline` replay and abort once the run drifts too far from it
+        #[arg(long)]
+        steady_state: Option<String>,
+
+        /// Abort when the error rate rises this much above the steady state
+        /// (0.2 = 20 points)
+        #[arg(long, default_value_t = 0.2, requires = "steady_state")]
+        max_error_rate_increase: f64,
+
+        /// Abort when p95 latency grows past this multiple of the steady state
+        #[arg(long, default_value_t = 3.0, requires = "steady_state")]
+        max_latency_factor: f64,
+
+        /// `text`, `json` or `junit`
+        #[arg(long, default_value = "text")]
+        report_format: String,
+
+        /// Write the report to a file instead of stdout
+        #[arg(short, long)]
+        output: Option<String>,
+    },
+
+    /// Replay captured requests against a server and compare the responses
+    Replay {
+        #[arg(short, long, default_value = "chaos-capture.db")]
+        input: String,
+
+        #[arg(short, long)]
+        url: String,
+
+        /// Compare headers, JSON bodies and latency as well as the status
+        #[arg(long)]
+        diff: bool,
+
+        /// Leave a header out of the comparison (repeatable)
+        #[arg(long = "ignore-header", value_name = "NAME")]
+        ignore_headers: Vec<String>,
+
+        /// Leave JSON body values out of the comparison, e.g. `$.meta` or
+        /// `$..updatedAt` (repeatable)
+        #[arg(long = "ignore-path", value_name = "PATH")]
+        ignore_paths: Vec<String>,
+
+        /// Flag responses taking this many times as long as the captured one...
+        #[arg(long, default_value_t = 2.0)]
+        latency_factor: f64,
+
+        /// ...and at least this many milliseconds longer
+        #[arg(long, default_value_t = 100)]
+        latency_slack_ms: u64,
+
+        /// `text` or `json`
+        #[arg(long, default_value = "text")]
+        report_format: String,
+
+        /// Write the report to a file instead of stdout
+        #[arg(short, long)]
+        output: Option<String>,
+
+        /// Exit with an error on `breaking` changes, on `changed` or worse,
+        /// on `any` difference, or `never`
+        #[arg(long, default_value = "breaking")]
+        fail_on: String,
+    },
+
+    /// Compare two captures per endpoint, e.g. before and after a deploy
+    Diff {
+        /// Capture to compare against
+        old: String,
+
+        new: String,
+
+        /// `text` or `json`
+        #[arg(long, default_value = "text")]
+        report_format: String,
+
+        /// Write the report to a file instead of stdout
+        #[arg(short, long)]
+        output: Option<String>,
     },
 
     /// Analyze captured traffic without generating tests
@@ -76,6 +268,47 @@ enum Commands {
         input: String,
     },
 
+    /// Validate captured traffic against an OpenAPI 3 specification
+    Validate {
+        #[arg(short, long, default_value = "chaos-capture.db")]
+        input: String,
+
+        /// OpenAPI document (YAML or JSON)
+        #[arg(short, long)]
+        spec: String,
+    },
+
+    /// Import traffic recorded by other tools into a capture database
+    Import {
+        /// pcap or pcapng packet capture
+        #[arg(long, conflicts_with = "har")]
+        pcap: Option<String>,
+
+        /// HTTP Archive (HAR) file
+        #[arg(long, conflicts_with = "pcap")]
+        har: Option<String>,
+
+        #[arg(short, long, default_value = "chaos-capture.db")]
+        output: String,
+
+        #[command(flatten)]
+        redaction: RedactArgs,
+    },
+
+    /// Export captured traffic to other formats
+    Export {
+        #[arg(short, long, default_value = "chaos-capture.db")]
+        input: String,
+
+        /// Write an HTTP Archive (HAR 1.2) file
+        #[arg(long, conflicts_with = "openapi")]
+        har: Option<String>,
+
+        /// Write an OpenAPI 3 specification (YAML for .yaml/.yml, JSON otherwise)
+        #[arg(long, conflicts_with = "har")]
+        openapi: Option<String>,
+    },
+
     /// Parse and analyze a query or command
     Parse {
         #[arg(short, long)]
@@ -86,6 +319,60 @@ enum Commands {
     },
 }
 
+/// Redaction rules shared by commands that capture or emit traffic
+#[derive(Args)]
+struct RedactArgs {
+    /// Redact matches of a regex as an environment variable, e.g.
+    /// `SESSION_ID=sess_[a-z0-9]+` (repeatable)
+    #[arg(long = "redact-regex", value_name = "ENV=REGEX")]
+    regexes: Vec<String>,
+
+    /// Redact JSON body values at a path, e.g. `SSN=$.user.ssn` (repeatable)
+    #[arg(long = "redact-json-path", value_name = "ENV=PATH")]
+    json_paths: Vec<String>,
+
+    /// Only apply the rules given on the command line
+    #[arg(long)]
+    no_default_redaction: bool,
+}
+
+impl RedactArgs {
+    fn redactor(&self) -> Result<redact::Redactor> {
+        let mut redactor = match self.no_default_redaction {
+            true => redact::Redactor::empty(),
+            false => redact::Redactor::new(),
+        };
+
+        for rule in &self.regexes {
+            let (env, pattern) = split_rule(rule)?;
+            redactor = redactor.with_regex(env, pattern)?;
+        }
+        for rule in &self.json_paths {
+            let (env, path) = split_rule(rule)?;
+            redactor = redactor.with_json_path(env, path)?;
+        }
+
+        Ok(redactor)
+    }
+}
+
+/// `ENV=RULE`, split on the first `=` so rules may contain their own
+fn split_rule(rule: &str) -> Result<(&str, &str)> {
+    match rule.split_once('=') {
+        Some((env, rule)) if !env.is_empty() && !rule.is_empty() => Ok((env, rule)),
+        _ => anyhow::bail!("Redaction rules look like ENV=RULE, got: {}", rule),
+    }
+}
+
+/// Response mutations from the `--mutate` rules
+fn parse_mutations(rules: &[String]) -> Result<chaos::Mutations> {
+    rules
+        .iter()
+        .try_fold(chaos::Mutations::new(), |mutations, rule| {
+            mutations.with_rule(rule)
+        })
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
     let cli = Cli::parse();
@@ -110,7 +397,12 @@ async fn main() -> Result<()> {
             duration,
             output,
             target,
+            mode,
+            mutations,
+            redaction,
         } => {
+            let replay_mode = replay::ReplayMode::from_observe(&mode)?;
+
             if let Some(pid) = pid {
                 info!("Observing process {} for {}", pid, duration);
                 info!("Output: {}", output);
@@ -119,7 +411,17 @@ async fn main() -> Result<()> {
                 info!("Intercepting traffic on port {} for {}", port, duration);
                 info!("Output: {}", output);
 
-                let mut interceptor = interceptor::HttpInterceptor::new(port, output);
+                let mut interceptor = interceptor::HttpInterceptor::new(port, output.clone())
+                    .with_redactor(redaction.redactor()?)
+                    .with_mutations(parse_mutations(&mutations)?);
+                if let Some(replay_mode) = replay_mode {
+                    if replay_mode == replay::ReplayMode::Vcr && target.is_none() {
+                        anyhow::bail!("--mode vcr needs a --target to record from");
+                    }
+                    let recordings = storage::Storage::new(&output)?.get_all_requests()?;
+                    interceptor = interceptor
+                        .with_replay(replay::Replayer::new(recordings).with_mode(replay_mode));
+                }
                 if let Some(target_url) = target {
                     interceptor = interceptor.with_target(target_url);
                 }
@@ -134,6 +436,10 @@ async fn main() -> Result<()> {
             language,
             framework,
             output,
+            mode,
+            base_url,
+            auth_headers,
+            redaction,
         } => {
             use std::fs;
 
@@ -145,37 +451,279 @@ async fn main() -> Result<()> {
             );
 
             let storage = storage::Storage::new(&input)?;
-            let requests = storage.get_all_requests()?;
+            let mut requests = storage.get_all_requests()?;
 
             info!("Loaded {} captured requests", requests.len());
 
+            // Captures made before a rule existed still get it applied
+            let redacted = redaction.redactor()?.redact_all(&mut requests);
+            if redacted > 0 {
+                info!("Redacted {} values", redacted);
+            }
+
             if requests.is_empty() {
                 println!("No requests found in capture file");
                 return Ok(());
             }
 
-            let generator = generators::get_generator(&language, framework.as_deref())?;
-            let test_code = generator.generate(&requests)?;
+            let (file_name, code, kind) = match mode.as_str() {
+                "test" => {
+                    generators::inject_auth_headers(&mut requests, &auth_headers);
+
+                    let base_url = match base_url {
+                        Some(url) => Some(url),
+                        None => storage.get_metadata("target")?,
+                    };
+                    let config = generators::SuiteConfig {
+                        base_url: base_url
+                            .or_else(|| generators::captured_base_url(&requests))
+                            .unwrap_or_else(|| generators::DEFAULT_BASE_URL.to_string()),
+                    };
+                    info!("Default base URL: {}", config.base_url);
+
+                    let generator = generators::get_generator(&language, framework.as_deref())?;
+                    let code = generator.generate_with(&requests, &config)?;
+                    (generator.file_name(), code, "tests")
+                }
+                "mock" => {
+                    if let Some(framework) = &framework {
+                        anyhow::bail!(
+                            "--framework {} only applies to --mode test; mock servers are picked by --language",
+                            framework
+                        );
+                    }
+                    let generator = mocks::get_mock_generator(&language)?;
+                    let code = generator.generate(&requests)?;
+                    (generator.file_name(), code, "mock stubs")
+                }
+                other => anyhow::bail!("Unsupported mode: {} (expected test or mock)", other),
+            };
 
             fs::create_dir_all(&output)?;
-            let filename = format!("{}/test_generated.{}", output, generator.file_extension());
-            fs::write(&filename, test_code)?;
+            let filename = format!("{}/{}", output, file_name);
+            fs::write(&filename, code)?;
 
-            info!("Generated tests written to: {}", filename);
-            println!("✓ Generated {} tests in {}", requests.len(), filename);
+            info!("Generated {} written to: {}", kind, filename);
+            println!("✓ Generated {} {} in {}", requests.len(), kind, filename);
         }
 
-        Commands::Chaos { level, input, url } => {
+        Commands::Serve {
+            input,
+            port,
+            latency,
+            mutations,
+        } => {
+            info!("Serving captured responses from {} on port {}", input, port);
+
+            let storage = storage::Storage::new(&input)?;
+            let requests = storage.get_all_requests()?;
+            let replayer = replay::Replayer::new(requests).with_latency(latency);
+            if replayer.len() == 0 {
+                anyhow::bail!("No captured responses to serve in {}", input);
+            }
+
+            interceptor::HttpInterceptor::new(port, input)
+                .with_replay(replayer)
+                .with_mutations(parse_mutations(&mutations)?)
+                .start()
+                .await?;
+        }
+
+        Commands::Chaos {
+            level,
+            input,
+            url,
+            workers,
+            rps,
+            ramp_up,
+            preserve_timing,
+            expectations,
+            schedules,
+            net_faults,
+            mutations,
+            retry_storm,
+            steady_state,
+            max_error_rate_increase,
+            max_latency_factor,
+            report_format,
+            output,
+        } => {
             info!("Running chaos testing at {} level", level);
             info!("Using capture: {}", input);
             info!("Target: {}", url);
 
+            let mut expect = chaos::Expectations::new();
+            for rule in &expectations {
+                expect = expect.with_rule(rule)?;
+            }
+
+            let mut schedule = chaos::Schedule::new();
+            for profile in &schedules {
+                schedule = schedule.with_profile(profile)?;
+            }
+
+            let mut network = chaos::NetworkFaults::new();
+            for fault in &net_faults {
+                network = network.with_fault(fault)?;
+            }
+
             let storage = storage::Storage::new(&input)?;
             let chaos_level = chaos::ChaosLevel::from_str(&level);
-            let engine = chaos::ChaosEngine::new(storage, chaos_level, url);
+            let mut engine = chaos::ChaosEngine::new(storage, chaos_level, url)
+                .with_load(chaos::Load {
+                    workers,
+                    rps,
+                    ramp_up,
+                    preserve_timing,
+                })
+                .with_expectations(expect.clone())
+                .with_schedule(schedule)
+                .with_network_faults(network)
+                .with_mutations(parse_mutations(&mutations)?);
+            if let Some(copies) = retry_storm {
+                if copies < 2 {
+                    anyhow::bail!("--retry-storm needs at least 2 copies, got: {}", copies);
+                }
+                engine = engine.with_retry_storm(copies);
+            }
+            if let Some(source) = &steady_state {
+                engine = engine.with_steady_state(
+                    chaos::steady::Source::parse(source)?,
+                    chaos::steady::BlastRadius {
+                        max_error_rate_increase,
+                        max_latency_factor,
+                    },
+                );
+            }
 
             let report = engine.run_chaos_tests().await?;
-            report.print();
+
+            let text = match report_format.as_str() {
+                "text" => None,
+                "json" => Some(serde_json::to_string_pretty(&report.to_json())?),
+                "junit" => Some(report.to_junit()),
+                other => anyhow::bail!(
+                    "Unsupported report format: {} (expected text, json or junit)",
+                    other
+                ),
+            };
+            match (text, output) {
+                (Some(text), Some(path)) => std::fs::write(&path, text)?,
+                (Some(text), None) => println!("{}", text),
+                (None, Some(_)) => anyhow::bail!("--output needs --report-format json or junit"),
+                (None, None) => report.print(),
+            }
+
+            if let Some(abort) = report
+                .steady_state
+                .as_ref()
+                .and_then(|s| s.aborted.as_ref())
+            {
+                anyhow::bail!("Experiment aborted: {}", abort.deviation);
+            }
+            if !expect.is_empty() && report.failed > 0 {
+                anyhow::bail!(
+                    "{} requests did not meet their resilience expectations",
+                    report.failed
+                );
+            }
+            if let Some(idempotency) = &report.idempotency {
+                let lacking = idempotency.lacking();
+                if !lacking.is_empty() {
+                    anyhow::bail!(
+                        "Duplicates created when retrying {} (no idempotency key support)",
+                        lacking
+                            .iter()
+                            .map(|e| e.as_str())
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    );
+                }
+            }
+        }
+
+        Commands::Replay {
+            input,
+            url,
+            diff,
+            ignore_headers,
+            ignore_paths,
+            latency_factor,
+            latency_slack_ms,
+            report_format,
+            output,
+            fail_on,
+        } => {
+            info!("Replaying {} against {}", input, url);
+
+            let fail_on = diff::Severity::from_fail_on(&fail_on)?;
+            let mut rules = diff::DiffRules::new()
+                .with_detail(diff)
+                .with_latency_budget(latency_factor, latency_slack_ms);
+            for header in &ignore_headers {
+                rules = rules.with_ignored_header(header);
+            }
+            for path in &ignore_paths {
+                rules = rules.with_ignored_path(path)?;
+            }
+
+            let storage = storage::Storage::new(&input)?;
+            let requests = storage.get_all_requests()?;
+            let report = diff::replay(&requests, &url, &rules).await?;
+
+            let text = match report_format.as_str() {
+                "text" => None,
+                "json" => Some(serde_json::to_string_pretty(&report.to_json())?),
+                other => anyhow::bail!(
+                    "Unsupported report format: {} (expected text or json)",
+                    other
+                ),
+            };
+            match (text, output) {
+                (Some(text), Some(path)) => std::fs::write(&path, text)?,
+                (Some(text), None) => println!("{}", text),
+                (None, Some(_)) => anyhow::bail!("--output needs --report-format json"),
+                (None, None) => report.print(),
+            }
+
+            if let Some(threshold) = fail_on {
+                let failing = report.failing(threshold);
+                if failing > 0 {
+                    anyhow::bail!(
+                        "{} replayed requests have {} or worse changes",
+                        failing,
+                        threshold
+                    );
+                }
+            }
+        }
+
+        Commands::Diff {
+            old,
+            new,
+            report_format,
+            output,
+        } => {
+            info!("Comparing {} with {}", old, new);
+
+            let before = storage::Storage::new(&old)?.get_all_requests()?;
+            let after = storage::Storage::new(&new)?.get_all_requests()?;
+            let diff = compare::CaptureDiff::compare(&before, &after);
+
+            let text = match report_format.as_str() {
+                "text" => None,
+                "json" => Some(serde_json::to_string_pretty(&diff)?),
+                other => anyhow::bail!(
+                    "Unsupported report format: {} (expected text or json)",
+                    other
+                ),
+            };
+            match (text, output) {
+                (Some(text), Some(path)) => std::fs::write(&path, text)?,
+                (Some(text), None) => println!("{}", text),
+                (None, Some(_)) => anyhow::bail!("--output needs --report-format json"),
+                (None, None) => diff.print(),
+            }
         }
 
         Commands::Analyze { input } => {
@@ -200,6 +748,77 @@ async fn main() -> Result<()> {
             report.print();
         }
 
+        Commands::Validate { input, spec } => {
+            info!("Validating {} against {}", input, spec);
+
+            let document = std::fs::read_to_string(&spec)?;
+            let spec = openapi::OpenApi::parse(&document, openapi::SpecFormat::from_path(&spec))?;
+
+            let storage = storage::Storage::new(&input)?;
+            let analyzer = analyzer::Analyzer::new(storage);
+            let report = analyzer.validate_contract(&spec)?;
+
+            report.print();
+        }
+
+        Commands::Import {
+            pcap,
+            har,
+            output,
+            redaction,
+        } => {
+            use importers::Importer;
+
+            let (path, importer): (String, Box<dyn Importer>) = if let Some(pcap) = pcap {
+                info!("Importing packet capture {}", pcap);
+                (pcap, Box::new(importers::PcapImporter::new()))
+            } else if let Some(har) = har {
+                info!("Importing HTTP archive {}", har);
+                (har, Box::new(importers::HarImporter::new()))
+            } else {
+                anyhow::bail!("Either --pcap or --har must be specified");
+            };
+            info!("Output: {}", output);
+
+            let data = std::fs::read(&path)?;
+            let mut requests = importer.import(&data)?;
+            redaction.redactor()?.redact_all(&mut requests);
+
+            let storage = storage::Storage::new(&output)?;
+            for request in &requests {
+                storage.store_request(request)?;
+            }
+
+            println!("✓ Imported {} requests into {}", requests.len(), output);
+        }
+
+        Commands::Export {
+            input,
+            har,
+            openapi,
+        } => {
+            use exporters::Exporter;
+
+            let (path, exporter): (String, Box<dyn Exporter>) = if let Some(har) = har {
+                (har, Box::new(exporters::HarExporter::new()))
+            } else if let Some(openapi) = openapi {
+                let format = openapi::SpecFormat::from_path(&openapi);
+                (openapi, Box::new(exporters::OpenApiExporter::new(format)))
+            } else {
+                anyhow::bail!("Either --har or --openapi must be specified");
+            };
+
+            info!("Exporting captured traffic from {}", input);
+
+            let storage = storage::Storage::new(&input)?;
+            let requests = storage.get_all_requests()?;
+
+            let document = exporter.export(&requests)?;
+            std::fs::write(&path, document)?;
+
+            println!("✓ Exported {} requests to {}", requests.len(), path);
+        }
+
         Commands::Parse { query, protocol } => {
             use parsers::grpc::GrpcParser;
             use parsers::http::HttpParser;
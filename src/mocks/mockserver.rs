@@ -0,0 +1,155 @@
+//! MockServer expectations
+//!
+//! Writes an expectation array for MockServer's `initializationJsonPath` or
+//! `PUT /mockserver/expectation`.
+
+use crate::mocks::{MockGenerator, Stub, path_regex, stubs};
+use crate::models::CapturedRequest;
+use anyhow::Result;
+use serde_json::{Map, Value, json};
+
+/// MockServer tries higher priorities first
+const EXACT_PRIORITY: u32 = 10;
+const FALLBACK_PRIORITY: u32 = 0;
+
+pub struct MockServerGenerator;
+
+impl MockServerGenerator {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl MockGenerator for MockServerGenerator {
+    fn generate(&self, requests: &[CapturedRequest]) -> Result<String> {
+        let mut expectations = Vec::new();
+
+        for stubs in stubs(requests).into_values() {
+            for stub in &stubs {
+                let mut request = Map::new();
+                request.insert("method".into(), json!(stub.method));
+                request.insert("path".into(), json!(stub.path));
+                if !stub.query.is_empty() {
+                    let mut params: Map<String, Value> = Map::new();
+                    for (k, v) in &stub.query {
+                        let values = params.entry(k.clone()).or_insert_with(|| json!([]));
+                        if let Value::Array(values) = values {
+                            values.push(json!(v));
+                        }
+                    }
+                    request.insert("queryStringParameters".into(), Value::Object(params));
+                }
+                let body = match (stub.request_json(), stub.request_text()) {
+                    (Some(body), _) => Some(json!({
+                        "type": "JSON",
+                        "json": body,
+                        "matchType": "ONLY_MATCHING_FIELDS",
+                    })),
+                    (None, Some(text)) => Some(json!({ "type": "STRING", "string": text })),
+                    (None, None) => None,
+                };
+                if let Some(body) = body {
+                    request.insert("body".into(), body);
+                }
+
+                expectations.push(json!({
+                    "priority": EXACT_PRIORITY,
+                    "httpRequest": request,
+                    "httpResponse": response(stub),
+                    "times": { "unlimited": true },
+                }));
+            }
+
+            // Other IDs, queries and bodies on the endpoint get the first answer
+            let first = &stubs[0];
+            expectations.push(json!({
+                "priority": FALLBACK_PRIORITY,
+                "httpRequest": {
+                    "method": first.method,
+                    "path": path_regex(&first.pattern),
+                },
+                "httpResponse": response(first),
+                "times": { "unlimited": true },
+            }));
+        }
+
+        Ok(serde_json::to_string_pretty(&expectations)?)
+    }
+
+    fn file_name(&self) -> String {
+        "mockserver-expectations.json".to_string()
+    }
+}
+
+fn response(stub: &Stub) -> Value {
+    let mut response = Map::new();
+    response.insert("statusCode".into(), json!(stub.response.status_code));
+
+    let headers: Map<String, Value> = stub
+        .headers()
+        .into_iter()
+        .map(|(k, v)| (k.clone(), json!([v])))
+        .collect();
+    if !headers.is_empty() {
+        response.insert("headers".into(), Value::Object(headers));
+    }
+
+    match stub.response_json() {
+        Some(body) => {
+            response.insert("body".into(), json!({ "type": "JSON", "json": body }));
+        }
+        None if stub.response.body.as_ref().is_some_and(|b| !b.is_empty()) => {
+            response.insert("body".into(), json!(stub.response_text()));
+        }
+        None => {}
+    }
+
+    Value::Object(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::captured;
+
+    #[test]
+    fn test_mockserver_expectations() {
+        let requests = vec![
+            captured(
+                "GET",
+                "/api/orders/7?expand=items&expand=user",
+                "",
+                200,
+                "[]",
+            ),
+            captured("PUT", "/api/orders/7", "{\"qty\":2}", 204, ""),
+        ];
+
+        let output = MockServerGenerator::new().generate(&requests).unwrap();
+        let expectations: Value = serde_json::from_str(&output).unwrap();
+        let expectations = expectations.as_array().unwrap();
+        assert_eq!(expectations.len(), 4);
+
+        let get = &expectations[0];
+        assert_eq!(get["priority"], EXACT_PRIORITY);
+        assert_eq!(get["httpRequest"]["path"], "/api/orders/7");
+        assert_eq!(
+            get["httpRequest"]["queryStringParameters"]["expand"],
+            serde_json::json!(["items", "user"])
+        );
+        assert_eq!(get["httpResponse"]["body"]["type"], "JSON");
+        assert_eq!(
+            get["httpResponse"]["headers"]["content-type"][0],
+            "application/json"
+        );
+        assert_eq!(expectations[1]["httpRequest"]["path"], "/api/orders/[0-9]+");
+
+        let put = &expectations[2];
+        assert_eq!(
+            put["httpRequest"]["body"]["matchType"],
+            "ONLY_MATCHING_FIELDS"
+        );
+        assert_eq!(put["httpResponse"]["statusCode"], 204);
+        assert!(put["httpResponse"].get("body").is_none());
+    }
+}
@@ -0,0 +1,230 @@
+//! Test doubles generated from captured traffic
+//!
+//! Every captured request/response pair becomes a stub that answers its
+//! endpoint pattern. Requests matching a captured URL and body get that
+//! variant's response; anything else on the endpoint gets the first one.
+
+pub mod mockserver;
+pub mod rust_server;
+pub mod wiremock;
+
+use crate::models::{CapturedRequest, Protocol, ResponseData};
+use crate::parsers::http::HttpParser;
+use anyhow::Result;
+use hyper::Uri;
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+pub use mockserver::MockServerGenerator;
+pub use rust_server::RustServerGenerator;
+pub use wiremock::WireMockGenerator;
+
+/// Response headers that describe the original connection rather than the
+/// response, and would be wrong when replayed
+pub const HOP_HEADERS: [&str; 5] = [
+    "connection",
+    "content-length",
+    "date",
+    "keep-alive",
+    "transfer-encoding",
+];
+
+pub trait MockGenerator {
+    fn generate(&self, requests: &[CapturedRequest]) -> Result<String>;
+    fn file_name(&self) -> String;
+}
+
+pub fn get_mock_generator(format: &str) -> Result<Box<dyn MockGenerator>> {
+    match format.to_lowercase().as_str() {
+        "wiremock" | "auto" => Ok(Box::new(WireMockGenerator::new())),
+        "mockserver" => Ok(Box::new(MockServerGenerator::new())),
+        "rust" | "rs" => Ok(Box::new(RustServerGenerator::new())),
+        _ => anyhow::bail!("Unsupported mock format: {}", format),
+    }
+}
+
+/// One captured answer for an endpoint
+#[derive(Debug)]
+pub struct Stub<'a> {
+    pub method: &'a str,
+    /// Endpoint pattern, e.g. `/api/users/{id}`
+    pub pattern: String,
+    /// Captured path without the query string
+    pub path: String,
+    /// Captured query parameters in URL order
+    pub query: Vec<(String, String)>,
+    pub body: Option<&'a [u8]>,
+    pub response: &'a ResponseData,
+}
+
+impl Stub<'_> {
+    /// Captured request target, path plus query string
+    pub fn target(&self) -> String {
+        match self.query.is_empty() {
+            true => self.path.clone(),
+            false => {
+                let pairs: Vec<String> = self
+                    .query
+                    .iter()
+                    .map(|(k, v)| format!("{}={}", k, v))
+                    .collect();
+                format!("{}?{}", self.path, pairs.join("&"))
+            }
+        }
+    }
+
+    /// Response headers worth replaying, sorted by name
+    pub fn headers(&self) -> Vec<(&String, &String)> {
+        let mut headers: Vec<(&String, &String)> = self
+            .response
+            .headers
+            .iter()
+            .filter(|(key, _)| !HOP_HEADERS.contains(&key.to_lowercase().as_str()))
+            .collect();
+        headers.sort();
+        headers
+    }
+
+    /// Request body when it is JSON
+    pub fn request_json(&self) -> Option<Value> {
+        serde_json::from_slice(self.body?).ok()
+    }
+
+    /// Response body when it is JSON
+    pub fn response_json(&self) -> Option<Value> {
+        serde_json::from_slice(self.response.body.as_deref()?).ok()
+    }
+
+    pub fn request_text(&self) -> Option<String> {
+        self.body.map(|b| String::from_utf8_lossy(b).into_owned())
+    }
+
+    pub fn response_text(&self) -> String {
+        self.response
+            .body
+            .as_deref()
+            .map(|b| String::from_utf8_lossy(b).into_owned())
+            .unwrap_or_default()
+    }
+}
+
+/// Stubs for every answered HTTP request, grouped by `METHOD pattern` in
+/// sorted order and without repeats of the same URL and body
+pub fn stubs(requests: &[CapturedRequest]) -> BTreeMap<String, Vec<Stub<'_>>> {
+    let mut grouped: BTreeMap<String, Vec<Stub>> = BTreeMap::new();
+
+    for req in requests {
+        if !matches!(req.protocol, Protocol::Http | Protocol::Https) {
+            continue;
+        }
+        let Some(response) = &req.response else {
+            continue;
+        };
+        let Ok(uri) = req.request.uri.parse::<Uri>() else {
+            continue;
+        };
+
+        let target = uri.path_and_query().map(|pq| pq.as_str()).unwrap_or("/");
+        let (path, query) = match target.split_once('?') {
+            Some((path, query)) => (path, parse_query(query)),
+            None => (target, Vec::new()),
+        };
+
+        let stub = Stub {
+            method: &req.request.method,
+            pattern: HttpParser::extract_endpoint_pattern(&uri),
+            path: path.to_string(),
+            query,
+            body: req.request.body.as_deref().filter(|b| !b.is_empty()),
+            response,
+        };
+
+        let stubs = grouped
+            .entry(format!("{} {}", stub.method, stub.pattern))
+            .or_default();
+        if !stubs
+            .iter()
+            .any(|s| s.path == stub.path && s.query == stub.query && s.body == stub.body)
+        {
+            stubs.push(stub);
+        }
+    }
+
+    grouped
+}
+
+/// Regex for the paths an endpoint pattern covers, e.g.
+/// `/api/users/{id}` -> `/api/users/[0-9]+`
+pub fn path_regex(pattern: &str) -> String {
+    pattern
+        .split('/')
+        .map(|segment| match segment {
+            "{id}" => "[0-9]+".to_string(),
+            "{uuid}" => "[A-Za-z0-9-]+".to_string(),
+            literal => regex::escape(literal),
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+pub fn parse_query(query: &str) -> Vec<(String, String)> {
+    query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| match pair.split_once('=') {
+            Some((k, v)) => (k.to_string(), v.to_string()),
+            None => (pair.to_string(), String::new()),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::captured;
+
+    #[test]
+    fn test_stubs_group_variants_by_endpoint() {
+        let requests = vec![
+            captured("GET", "/api/users/1", "", 200, "{\"id\":1}"),
+            captured("GET", "/api/users/2?full=true", "", 200, "{\"id\":2}"),
+            captured("GET", "/api/users/1", "", 200, "{\"id\":1}"),
+            captured(
+                "POST",
+                "/api/users",
+                "{\"name\":\"ann\"}",
+                201,
+                "{\"id\":3}",
+            ),
+        ];
+
+        let stubs = stubs(&requests);
+        let keys: Vec<&String> = stubs.keys().collect();
+        assert_eq!(keys, ["GET /api/users/{id}", "POST /api/users"]);
+
+        let users = &stubs["GET /api/users/{id}"];
+        assert_eq!(users.len(), 2);
+        assert_eq!(users[1].path, "/api/users/2");
+        assert_eq!(users[1].query, [("full".to_string(), "true".to_string())]);
+        assert_eq!(users[1].target(), "/api/users/2?full=true");
+        let headers: Vec<&str> = users[0].headers().iter().map(|(k, _)| k.as_str()).collect();
+        assert_eq!(headers, ["content-type"]);
+    }
+
+    #[test]
+    fn test_path_regex() {
+        assert_eq!(path_regex("/api/users/{id}"), "/api/users/[0-9]+");
+        assert_eq!(
+            path_regex("/v1.0/{uuid}/items"),
+            "/v1\\.0/[A-Za-z0-9-]+/items"
+        );
+    }
+
+    #[test]
+    fn test_unknown_mock_format() {
+        assert!(get_mock_generator("wiremock").is_ok());
+        assert!(get_mock_generator("mockserver").is_ok());
+        assert!(get_mock_generator("rust").is_ok());
+        assert!(get_mock_generator("python").is_err());
+    }
+}
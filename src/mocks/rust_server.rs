@@ -0,0 +1,192 @@
+//! Standalone Rust mock server
+//!
+//! Emits a single std-only source file, so the double builds with plain
+//! `rustc` and no Cargo project.
+
+use crate::mocks::{MockGenerator, stubs};
+use crate::models::CapturedRequest;
+use anyhow::Result;
+
+/// Request handling shared by every generated server
+const SERVER: &str = r#"fn main() {
+    let port = std::env::var("PORT").unwrap_or_else(|_| "8080".to_string());
+    let listener = TcpListener::bind(format!("0.0.0.0:{}", port)).expect("failed to bind");
+    println!("Mock server listening on port {}", port);
+
+    for stream in listener.incoming().flatten() {
+        thread::spawn(move || {
+            if let Err(err) = handle(stream) {
+                eprintln!("connection error: {}", err);
+            }
+        });
+    }
+}
+
+fn handle(mut stream: TcpStream) -> std::io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    let mut parts = line.split_whitespace();
+    let method = parts.next().unwrap_or_default().to_string();
+    let target = parts.next().unwrap_or("/").to_string();
+
+    let mut length = 0;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 || header.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("content-length") {
+                length = value.trim().parse().unwrap_or(0);
+            }
+        }
+    }
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body)?;
+    let body = String::from_utf8_lossy(&body);
+
+    let (status, headers, payload) = match find(&method, &target, &body) {
+        Some(stub) => (stub.status, stub.headers, stub.response.to_string()),
+        None => (404, &[][..], format!("No captured response for {} {}\n", method, target)),
+    };
+
+    let mut head = format!("HTTP/1.1 {} \r\n", status);
+    for (name, value) in headers {
+        head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    head.push_str(&format!("content-length: {}\r\n", payload.len()));
+    head.push_str("connection: close\r\n\r\n");
+    stream.write_all(head.as_bytes())?;
+    stream.write_all(payload.as_bytes())
+}
+
+/// The variant captured with this exact URL and body, else the first one
+/// captured for the endpoint
+fn find(method: &str, target: &str, body: &str) -> Option<&'static Stub> {
+    let path = target.split('?').next().unwrap_or(target);
+    let candidates: Vec<&'static Stub> = STUBS
+        .iter()
+        .filter(|stub| stub.method == method && matches(stub.pattern, path))
+        .collect();
+
+    candidates
+        .iter()
+        .find(|stub| stub.target == target && stub.body.unwrap_or_default() == body)
+        .or_else(|| candidates.iter().find(|stub| stub.target == target))
+        .or_else(|| candidates.first())
+        .copied()
+}
+
+/// Whether `path` fits a pattern like `/api/users/{id}`
+fn matches(pattern: &str, path: &str) -> bool {
+    let pattern: Vec<&str> = pattern.split('/').collect();
+    let path: Vec<&str> = path.split('/').collect();
+    pattern.len() == path.len()
+        && pattern
+            .iter()
+            .zip(&path)
+            .all(|(p, s)| p == s || (p.starts_with('{') && !s.is_empty()))
+}
+"#;
+
+pub struct RustServerGenerator;
+
+impl RustServerGenerator {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl MockGenerator for RustServerGenerator {
+    fn generate(&self, requests: &[CapturedRequest]) -> Result<String> {
+        let mut output = String::new();
+
+        output.push_str("//! Mock server generated from captured traffic\n");
+        output.push_str("//!\n");
+        output.push_str("//! Build with `rustc --edition 2021 -O mock_server.rs` and run\n");
+        output.push_str("//! `./mock_server`; PORT changes the port (default 8080). Requests\n");
+        output.push_str("//! matching a captured URL and body get that response, other requests\n");
+        output.push_str(
+            "//! to a captured endpoint get its first response, anything else a 404.\n\n",
+        );
+        output.push_str("use std::io::{BufRead, BufReader, Read, Write};\n");
+        output.push_str("use std::net::{TcpListener, TcpStream};\n");
+        output.push_str("use std::thread;\n\n");
+
+        output.push_str("struct Stub {\n");
+        output.push_str("    method: &'static str,\n");
+        output.push_str("    pattern: &'static str,\n");
+        output.push_str("    target: &'static str,\n");
+        output.push_str("    body: Option<&'static str>,\n");
+        output.push_str("    status: u16,\n");
+        output.push_str("    headers: &'static [(&'static str, &'static str)],\n");
+        output.push_str("    response: &'static str,\n");
+        output.push_str("}\n\n");
+
+        output.push_str("static STUBS: &[Stub] = &[\n");
+        for (endpoint, stubs) in stubs(requests) {
+            output.push_str(&format!("    // {}\n", endpoint));
+            for stub in &stubs {
+                let headers: Vec<String> = stub
+                    .headers()
+                    .into_iter()
+                    .map(|(k, v)| format!("({:?}, {:?})", k, v))
+                    .collect();
+                let body = match stub.request_text() {
+                    Some(body) => format!("Some({:?})", body),
+                    None => "None".to_string(),
+                };
+
+                output.push_str("    Stub {\n");
+                output.push_str(&format!("        method: {:?},\n", stub.method));
+                output.push_str(&format!("        pattern: {:?},\n", stub.pattern));
+                output.push_str(&format!("        target: {:?},\n", stub.target()));
+                output.push_str(&format!("        body: {},\n", body));
+                output.push_str(&format!("        status: {},\n", stub.response.status_code));
+                output.push_str(&format!("        headers: &[{}],\n", headers.join(", ")));
+                output.push_str(&format!("        response: {:?},\n", stub.response_text()));
+                output.push_str("    },\n");
+            }
+        }
+        output.push_str("];\n\n");
+
+        output.push_str(SERVER);
+
+        Ok(output)
+    }
+
+    fn file_name(&self) -> String {
+        "mock_server.rs".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::captured;
+
+    #[test]
+    fn test_rust_server_stub_table() {
+        let requests = vec![
+            captured("GET", "/api/users/1", "", 200, "{\"id\":1}"),
+            captured(
+                "POST",
+                "/api/users",
+                "{\"name\":\"ann\"}",
+                201,
+                "{\"id\":2}",
+            ),
+        ];
+
+        let output = RustServerGenerator::new().generate(&requests).unwrap();
+        assert!(output.contains("    // GET /api/users/{id}\n"));
+        assert!(output.contains("        pattern: \"/api/users/{id}\",\n"));
+        assert!(output.contains("        target: \"/api/users/1\",\n"));
+        assert!(output.contains("        body: Some(\"{\\\"name\\\":\\\"ann\\\"}\"),\n"));
+        assert!(output.contains("        headers: &[(\"content-type\", \"application/json\")],\n"));
+        assert!(output.contains("        response: \"{\\\"id\\\":2}\",\n"));
+        assert!(output.contains("fn find(method: &str, target: &str, body: &str)"));
+    }
+}
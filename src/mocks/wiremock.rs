@@ -0,0 +1,154 @@
+//! WireMock stub mappings
+//!
+//! Writes a mappings file for `POST /__admin/mappings/import` or the
+//! `mappings/` directory of a standalone WireMock.
+
+use crate::mocks::{MockGenerator, Stub, path_regex, stubs};
+use crate::models::CapturedRequest;
+use anyhow::Result;
+use serde_json::{Map, Value, json};
+
+/// Exact captured requests win over the per-endpoint fallback
+const EXACT_PRIORITY: u32 = 1;
+const FALLBACK_PRIORITY: u32 = 5;
+
+pub struct WireMockGenerator;
+
+impl WireMockGenerator {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl MockGenerator for WireMockGenerator {
+    fn generate(&self, requests: &[CapturedRequest]) -> Result<String> {
+        let mut mappings = Vec::new();
+
+        for (endpoint, stubs) in stubs(requests) {
+            for (i, stub) in stubs.iter().enumerate() {
+                let mut request = Map::new();
+                request.insert("method".into(), json!(stub.method));
+                request.insert("urlPath".into(), json!(stub.path));
+                if !stub.query.is_empty() {
+                    let params: Map<String, Value> = stub
+                        .query
+                        .iter()
+                        .map(|(k, v)| (k.clone(), json!({ "equalTo": v })))
+                        .collect();
+                    request.insert("queryParameters".into(), Value::Object(params));
+                }
+                let body_pattern = match (stub.request_json(), stub.request_text()) {
+                    (Some(body), _) => {
+                        Some(json!({ "equalToJson": body, "ignoreExtraElements": true }))
+                    }
+                    (None, Some(text)) => Some(json!({ "equalTo": text })),
+                    (None, None) => None,
+                };
+                if let Some(pattern) = body_pattern {
+                    request.insert("bodyPatterns".into(), json!([pattern]));
+                }
+
+                mappings.push(json!({
+                    "name": format!("{} #{}", endpoint, i + 1),
+                    "priority": EXACT_PRIORITY,
+                    "request": request,
+                    "response": response(stub),
+                }));
+            }
+
+            let first = &stubs[0];
+            let mut request = Map::new();
+            request.insert("method".into(), json!(first.method));
+            match first.pattern.contains('{') {
+                true => request.insert("urlPathPattern".into(), json!(path_regex(&first.pattern))),
+                false => request.insert("urlPath".into(), json!(first.pattern)),
+            };
+            mappings.push(json!({
+                "name": endpoint,
+                "priority": FALLBACK_PRIORITY,
+                "request": request,
+                "response": response(first),
+            }));
+        }
+
+        Ok(serde_json::to_string_pretty(
+            &json!({ "mappings": mappings }),
+        )?)
+    }
+
+    fn file_name(&self) -> String {
+        "wiremock-mappings.json".to_string()
+    }
+}
+
+fn response(stub: &Stub) -> Value {
+    let mut response = Map::new();
+    response.insert("status".into(), json!(stub.response.status_code));
+
+    let headers: Map<String, Value> = stub
+        .headers()
+        .into_iter()
+        .map(|(k, v)| (k.clone(), json!(v)))
+        .collect();
+    if !headers.is_empty() {
+        response.insert("headers".into(), Value::Object(headers));
+    }
+
+    match stub.response_json() {
+        Some(body) => {
+            response.insert("jsonBody".into(), body);
+        }
+        None if stub.response.body.as_ref().is_some_and(|b| !b.is_empty()) => {
+            response.insert("body".into(), json!(stub.response_text()));
+        }
+        None => {}
+    }
+
+    Value::Object(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::captured;
+
+    #[test]
+    fn test_wiremock_mappings() {
+        let requests = vec![
+            captured("GET", "/api/users/1?full=true", "", 200, "{\"id\":1}"),
+            captured("POST", "/api/users", "{\"name\":\"ann\"}", 201, "created"),
+        ];
+
+        let output = WireMockGenerator::new().generate(&requests).unwrap();
+        let document: Value = serde_json::from_str(&output).unwrap();
+        let mappings = document["mappings"].as_array().unwrap();
+        assert_eq!(mappings.len(), 4);
+
+        let exact = &mappings[0];
+        assert_eq!(exact["priority"], EXACT_PRIORITY);
+        assert_eq!(exact["request"]["urlPath"], "/api/users/1");
+        assert_eq!(
+            exact["request"]["queryParameters"]["full"]["equalTo"],
+            "true"
+        );
+        assert_eq!(exact["response"]["jsonBody"]["id"], 1);
+        assert_eq!(
+            exact["response"]["headers"]["content-type"],
+            "application/json"
+        );
+        assert!(exact["response"]["headers"].get("content-length").is_none());
+
+        let fallback = &mappings[1];
+        assert_eq!(fallback["priority"], FALLBACK_PRIORITY);
+        assert_eq!(fallback["request"]["urlPathPattern"], "/api/users/[0-9]+");
+
+        let post = &mappings[2];
+        assert_eq!(
+            post["request"]["bodyPatterns"][0]["equalToJson"]["name"],
+            "ann"
+        );
+        assert_eq!(post["response"]["status"], 201);
+        assert_eq!(post["response"]["body"], "created");
+        assert_eq!(mappings[3]["request"]["urlPath"], "/api/users");
+    }
+}
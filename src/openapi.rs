@@ -0,0 +1,215 @@
+//! OpenAPI 3 document model
+//!
+//! Covers the parts of the specification used for exporting and validating
+//! captured traffic. Schemas are kept as raw JSON values.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Serialization format of a spec file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpecFormat {
+    Json,
+    Yaml,
+}
+
+impl SpecFormat {
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Self {
+        match path.as_ref().extension().and_then(|e| e.to_str()) {
+            Some("yaml" | "yml") => Self::Yaml,
+            _ => Self::Json,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OpenApi {
+    pub openapi: String,
+    pub info: Info,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub servers: Vec<Server>,
+    #[serde(default)]
+    pub paths: BTreeMap<String, PathItem>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub components: Option<Value>,
+}
+
+impl OpenApi {
+    pub fn parse(data: &str, format: SpecFormat) -> Result<Self> {
+        match format {
+            SpecFormat::Json => serde_json::from_str(data).context("Invalid OpenAPI JSON"),
+            SpecFormat::Yaml => serde_yaml::from_str(data).context("Invalid OpenAPI YAML"),
+        }
+    }
+
+    pub fn to_string(&self, format: SpecFormat) -> Result<String> {
+        Ok(match format {
+            SpecFormat::Json => serde_json::to_string_pretty(self)?,
+            SpecFormat::Yaml => serde_yaml::to_string(self)?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Info {
+    pub title: String,
+    pub version: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Server {
+    pub url: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PathItem {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub parameters: Vec<Parameter>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub get: Option<Operation>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub put: Option<Operation>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub post: Option<Operation>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub delete: Option<Operation>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub options: Option<Operation>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub head: Option<Operation>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub patch: Option<Operation>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trace: Option<Operation>,
+}
+
+impl PathItem {
+    pub fn operation(&self, method: &str) -> Option<&Operation> {
+        match method.to_uppercase().as_str() {
+            "GET" => self.get.as_ref(),
+            "PUT" => self.put.as_ref(),
+            "POST" => self.post.as_ref(),
+            "DELETE" => self.delete.as_ref(),
+            "OPTIONS" => self.options.as_ref(),
+            "HEAD" => self.head.as_ref(),
+            "PATCH" => self.patch.as_ref(),
+            "TRACE" => self.trace.as_ref(),
+            _ => None,
+        }
+    }
+
+    pub fn operation_mut(&mut self, method: &str) -> Option<&mut Option<Operation>> {
+        match method.to_uppercase().as_str() {
+            "GET" => Some(&mut self.get),
+            "PUT" => Some(&mut self.put),
+            "POST" => Some(&mut self.post),
+            "DELETE" => Some(&mut self.delete),
+            "OPTIONS" => Some(&mut self.options),
+            "HEAD" => Some(&mut self.head),
+            "PATCH" => Some(&mut self.patch),
+            "TRACE" => Some(&mut self.trace),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Operation {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub operation_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub summary: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub parameters: Vec<Parameter>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_body: Option<RequestBody>,
+    #[serde(default)]
+    pub responses: BTreeMap<String, Response>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Parameter {
+    #[serde(rename = "$ref", default, skip_serializing_if = "Option::is_none")]
+    pub reference: Option<String>,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub name: String,
+    #[serde(rename = "in", default, skip_serializing_if = "String::is_empty")]
+    pub location: String,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub required: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schema: Option<Value>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RequestBody {
+    #[serde(rename = "$ref", default, skip_serializing_if = "Option::is_none")]
+    pub reference: Option<String>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub required: bool,
+    #[serde(default)]
+    pub content: BTreeMap<String, MediaType>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Response {
+    #[serde(rename = "$ref", default, skip_serializing_if = "Option::is_none")]
+    pub reference: Option<String>,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub content: BTreeMap<String, MediaType>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MediaType {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schema: Option<Value>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spec_format_from_path() {
+        assert_eq!(SpecFormat::from_path("api.yaml"), SpecFormat::Yaml);
+        assert_eq!(SpecFormat::from_path("api.yml"), SpecFormat::Yaml);
+        assert_eq!(SpecFormat::from_path("api.json"), SpecFormat::Json);
+    }
+
+    #[test]
+    fn test_parse_yaml_spec() {
+        let spec = OpenApi::parse(
+            r#"
+openapi: 3.0.3
+info:
+  title: Users
+  version: "1.0"
+paths:
+  /users/{id}:
+    parameters:
+      - name: id
+        in: path
+        required: true
+    get:
+      responses:
+        "200":
+          description: OK
+"#,
+            SpecFormat::Yaml,
+        )
+        .unwrap();
+
+        let item = &spec.paths["/users/{id}"];
+        assert_eq!(item.parameters[0].location, "path");
+        assert!(item.operation("get").is_some());
+        assert!(item.operation("post").is_none());
+    }
+}
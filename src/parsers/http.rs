@@ -1,4 +1,5 @@
 use crate::models::{RequestData, ResponseData};
+use hyper::header::{HeaderName, HeaderValue};
 use hyper::{HeaderMap, Method, Uri};
 use std::collections::HashMap;
 
@@ -42,7 +43,7 @@ impl HttpParser {
             .collect()
     }
 
-    fn parse_query_params(uri: &Uri) -> HashMap<String, String> {
+    pub fn parse_query_params(uri: &Uri) -> HashMap<String, String> {
         uri.query()
             .map(|q| {
                 q.split('&')
@@ -58,6 +59,140 @@ impl HttpParser {
             .unwrap_or_default()
     }
 
+    /// Parse an HTTP/1.x request from raw bytes
+    ///
+    /// Returns the request and the number of bytes it occupied, or `None` if the
+    /// data does not start with a complete request.
+    pub fn parse_raw_request(data: &[u8]) -> Option<(RequestData, usize)> {
+        let (start_line, headers, head_len) = Self::parse_raw_head(data)?;
+        let mut parts = start_line.split_whitespace();
+        let method: Method = parts.next()?.parse().ok()?;
+        let uri: Uri = parts.next()?.parse().ok()?;
+
+        let (body, body_len) = match Self::body_framing(&headers) {
+            BodyFraming::Chunked => Self::decode_chunked(&data[head_len..])?,
+            BodyFraming::Length(len) => (
+                data.get(head_len..head_len.checked_add(len)?)?.to_vec(),
+                len,
+            ),
+            BodyFraming::UntilClose => (Vec::new(), 0),
+        };
+        let body = (!body.is_empty()).then_some(body);
+
+        Some((
+            Self::parse_request(&method, &uri, &headers, body),
+            head_len + body_len,
+        ))
+    }
+
+    /// Parse an HTTP/1.x response from raw bytes
+    ///
+    /// `request_method` is needed because responses to `HEAD` never carry a body.
+    /// Responses without a length are read until the end of `data`.
+    pub fn parse_raw_response(data: &[u8], request_method: &str) -> Option<(ResponseData, usize)> {
+        let (start_line, headers, head_len) = Self::parse_raw_head(data)?;
+        let status_code: u16 = start_line.split_whitespace().nth(1)?.parse().ok()?;
+
+        let bodyless = request_method.eq_ignore_ascii_case("HEAD")
+            || (100..200).contains(&status_code)
+            || status_code == 204
+            || status_code == 304;
+
+        let (body, body_len) = if bodyless {
+            (Vec::new(), 0)
+        } else {
+            match Self::body_framing(&headers) {
+                BodyFraming::Chunked => Self::decode_chunked(&data[head_len..])?,
+                BodyFraming::Length(len) => (
+                    data.get(head_len..head_len.checked_add(len)?)?.to_vec(),
+                    len,
+                ),
+                BodyFraming::UntilClose => (data[head_len..].to_vec(), data.len() - head_len),
+            }
+        };
+        let body = (!body.is_empty()).then_some(body);
+
+        Some((
+            Self::parse_response(status_code, &headers, body),
+            head_len + body_len,
+        ))
+    }
+
+    fn parse_raw_head(data: &[u8]) -> Option<(String, HeaderMap, usize)> {
+        let head_end = data.windows(4).position(|w| w == b"\r\n\r\n")?;
+        let head = std::str::from_utf8(&data[..head_end]).ok()?;
+        let mut lines = head.split("\r\n");
+        let start_line = lines.next()?.to_string();
+
+        let mut headers = HeaderMap::new();
+        for line in lines {
+            if let Some((name, value)) = line.split_once(':')
+                && let (Ok(name), Ok(value)) = (
+                    HeaderName::from_bytes(name.trim().as_bytes()),
+                    HeaderValue::from_str(value.trim()),
+                )
+            {
+                headers.append(name, value);
+            }
+        }
+
+        Some((start_line, headers, head_end + 4))
+    }
+
+    fn body_framing(headers: &HeaderMap) -> BodyFraming {
+        let chunked = headers
+            .get("transfer-encoding")
+            .and_then(|v| v.to_str().ok())
+            .map(|s| s.to_ascii_lowercase().contains("chunked"))
+            .unwrap_or(false);
+
+        if chunked {
+            return BodyFraming::Chunked;
+        }
+
+        match headers
+            .get("content-length")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|s| s.trim().parse().ok())
+        {
+            Some(len) => BodyFraming::Length(len),
+            None => BodyFraming::UntilClose,
+        }
+    }
+
+    fn decode_chunked(data: &[u8]) -> Option<(Vec<u8>, usize)> {
+        let mut body = Vec::new();
+        let mut pos = 0;
+
+        loop {
+            let line_end = pos + data.get(pos..)?.windows(2).position(|w| w == b"\r\n")?;
+            let size_line = std::str::from_utf8(&data[pos..line_end]).ok()?;
+            let size_hex = size_line.split(';').next()?.trim();
+            let size = usize::from_str_radix(size_hex, 16).ok()?;
+            pos = line_end + 2;
+
+            if size == 0 {
+                // Skip trailers up to the terminating empty line
+                loop {
+                    let line_end = pos + data.get(pos..)?.windows(2).position(|w| w == b"\r\n")?;
+                    let empty = line_end == pos;
+                    pos = line_end + 2;
+                    if empty {
+                        return Some((body, pos));
+                    }
+                }
+            }
+
+            // Incomplete framing (data or its CRLF still missing) is not a body yet
+            let data_end = pos.checked_add(size)?;
+            if data.get(data_end..data_end.checked_add(2)?)? != b"\r\n" {
+                return None;
+            }
+            body.extend_from_slice(&data[pos..data_end]);
+            pos = data_end + 2;
+        }
+    }
+
     pub fn is_json_content(headers: &HeaderMap) -> bool {
         headers
             .get("content-type")
@@ -66,12 +201,32 @@ impl HttpParser {
             .unwrap_or(false)
     }
 
+    /// Classify an HTTP method by what re-sending it does
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chaos_testing::parsers::http::{HttpParser, MethodType};
+    ///
+    /// assert_eq!(HttpParser::classify_method("GET"), MethodType::Safe);
+    /// assert_eq!(HttpParser::classify_method("put"), MethodType::Idempotent);
+    /// assert_eq!(HttpParser::classify_method("POST"), MethodType::NonIdempotent);
+    /// ```
+    pub fn classify_method(method: &str) -> MethodType {
+        match method.trim().to_uppercase().as_str() {
+            "GET" | "HEAD" | "OPTIONS" | "TRACE" => MethodType::Safe,
+            "PUT" | "DELETE" => MethodType::Idempotent,
+            "POST" | "PATCH" => MethodType::NonIdempotent,
+            _ => MethodType::Other,
+        }
+    }
+
     pub fn extract_endpoint_pattern(uri: &Uri) -> String {
         let path = uri.path();
 
         path.split('/')
             .map(|segment| {
-                if segment.chars().all(|c| c.is_numeric()) {
+                if !segment.is_empty() && segment.chars().all(|c| c.is_numeric()) {
                     "{id}"
                 } else if segment.len() > 20
                     && segment.chars().all(|c| c.is_alphanumeric() || c == '-')
@@ -85,3 +240,105 @@ impl HttpParser {
             .join("/")
     }
 }
+
+/// HTTP method classification by RFC 9110 semantics
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MethodType {
+    /// Read-only, e.g. GET
+    Safe,
+    /// Sending it twice has the same effect as once, e.g. PUT
+    Idempotent,
+    /// Each send may change state again, e.g. POST
+    NonIdempotent,
+    Other,
+}
+
+enum BodyFraming {
+    Chunked,
+    Length(usize),
+    UntilClose,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_endpoint_pattern() {
+        let pattern = |uri: &str| HttpParser::extract_endpoint_pattern(&uri.parse().unwrap());
+        assert_eq!(pattern("/api/users/42"), "/api/users/{id}");
+        assert_eq!(
+            pattern("/api/orders/3f2b8c1e-9d4a-4b7e-a1c2-5e6f7a8b9c0d?x=1"),
+            "/api/orders/{uuid}"
+        );
+        assert_eq!(pattern("/"), "/");
+        assert_eq!(pattern("/api/users/"), "/api/users/");
+    }
+
+    #[test]
+    fn test_parse_raw_request() {
+        let raw = b"POST /api/orders?dry=1 HTTP/1.1\r\nHost: localhost\r\nContent-Length: 7\r\n\r\n{\"a\":1}GET /next HTTP/1.1\r\n\r\n";
+        let (request, consumed) = HttpParser::parse_raw_request(raw).unwrap();
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.uri, "/api/orders?dry=1");
+        assert_eq!(request.query_params.get("dry"), Some(&"1".to_string()));
+        assert_eq!(request.body.as_deref(), Some(&b"{\"a\":1}"[..]));
+
+        let (next, _) = HttpParser::parse_raw_request(&raw[consumed..]).unwrap();
+        assert_eq!(next.uri, "/next");
+    }
+
+    #[test]
+    fn test_parse_raw_response_chunked() {
+        let raw = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n4\r\nWiki\r\n5\r\npedia\r\n0\r\n\r\n";
+        let (response, consumed) = HttpParser::parse_raw_response(raw, "GET").unwrap();
+        assert_eq!(response.status_code, 200);
+        assert_eq!(response.body.as_deref(), Some(&b"Wikipedia"[..]));
+        assert_eq!(consumed, raw.len());
+    }
+
+    #[test]
+    fn test_parse_raw_response_head_has_no_body() {
+        let raw = b"HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\n";
+        let (response, consumed) = HttpParser::parse_raw_response(raw, "HEAD").unwrap();
+        assert!(response.body.is_none());
+        assert_eq!(consumed, raw.len());
+    }
+
+    #[test]
+    fn test_parse_raw_incomplete() {
+        assert!(HttpParser::parse_raw_request(b"GET / HTTP/1.1\r\nHost: x").is_none());
+        assert!(
+            HttpParser::parse_raw_response(
+                b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nab",
+                "GET"
+            )
+            .is_none()
+        );
+    }
+
+    #[test]
+    fn test_parse_raw_truncated_chunked() {
+        let head = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n".to_vec();
+        for body in [
+            &b"4\r\nWiki"[..],
+            b"4\r\nWi",
+            b"4\r\nWiki\r",
+            b"4\r\nWiki\r\n0\r\n",
+        ] {
+            let raw = [head.clone(), body.to_vec()].concat();
+            assert!(HttpParser::parse_raw_response(&raw, "GET").is_none());
+        }
+        let request =
+            b"POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\nffffffffffffffff\r\nab";
+        assert!(HttpParser::parse_raw_request(request).is_none());
+    }
+
+    #[test]
+    fn test_parse_raw_huge_content_length() {
+        let request = b"POST / HTTP/1.1\r\nContent-Length: 18446744073709551615\r\n\r\nab";
+        assert!(HttpParser::parse_raw_request(request).is_none());
+        let response = b"HTTP/1.1 200 OK\r\nContent-Length: 18446744073709551615\r\n\r\nab";
+        assert!(HttpParser::parse_raw_response(response, "GET").is_none());
+    }
+}
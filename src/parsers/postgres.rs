@@ -55,6 +55,37 @@ impl PostgresParser {
         ))
     }
 
+    /// Length in bytes of the first complete typed message in `data`
+    pub fn message_len(data: &[u8]) -> Option<usize> {
+        if data.len() < 5 {
+            return None;
+        }
+
+        let length = u32::from_be_bytes([data[1], data[2], data[3], data[4]]) as usize;
+        let total = 1 + length;
+        (length >= 4 && data.len() >= total).then_some(total)
+    }
+
+    /// Length of the untyped startup-phase message at the start of a client stream
+    ///
+    /// Covers StartupMessage, SSLRequest, GSSENCRequest and CancelRequest, which are
+    /// the only messages sent without a type byte.
+    pub fn startup_len(data: &[u8]) -> Option<usize> {
+        if data.len() < 8 {
+            return None;
+        }
+
+        let length = u32::from_be_bytes([data[0], data[1], data[2], data[3]]) as usize;
+        let code = u32::from_be_bytes([data[4], data[5], data[6], data[7]]);
+        let known = matches!(code, 196608 | 80877102 | 80877103 | 80877104);
+        (known && length >= 8 && data.len() >= length).then_some(length)
+    }
+
+    /// Check if the startup-phase message is an SSLRequest
+    pub fn is_ssl_request(data: &[u8]) -> bool {
+        data.len() >= 8 && data[4..8] == 80877103u32.to_be_bytes()
+    }
+
     pub fn message_type(data: &[u8]) -> Option<PostgresMessageType> {
         if data.is_empty() {
             return None;
@@ -107,4 +138,18 @@ mod tests {
             Some(PostgresMessageType::Parse)
         );
     }
+
+    #[test]
+    fn test_message_framing() {
+        let mut data = vec![b'Q', 0, 0, 0, 13];
+        data.extend_from_slice(b"SELECT 1\0");
+        data.extend_from_slice(&[b'X', 0, 0, 0, 4]);
+        assert_eq!(PostgresParser::message_len(&data), Some(14));
+        assert_eq!(PostgresParser::message_len(&data[14..]), Some(5));
+        assert_eq!(PostgresParser::message_len(&data[..10]), None);
+
+        let ssl = [0, 0, 0, 8, 4, 210, 22, 47];
+        assert_eq!(PostgresParser::startup_len(&ssl), Some(8));
+        assert!(PostgresParser::is_ssl_request(&ssl));
+    }
 }
@@ -25,6 +25,45 @@ impl RedisParser {
         }
     }
 
+    /// Length in bytes of the first complete RESP value in `data`
+    ///
+    /// Used to split a stream of pipelined commands or replies into frames.
+    pub fn frame_len(data: &[u8]) -> Option<usize> {
+        let line_end = data.windows(2).position(|w| w == b"\r\n")?;
+
+        match *data.first()? {
+            b'+' | b'-' | b':' => Some(line_end + 2),
+            b'$' => {
+                let len: i64 = std::str::from_utf8(data.get(1..line_end)?)
+                    .ok()?
+                    .parse()
+                    .ok()?;
+                if len < 0 {
+                    return Some(line_end + 2);
+                }
+                let end = usize::try_from(len).ok()?.checked_add(line_end + 4)?;
+                (data.len() >= end).then_some(end)
+            }
+            b'*' => {
+                let count: i64 = std::str::from_utf8(data.get(1..line_end)?)
+                    .ok()?
+                    .parse()
+                    .ok()?;
+                let mut pos = line_end + 2;
+                for _ in 0..count.max(0) {
+                    pos += Self::frame_len(data.get(pos..)?)?;
+                }
+                Some(pos)
+            }
+            _ => None,
+        }
+    }
+
+    /// Check if a RESP reply is an error (`-ERR ...`)
+    pub fn is_error_reply(data: &[u8]) -> bool {
+        data.first() == Some(&b'-')
+    }
+
     #[allow(dead_code)]
     fn parse_array(data: &[u8]) -> Option<RedisCommand> {
         let lines: Vec<&[u8]> = data.split(|&b| b == b'\n').collect();
@@ -116,4 +155,18 @@ mod tests {
         assert!(RedisParser::is_read_only("GET"));
         assert!(!RedisParser::is_read_only("SET"));
     }
+
+    #[test]
+    fn test_frame_len() {
+        let pipeline = b"*2\r\n$3\r\nGET\r\n$3\r\nkey\r\n*1\r\n$4\r\nPING\r\n";
+        assert_eq!(RedisParser::frame_len(pipeline), Some(22));
+        assert_eq!(RedisParser::frame_len(&pipeline[22..]), Some(14));
+        assert_eq!(RedisParser::frame_len(b"+OK\r\n"), Some(5));
+        assert_eq!(RedisParser::frame_len(b"$-1\r\n"), Some(5));
+        assert_eq!(RedisParser::frame_len(b"$5\r\nhel"), None);
+        assert_eq!(RedisParser::frame_len(b"$9223372036854775807\r\nhel"), None);
+        assert_eq!(RedisParser::frame_len(b"*3\r\n+OK\r\n"), None);
+        assert_eq!(RedisParser::frame_len(b"\r\n"), None);
+        assert!(RedisParser::is_error_reply(b"-ERR unknown\r\n"));
+    }
 }
@@ -0,0 +1,775 @@
+//! Sensitive data redaction
+//!
+//! Secrets are replaced by a placeholder naming the environment variable that
+//! should supply them, e.g. `__REDACTED_AUTHORIZATION__`. Generators turn
+//! placeholders back into environment lookups, so tests run against real
+//! credentials without the capture or the generated code containing them.
+
+use crate::generators::flow::Key;
+use crate::models::CapturedRequest;
+use anyhow::{Context, Result};
+use regex::Regex;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::LazyLock;
+
+const PREFIX: &str = "__REDACTED_";
+
+/// Headers whose whole value is a credential
+const SENSITIVE_HEADERS: [&str; 7] = [
+    "authorization",
+    "proxy-authorization",
+    "cookie",
+    "set-cookie",
+    "x-api-key",
+    "x-auth-token",
+    "x-csrf-token",
+];
+
+/// Placeholder standing in for the value of environment variable `env`
+pub fn placeholder(env: &str) -> String {
+    format!("{}{}__", PREFIX, env)
+}
+
+/// Literal text or the name of the environment variable a placeholder refers to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Segment<'a> {
+    Text(&'a str),
+    Env(&'a str),
+}
+
+/// Split a value into literal text and placeholders
+pub fn split(value: &str) -> Vec<Segment<'_>> {
+    static PLACEHOLDER: LazyLock<Regex> = LazyLock::new(|| {
+        Regex::new(r"__REDACTED_([A-Z0-9]+(?:_[A-Z0-9]+)*)__").expect("valid placeholder regex")
+    });
+
+    let mut segments = Vec::new();
+    let mut last = 0;
+    for caps in PLACEHOLDER.captures_iter(value) {
+        let whole = caps.get(0).expect("match");
+        if whole.start() > last {
+            segments.push(Segment::Text(&value[last..whole.start()]));
+        }
+        segments.push(Segment::Env(caps.get(1).expect("name").as_str()));
+        last = whole.end();
+    }
+    if last < value.len() {
+        segments.push(Segment::Text(&value[last..]));
+    }
+    segments
+}
+
+/// Whether a value holds any placeholder
+pub fn is_redacted(value: &str) -> bool {
+    split(value).iter().any(|s| matches!(s, Segment::Env(_)))
+}
+
+/// Placeholders replaced by their environment variable, `{TEST_ENV}_NAME`
+/// over `NAME` like generated suites; unset variables stay placeholders
+pub fn resolve(value: &str) -> String {
+    let prefix = std::env::var("TEST_ENV").unwrap_or_default().to_uppercase();
+    resolve_with(value, |name| {
+        std::env::var(format!("{}_{}", prefix, name))
+            .or_else(|_| std::env::var(name))
+            .ok()
+    })
+}
+
+fn resolve_with(value: &str, lookup: impl Fn(&str) -> Option<String>) -> String {
+    split(value)
+        .into_iter()
+        .map(|segment| match segment {
+            Segment::Text(text) => text.to_string(),
+            Segment::Env(name) => lookup(name).unwrap_or_else(|| placeholder(name)),
+        })
+        .collect()
+}
+
+/// `x-api-key` -> `X_API_KEY`, `accessToken` -> `ACCESS_TOKEN`
+pub fn env_name(name: &str) -> String {
+    let mut out = String::new();
+    let mut previous_lower = false;
+    for c in name.chars() {
+        if c.is_ascii_uppercase() && previous_lower {
+            out.push('_');
+        }
+        previous_lower = c.is_ascii_lowercase() || c.is_ascii_digit();
+        if c.is_ascii_alphanumeric() {
+            out.push(c.to_ascii_uppercase());
+        } else if !out.is_empty() && !out.ends_with('_') {
+            out.push('_');
+        }
+    }
+    let out = out.trim_end_matches('_').to_string();
+
+    match out.chars().next() {
+        None => "SECRET".to_string(),
+        Some(c) if c.is_ascii_digit() => format!("SECRET_{}", out),
+        Some(_) => out,
+    }
+}
+
+/// JSON keys and query parameters holding credentials
+///
+/// Names are compared word by word, so `client_secret` and `accessToken`
+/// are sensitive but `max_tokens`, `token_count` and `tokenizer` are not.
+fn is_sensitive_field(name: &str) -> bool {
+    let mut words = vec![String::new()];
+    let mut previous_lower = false;
+    for c in name.chars() {
+        if !c.is_ascii_alphanumeric() || (c.is_ascii_uppercase() && previous_lower) {
+            words.push(String::new());
+        }
+        previous_lower = c.is_ascii_lowercase() || c.is_ascii_digit();
+        if let Some(word) = words.last_mut().filter(|_| c.is_ascii_alphanumeric()) {
+            word.push(c.to_ascii_lowercase());
+        }
+    }
+    words.retain(|w| !w.is_empty());
+    let compact = words.concat();
+    words
+        .iter()
+        .any(|w| matches!(w.as_str(), "password" | "passwd" | "secret"))
+        || words.last().is_some_and(|w| w == "token")
+        || compact == "apikey"
+        || compact == "authorization"
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum PathStep {
+    Field(String),
+    Index(usize),
+    Wildcard,
+    /// `..name`: the field at any depth
+    Descend(String),
+}
+
+/// A JSON path such as `$.user.ssn`, `$.cards[*].number` or `$..password`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JsonPath(Vec<PathStep>);
+
+impl JsonPath {
+    pub fn parse(path: &str) -> Result<Self> {
+        let mut rest = path
+            .trim()
+            .strip_prefix('$')
+            .with_context(|| format!("JSON path must start with `$`: {}", path))?;
+        let mut steps = Vec::new();
+
+        let field_len = |s: &str| s.find(['.', '[']).unwrap_or(s.len());
+
+        while !rest.is_empty() {
+            if let Some(after) = rest.strip_prefix("..") {
+                let len = field_len(after);
+                anyhow::ensure!(len > 0, "Missing field after `..` in JSON path: {}", path);
+                steps.push(PathStep::Descend(after[..len].to_string()));
+                rest = &after[len..];
+            } else if let Some(after) = rest.strip_prefix('.') {
+                let len = field_len(after);
+                anyhow::ensure!(len > 0, "Empty field in JSON path: {}", path);
+                steps.push(match &after[..len] {
+                    "*" => PathStep::Wildcard,
+                    name => PathStep::Field(name.to_string()),
+                });
+                rest = &after[len..];
+            } else if let Some(after) = rest.strip_prefix('[') {
+                let end = after
+                    .find(']')
+                    .with_context(|| format!("Unclosed `[` in JSON path: {}", path))?;
+                let inner = after[..end].trim();
+                steps.push(if inner == "*" {
+                    PathStep::Wildcard
+                } else if let Ok(index) = inner.parse() {
+                    PathStep::Index(index)
+                } else {
+                    let name = inner
+                        .strip_prefix(['\'', '"'])
+                        .and_then(|s| s.strip_suffix(['\'', '"']))
+                        .with_context(|| format!("Invalid selector `[{}]` in {}", inner, path))?;
+                    PathStep::Field(name.to_string())
+                });
+                rest = &after[end + 1..];
+            } else {
+                anyhow::bail!("Unexpected `{}` in JSON path: {}", rest, path);
+            }
+        }
+
+        anyhow::ensure!(
+            !steps.is_empty(),
+            "JSON path selects the whole body: {}",
+            path
+        );
+        Ok(Self(steps))
+    }
+
+    /// Whether the value at `path` is selected by this path or lies inside
+    /// a selected value
+    pub fn covers(&self, path: &[Key]) -> bool {
+        covers(&self.0, path)
+    }
+
+    /// Replace every selected value; returns how many were replaced
+    fn replace(&self, value: &mut Value, with: &str) -> usize {
+        replace_at(value, &self.0, with)
+    }
+}
+
+fn covers(steps: &[PathStep], path: &[Key]) -> bool {
+    let Some((step, rest)) = steps.split_first() else {
+        return true;
+    };
+
+    match (step, path.split_first()) {
+        (_, None) => false,
+        (PathStep::Field(name), Some((Key::Field(key), tail))) => name == key && covers(rest, tail),
+        (PathStep::Index(i), Some((Key::Index(index), tail))) => i == index && covers(rest, tail),
+        (PathStep::Wildcard, Some((_, tail))) => covers(rest, tail),
+        (PathStep::Descend(name), Some((key, tail))) => {
+            matches!(key, Key::Field(key) if key == name) && covers(rest, tail)
+                || covers(steps, tail)
+        }
+        _ => false,
+    }
+}
+
+fn replace_at(value: &mut Value, steps: &[PathStep], with: &str) -> usize {
+    let Some((step, rest)) = steps.split_first() else {
+        return match value {
+            Value::String(s) if s == with => 0,
+            Value::Object(_) | Value::Array(_) | Value::Null => 0,
+            _ => {
+                *value = Value::String(with.to_string());
+                1
+            }
+        };
+    };
+
+    match (step, value) {
+        (PathStep::Field(name), Value::Object(map)) => map
+            .get_mut(name)
+            .map(|child| replace_at(child, rest, with))
+            .unwrap_or(0),
+        (PathStep::Index(i), Value::Array(items)) => items
+            .get_mut(*i)
+            .map(|child| replace_at(child, rest, with))
+            .unwrap_or(0),
+        (PathStep::Wildcard, Value::Object(map)) => map
+            .values_mut()
+            .map(|child| replace_at(child, rest, with))
+            .sum(),
+        (PathStep::Wildcard, Value::Array(items)) => items
+            .iter_mut()
+            .map(|child| replace_at(child, rest, with))
+            .sum(),
+        (PathStep::Descend(name), value @ (Value::Object(_) | Value::Array(_))) => {
+            let mut count = 0;
+            if let Value::Object(map) = &mut *value
+                && let Some(child) = map.get_mut(name)
+            {
+                count += replace_at(child, rest, with);
+            }
+            let children: Vec<&mut Value> = match value {
+                Value::Object(map) => map.values_mut().collect(),
+                Value::Array(items) => items.iter_mut().collect(),
+                _ => Vec::new(),
+            };
+            for child in children {
+                count += replace_at(child, steps, with);
+            }
+            count
+        }
+        _ => 0,
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Pattern {
+    env: String,
+    regex: Regex,
+    /// Extra check on a match, e.g. the Luhn checksum for card numbers
+    valid: fn(&str) -> bool,
+    /// Only applied to body values, not to URIs or headers
+    bodies_only: bool,
+}
+
+/// Replaces secrets in captured requests with environment placeholders
+#[derive(Debug, Clone)]
+pub struct Redactor {
+    headers: HashMap<String, String>,
+    fields: bool,
+    patterns: Vec<Pattern>,
+    paths: Vec<(JsonPath, String)>,
+}
+
+impl Default for Redactor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Redactor {
+    /// Redactor with the built-in rules: credential headers, token and
+    /// password fields, card numbers and e-mail addresses
+    pub fn new() -> Self {
+        let headers = SENSITIVE_HEADERS
+            .iter()
+            .map(|h| (h.to_string(), env_name(h)))
+            .collect();
+
+        let patterns = vec![
+            Pattern {
+                env: "CARD_NUMBER".to_string(),
+                regex: Regex::new(r"\b\d(?:[ -]?\d){12,18}\b").expect("valid card regex"),
+                valid: luhn,
+                // Long numeric ids in paths pass the Luhn check by chance
+                bodies_only: true,
+            },
+            Pattern {
+                env: "EMAIL".to_string(),
+                regex: Regex::new(
+                    r"[A-Za-z0-9._%+-]+@[A-Za-z0-9-]+(?:\.[A-Za-z0-9-]+)*\.[A-Za-z]{2,}",
+                )
+                .expect("valid e-mail regex"),
+                valid: |_| true,
+                bodies_only: false,
+            },
+        ];
+
+        Self {
+            headers,
+            fields: true,
+            patterns,
+            paths: Vec::new(),
+        }
+    }
+
+    /// Redactor without any rules, for adding only user-defined ones
+    pub fn empty() -> Self {
+        Self {
+            headers: HashMap::new(),
+            fields: false,
+            patterns: Vec::new(),
+            paths: Vec::new(),
+        }
+    }
+
+    /// Redact every match of `pattern` as environment variable `env`
+    pub fn with_regex(mut self, env: &str, pattern: &str) -> Result<Self> {
+        let regex =
+            Regex::new(pattern).with_context(|| format!("Invalid redaction regex: {}", pattern))?;
+        self.patterns.push(Pattern {
+            env: env_name(env),
+            regex,
+            valid: |_| true,
+            bodies_only: false,
+        });
+        Ok(self)
+    }
+
+    /// Redact the JSON body values selected by `path` as environment
+    /// variable `env`
+    pub fn with_json_path(mut self, env: &str, path: &str) -> Result<Self> {
+        self.paths.push((JsonPath::parse(path)?, env_name(env)));
+        Ok(self)
+    }
+
+    /// Redact a request and its response in place; returns how many values
+    /// were replaced
+    pub fn redact(&self, captured: &mut CapturedRequest) -> usize {
+        let mut count = 0;
+
+        let request = &mut captured.request;
+        count += self.redact_headers(&mut request.headers);
+        count += self.redact_uri(&mut request.uri);
+        for (name, value) in request.query_params.iter_mut() {
+            count += self.redact_query_value(name, value);
+        }
+        if let Some(body) = request.body.as_mut() {
+            count += self.redact_body(body);
+        }
+
+        if let Some(response) = captured.response.as_mut() {
+            count += self.redact_headers(&mut response.headers);
+            if let Some(body) = response.body.as_mut() {
+                count += self.redact_body(body);
+            }
+        }
+
+        count
+    }
+
+    pub fn redact_all(&self, captured: &mut [CapturedRequest]) -> usize {
+        captured.iter_mut().map(|c| self.redact(c)).sum()
+    }
+
+    fn redact_headers(&self, headers: &mut HashMap<String, String>) -> usize {
+        let mut count = 0;
+        for (name, value) in headers.iter_mut() {
+            if let Some(env) = self.headers.get(&name.to_lowercase()) {
+                let replacement = placeholder(env);
+                if *value != replacement {
+                    *value = replacement;
+                    count += 1;
+                }
+            } else {
+                count += self.redact_text(value, false);
+            }
+        }
+        count
+    }
+
+    fn redact_uri(&self, uri: &mut String) -> usize {
+        let (path, query) = match uri.split_once('?') {
+            Some((path, query)) => (path.to_string(), Some(query.to_string())),
+            None => (uri.clone(), None),
+        };
+
+        let mut path = path;
+        let mut count = self.redact_text(&mut path, false);
+
+        let query = query.map(|query| {
+            query
+                .split('&')
+                .map(|pair| match pair.split_once('=') {
+                    Some((name, value)) => {
+                        let mut value = value.to_string();
+                        count += self.redact_query_value(name, &mut value);
+                        format!("{}={}", name, value)
+                    }
+                    None => pair.to_string(),
+                })
+                .collect::<Vec<_>>()
+                .join("&")
+        });
+
+        if count > 0 {
+            *uri = match query {
+                Some(query) => format!("{}?{}", path, query),
+                None => path,
+            };
+        }
+        count
+    }
+
+    fn redact_query_value(&self, name: &str, value: &mut String) -> usize {
+        if self.fields && is_sensitive_field(name) {
+            let replacement = placeholder(&env_name(name));
+            if *value != replacement {
+                *value = replacement;
+                return 1;
+            }
+            return 0;
+        }
+        self.redact_text(value, false)
+    }
+
+    fn redact_body(&self, body: &mut Vec<u8>) -> usize {
+        if let Ok(mut value) = serde_json::from_slice::<Value>(body) {
+            let mut count = 0;
+            for (path, env) in &self.paths {
+                count += path.replace(&mut value, &placeholder(env));
+            }
+            count += self.redact_json(&mut value);
+            if count > 0 {
+                *body = serde_json::to_vec(&value).unwrap_or_default();
+            }
+            return count;
+        }
+
+        // Binary bodies are left alone
+        let Ok(text) = std::str::from_utf8(body) else {
+            return 0;
+        };
+        let mut text = text.to_string();
+        let count = self.redact_text(&mut text, true);
+        if count > 0 {
+            *body = text.into_bytes();
+        }
+        count
+    }
+
+    fn redact_json(&self, value: &mut Value) -> usize {
+        match value {
+            Value::Object(map) => map
+                .iter_mut()
+                .map(|(key, child)| {
+                    // Other types are left as they are rather than retyped
+                    if self.fields && child.is_string() && is_sensitive_field(key) {
+                        let replacement = placeholder(&env_name(key));
+                        if child.as_str() == Some(replacement.as_str()) {
+                            return 0;
+                        }
+                        *child = Value::String(replacement);
+                        return 1;
+                    }
+                    self.redact_json(child)
+                })
+                .sum(),
+            Value::Array(items) => items.iter_mut().map(|child| self.redact_json(child)).sum(),
+            Value::String(s) => self.redact_text(s, true),
+            _ => 0,
+        }
+    }
+
+    /// Apply the regex rules to free text
+    fn redact_text(&self, text: &mut String, body: bool) -> usize {
+        let mut count = 0;
+        for pattern in self.patterns.iter().filter(|p| body || !p.bodies_only) {
+            let replacement = placeholder(&pattern.env);
+            let mut matched = 0;
+            let redacted = pattern.regex.replace_all(text, |caps: &regex::Captures| {
+                let found = &caps[0];
+                if found.contains(PREFIX) || !(pattern.valid)(found) {
+                    return found.to_string();
+                }
+                matched += 1;
+                replacement.clone()
+            });
+            if matched > 0 {
+                *text = redacted.into_owned();
+                count += matched;
+            }
+        }
+        count
+    }
+}
+
+/// Luhn checksum, to tell card numbers from other long digit runs
+fn luhn(candidate: &str) -> bool {
+    let digits: Vec<u32> = candidate.chars().filter_map(|c| c.to_digit(10)).collect();
+    if !(13..=19).contains(&digits.len()) {
+        return false;
+    }
+
+    let sum: u32 = digits
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, &d)| match i % 2 {
+            1 if d * 2 > 9 => d * 2 - 9,
+            1 => d * 2,
+            _ => d,
+        })
+        .sum();
+    sum.is_multiple_of(10)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A login with an Authorization header, answered with a cookie
+    fn captured(uri: &str, body: &str, response: &str) -> CapturedRequest {
+        let mut captured = crate::fixtures::captured("POST", uri, body, 200, response);
+        captured.request.headers = HashMap::from([
+            ("Authorization".to_string(), "Bearer s3cr3t".to_string()),
+            ("accept".to_string(), "application/json".to_string()),
+        ]);
+        if let Some(response) = captured.response.as_mut() {
+            response.headers.insert(
+                "set-cookie".to_string(),
+                "session=abc; HttpOnly".to_string(),
+            );
+        }
+        captured
+    }
+
+    fn json(body: &Option<Vec<u8>>) -> Value {
+        serde_json::from_slice(body.as_deref().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_builtin_rules() {
+        let mut req = captured(
+            "/api/login?api_key=k123&page=2",
+            r#"{"user":"ada@example.com","password":"hunter2","card":"4111 1111 1111 1111"}"#,
+            r#"{"access_token":"abc.def","order":"1234567890123"}"#,
+        );
+        let count = Redactor::new().redact(&mut req);
+
+        // The API key counts in the URI and in the parsed query parameters
+        assert_eq!(count, 8);
+        assert_eq!(
+            req.request.headers["Authorization"],
+            "__REDACTED_AUTHORIZATION__"
+        );
+        assert_eq!(req.request.headers["accept"], "application/json");
+        assert_eq!(
+            req.request.uri,
+            "/api/login?api_key=__REDACTED_API_KEY__&page=2"
+        );
+
+        let body = json(&req.request.body);
+        assert_eq!(body["user"], "__REDACTED_EMAIL__");
+        assert_eq!(body["password"], "__REDACTED_PASSWORD__");
+        assert_eq!(body["card"], "__REDACTED_CARD_NUMBER__");
+
+        let response = req.response.as_ref().unwrap();
+        assert_eq!(response.headers["set-cookie"], "__REDACTED_SET_COOKIE__");
+        let body = json(&response.body);
+        assert_eq!(body["access_token"], "__REDACTED_ACCESS_TOKEN__");
+        // Fails the Luhn check, so it is not a card number
+        assert_eq!(body["order"], "1234567890123");
+    }
+
+    #[test]
+    fn test_sensitive_fields() {
+        for name in [
+            "password",
+            "newPassword",
+            "client_secret",
+            "token",
+            "access_token",
+            "refreshToken",
+            "id-token",
+            "api_key",
+            "Authorization",
+        ] {
+            assert!(is_sensitive_field(name), "{}", name);
+        }
+        for name in [
+            "max_tokens",
+            "token_count",
+            "tokenizer",
+            "secretary",
+            "",
+            "1",
+        ] {
+            assert!(!is_sensitive_field(name), "{}", name);
+        }
+
+        let mut req = captured(
+            "/orders/4111111111111111",
+            r#"{"max_tokens":256,"token_count":"12","access_token":42,"refresh_token":"r-1"}"#,
+            "{}",
+        );
+        Redactor::new().redact(&mut req);
+        // Card numbers are only looked for in bodies
+        assert_eq!(req.request.uri, "/orders/4111111111111111");
+        let body = json(&req.request.body);
+        assert_eq!(body["max_tokens"], 256);
+        assert_eq!(body["token_count"], "12");
+        assert_eq!(body["access_token"], 42);
+        assert_eq!(body["refresh_token"], "__REDACTED_REFRESH_TOKEN__");
+    }
+
+    #[test]
+    fn test_redaction_is_idempotent() {
+        let mut req = captured("/api/users", r#"{"password":"x"}"#, "{}");
+        let redactor = Redactor::new();
+
+        assert!(redactor.redact(&mut req) > 0);
+        let once = req.clone();
+        assert_eq!(redactor.redact(&mut req), 0);
+        assert_eq!(req.request.headers, once.request.headers);
+        assert_eq!(req.request.body, once.request.body);
+    }
+
+    #[test]
+    fn test_user_rules() {
+        let mut req = captured(
+            "/api/people/123-45-6789",
+            r#"{"people":[{"ssn":"123-45-6789","name":"ada"},{"ssn":"987-65-4321"}],"meta":{"ssn":1}}"#,
+            "plain text 555-12-3456",
+        );
+        let redactor = Redactor::empty()
+            .with_regex("ssn", r"\d{3}-\d{2}-\d{4}")
+            .unwrap()
+            .with_json_path("person-name", "$.people[*].name")
+            .unwrap();
+
+        redactor.redact(&mut req);
+
+        assert_eq!(req.request.headers["Authorization"], "Bearer s3cr3t");
+        assert_eq!(req.request.uri, "/api/people/__REDACTED_SSN__");
+        let body = json(&req.request.body);
+        assert_eq!(body["people"][0]["ssn"], "__REDACTED_SSN__");
+        assert_eq!(body["people"][0]["name"], "__REDACTED_PERSON_NAME__");
+        assert_eq!(body["people"][1]["ssn"], "__REDACTED_SSN__");
+        assert_eq!(body["meta"]["ssn"], 1);
+        assert_eq!(
+            req.response.as_ref().unwrap().body.as_deref(),
+            Some(&b"plain text __REDACTED_SSN__"[..])
+        );
+    }
+
+    #[test]
+    fn test_json_path() {
+        let mut value: Value = serde_json::from_str(
+            r#"{"a":{"password":1,"b":[{"password":2},{"c":{"password":3}}]},"x":[10,20]}"#,
+        )
+        .unwrap();
+
+        let count = JsonPath::parse("$..password")
+            .unwrap()
+            .replace(&mut value, "P");
+        assert_eq!(count, 3);
+        assert_eq!(value["a"]["b"][1]["c"]["password"], "P");
+
+        let count = JsonPath::parse("$['x'][1]")
+            .unwrap()
+            .replace(&mut value, "X");
+        assert_eq!(count, 1);
+        assert_eq!(value["x"], serde_json::json!([10, "X"]));
+
+        assert!(JsonPath::parse("a.b").is_err());
+        assert!(JsonPath::parse("$.a[").is_err());
+        assert!(JsonPath::parse("$").is_err());
+    }
+
+    #[test]
+    fn test_json_path_covers() {
+        let field = |name: &str| Key::Field(name.to_string());
+        let path = [field("items"), Key::Index(2), field("token")];
+
+        assert!(JsonPath::parse("$.items[2].token").unwrap().covers(&path));
+        assert!(JsonPath::parse("$.items[*]").unwrap().covers(&path));
+        assert!(JsonPath::parse("$.items").unwrap().covers(&path));
+        assert!(JsonPath::parse("$..token").unwrap().covers(&path));
+        assert!(!JsonPath::parse("$.items[1].token").unwrap().covers(&path));
+        assert!(!JsonPath::parse("$..id").unwrap().covers(&path));
+        assert!(!JsonPath::parse("$.items[2].token.x").unwrap().covers(&path));
+    }
+
+    #[test]
+    fn test_resolve_placeholders() {
+        let lookup = |name: &str| (name == "TOKEN").then(|| "abc".to_string());
+        assert_eq!(
+            resolve_with("Bearer __REDACTED_TOKEN__", lookup),
+            "Bearer abc"
+        );
+        assert_eq!(
+            resolve_with("__REDACTED_OTHER__", lookup),
+            "__REDACTED_OTHER__"
+        );
+    }
+
+    #[test]
+    fn test_split_placeholders() {
+        assert_eq!(
+            split("Bearer __REDACTED_TOKEN__!"),
+            vec![
+                Segment::Text("Bearer "),
+                Segment::Env("TOKEN"),
+                Segment::Text("!")
+            ]
+        );
+        assert_eq!(
+            split("__REDACTED_X_API_KEY__"),
+            vec![Segment::Env("X_API_KEY")]
+        );
+        assert_eq!(
+            split("__REDACTED_lower__ ok"),
+            vec![Segment::Text("__REDACTED_lower__ ok")]
+        );
+        assert!(!is_redacted("plain"));
+    }
+
+    #[test]
+    fn test_env_name() {
+        assert_eq!(env_name("x-api-key"), "X_API_KEY");
+        assert_eq!(env_name("accessToken"), "ACCESS_TOKEN");
+        assert_eq!(env_name("2fa"), "SECRET_2FA");
+    }
+}
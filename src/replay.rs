@@ -0,0 +1,461 @@
+//! Answering requests from captured traffic
+//!
+//! `serve` turns a capture into a hermetic fake backend. Each incoming
+//! request is matched to the most similar captured request with the same
+//! method and endpoint pattern, and gets that request's recorded response.
+
+use crate::mocks;
+use crate::models::{CapturedRequest, Protocol, RequestData, ResponseData};
+use crate::parsers::http::HttpParser;
+use anyhow::Result;
+use hyper::Uri;
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, RwLock};
+use std::time::Duration;
+
+/// How recordings answer requests
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReplayMode {
+    /// Every request gets the most similar recording (`serve`)
+    Nearest,
+    /// Identical requests are replayed, others forwarded and recorded
+    Vcr,
+    /// Identical requests are replayed, others are errors
+    ReplayOnly,
+}
+
+impl ReplayMode {
+    /// The `observe --mode` value, or `None` for plain recording
+    pub fn from_observe(mode: &str) -> Result<Option<Self>> {
+        match mode {
+            "record" => Ok(None),
+            "vcr" => Ok(Some(Self::Vcr)),
+            "replay-only" => Ok(Some(Self::ReplayOnly)),
+            other => anyhow::bail!(
+                "Unsupported mode: {} (expected record, vcr or replay-only)",
+                other
+            ),
+        }
+    }
+}
+
+/// A captured request indexed for matching
+struct Entry {
+    method: String,
+    pattern: String,
+    path: String,
+    query: BTreeSet<(String, String)>,
+    request: CapturedRequest,
+}
+
+impl Entry {
+    /// Answered HTTP requests only
+    fn new(request: CapturedRequest) -> Option<Self> {
+        if !matches!(request.protocol, Protocol::Http | Protocol::Https)
+            || request.response.is_none()
+        {
+            return None;
+        }
+        let uri = request.request.uri.parse::<Uri>().ok()?;
+        let (path, query) = split_target(&uri);
+
+        Some(Self {
+            method: request.request.method.to_uppercase(),
+            pattern: HttpParser::extract_endpoint_pattern(&uri),
+            path,
+            query,
+            request,
+        })
+    }
+
+    fn body(&self) -> &[u8] {
+        self.request.request.body.as_deref().unwrap_or_default()
+    }
+}
+
+pub struct Replayer {
+    mode: ReplayMode,
+    entries: RwLock<Vec<Entry>>,
+    latency: bool,
+    served: AtomicUsize,
+    recorded: AtomicUsize,
+    unmatched: Mutex<BTreeMap<String, usize>>,
+}
+
+impl Replayer {
+    /// Index every answered HTTP request in capture order
+    pub fn new(requests: Vec<CapturedRequest>) -> Self {
+        Self {
+            mode: ReplayMode::Nearest,
+            entries: RwLock::new(requests.into_iter().filter_map(Entry::new).collect()),
+            latency: false,
+            served: AtomicUsize::new(0),
+            recorded: AtomicUsize::new(0),
+            unmatched: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    pub fn with_mode(mut self, mode: ReplayMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Delay each response by the time the original took
+    pub fn with_latency(mut self, latency: bool) -> Self {
+        self.latency = latency;
+        self
+    }
+
+    pub fn mode(&self) -> ReplayMode {
+        self.mode
+    }
+
+    /// Number of captured responses available
+    pub fn len(&self) -> usize {
+        self.entries.read().unwrap().len()
+    }
+
+    /// The captured request most similar to this one
+    ///
+    /// Candidates share the method and endpoint pattern. An identical path
+    /// counts most, then the overlap of query parameters and of the body
+    /// (JSON bodies field by field). Ties go to the earliest capture.
+    pub fn find(&self, method: &str, uri: &Uri, body: &[u8]) -> Option<CapturedRequest> {
+        let method = method.to_uppercase();
+        let pattern = HttpParser::extract_endpoint_pattern(uri);
+        let (path, query) = split_target(uri);
+
+        let entries = self.entries.read().unwrap();
+        let mut best: Option<(f64, &Entry)> = None;
+        for entry in entries.iter() {
+            if entry.method != method || entry.pattern != pattern {
+                continue;
+            }
+
+            let score = if entry.path == path { 2.0 } else { 0.0 }
+                + jaccard(&entry.query, &query)
+                + body_similarity(entry.body(), body);
+
+            if best.is_none_or(|(top, _)| score > top) {
+                best = Some((score, entry));
+            }
+        }
+
+        best.map(|(_, entry)| entry.request.clone())
+    }
+
+    /// The earliest recording of this exact request: same method, path,
+    /// query parameters in any order and body (JSON compared by value)
+    ///
+    /// `request` should be redacted like the recordings were, so secrets
+    /// that were replaced before storing still match. Requests differing
+    /// only in redacted values, such as two users' tokens or passwords,
+    /// therefore share a recording.
+    pub fn find_exact(&self, request: &RequestData) -> Option<CapturedRequest> {
+        let uri = request.uri.parse::<Uri>().ok()?;
+        let method = request.method.to_uppercase();
+        let (path, query) = split_target(&uri);
+        let body = request.body.as_deref().unwrap_or_default();
+
+        self.entries
+            .read()
+            .unwrap()
+            .iter()
+            .find(|entry| {
+                entry.method == method
+                    && entry.path == path
+                    && entry.query == query
+                    && same_body(entry.body(), body)
+            })
+            .map(|entry| entry.request.clone())
+    }
+
+    /// Make a new recording available to later requests
+    pub fn record(&self, captured: CapturedRequest) {
+        if let Some(entry) = Entry::new(captured) {
+            self.recorded.fetch_add(1, Ordering::Relaxed);
+            self.entries.write().unwrap().push(entry);
+        }
+    }
+
+    /// Response to send for a match, and how long to wait before sending it
+    pub fn answer<'a>(&self, captured: &'a CapturedRequest) -> (&'a ResponseData, Duration) {
+        self.served.fetch_add(1, Ordering::Relaxed);
+
+        let delay = match self.latency {
+            true => Duration::from_millis(captured.duration_ms.unwrap_or(0)),
+            false => Duration::ZERO,
+        };
+        let response = captured
+            .response
+            .as_ref()
+            .expect("only answered requests are indexed");
+        (response, delay)
+    }
+
+    pub fn record_unmatched(&self, method: &str, uri: &Uri) {
+        let mut unmatched = self.unmatched.lock().unwrap();
+        *unmatched.entry(format!("{} {}", method, uri)).or_default() += 1;
+    }
+
+    pub fn report(&self) -> ReplayReport {
+        ReplayReport {
+            served: self.served.load(Ordering::Relaxed),
+            recorded: self.recorded.load(Ordering::Relaxed),
+            unmatched: self
+                .unmatched
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|(request, count)| (request.clone(), *count))
+                .collect(),
+        }
+    }
+}
+
+/// Response headers to replay, without the ones describing the original
+/// connection
+pub fn replay_headers(response: &ResponseData) -> Vec<(&String, &String)> {
+    response
+        .headers
+        .iter()
+        .filter(|(key, _)| !mocks::HOP_HEADERS.contains(&key.to_lowercase().as_str()))
+        .collect()
+}
+
+#[derive(Debug, Default)]
+pub struct ReplayReport {
+    pub served: usize,
+    /// New recordings made in VCR mode
+    pub recorded: usize,
+    /// Requests nothing was captured for, with how often each arrived
+    pub unmatched: Vec<(String, usize)>,
+}
+
+impl ReplayReport {
+    pub fn print(&self) {
+        let unmatched: usize = self.unmatched.iter().map(|(_, count)| count).sum();
+
+        println!("\n=== Replay Report ===\n");
+        println!("Served: {}", self.served);
+        if self.recorded > 0 {
+            println!("Recorded: {}", self.recorded);
+        }
+        println!("Unmatched: {}", unmatched);
+
+        if !self.unmatched.is_empty() {
+            println!("\nUnmatched requests:");
+            for (request, count) in &self.unmatched {
+                println!("  {} (x{})", request, count);
+            }
+        }
+
+        println!();
+    }
+}
+
+fn split_target(uri: &Uri) -> (String, BTreeSet<(String, String)>) {
+    let query = uri
+        .query()
+        .map(|q| mocks::parse_query(q).into_iter().collect())
+        .unwrap_or_default();
+    (uri.path().to_string(), query)
+}
+
+/// Share of items the two sets have in common; two empty sets are equal
+fn jaccard<T: Ord>(a: &BTreeSet<T>, b: &BTreeSet<T>) -> f64 {
+    if a.is_empty() && b.is_empty() {
+        return 1.0;
+    }
+    a.intersection(b).count() as f64 / a.union(b).count() as f64
+}
+
+/// Identical bytes, or JSON documents with equal values
+fn same_body(a: &[u8], b: &[u8]) -> bool {
+    a == b
+        || matches!(
+            (serde_json::from_slice::<Value>(a), serde_json::from_slice::<Value>(b)),
+            (Ok(a), Ok(b)) if a == b
+        )
+}
+
+fn body_similarity(a: &[u8], b: &[u8]) -> f64 {
+    if a == b {
+        return 1.0;
+    }
+    match (
+        serde_json::from_slice::<Value>(a),
+        serde_json::from_slice::<Value>(b),
+    ) {
+        (Ok(a), Ok(b)) => jaccard(&leaves(&a), &leaves(&b)),
+        _ => 0.0,
+    }
+}
+
+/// Every scalar in a JSON document as `path=value`
+fn leaves(value: &Value) -> BTreeSet<String> {
+    fn walk(value: &Value, path: String, out: &mut BTreeSet<String>) {
+        match value {
+            Value::Object(map) => {
+                for (key, value) in map {
+                    walk(value, format!("{}.{}", path, key), out);
+                }
+            }
+            Value::Array(items) => {
+                for (i, value) in items.iter().enumerate() {
+                    walk(value, format!("{}[{}]", path, i), out);
+                }
+            }
+            scalar => {
+                out.insert(format!("{}={}", path, scalar));
+            }
+        }
+    }
+
+    let mut out = BTreeSet::new();
+    walk(value, "$".to_string(), &mut out);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::captured;
+
+    fn find(replayer: &Replayer, method: &str, uri: &str) -> Option<String> {
+        let uri = uri.parse::<Uri>().unwrap();
+        replayer.find(method, &uri, b"").map(|req| req.request.uri)
+    }
+
+    #[test]
+    fn test_find_prefers_exact_path_and_query() {
+        let replayer = Replayer::new(vec![
+            captured("GET", "/api/users/1", "", 200, "{\"id\":1}"),
+            captured("GET", "/api/users/2?full=true", "", 200, "{\"id\":2}"),
+            captured("GET", "/api/users/2", "", 200, "{\"id\":2}"),
+        ]);
+
+        assert_eq!(
+            find(&replayer, "GET", "/api/users/2").as_deref(),
+            Some("/api/users/2")
+        );
+        assert_eq!(
+            find(&replayer, "get", "/api/users/2?full=true").as_deref(),
+            Some("/api/users/2?full=true")
+        );
+        // Same endpoint, unseen id: earliest capture
+        assert_eq!(
+            find(&replayer, "GET", "/api/users/9").as_deref(),
+            Some("/api/users/1")
+        );
+        assert_eq!(find(&replayer, "DELETE", "/api/users/1").as_deref(), None);
+        assert_eq!(find(&replayer, "GET", "/api/orders").as_deref(), None);
+    }
+
+    #[test]
+    fn test_find_by_body_similarity() {
+        let replayer = Replayer::new(vec![
+            captured(
+                "POST",
+                "/api/login",
+                "{\"user\":\"ann\",\"password\":\"x\"}",
+                200,
+                "{\"ok\":true}",
+            ),
+            captured(
+                "POST",
+                "/api/login",
+                "{\"user\":\"bob\",\"password\":\"y\"}",
+                401,
+                "{\"ok\":false}",
+            ),
+        ]);
+
+        let uri = "/api/login".parse::<Uri>().unwrap();
+        let matched = replayer
+            .find("POST", &uri, b"{\"user\":\"bob\",\"password\":\"z\"}")
+            .unwrap();
+        assert_eq!(matched.response.as_ref().unwrap().status_code, 401);
+    }
+
+    #[test]
+    fn test_find_exact_and_record() {
+        let replayer = Replayer::new(vec![captured(
+            "POST",
+            "/api/users?a=1&b=2",
+            "{\"name\":\"ann\",\"age\":3}",
+            201,
+            "{\"id\":1}",
+        )])
+        .with_mode(ReplayMode::Vcr);
+
+        let mut request = captured(
+            "POST",
+            "/api/users?b=2&a=1",
+            "{ \"age\": 3, \"name\": \"ann\" }",
+            0,
+            "",
+        )
+        .request;
+        assert!(replayer.find_exact(&request).is_some());
+
+        request.body = Some(b"{\"name\":\"bob\"}".to_vec());
+        assert!(replayer.find_exact(&request).is_none());
+        request.uri = "/api/users?a=1".to_string();
+        assert!(replayer.find_exact(&request).is_none());
+
+        replayer.record(captured(
+            "POST",
+            "/api/users?a=1",
+            "{\"name\":\"bob\"}",
+            201,
+            "{}",
+        ));
+        assert!(replayer.find_exact(&request).is_some());
+        assert_eq!(replayer.len(), 2);
+        assert_eq!(replayer.report().recorded, 1);
+    }
+
+    #[test]
+    fn test_observe_modes() {
+        assert_eq!(ReplayMode::from_observe("record").unwrap(), None);
+        assert_eq!(
+            ReplayMode::from_observe("vcr").unwrap(),
+            Some(ReplayMode::Vcr)
+        );
+        assert_eq!(
+            ReplayMode::from_observe("replay-only").unwrap(),
+            Some(ReplayMode::ReplayOnly)
+        );
+        assert!(ReplayMode::from_observe("serve").is_err());
+    }
+
+    #[test]
+    fn test_answer_and_report() {
+        let replayer =
+            Replayer::new(vec![captured("GET", "/health", "", 200, "ok")]).with_latency(true);
+        assert_eq!(replayer.len(), 1);
+
+        let captured = replayer
+            .find("GET", &"/health".parse().unwrap(), b"")
+            .unwrap();
+        let (response, delay) = replayer.answer(&captured);
+        assert_eq!(response.status_code, 200);
+        assert_eq!(delay, Duration::from_millis(5));
+        let headers: Vec<&str> = replay_headers(response)
+            .iter()
+            .map(|(k, _)| k.as_str())
+            .collect();
+        assert_eq!(headers, ["content-type"]);
+
+        let missing = "/missing".parse().unwrap();
+        replayer.record_unmatched("GET", &missing);
+        replayer.record_unmatched("GET", &missing);
+
+        let report = replayer.report();
+        assert_eq!(report.served, 1);
+        assert_eq!(report.unmatched, [("GET /missing".to_string(), 2)]);
+    }
+}
@@ -0,0 +1,594 @@
+//! JSON schema inference and validation for captured bodies
+//!
+//! Produces OpenAPI 3.0 flavoured schemas: `null` samples make a schema
+//! `nullable` and samples of incompatible types widen to the empty schema.
+//! Validation supports the keyword subset that OpenAPI documents commonly use.
+
+use chrono::DateTime;
+use regex::Regex;
+use serde::Serialize;
+use serde_json::{Map, Value, json};
+use std::collections::BTreeSet;
+use std::fmt;
+
+/// Infer a schema describing a single JSON value
+pub fn infer(value: &Value) -> Value {
+    match value {
+        Value::Null => json!({ "nullable": true }),
+        Value::Bool(_) => json!({ "type": "boolean" }),
+        Value::Number(n) if n.is_f64() => json!({ "type": "number" }),
+        Value::Number(_) => json!({ "type": "integer" }),
+        Value::String(s) => match string_format(s) {
+            Some(format) => json!({ "type": "string", "format": format }),
+            None => json!({ "type": "string" }),
+        },
+        Value::Array(items) => {
+            let items = infer_all(items).unwrap_or_else(|| json!({}));
+            json!({ "type": "array", "items": items })
+        }
+        Value::Object(fields) => {
+            let properties: Map<String, Value> =
+                fields.iter().map(|(k, v)| (k.clone(), infer(v))).collect();
+            let required: Vec<&String> = fields.keys().collect();
+            json!({ "type": "object", "properties": properties, "required": required })
+        }
+    }
+}
+
+/// Infer a schema describing every value in `values`
+pub fn infer_all<'a>(values: impl IntoIterator<Item = &'a Value>) -> Option<Value> {
+    values
+        .into_iter()
+        .map(infer)
+        .reduce(|acc, schema| merge(&acc, &schema))
+}
+
+/// Widen two schemas into one that accepts values of either
+pub fn merge(a: &Value, b: &Value) -> Value {
+    let nullable = is_nullable(a) || is_nullable(b);
+    let mut merged = match (schema_type(a), schema_type(b)) {
+        (None, _) if is_null_only(a) => strip_nullable(b),
+        (_, None) if is_null_only(b) => strip_nullable(a),
+        (Some(ta), Some(tb)) if ta == tb => merge_same_type(ta, a, b),
+        (Some("integer"), Some("number")) | (Some("number"), Some("integer")) => {
+            json!({ "type": "number" })
+        }
+        _ => json!({}),
+    };
+
+    if nullable && let Value::Object(map) = &mut merged {
+        map.insert("nullable".to_string(), Value::Bool(true));
+    }
+    merged
+}
+
+/// Validate `value` against `schema`, returning one message per violation
+///
+/// `root` is the document that `$ref` pointers such as
+/// `#/components/schemas/User` are resolved against.
+pub fn validate(value: &Value, schema: &Value, root: &Value) -> Vec<String> {
+    let mut errors = Vec::new();
+    validate_at(value, schema, root, "$", &[], &mut errors);
+    errors
+}
+
+/// `seen` holds the references followed since the last step into `value`;
+/// meeting one again means the schema loops without consuming any input
+fn validate_at(
+    value: &Value,
+    schema: &Value,
+    root: &Value,
+    path: &str,
+    seen: &[&str],
+    errors: &mut Vec<String>,
+) {
+    if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+        if seen.contains(&reference) {
+            errors.push(format!("{}: circular reference {}", path, reference));
+            return;
+        }
+        match root.pointer(reference.trim_start_matches('#')) {
+            Some(target) => {
+                let seen = [seen, &[reference]].concat();
+                validate_at(value, target, root, path, &seen, errors)
+            }
+            None => errors.push(format!("{}: unresolved reference {}", path, reference)),
+        }
+        return;
+    }
+
+    if value.is_null() {
+        if !is_nullable(schema) && schema_type(schema).is_some_and(|t| t != "null") {
+            errors.push(format!("{}: null is not allowed", path));
+        }
+        return;
+    }
+
+    if let Some(expected) = schema_type(schema)
+        && !type_matches(value, expected)
+    {
+        errors.push(format!(
+            "{}: expected {}, got {}",
+            path,
+            expected,
+            value_type(value)
+        ));
+        return;
+    }
+
+    if let Some(options) = schema.get("enum").and_then(Value::as_array)
+        && !options.contains(value)
+    {
+        errors.push(format!(
+            "{}: {} is not one of {}",
+            path,
+            value,
+            Value::from(options.clone())
+        ));
+    }
+
+    for sub in schema
+        .get("allOf")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+    {
+        validate_at(value, sub, root, path, seen, errors);
+    }
+    for keyword in ["anyOf", "oneOf"] {
+        if let Some(options) = schema.get(keyword).and_then(Value::as_array) {
+            let matching = options
+                .iter()
+                .filter(|sub| {
+                    let mut errors = Vec::new();
+                    validate_at(value, sub, root, path, seen, &mut errors);
+                    errors.is_empty()
+                })
+                .count();
+            let valid = if keyword == "oneOf" {
+                matching == 1
+            } else {
+                matching > 0
+            };
+            if !valid {
+                errors.push(format!("{}: does not match {}", path, keyword));
+            }
+        }
+    }
+
+    match value {
+        Value::Object(fields) => {
+            for name in schema
+                .get("required")
+                .and_then(Value::as_array)
+                .into_iter()
+                .flatten()
+            {
+                if let Some(name) = name.as_str()
+                    && !fields.contains_key(name)
+                {
+                    errors.push(format!("{}: missing required property '{}'", path, name));
+                }
+            }
+
+            let properties = schema.get("properties").and_then(Value::as_object);
+            for (name, field) in fields {
+                let field_path = format!("{}.{}", path, name);
+                match (
+                    properties.and_then(|p| p.get(name)),
+                    schema.get("additionalProperties"),
+                ) {
+                    (Some(sub), _) => validate_at(field, sub, root, &field_path, &[], errors),
+                    (None, Some(Value::Bool(false))) => {
+                        errors.push(format!("{}: property is not allowed", field_path))
+                    }
+                    (None, Some(sub @ Value::Object(_))) => {
+                        validate_at(field, sub, root, &field_path, &[], errors)
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Value::Array(items) => {
+            if let Some(sub) = schema.get("items") {
+                for (i, item) in items.iter().enumerate() {
+                    validate_at(item, sub, root, &format!("{}[{}]", path, i), &[], errors);
+                }
+            }
+            check_bound(
+                schema,
+                "minItems",
+                items.len() as f64,
+                path,
+                errors,
+                |v, b| v >= b,
+            );
+            check_bound(
+                schema,
+                "maxItems",
+                items.len() as f64,
+                path,
+                errors,
+                |v, b| v <= b,
+            );
+        }
+        Value::String(s) => {
+            let len = s.chars().count() as f64;
+            check_bound(schema, "minLength", len, path, errors, |v, b| v >= b);
+            check_bound(schema, "maxLength", len, path, errors, |v, b| v <= b);
+            if let Some(pattern) = schema.get("pattern").and_then(Value::as_str)
+                && let Ok(re) = Regex::new(pattern)
+                && !re.is_match(s)
+            {
+                errors.push(format!("{}: does not match pattern {}", path, pattern));
+            }
+        }
+        Value::Number(n) => {
+            let n = n.as_f64().unwrap_or_default();
+            check_bound(schema, "minimum", n, path, errors, |v, b| v >= b);
+            check_bound(schema, "maximum", n, path, errors, |v, b| v <= b);
+        }
+        _ => {}
+    }
+}
+
+fn check_bound(
+    schema: &Value,
+    keyword: &str,
+    actual: f64,
+    path: &str,
+    errors: &mut Vec<String>,
+    ok: impl Fn(f64, f64) -> bool,
+) {
+    if let Some(bound) = schema.get(keyword).and_then(Value::as_f64)
+        && !ok(actual, bound)
+    {
+        errors.push(format!("{}: violates {} {}", path, keyword, bound));
+    }
+}
+
+fn type_matches(value: &Value, expected: &str) -> bool {
+    match expected {
+        "integer" => value.as_f64().is_some_and(|n| n.fract() == 0.0),
+        "number" => value.is_number(),
+        other => value_type(value) == other,
+    }
+}
+
+fn value_type(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(n) if n.is_f64() => "number",
+        Value::Number(_) => "integer",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+fn merge_same_type(schema_type: &str, a: &Value, b: &Value) -> Value {
+    match schema_type {
+        "object" => {
+            let empty = Map::new();
+            let props_a = a["properties"].as_object().unwrap_or(&empty);
+            let props_b = b["properties"].as_object().unwrap_or(&empty);
+
+            let keys: BTreeSet<&String> = props_a.keys().chain(props_b.keys()).collect();
+            let mut properties = Map::new();
+            for key in keys {
+                let schema = match (props_a.get(key), props_b.get(key)) {
+                    (Some(x), Some(y)) => merge(x, y),
+                    (Some(x), None) | (None, Some(x)) => x.clone(),
+                    (None, None) => continue,
+                };
+                properties.insert(key.clone(), schema);
+            }
+
+            let required_a = required_set(a);
+            let required_b = required_set(b);
+            let required: Vec<&String> = required_a.intersection(&required_b).collect();
+
+            json!({ "type": "object", "properties": properties, "required": required })
+        }
+        "array" => {
+            // Empty arrays carry no information about their items
+            let is_unknown = |s: &Value| s.as_object().is_none_or(|m| m.is_empty());
+            let items = match (&a["items"], &b["items"]) {
+                (x, y) if is_unknown(x) => y.clone(),
+                (x, y) if is_unknown(y) => x.clone(),
+                (x, y) => merge(x, y),
+            };
+            json!({ "type": "array", "items": items })
+        }
+        "string" if a.get("format").is_some() && a.get("format") == b.get("format") => {
+            json!({ "type": "string", "format": a["format"] })
+        }
+        other => json!({ "type": other }),
+    }
+}
+
+/// One difference between two schemas of the same body
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SchemaChange {
+    /// Where in the body, e.g. `$.items[].id`
+    pub path: String,
+    /// Type before, `None` for a new field
+    pub before: Option<String>,
+    /// Type after, `None` for a removed field
+    pub after: Option<String>,
+}
+
+impl fmt::Display for SchemaChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let or_missing = |t: &Option<String>| t.clone().unwrap_or_else(|| "missing".to_string());
+        write!(
+            f,
+            "{}: {} -> {}",
+            self.path,
+            or_missing(&self.before),
+            or_missing(&self.after)
+        )
+    }
+}
+
+/// Fields added, removed or retyped between two inferred schemas
+pub fn diff(old: &Value, new: &Value) -> Vec<SchemaChange> {
+    let mut changes = Vec::new();
+    diff_at("$", old, new, &mut changes);
+    changes
+}
+
+fn diff_at(path: &str, old: &Value, new: &Value, changes: &mut Vec<SchemaChange>) {
+    let (before, after) = (describe(old), describe(new));
+    if before != after {
+        changes.push(SchemaChange {
+            path: path.to_string(),
+            before: Some(before),
+            after: Some(after),
+        });
+        return;
+    }
+
+    match schema_type(old) {
+        Some("object") => {
+            let empty = Map::new();
+            let old = old["properties"].as_object().unwrap_or(&empty);
+            let new = new["properties"].as_object().unwrap_or(&empty);
+            let keys: BTreeSet<&String> = old.keys().chain(new.keys()).collect();
+
+            for key in keys {
+                let path = format!("{}.{}", path, key);
+                match (old.get(key), new.get(key)) {
+                    (Some(old), Some(new)) => diff_at(&path, old, new, changes),
+                    (before, after) => changes.push(SchemaChange {
+                        path,
+                        before: before.map(describe),
+                        after: after.map(describe),
+                    }),
+                }
+            }
+        }
+        Some("array") => diff_at(
+            &format!("{}[]", path),
+            &old["items"],
+            &new["items"],
+            changes,
+        ),
+        _ => {}
+    }
+}
+
+/// `integer`, `string (uuid)` or `any`, with ` | null` when nullable
+fn describe(schema: &Value) -> String {
+    let mut text = match (
+        schema_type(schema),
+        schema.get("format").and_then(Value::as_str),
+    ) {
+        (Some(t), Some(format)) => format!("{} ({})", t, format),
+        (Some(t), None) => t.to_string(),
+        (None, _) if is_null_only(schema) => return "null".to_string(),
+        (None, _) => "any".to_string(),
+    };
+    if is_nullable(schema) {
+        text.push_str(" | null");
+    }
+    text
+}
+
+fn schema_type(schema: &Value) -> Option<&str> {
+    schema.get("type").and_then(Value::as_str)
+}
+
+fn is_nullable(schema: &Value) -> bool {
+    schema.get("nullable").and_then(Value::as_bool) == Some(true)
+}
+
+fn is_null_only(schema: &Value) -> bool {
+    schema.as_object().is_some_and(|m| m.len() == 1) && is_nullable(schema)
+}
+
+fn strip_nullable(schema: &Value) -> Value {
+    let mut schema = schema.clone();
+    if let Value::Object(map) = &mut schema {
+        map.remove("nullable");
+    }
+    schema
+}
+
+fn required_set(schema: &Value) -> BTreeSet<String> {
+    schema["required"]
+        .as_array()
+        .map(|r| {
+            r.iter()
+                .filter_map(|v| v.as_str().map(String::from))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn string_format(s: &str) -> Option<&'static str> {
+    if DateTime::parse_from_rfc3339(s).is_ok() {
+        Some("date-time")
+    } else if s.len() == 36 && uuid::Uuid::parse_str(s).is_ok() {
+        Some("uuid")
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_schema_diff() {
+        let old = infer(&json!({"id": 1, "name": "ann", "tags": [{"k": "a"}], "at": null}));
+        let new =
+            infer(&json!({"id": "1", "tags": [{"k": "a", "v": 2}], "email": "a@b", "at": null}));
+
+        let changes: Vec<String> = diff(&old, &new).iter().map(|c| c.to_string()).collect();
+        assert_eq!(
+            changes,
+            [
+                "$.email: missing -> string",
+                "$.id: integer -> string",
+                "$.name: string -> missing",
+                "$.tags[].v: missing -> integer",
+            ]
+        );
+        assert!(diff(&old, &old).is_empty());
+    }
+
+    #[test]
+    fn test_infer_object() {
+        let schema = infer(&json!({
+            "id": 7,
+            "price": 9.5,
+            "created": "2026-01-01T00:00:00Z",
+            "tags": ["a"]
+        }));
+
+        assert_eq!(schema["type"], "object");
+        assert_eq!(schema["properties"]["id"]["type"], "integer");
+        assert_eq!(schema["properties"]["price"]["type"], "number");
+        assert_eq!(schema["properties"]["created"]["format"], "date-time");
+        assert_eq!(schema["properties"]["tags"]["items"]["type"], "string");
+        assert_eq!(schema["required"].as_array().unwrap().len(), 4);
+    }
+
+    #[test]
+    fn test_merge_optional_and_nullable_fields() {
+        let schema = infer_all(&[
+            json!({ "id": 1, "note": null }),
+            json!({ "id": 2.5, "note": "x", "extra": true }),
+        ])
+        .unwrap();
+
+        assert_eq!(schema["properties"]["id"]["type"], "number");
+        assert_eq!(schema["properties"]["note"]["type"], "string");
+        assert_eq!(schema["properties"]["note"]["nullable"], true);
+        assert_eq!(schema["properties"]["extra"]["type"], "boolean");
+        assert_eq!(schema["required"], json!(["id", "note"]));
+    }
+
+    #[test]
+    fn test_merge_empty_array_items() {
+        let schema = infer_all(&[json!([]), json!([1, 2])]).unwrap();
+        assert_eq!(schema["items"]["type"], "integer");
+    }
+
+    #[test]
+    fn test_validate_inferred_schema() {
+        let schema = infer(&json!({ "id": 1, "tags": ["a"] }));
+        let root = json!({});
+
+        assert!(validate(&json!({ "id": 2, "tags": [] }), &schema, &root).is_empty());
+
+        let errors = validate(&json!({ "id": "2", "tags": [1] }), &schema, &root);
+        assert_eq!(
+            errors,
+            vec![
+                "$.id: expected integer, got string",
+                "$.tags[0]: expected string, got integer"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validate_refs_and_constraints() {
+        let root = json!({
+            "components": { "schemas": { "Status": {
+                "type": "string", "enum": ["open", "closed"]
+            }}}
+        });
+        let schema = json!({
+            "type": "object",
+            "required": ["status", "count"],
+            "additionalProperties": false,
+            "properties": {
+                "status": { "$ref": "#/components/schemas/Status" },
+                "count": { "type": "integer", "minimum": 0 }
+            }
+        });
+
+        let errors = validate(
+            &json!({ "status": "lost", "count": -1, "x": 1 }),
+            &schema,
+            &root,
+        );
+        assert_eq!(errors.len(), 3);
+        assert!(errors[0].starts_with("$.count: violates minimum"));
+        assert!(errors[1].starts_with("$.status: \"lost\" is not one of"));
+        assert_eq!(errors[2], "$.x: property is not allowed");
+
+        let errors = validate(&json!({}), &schema, &root);
+        assert_eq!(errors.len(), 2);
+    }
+
+    #[test]
+    fn test_validate_recursive_refs() {
+        let root = json!({
+            "components": { "schemas": {
+                "Node": {
+                    "type": "object",
+                    "properties": {
+                        "children": { "type": "array", "items": { "$ref": "#/components/schemas/Node" } }
+                    }
+                },
+                "Loop": { "$ref": "#/components/schemas/Loop" },
+                "Either": { "anyOf": [{ "$ref": "#/components/schemas/Either" }] }
+            }}
+        });
+
+        let tree = json!({ "children": [{ "children": [{ "children": [] }] }] });
+        let node = json!({ "$ref": "#/components/schemas/Node" });
+        assert!(validate(&tree, &node, &root).is_empty());
+        assert_eq!(
+            validate(&json!({ "children": [1] }), &node, &root),
+            vec!["$.children[0]: expected object, got integer"]
+        );
+
+        let errors = validate(
+            &json!(1),
+            &json!({ "$ref": "#/components/schemas/Loop" }),
+            &root,
+        );
+        assert_eq!(
+            errors,
+            vec!["$: circular reference #/components/schemas/Loop"]
+        );
+
+        let errors = validate(
+            &json!(1),
+            &json!({ "$ref": "#/components/schemas/Either" }),
+            &root,
+        );
+        assert_eq!(errors, vec!["$: does not match anyOf"]);
+    }
+
+    #[test]
+    fn test_merge_incompatible_types() {
+        assert_eq!(merge(&infer(&json!("a")), &infer(&json!(1))), json!({}));
+    }
+}
@@ -1,6 +1,6 @@
 use crate::models::CapturedRequest;
 use anyhow::Result;
-use rusqlite::{Connection, params};
+use rusqlite::{Connection, OptionalExtension, params};
 use std::path::Path;
 use std::sync::Mutex;
 
@@ -42,6 +42,14 @@ impl Storage {
 
         conn.execute("CREATE INDEX IF NOT EXISTS idx_uri ON requests(uri)", [])?;
 
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS metadata (
+                key TEXT PRIMARY KEY,
+                value TEXT NOT NULL
+            )",
+            [],
+        )?;
+
         Ok(())
     }
 
@@ -77,6 +85,28 @@ impl Storage {
         Ok(())
     }
 
+    /// Record a fact about the capture, e.g. the `target` it was proxied to
+    pub fn set_metadata(&self, key: &str, value: &str) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT OR REPLACE INTO metadata (key, value) VALUES (?1, ?2)",
+            params![key, value],
+        )?;
+        Ok(())
+    }
+
+    pub fn get_metadata(&self, key: &str) -> Result<Option<String>> {
+        let conn = self.conn.lock().unwrap();
+        let value = conn
+            .query_row(
+                "SELECT value FROM metadata WHERE key = ?1",
+                params![key],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(value)
+    }
+
     pub fn get_all_requests(&self) -> Result<Vec<CapturedRequest>> {
         let conn = self.conn.lock().unwrap();
         let mut stmt = conn.prepare(
@@ -152,6 +182,7 @@ impl Storage {
         duration_ms: Option<u64>,
     ) -> Result<CapturedRequest> {
         use crate::models::{Protocol, RequestData, ResponseData};
+        use crate::parsers::http::HttpParser;
 
         let headers = serde_json::from_str(&headers_json)?;
         let protocol = match protocol.as_str() {
@@ -177,6 +208,13 @@ impl Storage {
             None
         };
 
+        // Query parameters are not stored separately; read them back from the
+        // URI so every loaded request carries them, whatever recorded it
+        let query_params = uri
+            .parse::<hyper::Uri>()
+            .map(|u| HttpParser::parse_query_params(&u))
+            .unwrap_or_default();
+
         Ok(CapturedRequest {
             id,
             timestamp: chrono::DateTime::parse_from_rfc3339(&timestamp)?.into(),
@@ -186,7 +224,7 @@ impl Storage {
                 uri,
                 headers,
                 body,
-                query_params: Default::default(),
+                query_params,
             },
             response,
             duration_ms,